@@ -0,0 +1,247 @@
+use crate::trace::Trace;
+use quiche_moq_wire::NamespaceTrackname;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct ObjectKey {
+    /// Full track name, or `alias <n>` if the subscription is not part of the trace
+    pub(crate) track: String,
+    pub(crate) group: u64,
+    pub(crate) object: u64,
+}
+
+/// Object events of one trace, with track aliases resolved to track names.
+pub(crate) struct TraceObjects {
+    pub(crate) name: String,
+    pub(crate) created: Vec<(ObjectKey, f64)>,
+    pub(crate) received: Vec<(ObjectKey, f64)>,
+}
+
+pub(crate) struct ObjectLatency {
+    pub(crate) key: ObjectKey,
+    /// Milliseconds from `subgroup_object_created` to `subgroup_object_received`
+    pub(crate) latency: f64,
+}
+
+pub(crate) struct TrackReport {
+    pub(crate) track: String,
+    /// In receive order
+    pub(crate) objects: Vec<ObjectLatency>,
+    /// Received objects without a matching created event
+    pub(crate) unmatched: usize,
+    /// Objects created within the received range of the track, but never received
+    pub(crate) lost: usize,
+    /// Interarrival jitter in milliseconds, https://www.rfc-editor.org/rfc/rfc3550#section-6.4.1
+    pub(crate) jitter: f64,
+}
+
+impl TrackReport {
+    /// Latency at quantile `q` in `[0, 1]`
+    pub(crate) fn latency_quantile(&self, q: f64) -> Option<f64> {
+        let mut latencies: Vec<f64> = self.objects.iter().map(|o| o.latency).collect();
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort_by(f64::total_cmp);
+        let i = ((latencies.len() - 1) as f64 * q).round() as usize;
+        Some(latencies[i])
+    }
+
+    pub(crate) fn latency_mean(&self) -> Option<f64> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        Some(self.objects.iter().map(|o| o.latency).sum::<f64>() / self.objects.len() as f64)
+    }
+}
+
+/// Decode a `MOQTByteString`
+fn byte_string(v: &Value) -> Vec<u8> {
+    if let Some(s) = v["value"].as_str() {
+        return s.as_bytes().to_vec();
+    }
+    let hex = v["value_bytes"].as_str().unwrap_or_default();
+    (0..hex.len() / 2)
+        .filter_map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+        .collect()
+}
+
+fn track_name(msg: &Value) -> String {
+    let namespace = msg["track_namespace"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(byte_string)
+        .collect();
+    NamespaceTrackname::new(namespace, byte_string(&msg["track_name"])).to_string()
+}
+
+pub(crate) fn trace_objects(trace: &Trace) -> TraceObjects {
+    let mut request_tracks: HashMap<u64, String> = HashMap::new();
+    let mut alias_tracks: HashMap<u64, String> = HashMap::new();
+    // stream id -> (track alias, group id)
+    let mut streams: HashMap<u64, (u64, u64)> = HashMap::new();
+    let mut objects = TraceObjects {
+        name: trace.name.clone(),
+        created: vec![],
+        received: vec![],
+    };
+    for event in &trace.events {
+        let data = &event.data;
+        match event.name.as_str() {
+            "moqt:control_message_created" | "moqt:control_message_parsed" => {
+                let msg = &data["message"];
+                let Some(request_id) = msg["request_id"].as_u64() else { continue };
+                match msg["type"].as_str() {
+                    Some("subscribe") => {
+                        let track = track_name(msg);
+                        // track alias is part of SUBSCRIBE until draft 11
+                        if let Some(alias) = msg["track_alias"].as_u64() {
+                            alias_tracks.insert(alias, track.clone());
+                        }
+                        request_tracks.insert(request_id, track);
+                    }
+                    Some("subscribe_ok") => {
+                        if let (Some(alias), Some(track)) = (msg["track_alias"].as_u64(), request_tracks.get(&request_id)) {
+                            alias_tracks.insert(alias, track.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "moqt:subgroup_header_created" | "moqt:subgroup_header_parsed" => {
+                if let (Some(stream_id), Some(alias), Some(group)) =
+                    (data["stream_id"].as_u64(), data["track_alias"].as_u64(), data["group_id"].as_u64())
+                {
+                    streams.insert(stream_id, (alias, group));
+                }
+            }
+            name @ ("moqt:subgroup_object_created" | "moqt:subgroup_object_received") => {
+                let (Some(stream_id), Some(object)) = (data["stream_id"].as_u64(), data["object_id"].as_u64()) else {
+                    continue;
+                };
+                let Some(&(alias, group)) = streams.get(&stream_id) else { continue };
+                let key = ObjectKey {
+                    track: alias_tracks.get(&alias).cloned().unwrap_or_else(|| format!("alias {alias}")),
+                    group: data["group_id"].as_u64().unwrap_or(group),
+                    object,
+                };
+                if name == "moqt:subgroup_object_created" {
+                    objects.created.push((key, event.time));
+                } else {
+                    objects.received.push((key, event.time));
+                }
+            }
+            _ => {}
+        }
+    }
+    objects
+}
+
+/// Match received objects of each trace with the earliest creation of the object in any trace.
+/// Returns the reports of all traces that received objects, by trace name.
+pub(crate) fn analyze(traces: &[TraceObjects]) -> Vec<(String, Vec<TrackReport>)> {
+    let mut origin: HashMap<&ObjectKey, f64> = HashMap::new();
+    for (key, time) in traces.iter().flat_map(|t| &t.created) {
+        origin
+            .entry(key)
+            .and_modify(|t| *t = t.min(*time))
+            .or_insert(*time);
+    }
+    let mut reports = vec![];
+    for trace in traces {
+        let mut tracks: BTreeMap<&str, Vec<&(ObjectKey, f64)>> = BTreeMap::new();
+        for received in &trace.received {
+            tracks.entry(received.0.track.as_str()).or_default().push(received);
+        }
+        if tracks.is_empty() {
+            continue;
+        }
+        let reports_of_trace = tracks
+            .into_iter()
+            .map(|(track, received)| track_report(track, &received, &origin))
+            .collect();
+        reports.push((trace.name.clone(), reports_of_trace));
+    }
+    reports
+}
+
+fn track_report(track: &str, received: &[&(ObjectKey, f64)], origin: &HashMap<&ObjectKey, f64>) -> TrackReport {
+    let mut report = TrackReport {
+        track: track.to_string(),
+        objects: vec![],
+        unmatched: 0,
+        lost: 0,
+        jitter: 0.0,
+    };
+    let mut prev_transit = None;
+    for (key, time) in received {
+        let Some(created) = origin.get(key) else {
+            report.unmatched += 1;
+            continue;
+        };
+        let transit = time - created;
+        if let Some(prev) = prev_transit {
+            let d: f64 = transit - prev;
+            report.jitter += (d.abs() - report.jitter) / 16.0;
+        }
+        prev_transit = Some(transit);
+        report.objects.push(ObjectLatency {
+            key: key.clone(),
+            latency: transit,
+        });
+    }
+    let created_times = report.objects.iter().map(|o| origin[&o.key]);
+    let first = created_times.clone().fold(f64::INFINITY, f64::min);
+    let last = created_times.fold(f64::NEG_INFINITY, f64::max);
+    report.lost = origin
+        .iter()
+        .filter(|(key, time)| key.track == track && **time >= first && **time <= last)
+        .filter(|(key, _)| !report.objects.iter().any(|o| &o.key == **key))
+        .count();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{analyze, trace_objects};
+    use crate::trace::parse_json_seq;
+
+    const PUBLISHER: &str = "\u{1e}{\"qlog_version\":\"0.3\",\"trace\":{\"common_fields\":{\"reference_time\":1000.0}}}
+\u{1e}{\"time\":1.0,\"name\":\"moqt:control_message_parsed\",\"data\":{\"message\":{\"type\":\"subscribe\",\"request_id\":0,\"track_namespace\":[{\"value\":\"n1\"}],\"track_name\":{\"value\":\"t1\"}}}}
+\u{1e}{\"time\":2.0,\"name\":\"moqt:control_message_created\",\"data\":{\"message\":{\"type\":\"subscribe_ok\",\"request_id\":0,\"track_alias\":5}}}
+\u{1e}{\"time\":3.0,\"name\":\"moqt:subgroup_header_created\",\"data\":{\"stream_id\":3,\"track_alias\":5,\"group_id\":0,\"subgroup_id\":0}}
+\u{1e}{\"time\":10.0,\"name\":\"moqt:subgroup_object_created\",\"data\":{\"stream_id\":3,\"group_id\":0,\"object_id\":0}}
+\u{1e}{\"time\":20.0,\"name\":\"moqt:subgroup_object_created\",\"data\":{\"stream_id\":3,\"group_id\":0,\"object_id\":1}}
+\u{1e}{\"time\":30.0,\"name\":\"moqt:subgroup_object_created\",\"data\":{\"stream_id\":3,\"group_id\":0,\"object_id\":2}}
+";
+
+    const SUBSCRIBER: &str = "\u{1e}{\"qlog_version\":\"0.3\",\"trace\":{\"common_fields\":{\"reference_time\":1005.0}}}
+\u{1e}{\"time\":0.0,\"name\":\"moqt:control_message_created\",\"data\":{\"message\":{\"type\":\"subscribe\",\"request_id\":0,\"track_namespace\":[{\"value\":\"n1\"}],\"track_name\":{\"value\":\"t1\"}}}}
+\u{1e}{\"time\":1.0,\"name\":\"moqt:control_message_parsed\",\"data\":{\"message\":{\"type\":\"subscribe_ok\",\"request_id\":0,\"track_alias\":7}}}
+\u{1e}{\"time\":2.0,\"name\":\"moqt:subgroup_header_parsed\",\"data\":{\"stream_id\":2,\"track_alias\":7,\"group_id\":0,\"subgroup_id\":0}}
+\u{1e}{\"time\":10.0,\"name\":\"moqt:subgroup_object_received\",\"data\":{\"stream_id\":2,\"group_id\":0,\"object_id\":0}}
+\u{1e}{\"time\":35.0,\"name\":\"moqt:subgroup_object_received\",\"data\":{\"stream_id\":2,\"group_id\":0,\"object_id\":2}}
+";
+
+    #[test]
+    fn latency_jitter_loss() {
+        let traces = [
+            trace_objects(&parse_json_seq("pub", PUBLISHER).unwrap()),
+            trace_objects(&parse_json_seq("sub", SUBSCRIBER).unwrap()),
+        ];
+        let reports = analyze(&traces);
+        assert_eq!(reports.len(), 1);
+        let (name, tracks) = &reports[0];
+        assert_eq!(name, "sub");
+        assert_eq!(tracks.len(), 1);
+        let track = &tracks[0];
+        assert_eq!(track.track, "n1--t1");
+        let latencies: Vec<f64> = track.objects.iter().map(|o| o.latency).collect();
+        assert_eq!(latencies, [5.0, 10.0]);
+        assert_eq!(track.jitter, 5.0 / 16.0);
+        assert_eq!(track.lost, 1);
+        assert_eq!(track.unmatched, 0);
+    }
+}
@@ -0,0 +1,66 @@
+//! Reports per object latency, jitter and loss per track from MoQ qlog files.
+//! Latency is measured from `moqt:subgroup_object_created` to `moqt:subgroup_object_received`,
+//! so the clocks of the hosts that wrote the files must be synchronized.
+use crate::analysis::{analyze, trace_objects};
+use crate::trace::read_traces;
+use clap::Parser;
+use std::path::PathBuf;
+
+mod analysis;
+mod trace;
+
+#[derive(Parser)]
+struct Args {
+    /// qlog files of all endpoints, e.g. of publisher, relay and subscriber.
+    /// JSON-SEQ (.sqlog) and JSON (.qlog) are supported.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Print the latency of every received object
+    #[arg(long)]
+    objects: bool,
+}
+
+fn fmt_ms(v: Option<f64>) -> String {
+    v.map(|v| format!("{v:.3}")).unwrap_or_else(|| "-".to_string())
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut traces = vec![];
+    for file in &args.files {
+        match read_traces(file) {
+            Ok(v) => traces.extend(v),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    for trace in traces.iter().filter(|t| !t.has_reference_time) {
+        eprintln!("warning: {} has no reference time, latency across files is meaningless", trace.name);
+    }
+    let objects: Vec<_> = traces.iter().map(trace_objects).collect();
+    for (trace, tracks) in analyze(&objects) {
+        println!("{trace}");
+        for track in tracks {
+            println!(
+                "  {}: received {} lost {} unmatched {} latency ms min {} mean {} p50 {} p95 {} max {} jitter ms {:.3}",
+                track.track,
+                track.objects.len(),
+                track.lost,
+                track.unmatched,
+                fmt_ms(track.latency_quantile(0.0)),
+                fmt_ms(track.latency_mean()),
+                fmt_ms(track.latency_quantile(0.5)),
+                fmt_ms(track.latency_quantile(0.95)),
+                fmt_ms(track.latency_quantile(1.0)),
+                track.jitter,
+            );
+            if args.objects {
+                for o in &track.objects {
+                    println!("    group {} object {}: {:.3} ms", o.key.group, o.key.object, o.latency);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use serde_json::Value;
+use std::path::Path;
+
+pub(crate) struct Event {
+    /// Milliseconds since the unix epoch, or since the start of the trace if it has no reference time.
+    pub(crate) time: f64,
+    pub(crate) name: String,
+    pub(crate) data: Value,
+}
+
+pub(crate) struct Trace {
+    pub(crate) name: String,
+    /// `false` if times are relative to an unknown start, latency is only meaningful within the trace.
+    pub(crate) has_reference_time: bool,
+    pub(crate) events: Vec<Event>,
+}
+
+/// Read a JSON-SEQ (`.sqlog`) or JSON (`.qlog`) file.
+pub(crate) fn read_traces(path: &Path) -> Result<Vec<Trace>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let name = path.display().to_string();
+    if text.trim_start().starts_with('\u{1e}') {
+        Ok(vec![parse_json_seq(&name, &text)?])
+    } else {
+        parse_json(&name, &text)
+    }
+}
+
+/// https://www.ietf.org/archive/id/draft-ietf-quic-qlog-main-schema-09.html#name-qlogfileseq-schema
+pub(crate) fn parse_json_seq(name: &str, text: &str) -> Result<Trace, String> {
+    let mut records = text
+        .split('\u{1e}')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(|r| serde_json::from_str::<Value>(r).map_err(|e| format!("{name}: {e}")));
+    let header = records.next().ok_or_else(|| format!("{name}: empty file"))??;
+    let reference_time = reference_time(&header["trace"]);
+    let mut events = vec![];
+    for record in records {
+        events.extend(event(&record?, reference_time));
+    }
+    Ok(Trace {
+        name: name.to_string(),
+        has_reference_time: reference_time.is_some(),
+        events,
+    })
+}
+
+/// https://www.ietf.org/archive/id/draft-ietf-quic-qlog-main-schema-09.html#name-qlogfile-schema
+pub(crate) fn parse_json(name: &str, text: &str) -> Result<Vec<Trace>, String> {
+    let file: Value = serde_json::from_str(text).map_err(|e| format!("{name}: {e}"))?;
+    let traces = file["traces"]
+        .as_array()
+        .ok_or_else(|| format!("{name}: no traces"))?;
+    Ok(traces
+        .iter()
+        .enumerate()
+        .map(|(i, trace)| {
+            let reference_time = reference_time(trace);
+            Trace {
+                name: if traces.len() == 1 { name.to_string() } else { format!("{name}#{i}") },
+                has_reference_time: reference_time.is_some(),
+                events: trace["events"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| event(e, reference_time))
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+fn reference_time(trace: &Value) -> Option<f64> {
+    trace["common_fields"]["reference_time"]
+        .as_f64()
+        .or_else(|| trace["reference_time"].as_f64())
+}
+
+fn event(record: &Value, reference_time: Option<f64>) -> Option<Event> {
+    Some(Event {
+        time: reference_time.unwrap_or(0.0) + record["time"].as_f64()?,
+        name: record["name"].as_str()?.to_string(),
+        data: record["data"].clone(),
+    })
+}
@@ -0,0 +1,177 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use quiche_moq::wire::Namespace;
+
+/// Who may publish or subscribe to the namespaces starting with `namespace`.
+/// The rule with the longest matching namespace applies; namespaces without a rule are open to everyone.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AclRule {
+    pub(crate) namespace: NamespacePrefix,
+    /// Networks allowed to publish the namespaces; unset allows everyone.
+    #[serde(default)]
+    pub(crate) publish: Option<Vec<IpNet>>,
+    /// Networks allowed to subscribe or fetch; unset allows everyone.
+    #[serde(default)]
+    pub(crate) subscribe: Option<Vec<IpNet>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    Publish,
+    Subscribe,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Acl(pub(crate) Vec<AclRule>);
+
+impl Acl {
+    pub(crate) fn allows(&self, namespace: &Namespace, peer: Option<IpAddr>, action: Action) -> bool {
+        let Some(rule) = self.0.iter()
+            .filter(|r| r.namespace.matches(namespace))
+            .max_by_key(|r| r.namespace.0.len())
+        else {
+            return true;
+        };
+        let nets = match action {
+            Action::Publish => &rule.publish,
+            Action::Subscribe => &rule.subscribe,
+        };
+        match (nets, peer) {
+            (None, _) => true,
+            (Some(nets), Some(peer)) => nets.iter().any(|n| n.contains(peer)),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Namespace in its text form, e.g. `example.2enet-team2`, matching itself and all longer namespaces.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct NamespacePrefix(pub(crate) Namespace);
+
+impl NamespacePrefix {
+    pub(crate) fn matches(&self, namespace: &Namespace) -> bool {
+        namespace.0.0.starts_with(&self.0.0.0)
+    }
+}
+
+impl TryFrom<String> for NamespacePrefix {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Ok(Self(s.parse()?))
+    }
+}
+
+impl From<NamespacePrefix> for String {
+    fn from(p: NamespacePrefix) -> Self {
+        p.0.to_string()
+    }
+}
+
+/// An address or network in CIDR notation, e.g. `10.0.0.0/8` or `::1`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        // an IPv4 client on a dual-stack listener shows up as an IPv4-mapped IPv6 address
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            v4 => v4,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_len)).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix_len)).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|e| format!("{s}: {e}"))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len.parse().map_err(|e| format!("{s}: {e}"))?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(format!("{s}: prefix length exceeds {max_len}"));
+        }
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl TryFrom<String> for IpNet {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(namespace: &str, publish: Option<&[&str]>, subscribe: Option<&[&str]>) -> AclRule {
+        let nets = |v: &[&str]| v.iter().map(|n| n.parse().unwrap()).collect();
+        AclRule {
+            namespace: namespace.to_string().try_into().unwrap(),
+            publish: publish.map(nets),
+            subscribe: subscribe.map(nets),
+        }
+    }
+
+    #[test]
+    fn ip_net() {
+        let net: IpNet = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("10.2.0.1".parse().unwrap()));
+        assert!(!net.contains("::1".parse().unwrap()));
+        let any: IpNet = "::/0".parse().unwrap();
+        assert!(any.contains("2001:db8::1".parse().unwrap()));
+        assert!("::1".parse::<IpNet>().unwrap().contains("::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn longest_namespace_wins() {
+        let acl = Acl(vec![
+            rule("live", Some(&["10.0.0.0/8"]), None),
+            rule("live-public", Some(&["10.0.0.0/8"]), Some(&[])),
+            rule("live-public-vip", None, Some(&["192.168.0.0/16"])),
+        ]);
+        let inside = Some("10.0.0.1".parse().unwrap());
+        let outside = Some("192.168.1.1".parse().unwrap());
+        let ns = |s: &str| s.parse::<Namespace>().unwrap();
+        assert!(acl.allows(&ns("live-sports"), inside, Action::Publish));
+        assert!(!acl.allows(&ns("live-sports"), outside, Action::Publish));
+        assert!(acl.allows(&ns("live-sports"), outside, Action::Subscribe));
+        assert!(!acl.allows(&ns("live-public"), outside, Action::Subscribe));
+        assert!(acl.allows(&ns("live-public-vip"), outside, Action::Subscribe));
+        assert!(acl.allows(&ns("live-public-vip"), outside, Action::Publish));
+        assert!(acl.allows(&ns("vod"), None, Action::Publish));
+        assert!(!acl.allows(&ns("live"), None, Action::Publish));
+    }
+}
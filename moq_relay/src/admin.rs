@@ -0,0 +1,196 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use log::{error, info};
+use quiche_mio_runner::quiche_endpoint::ClientId;
+use quiche_moq::wire::{Location, Version, version_to_name};
+use serde_json::{Value, json};
+
+use crate::Relay;
+
+/// Time a client has to send its request before the connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Only the request line and headers are read, a request body is ignored.
+const MAX_REQUEST_LEN: usize = 8192;
+
+/// Local HTTP/1.1 listener for metrics and the admin view, polled from the relay loop.
+/// Every request is answered on its own connection, which is then closed.
+///
+/// - `GET /metrics`: Prometheus metrics
+/// - `GET /sessions`: connections with their peer address and MoQ version
+/// - `GET /namespaces`: published namespaces with their publisher and standby publishers
+/// - `GET /subscriptions`: tracks with their publisher, cache and subscribers
+/// - `POST /sessions/<client id>/close`: close a connection
+/// - `POST /drain`: drain the relay, see [`Relay::drain`]
+pub(crate) struct AdminServer {
+    listener: TcpListener,
+    pending: Vec<PendingRequest>,
+}
+
+struct PendingRequest {
+    stream: TcpStream,
+    buf: Vec<u8>,
+    accepted: Instant,
+}
+
+pub(crate) struct Request {
+    stream: TcpStream,
+    pub(crate) method: String,
+    /// Path without query string.
+    pub(crate) path: String,
+}
+
+/// A connection of the relay as listed by the admin view.
+pub(crate) struct Session {
+    pub(crate) client_id: ClientId,
+    pub(crate) peer: Option<SocketAddr>,
+    /// Set once the MoQ session is established.
+    pub(crate) version: Option<Version>,
+    pub(crate) closed: bool,
+}
+
+impl AdminServer {
+    pub(crate) fn bind(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!("admin listening on http://{}", listener.local_addr()?);
+        Ok(Self { listener, pending: vec![] })
+    }
+
+    /// Accept new connections and return the requests that have been received completely.
+    pub(crate) fn poll(&mut self) -> Vec<Request> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        self.pending.push(PendingRequest { stream, buf: vec![], accepted: Instant::now() });
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    error!("admin accept: {e}");
+                    break;
+                }
+            }
+        }
+        let now = Instant::now();
+        let mut requests = vec![];
+        for mut p in std::mem::take(&mut self.pending) {
+            let mut chunk = [0u8; 1024];
+            let closed = loop {
+                match p.stream.read(&mut chunk) {
+                    Ok(0) => break true,
+                    Ok(n) => p.buf.extend_from_slice(&chunk[..n]),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break false,
+                    Err(_) => break true,
+                }
+            };
+            let Some(end) = p.buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                if !closed && p.buf.len() <= MAX_REQUEST_LEN && now - p.accepted < REQUEST_TIMEOUT {
+                    self.pending.push(p);
+                }
+                continue;
+            };
+            let head = String::from_utf8_lossy(&p.buf[..end]);
+            let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+            let method = request_line.next().unwrap_or_default().to_string();
+            let target = request_line.next().unwrap_or_default();
+            let path = target.split('?').next().unwrap_or_default().to_string();
+            requests.push(Request { stream: p.stream, method, path });
+        }
+        requests
+    }
+}
+
+impl Request {
+    /// Client ID of `POST /sessions/<client id>/close`.
+    pub(crate) fn close_session(&self) -> Option<ClientId> {
+        if self.method != "POST" { return None; }
+        self.path.strip_prefix("/sessions/")?.strip_suffix("/close")?.parse().ok()
+    }
+
+    pub(crate) fn respond(mut self, status: u16, content_type: &str, body: &str) {
+        let reason = match status {
+            200 => "OK",
+            404 => "Not Found",
+            _ => "",
+        };
+        // the responses are small, a client that does not read them in time is dropped
+        let _ = self.stream.set_nonblocking(false);
+        let _ = self.stream.set_write_timeout(Some(Duration::from_millis(100)));
+        let res = write!(
+            self.stream,
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len(),
+        );
+        if let Err(e) = res {
+            error!("admin response to {} {}: {e}", self.method, self.path);
+        }
+    }
+
+    pub(crate) fn respond_json(self, status: u16, body: &Value) {
+        self.respond(status, "application/json", &body.to_string());
+    }
+}
+
+pub(crate) fn sessions_json(sessions: &[Session]) -> Value {
+    sessions.iter()
+        .map(|s| json!({
+            "client_id": s.client_id.to_string(),
+            "peer": s.peer.map(|a| a.to_string()),
+            "version": s.version.map(version_to_name),
+            "closed": s.closed,
+        }))
+        .collect()
+}
+
+pub(crate) fn namespaces_json(app_data: &Relay) -> Value {
+    app_data.namespaces.iter()
+        .map(|(ns, publisher)| json!({
+            "namespace": ns.to_string(),
+            "publisher": publisher.to_string(),
+            "standby_publishers": app_data.standby_publishers.iter()
+                .filter(|(n, _)| *n == ns)
+                .map(|(_, id)| id.to_string())
+                .collect::<Vec<_>>(),
+        }))
+        .collect()
+}
+
+pub(crate) fn subscriptions_json(app_data: &Relay) -> Value {
+    app_data.subscriptions.iter()
+        .map(|(nt, sub)| json!({
+            "track": nt.to_string(),
+            "publisher": sub.publisher.as_ref().map(|p| json!({
+                "client_id": p.client_id.to_string(),
+                "request_id": p.request_id,
+                "track_alias": p.track_alias,
+                "largest_location": p.largest_location.map(location_json),
+                "open_streams": p.streams.len(),
+                "failover": p.failover,
+            })),
+            "cache": {
+                "objects": sub.cache.len(),
+                "bytes": sub.cache.bytes(),
+                "first_location": sub.cache.first_location().map(location_json),
+                "largest_location": sub.cache.largest_location().map(location_json),
+            },
+            "subscribers": sub.subscribers.iter().map(|s| json!({
+                "client_id": s.client_id.to_string(),
+                "request_id": s.request_id,
+                "track_alias": s.track_alias,
+                "start": s.start.map(location_json),
+                "end_group": s.end_group,
+                "open_subgroups": s.subgroups.values().filter(|d| !d.done).count(),
+                "lag_groups": s.lag_groups(&sub.cache),
+                "backlog_bytes": s.backlog(&sub.cache),
+                "groups_skipped": s.groups_skipped,
+                "publisher_gone": s.publisher_gone,
+            })).collect::<Vec<_>>(),
+        }))
+        .collect()
+}
+
+fn location_json(l: Location) -> Value {
+    json!({ "group": l.group, "object": l.object })
+}
@@ -1,12 +1,82 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use clap::Parser;
 
+use moq_relay::Config;
+
+/// Settings given here override the configuration file.
 #[derive(Parser)]
 pub(crate) struct Args {
+    /// TOML configuration file
+    #[arg(short, long)]
+    pub(crate) config: Option<PathBuf>,
+    /// Upstream relay URL; may be repeated
     #[arg(short, long)]
-    pub(crate) relay: Option<String>,
-    #[arg(short, long, default_value_t = 8080)]
-    pub(crate) port: u16,
-    /// Maximum QUIC idle timeout in milliseconds
-    #[arg(long, default_value_t = 30000)]
-    pub(crate) timeout: u64,
+    pub(crate) relay: Vec<String>,
+    /// Listen on 0.0.0.0 with this port [default: 8080]
+    #[arg(short, long, conflicts_with = "listen")]
+    pub(crate) port: Option<u16>,
+    /// Listen address, e.g. [::]:4443; may be repeated
+    #[arg(long)]
+    pub(crate) listen: Vec<SocketAddr>,
+    /// PEM certificate file, reloaded on SIGHUP
+    #[arg(long, requires = "key")]
+    pub(crate) cert: Option<PathBuf>,
+    /// PEM private key file
+    #[arg(long, requires = "cert")]
+    pub(crate) key: Option<PathBuf>,
+    /// Local address of the HTTP listener for metrics and the admin view, e.g. 127.0.0.1:9090
+    #[arg(long)]
+    pub(crate) admin: Option<SocketAddr>,
+    /// Maximum QUIC idle timeout in milliseconds [default: 30000]
+    #[arg(long)]
+    pub(crate) timeout: Option<u64>,
+    /// Maximum number of objects cached per track [default: 10000]
+    #[arg(long)]
+    pub(crate) cache_max_objects: Option<usize>,
+    /// Maximum payload bytes cached per track [default: 67108864]
+    #[arg(long)]
+    pub(crate) cache_max_bytes: Option<usize>,
+    /// Maximum age of cached objects in milliseconds; a smaller MAX_CACHE_DURATION from the publisher takes precedence [default: 10000]
+    #[arg(long)]
+    pub(crate) cache_max_duration: Option<u64>,
+    /// Payload bytes a subscriber may fall behind before it skips to the newest group [default: 4194304]
+    #[arg(long)]
+    pub(crate) max_subscriber_backlog: Option<usize>,
+}
+
+impl Args {
+    pub(crate) fn apply(&self, config: &mut Config) {
+        if !self.relay.is_empty() {
+            config.upstream = self.relay.clone();
+        }
+        if let Some(port) = self.port {
+            config.listen = vec![SocketAddr::from(([0, 0, 0, 0], port))];
+        }
+        if !self.listen.is_empty() {
+            config.listen = self.listen.clone();
+        }
+        if self.cert.is_some() {
+            config.cert = self.cert.clone();
+            config.key = self.key.clone();
+        }
+        if self.admin.is_some() {
+            config.admin = self.admin;
+        }
+        if let Some(timeout) = self.timeout {
+            config.quic.max_idle_timeout = timeout;
+        }
+        if let Some(max_objects) = self.cache_max_objects {
+            config.cache.max_objects = max_objects;
+        }
+        if let Some(max_bytes) = self.cache_max_bytes {
+            config.cache.max_bytes = max_bytes;
+        }
+        if let Some(max_duration) = self.cache_max_duration {
+            config.cache.max_duration = max_duration;
+        }
+        if let Some(max_backlog) = self.max_subscriber_backlog {
+            config.subscriber.max_backlog = max_backlog;
+        }
+    }
 }
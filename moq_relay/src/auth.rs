@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use quiche_moq::wire::{AuthToken, ErrorCode, Namespace, Parameters, REQUEST_ERROR_EXPIRED_AUTH_TOKEN, REQUEST_ERROR_MALFORMED_AUTH_TOKEN, REQUEST_ERROR_UNAUTHORIZED, Version};
+
+use crate::acl::{Action, NamespacePrefix};
+
+/// Decides whether the bearer of a token may publish or subscribe to a namespace.
+/// `token` is `None` if neither the connection nor the request carried one.
+pub(crate) trait Authorizer {
+    /// Returns the REQUEST_ERROR code to reject the request with.
+    fn authorize(&self, token: Option<&[u8]>, namespace: &Namespace, action: Action, now: SystemTime) -> Result<(), ErrorCode>;
+}
+
+/// Rights granted by a token, e.g. `{"publish":["live-alice"],"subscribe":["live"],"exp":1760000000}`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Claims {
+    /// Namespace prefixes the bearer may publish.
+    #[serde(default)]
+    pub(crate) publish: Vec<NamespacePrefix>,
+    /// Namespace prefixes the bearer may subscribe to or fetch from.
+    #[serde(default)]
+    pub(crate) subscribe: Vec<NamespacePrefix>,
+    /// Expiry in seconds since the Unix epoch; never expires if unset.
+    #[serde(default)]
+    pub(crate) exp: Option<u64>,
+}
+
+/// Verifies tokens of the form `<claims>.<signature>`: the base64url-encoded JSON [`Claims`] and
+/// their base64url-encoded HMAC-SHA256 signature, both without padding.
+/// Any of the keys may have signed a token, so keys can be rotated without invalidating tokens.
+pub(crate) struct HmacAuthorizer {
+    keys: Vec<hmac::Key>,
+}
+
+impl HmacAuthorizer {
+    pub(crate) fn new(keys: &[String]) -> Self {
+        Self { keys: keys.iter().map(|k| hmac::Key::new(hmac::HMAC_SHA256, k.as_bytes())).collect() }
+    }
+
+    /// Create a token with the first key.
+    #[cfg(test)]
+    pub(crate) fn sign(&self, claims: &Claims) -> String {
+        let claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap());
+        let tag = hmac::sign(&self.keys[0], claims.as_bytes());
+        format!("{claims}.{}", URL_SAFE_NO_PAD.encode(tag))
+    }
+
+    fn verify(&self, token: &[u8]) -> Result<Claims, ErrorCode> {
+        let token = std::str::from_utf8(token).map_err(|_| REQUEST_ERROR_MALFORMED_AUTH_TOKEN)?;
+        let (claims, signature) = token.split_once('.').ok_or(REQUEST_ERROR_MALFORMED_AUTH_TOKEN)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| REQUEST_ERROR_MALFORMED_AUTH_TOKEN)?;
+        if !self.keys.iter().any(|k| hmac::verify(k, claims.as_bytes(), &signature).is_ok()) {
+            return Err(REQUEST_ERROR_UNAUTHORIZED);
+        }
+        let claims = URL_SAFE_NO_PAD.decode(claims).map_err(|_| REQUEST_ERROR_MALFORMED_AUTH_TOKEN)?;
+        serde_json::from_slice(&claims).map_err(|_| REQUEST_ERROR_MALFORMED_AUTH_TOKEN)
+    }
+}
+
+impl Authorizer for HmacAuthorizer {
+    fn authorize(&self, token: Option<&[u8]>, namespace: &Namespace, action: Action, now: SystemTime) -> Result<(), ErrorCode> {
+        let claims = self.verify(token.ok_or(REQUEST_ERROR_UNAUTHORIZED)?)?;
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if claims.exp.is_some_and(|exp| exp <= now) {
+            return Err(REQUEST_ERROR_EXPIRED_AUTH_TOKEN);
+        }
+        let prefixes = match action {
+            Action::Publish => &claims.publish,
+            Action::Subscribe => &claims.subscribe,
+        };
+        if prefixes.iter().any(|p| p.matches(namespace)) { Ok(()) } else { Err(REQUEST_ERROR_UNAUTHORIZED) }
+    }
+}
+
+/// The `token` query parameter of `path`, e.g. `/moq?token=...`.
+pub(crate) fn query_token(path: &str) -> Option<Vec<u8>> {
+    let (_, query) = path.split_once('?')?;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(k, _)| k == "token")
+        .map(|(_, v)| v.into_owned().into_bytes())
+}
+
+/// Tokens of one connection.
+pub(crate) struct ConnTokens {
+    /// From the `token` query parameter of the WebTransport CONNECT request, or of the
+    /// PATH setup parameter for raw QUIC clients. Used by requests without a token of their own.
+    pub(crate) connection: Option<Vec<u8>>,
+    /// Registered by AUTHORIZATION_TOKEN parameters, draft 11+.
+    aliases: HashMap<u64, Vec<u8>>,
+    /// Negotiated MoQ version, which selects the token parameter.
+    version: Version,
+}
+
+impl ConnTokens {
+    /// `path` is a path with an optional query, e.g. `/moq?token=...`.
+    pub(crate) fn new(path: Option<&str>, version: Version) -> Self {
+        Self { connection: path.and_then(query_token), aliases: HashMap::new(), version }
+    }
+
+    /// Token of a request: its AUTHORIZATION_TOKEN parameter if it has one, else the connection token.
+    pub(crate) fn request_token(&mut self, parameters: &Parameters) -> Result<Option<Vec<u8>>, ErrorCode> {
+        let Some(token) = parameters.authorization_token(self.version) else {
+            return Ok(self.connection.clone());
+        };
+        match token.map_err(|_| REQUEST_ERROR_MALFORMED_AUTH_TOKEN)? {
+            AuthToken::UseValue { value, .. } => Ok(Some(value)),
+            AuthToken::Register { alias, value, .. } => {
+                self.aliases.insert(alias, value.clone());
+                Ok(Some(value))
+            }
+            AuthToken::UseAlias { alias } => self.aliases.get(&alias).cloned().map(Some).ok_or(REQUEST_ERROR_MALFORMED_AUTH_TOKEN),
+            AuthToken::Delete { alias } => {
+                self.aliases.remove(&alias);
+                Ok(self.connection.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use quiche_moq::wire::MOQ_VERSION_DRAFT_14;
+
+    fn ns(s: &str) -> Namespace {
+        s.parse().unwrap()
+    }
+
+    fn prefixes(v: &[&str]) -> Vec<NamespacePrefix> {
+        v.iter().map(|p| p.to_string().try_into().unwrap()).collect()
+    }
+
+    #[test]
+    fn hmac_token() {
+        let auth = HmacAuthorizer::new(&["new".to_string(), "old".to_string()]);
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let token = auth.sign(&Claims { publish: prefixes(&["live-alice"]), subscribe: prefixes(&["live"]), exp: Some(2000) });
+        let token = Some(token.as_bytes());
+        assert_eq!(auth.authorize(token, &ns("live-alice-cam"), Action::Publish, now), Ok(()));
+        assert_eq!(auth.authorize(token, &ns("live-bob"), Action::Publish, now), Err(REQUEST_ERROR_UNAUTHORIZED));
+        assert_eq!(auth.authorize(token, &ns("live-bob"), Action::Subscribe, now), Ok(()));
+        assert_eq!(auth.authorize(token, &ns("vod"), Action::Subscribe, now), Err(REQUEST_ERROR_UNAUTHORIZED));
+        let later = UNIX_EPOCH + Duration::from_secs(2000);
+        assert_eq!(auth.authorize(token, &ns("live"), Action::Subscribe, later), Err(REQUEST_ERROR_EXPIRED_AUTH_TOKEN));
+        assert_eq!(auth.authorize(None, &ns("live"), Action::Subscribe, now), Err(REQUEST_ERROR_UNAUTHORIZED));
+        assert_eq!(auth.authorize(Some(b"garbage"), &ns("live"), Action::Subscribe, now), Err(REQUEST_ERROR_MALFORMED_AUTH_TOKEN));
+
+        // signed by the second key, rejected once that key is removed
+        let old = HmacAuthorizer::new(&["old".to_string()]).sign(&Claims { subscribe: prefixes(&["live"]), ..Default::default() });
+        assert_eq!(auth.authorize(Some(old.as_bytes()), &ns("live"), Action::Subscribe, now), Ok(()));
+        let rotated = HmacAuthorizer::new(&["new".to_string()]);
+        assert_eq!(rotated.authorize(Some(old.as_bytes()), &ns("live"), Action::Subscribe, now), Err(REQUEST_ERROR_UNAUTHORIZED));
+    }
+
+    #[test]
+    fn token_sources() {
+        let v = MOQ_VERSION_DRAFT_14;
+        let mut tokens = ConnTokens::new(Some("/moq?x=1&token=abc%2Bd"), v);
+        assert_eq!(tokens.connection.as_deref(), Some(b"abc+d".as_slice()));
+        assert!(ConnTokens::new(Some("/moq"), v).connection.is_none());
+        assert!(ConnTokens::new(None, v).connection.is_none());
+
+        let params = |t: AuthToken| Parameters(vec![t.to_parameter(v).unwrap()]);
+        assert_eq!(tokens.request_token(&Parameters(vec![])), Ok(Some(b"abc+d".to_vec())));
+        let register = params(AuthToken::Register { alias: 1, token_type: 0, value: b"req".to_vec() });
+        assert_eq!(tokens.request_token(&register), Ok(Some(b"req".to_vec())));
+        assert_eq!(tokens.request_token(&params(AuthToken::UseAlias { alias: 1 })), Ok(Some(b"req".to_vec())));
+        assert_eq!(tokens.request_token(&params(AuthToken::Delete { alias: 1 })), Ok(Some(b"abc+d".to_vec())));
+        assert_eq!(tokens.request_token(&params(AuthToken::UseAlias { alias: 1 })), Err(REQUEST_ERROR_MALFORMED_AUTH_TOKEN));
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::time::{Duration, Instant};
+use quiche_moq::wire::control_message::FetchRange;
+use quiche_moq::wire::{KeyValuePairs, Location};
+
+/// Bounds of a [`TrackCache`]. The oldest objects are evicted first once any limit is exceeded.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CacheLimits {
+    pub(crate) max_objects: usize,
+    pub(crate) max_bytes: usize,
+    pub(crate) max_duration: Duration,
+}
+
+pub(crate) struct CachedObject {
+    pub(crate) location: Location,
+    pub(crate) subgroup_id: u64,
+    pub(crate) ext_hdrs: KeyValuePairs,
+    /// Object status for objects without payload.
+    pub(crate) status: Option<u64>,
+    /// Allocated to the full payload length; only `payload[..written]` has been received.
+    pub(crate) payload: Vec<u8>,
+    pub(crate) written: usize,
+    received: Instant,
+}
+
+impl CachedObject {
+    pub(crate) fn is_complete(&self) -> bool {
+        self.written == self.payload.len()
+    }
+}
+
+/// State of a subgroup with cached objects.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct CachedSubgroup {
+    /// The publisher finished the subgroup stream, no more objects will be added.
+    pub(crate) fin: bool,
+    /// The subgroup stream ended mid-object; its incomplete object has been dropped.
+    pub(crate) reset: bool,
+}
+
+/// Recent objects of one track in location order.
+///
+/// Objects of concurrent subgroups and groups are received in parallel, so several objects
+/// may be incomplete at a time. Subscribers read from the cache at their own pace, so a late
+/// joiner can start at the latest group instead of waiting for the next one.
+pub(crate) struct TrackCache {
+    limits: CacheLimits,
+    objects: BTreeMap<Location, CachedObject>,
+    /// Subgroups of the cached objects by group and subgroup ID.
+    subgroups: BTreeMap<(u64, u64), CachedSubgroup>,
+    /// Sum of the payload lengths of all cached objects.
+    bytes: usize,
+}
+
+impl TrackCache {
+    pub(crate) fn new(limits: CacheLimits) -> Self {
+        Self { limits, objects: BTreeMap::new(), subgroups: BTreeMap::new(), bytes: 0 }
+    }
+
+    /// Apply the publisher's MAX_CACHE_DURATION. It can only shorten the configured duration.
+    pub(crate) fn limit_duration(&mut self, max_duration: Duration) {
+        self.limits.max_duration = self.limits.max_duration.min(max_duration);
+    }
+
+    /// Start caching a new object announced by the publisher and return it to fill in its payload.
+    /// Evicts old objects first to make room. A location that is already cached
+    /// (e.g. the publisher restarted its groups) drops all complete objects.
+    pub(crate) fn push(
+        &mut self,
+        location: Location,
+        subgroup_id: u64,
+        payload_len: usize,
+        status: Option<u64>,
+        ext_hdrs: KeyValuePairs,
+        now: Instant,
+    ) -> &mut CachedObject {
+        if self.objects.contains_key(&location) {
+            self.objects.retain(|_, o| !o.is_complete());
+            self.subgroups.retain(|&(group, subgroup_id), _| {
+                self.objects.values().any(|o| o.location.group == group && o.subgroup_id == subgroup_id)
+            });
+            self.bytes = self.objects.values().map(|o| o.payload.len()).sum();
+            // an incomplete object at the same location is replaced
+            if let Some(o) = self.objects.remove(&location) {
+                self.bytes -= o.payload.len();
+            }
+        }
+        self.evict(payload_len, now);
+        self.bytes += payload_len;
+        self.subgroups.entry((location.group, subgroup_id)).or_default();
+        self.objects.insert(location, CachedObject {
+            location,
+            subgroup_id,
+            ext_hdrs,
+            status,
+            payload: vec![0; payload_len],
+            written: 0,
+            received: now,
+        });
+        self.objects.get_mut(&location).unwrap()
+    }
+
+    /// Add a complete object older than the newest one, e.g. received by a FETCH.
+    /// Skipped if it is already cached, would follow the newest object or the cache is full;
+    /// older objects never evict newer ones.
+    pub(crate) fn insert(
+        &mut self,
+        location: Location,
+        subgroup_id: u64,
+        status: Option<u64>,
+        ext_hdrs: KeyValuePairs,
+        payload: Vec<u8>,
+        now: Instant,
+    ) {
+        if self.largest_location().is_none_or(|l| location >= l)
+            || self.objects.contains_key(&location)
+            || self.objects.len() >= self.limits.max_objects
+            || self.bytes + payload.len() > self.limits.max_bytes
+        {
+            return;
+        }
+        self.bytes += payload.len();
+        // all objects of a fetched subgroup are received in one go
+        self.subgroups.entry((location.group, subgroup_id)).or_insert(CachedSubgroup { fin: true, reset: false });
+        let written = payload.len();
+        self.objects.insert(location, CachedObject { location, subgroup_id, ext_hdrs, status, payload, written, received: now });
+    }
+
+    /// Evicts the oldest complete objects, objects still being received are kept.
+    fn evict(&mut self, incoming: usize, now: Instant) {
+        let mut evicted = vec![];
+        let mut count = self.objects.len();
+        for o in self.objects.values() {
+            let full = count >= self.limits.max_objects
+                || self.bytes + incoming > self.limits.max_bytes
+                || now.duration_since(o.received) > self.limits.max_duration;
+            if !full { break; }
+            if !o.is_complete() { continue; }
+            self.bytes -= o.payload.len();
+            count -= 1;
+            evicted.push(o.location);
+        }
+        for location in evicted {
+            self.objects.remove(&location);
+        }
+        match self.first_location() {
+            Some(first) => self.subgroups.retain(|&(group, _), _| group >= first.group),
+            None => self.subgroups.clear(),
+        }
+    }
+
+    /// The newest object if it is still being received, for caches filled from a single stream.
+    pub(crate) fn live_mut(&mut self) -> Option<&mut CachedObject> {
+        self.objects.values_mut().next_back().filter(|o| !o.is_complete())
+    }
+
+    /// Drop the newest object if it is still being received, e.g. because its stream was reset.
+    pub(crate) fn remove_live(&mut self) {
+        if let Some(location) = self.live_mut().map(|o| o.location) {
+            self.remove(location);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, location: Location) {
+        if let Some(o) = self.objects.remove(&location) {
+            self.bytes -= o.payload.len();
+        }
+    }
+
+    /// No more objects will be added to the subgroup. `reset`: it ended mid-object.
+    pub(crate) fn finish_subgroup(&mut self, group: u64, subgroup_id: u64, reset: bool) {
+        if let Some(s) = self.subgroups.get_mut(&(group, subgroup_id)) {
+            s.fin = true;
+            s.reset |= reset;
+        }
+    }
+
+    pub(crate) fn subgroup(&self, group: u64, subgroup_id: u64) -> Option<&CachedSubgroup> {
+        self.subgroups.get(&(group, subgroup_id))
+    }
+
+    /// Cached subgroups of `group` and later groups, ordered by group and subgroup ID.
+    pub(crate) fn subgroups_from(&self, group: u64) -> impl Iterator<Item = ((u64, u64), &CachedSubgroup)> {
+        self.subgroups.range((group, 0)..).map(|(&k, v)| (k, v))
+    }
+
+    /// The next object of a subgroup after object ID `after`, or its first cached object.
+    pub(crate) fn next_in_subgroup(&self, group: u64, subgroup_id: u64, after: Option<u64>) -> Option<&CachedObject> {
+        let start = match after {
+            Some(object) => Excluded(Location { group, object }),
+            None => Included(Location { group, object: 0 }),
+        };
+        self.objects
+            .range((start, Included(Location { group, object: u64::MAX })))
+            .map(|(_, o)| o)
+            .find(|o| o.subgroup_id == subgroup_id)
+    }
+
+    /// Payload bytes of the objects of a subgroup after object ID `after`, or of all its cached objects.
+    pub(crate) fn subgroup_bytes_after(&self, group: u64, subgroup_id: u64, after: Option<u64>) -> usize {
+        let start = match after {
+            Some(object) => Excluded(Location { group, object }),
+            None => Included(Location { group, object: 0 }),
+        };
+        self.objects
+            .range((start, Included(Location { group, object: u64::MAX })))
+            .filter(|(_, o)| o.subgroup_id == subgroup_id)
+            .map(|(_, o)| o.payload.len())
+            .sum()
+    }
+
+    pub(crate) fn get(&self, location: Location) -> Option<&CachedObject> {
+        self.objects.get(&location)
+    }
+
+    pub(crate) fn get_mut(&mut self, location: Location) -> Option<&mut CachedObject> {
+        self.objects.get_mut(&location)
+    }
+
+    /// The next cached object after `location` in location order.
+    pub(crate) fn next_after(&self, location: Location) -> Option<&CachedObject> {
+        self.objects.range((Excluded(location), Unbounded)).next().map(|(_, o)| o)
+    }
+
+    /// The first cached object at or after `location`.
+    pub(crate) fn first_from(&self, location: Location) -> Option<&CachedObject> {
+        self.objects.range(location..).next().map(|(_, o)| o)
+    }
+
+    /// Number of cached objects, including incomplete ones.
+    pub(crate) fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Sum of the payload lengths of the cached objects.
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Location of the oldest cached object.
+    pub(crate) fn first_location(&self) -> Option<Location> {
+        self.objects.keys().next().copied()
+    }
+
+    /// Location of the last cached object within `range`.
+    pub(crate) fn last_in(&self, range: &FetchRange) -> Option<Location> {
+        self.objects
+            .range(range.start..)
+            .take_while(|(l, _)| range.contains(**l))
+            .last()
+            .map(|(l, _)| *l)
+    }
+
+    /// Location of the newest object received from the publisher.
+    pub(crate) fn largest_location(&self) -> Option<Location> {
+        self.objects.keys().next_back().copied()
+    }
+
+    /// The newest object with a payload that has been received completely.
+    pub(crate) fn latest_complete(&self) -> Option<&CachedObject> {
+        self.objects.values().rev().find(|o| o.is_complete() && !o.payload.is_empty())
+    }
+
+    /// Groups with cached subgroups, oldest first.
+    pub(crate) fn groups(&self) -> impl DoubleEndedIterator<Item = u64> {
+        self.subgroups.keys().map(|&(group, _)| group).collect::<BTreeSet<_>>().into_iter()
+    }
+
+    /// Cached objects of `group` in object order.
+    pub(crate) fn group(&self, group: u64) -> impl Iterator<Item = &CachedObject> {
+        self.objects.range(Location { group, object: 0 }..=Location { group, object: u64::MAX }).map(|(_, o)| o)
+    }
+
+    /// The publisher finished all cached subgroups of `group`.
+    pub(crate) fn is_group_finished(&self, group: u64) -> bool {
+        let mut subgroups = self.subgroups.range((group, 0)..=(group, u64::MAX)).peekable();
+        subgroups.peek().is_some() && subgroups.all(|(_, s)| s.fin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: CacheLimits = CacheLimits {
+        max_objects: 4,
+        max_bytes: 100,
+        max_duration: Duration::from_secs(10),
+    };
+
+    fn loc(group: u64, object: u64) -> Location {
+        Location { group, object }
+    }
+
+    fn push_complete(cache: &mut TrackCache, location: Location, len: usize, now: Instant) {
+        let o = cache.push(location, 0, len, None, KeyValuePairs::new(), now);
+        o.written = len;
+    }
+
+    #[test]
+    fn concurrent_subgroups() {
+        let now = Instant::now();
+        let mut cache = TrackCache::new(LIMITS);
+        assert!(cache.largest_location().is_none());
+        cache.push(loc(1, 0), 0, 10, None, KeyValuePairs::new(), now);
+        cache.push(loc(1, 1), 1, 10, None, KeyValuePairs::new(), now);
+        cache.push(loc(1, 2), 0, 10, None, KeyValuePairs::new(), now);
+        assert_eq!(cache.next_in_subgroup(1, 0, None).unwrap().location, loc(1, 0));
+        assert_eq!(cache.next_in_subgroup(1, 0, Some(0)).unwrap().location, loc(1, 2));
+        assert_eq!(cache.next_in_subgroup(1, 1, None).unwrap().location, loc(1, 1));
+        assert!(cache.next_in_subgroup(1, 1, Some(1)).is_none());
+        assert_eq!(cache.subgroup_bytes_after(1, 0, None), 20);
+        assert_eq!(cache.subgroup_bytes_after(1, 0, Some(0)), 10);
+        assert_eq!(cache.subgroup_bytes_after(1, 1, Some(1)), 0);
+        let subgroups: Vec<_> = cache.subgroups_from(1).map(|(k, _)| k).collect();
+        assert_eq!(subgroups, [(1, 0), (1, 1)]);
+        assert!(cache.subgroups_from(2).next().is_none());
+
+        cache.finish_subgroup(1, 1, true);
+        assert!(cache.subgroup(1, 1).unwrap().reset);
+        assert!(!cache.subgroup(1, 0).unwrap().fin);
+        assert_eq!(cache.group(1).map(|o| o.location.object).collect::<Vec<_>>(), [0, 1, 2]);
+        assert!(!cache.is_group_finished(1));
+        cache.finish_subgroup(1, 0, false);
+        assert!(cache.is_group_finished(1));
+        assert!(!cache.is_group_finished(2));
+
+        // incomplete objects are not evicted
+        push_complete(&mut cache, loc(2, 0), 1, now);
+        push_complete(&mut cache, loc(2, 1), 1, now);
+        assert!(cache.get(loc(1, 0)).is_some());
+        cache.get_mut(loc(1, 0)).unwrap().written = 10;
+        push_complete(&mut cache, loc(2, 2), 1, now);
+        assert!(cache.get(loc(1, 0)).is_none());
+        assert!(cache.get(loc(1, 1)).is_some());
+        assert_eq!(cache.groups().collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn evicts_by_count_bytes_and_duration() {
+        let now = Instant::now();
+        let mut cache = TrackCache::new(LIMITS);
+        for i in 0..5 {
+            push_complete(&mut cache, loc(0, i), 1, now);
+        }
+        assert!(cache.get(loc(0, 0)).is_none());
+        assert!(cache.get(loc(0, 1)).is_some());
+
+        push_complete(&mut cache, loc(1, 0), 98, now);
+        assert!(cache.get(loc(0, 2)).is_none());
+        assert!(cache.get(loc(0, 4)).is_some());
+
+        cache.limit_duration(Duration::from_secs(1));
+        push_complete(&mut cache, loc(2, 0), 0, now + Duration::from_secs(2));
+        assert_eq!(cache.next_after(loc(0, 0)).unwrap().location, loc(2, 0));
+        assert!(cache.subgroup(0, 0).is_none());
+    }
+
+    #[test]
+    fn fetch_range_and_insert() {
+        let now = Instant::now();
+        let mut cache = TrackCache::new(LIMITS);
+        push_complete(&mut cache, loc(2, 0), 1, now);
+        push_complete(&mut cache, loc(2, 1), 1, now);
+        let range = FetchRange { start: loc(1, 0), end: loc(2, 1) };
+        assert_eq!(cache.first_from(range.start).unwrap().location, loc(2, 0));
+        assert_eq!(cache.last_in(&range), Some(loc(2, 0)));
+        assert_eq!(cache.last_in(&FetchRange { start: loc(0, 0), end: loc(1, 0) }), None);
+
+        cache.insert(loc(1, 1), 0, None, KeyValuePairs::new(), vec![1], now);
+        cache.insert(loc(1, 0), 0, None, KeyValuePairs::new(), vec![0], now);
+        cache.insert(loc(3, 0), 0, None, KeyValuePairs::new(), vec![3], now);
+        assert_eq!(cache.first_location(), Some(loc(1, 0)));
+        assert_eq!(cache.next_after(loc(1, 0)).unwrap().location, loc(1, 1));
+        assert_eq!(cache.largest_location(), Some(loc(2, 1)));
+        assert!(cache.get(loc(1, 1)).unwrap().is_complete());
+        assert!(cache.subgroup(1, 0).unwrap().fin);
+
+        // full, older objects are not added
+        cache.insert(loc(0, 0), 0, None, KeyValuePairs::new(), vec![0], now);
+        assert_eq!(cache.first_location(), Some(loc(1, 0)));
+    }
+
+    #[test]
+    fn remove_live_and_restart() {
+        let now = Instant::now();
+        let mut cache = TrackCache::new(LIMITS);
+        push_complete(&mut cache, loc(3, 0), 1, now);
+        cache.push(loc(3, 1), 0, 10, None, KeyValuePairs::new(), now);
+        assert_eq!(cache.latest_complete().unwrap().location, loc(3, 0));
+        cache.remove_live();
+        assert_eq!(cache.largest_location(), Some(loc(3, 0)));
+        cache.remove_live();
+        assert_eq!(cache.largest_location(), Some(loc(3, 0)));
+
+        cache.push(loc(4, 0), 1, 10, None, KeyValuePairs::new(), now);
+        push_complete(&mut cache, loc(3, 0), 1, now);
+        assert_eq!(cache.first_location(), Some(loc(3, 0)));
+        assert!(cache.get(loc(4, 0)).is_some());
+        assert!(cache.subgroup(4, 1).is_some());
+    }
+}
@@ -0,0 +1,164 @@
+//! Just enough of ISO BMFF (ISO/IEC 14496-12) to time the CMAF fragments of a track for the
+//! playlists of [`crate::packaging`]: the timescale from the init segment, and the decode time
+//! and duration of the fragments of an object.
+
+use octets::Octets;
+
+/// Sample timing of a CMAF track, from its init segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Timing {
+    /// Ticks per second of the decode times and durations.
+    pub(crate) timescale: u32,
+    /// `trex` default, for fragments that give neither sample durations nor a `tfhd` default.
+    default_sample_duration: u32,
+}
+
+impl Timing {
+    /// Timing of the first track of `init`, an init segment with `moov`.
+    pub(crate) fn from_init(init: &[u8]) -> Option<Self> {
+        let moov = child(init, b"moov")?;
+        let mdhd = child(child(child(moov, b"trak")?, b"mdia")?, b"mdhd")?;
+        let mut b = Octets::with_slice(mdhd);
+        let version = b.get_u8().ok()?;
+        b.skip(3 + if version == 1 { 16 } else { 8 }).ok()?;
+        let timescale = b.get_u32().ok().filter(|&t| t > 0)?;
+        let default_sample_duration = child(moov, b"mvex")
+            .and_then(|mvex| child(mvex, b"trex"))
+            .and_then(|trex| {
+                let mut b = Octets::with_slice(trex);
+                b.skip(12).ok()?;
+                b.get_u32().ok()
+            })
+            .unwrap_or(0);
+        Some(Self { timescale, default_sample_duration })
+    }
+
+    /// Decode time of the first sample and the total duration of the `moof` boxes of `chunk`,
+    /// e.g. an object holding one or more CMAF chunks, in ticks of the timescale.
+    pub(crate) fn fragments(&self, chunk: &[u8]) -> Option<(u64, u64)> {
+        let mut start = None;
+        let mut duration = 0;
+        for (kind, moof) in boxes(chunk) {
+            if kind != b"moof" { continue; }
+            let traf = child(moof, b"traf")?;
+            let decode_time = child(traf, b"tfdt").and_then(|tfdt| {
+                let mut b = Octets::with_slice(tfdt);
+                let version = b.get_u8().ok()?;
+                b.skip(3).ok()?;
+                if version == 1 { b.get_u64().ok() } else { b.get_u32().ok().map(u64::from) }
+            })?;
+            start.get_or_insert(decode_time);
+            duration += self.traf_duration(traf)?;
+        }
+        Some((start?, duration))
+    }
+
+    /// Sum of the sample durations of the `trun` boxes of `traf`.
+    fn traf_duration(&self, traf: &[u8]) -> Option<u64> {
+        let mut b = Octets::with_slice(child(traf, b"tfhd")?);
+        let flags = b.get_u32().ok()? & 0xffffff;
+        b.skip(4).ok()?;
+        if flags & 0x01 != 0 { b.skip(8).ok()?; }
+        if flags & 0x02 != 0 { b.skip(4).ok()?; }
+        let default_duration = if flags & 0x08 != 0 { b.get_u32().ok()? } else { self.default_sample_duration };
+
+        let mut duration = 0;
+        for (kind, trun) in boxes(traf) {
+            if kind != b"trun" { continue; }
+            let mut b = Octets::with_slice(trun);
+            let flags = b.get_u32().ok()? & 0xffffff;
+            let sample_count = b.get_u32().ok()?;
+            if flags & 0x001 != 0 { b.skip(4).ok()?; }
+            if flags & 0x004 != 0 { b.skip(4).ok()?; }
+            if flags & 0x100 == 0 {
+                duration += u64::from(sample_count) * u64::from(default_duration);
+                continue;
+            }
+            let sample_fields = [0x200, 0x400, 0x800].iter().filter(|&&f| flags & f != 0).count();
+            for _ in 0..sample_count {
+                duration += u64::from(b.get_u32().ok()?);
+                b.skip(4 * sample_fields).ok()?;
+            }
+        }
+        Some(duration)
+    }
+}
+
+/// Type and body of the boxes in `buf`, up to the first truncated one.
+fn boxes(buf: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = buf;
+    std::iter::from_fn(move || {
+        let mut b = Octets::with_slice(rest);
+        let size = b.get_u32().ok()? as usize;
+        b.skip(4).ok()?;
+        let kind = &rest[4..8];
+        let (header, size) = match size {
+            0 => (8, rest.len()),
+            1 => (16, usize::try_from(b.get_u64().ok()?).ok()?),
+            size => (8, size),
+        };
+        if size < header || size > rest.len() {
+            return None;
+        }
+        let body = &rest[header..size];
+        rest = &rest[size..];
+        Some((kind, body))
+    })
+}
+
+/// Body of the first child box of type `kind`.
+fn child<'a>(buf: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(buf).find(|(k, _)| *k == kind).map(|(_, body)| body)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn bmff_box(kind: &[u8; 4], body: &[&[u8]]) -> Vec<u8> {
+        let body = body.concat();
+        [&(8 + body.len() as u32).to_be_bytes()[..], &kind[..], &body[..]].concat()
+    }
+
+    /// Init segment with one track of `timescale`.
+    pub(crate) fn init(timescale: u32) -> Vec<u8> {
+        let mdhd = bmff_box(b"mdhd", &[&[0; 12], &timescale.to_be_bytes(), &[0; 8]]);
+        let trex = bmff_box(b"trex", &[&[0; 4], &1u32.to_be_bytes(), &1u32.to_be_bytes(), &0u32.to_be_bytes(), &[0; 8]]);
+        [
+            bmff_box(b"ftyp", &[b"cmfc", &[0; 4]]),
+            bmff_box(b"moov", &[
+                &bmff_box(b"trak", &[&bmff_box(b"mdia", &[&mdhd])]),
+                &bmff_box(b"mvex", &[&trex]),
+            ]),
+        ].concat()
+    }
+
+    /// Fragment of `samples` samples of `sample_duration` ticks each, starting at `decode_time`.
+    pub(crate) fn fragment(decode_time: u64, samples: u32, sample_duration: u32) -> Vec<u8> {
+        let tfhd = bmff_box(b"tfhd", &[&0x020008u32.to_be_bytes(), &1u32.to_be_bytes(), &sample_duration.to_be_bytes()]);
+        let tfdt = bmff_box(b"tfdt", &[&0x01000000u32.to_be_bytes(), &decode_time.to_be_bytes()]);
+        let trun = bmff_box(b"trun", &[&0x000001u32.to_be_bytes(), &samples.to_be_bytes(), &0u32.to_be_bytes()]);
+        [
+            bmff_box(b"moof", &[&bmff_box(b"traf", &[&tfhd, &tfdt, &trun])]),
+            bmff_box(b"mdat", &[&vec![0; samples as usize]]),
+        ].concat()
+    }
+
+    #[test]
+    fn timing() {
+        let timing = Timing::from_init(&init(90000)).unwrap();
+        assert_eq!(timing, Timing { timescale: 90000, default_sample_duration: 0 });
+        assert_eq!(timing.fragments(&fragment(180000, 30, 3000)), Some((180000, 90000)));
+        // two chunks in one object
+        let chunks = [fragment(0, 15, 3000), fragment(45000, 15, 3000)].concat();
+        assert_eq!(timing.fragments(&chunks), Some((0, 90000)));
+        // sample durations in trun
+        let trun = bmff_box(b"trun", &[&0x000100u32.to_be_bytes(), &2u32.to_be_bytes(), &1000u32.to_be_bytes(), &2000u32.to_be_bytes()]);
+        let tfhd = bmff_box(b"tfhd", &[&0u32.to_be_bytes(), &1u32.to_be_bytes()]);
+        let tfdt = bmff_box(b"tfdt", &[&0u32.to_be_bytes(), &7u32.to_be_bytes()]);
+        let moof = bmff_box(b"moof", &[&bmff_box(b"traf", &[&tfhd, &tfdt, &trun])]);
+        assert_eq!(timing.fragments(&moof), Some((7, 3000)));
+        assert_eq!(timing.fragments(b"not a fragment"), None);
+        assert_eq!(Timing::from_init(b"\0\0\0\x08free"), None);
+    }
+}
@@ -0,0 +1,417 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use url::Url;
+use quiche_mio_runner::quiche_endpoint::quiche;
+use quiche_moq as moq;
+use quiche_moq::wire::{RequestId, SUPPORTED_MOQ_VERSIONS, Version};
+
+use crate::acl::{Acl, AclRule, NamespacePrefix};
+use crate::cache::CacheLimits;
+use crate::dvr::Dvr;
+use crate::packaging::Packager;
+
+/// Relay configuration read from a TOML file, e.g.
+///
+/// ```toml
+/// listen = ["0.0.0.0:4443", "[::]:4443"]
+/// cert = "/etc/moq-relay/cert.pem"
+/// key = "/etc/moq-relay/key.pem"
+/// upstream = ["https://origin.example.org:4443"]
+/// admin = "127.0.0.1:9090"
+///
+/// [quic]
+/// max_idle_timeout = 10000
+///
+/// [moq]
+/// versions = [14, 16]
+///
+/// [[acl]]
+/// namespace = "live"
+/// publish = ["10.0.0.0/8"]
+///
+/// [auth]
+/// hmac_keys = ["change me"]
+///
+/// [limits]
+/// sessions_per_ip = 10
+///
+/// [drain]
+/// grace_period = 30
+/// redirect = "https://relay2.example.org:4443"
+///
+/// [dvr]
+/// dir = "/var/lib/moq-relay/dvr"
+/// namespaces = ["live"]
+///
+/// [packaging]
+/// namespaces = ["live"]
+/// ```
+///
+/// Every setting is optional; command line arguments override the file.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// UDP addresses to listen on.
+    pub listen: Vec<SocketAddr>,
+    /// PEM certificate; reloaded on SIGHUP. A self-signed certificate is generated if unset.
+    pub cert: Option<PathBuf>,
+    /// PEM private key of `cert`.
+    pub key: Option<PathBuf>,
+    /// Relays to pull from; see `--relay`.
+    pub upstream: Vec<String>,
+    /// Local address of the HTTP listener for metrics and the admin view; disabled if unset.
+    pub admin: Option<SocketAddr>,
+    pub quic: QuicConfig,
+    pub moq: MoqConfig,
+    pub cache: CacheConfig,
+    pub subscriber: SubscriberConfig,
+    /// Access rules; only settable through the configuration file.
+    pub(crate) acl: Vec<AclRule>,
+    pub auth: AuthConfig,
+    pub dvr: DvrConfig,
+    pub limits: LimitsConfig,
+    pub drain: DrainConfig,
+    pub packaging: PackagingConfig,
+}
+
+/// QUIC transport limits for all connections.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuicConfig {
+    /// Maximum idle timeout in milliseconds.
+    pub max_idle_timeout: u64,
+    pub initial_max_data: u64,
+    pub initial_max_stream_data: u64,
+    pub initial_max_streams_bidi: u64,
+    pub initial_max_streams_uni: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MoqConfig {
+    /// Supported draft numbers; the newest one is offered first. Every draft by default,
+    /// the relay forwards between sessions of different drafts.
+    pub versions: Vec<u64>,
+    /// MAX_REQUEST_ID granted to each peer.
+    pub max_request_id: RequestId,
+}
+
+/// Bounds of each track's cache, see [`CacheLimits`].
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub max_objects: usize,
+    pub max_bytes: usize,
+    /// Milliseconds; a smaller MAX_CACHE_DURATION from the publisher takes precedence.
+    pub max_duration: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubscriberConfig {
+    /// Payload bytes a subscriber may fall behind the publisher before it skips to the newest group.
+    pub max_backlog: usize,
+}
+
+/// Token-based authorization, see [`HmacAuthorizer`](crate::auth::HmacAuthorizer).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Keys that sign tokens; authorization is disabled if empty.
+    pub hmac_keys: Vec<String>,
+}
+
+/// Recording of tracks to disk, see [`Dvr`].
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DvrConfig {
+    /// Directory of the recordings; nothing is recorded if unset.
+    pub dir: Option<PathBuf>,
+    /// Namespace prefixes whose tracks are recorded, e.g. `live`.
+    pub namespaces: Vec<String>,
+    /// Seconds a recording is kept.
+    pub max_age: u64,
+    /// Bytes kept of each track's recording.
+    pub max_bytes: u64,
+}
+
+/// Quotas of each client session, so that one client cannot exhaust the relay.
+/// Upstream relays are exempt. Bytes buffered for a subscription are bounded by
+/// `[cache] max_bytes` and `[subscriber] max_backlog`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Sessions from one IP address; further connections are refused.
+    pub sessions_per_ip: usize,
+    /// Subscriptions of one session; further SUBSCRIBEs are held until one ends.
+    pub subscriptions_per_session: usize,
+    /// Namespaces published by one session; further PUBLISH_NAMESPACEs are held until one ends.
+    pub namespaces_per_session: usize,
+}
+
+/// Graceful shutdown, see [`Relay::drain`](crate::Relay::drain).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrainConfig {
+    /// Seconds the clients have to move to another relay after GOAWAY.
+    pub grace_period: u64,
+    /// New session URI sent with GOAWAY, e.g. another relay; clients reconnect to the same URI if unset.
+    pub redirect: Option<String>,
+}
+
+/// LL-HLS and DASH for the CMAF tracks of catalogued broadcasts, see [`Packager`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PackagingConfig {
+    /// Namespace prefixes whose broadcasts are packaged, e.g. `live`; nothing is packaged if empty.
+    pub namespaces: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen: vec![SocketAddr::from(([0, 0, 0, 0], 8080))],
+            cert: None,
+            key: None,
+            upstream: vec![],
+            admin: None,
+            quic: QuicConfig::default(),
+            moq: MoqConfig::default(),
+            cache: CacheConfig::default(),
+            subscriber: SubscriberConfig::default(),
+            acl: vec![],
+            auth: AuthConfig::default(),
+            dvr: DvrConfig::default(),
+            limits: LimitsConfig::default(),
+            drain: DrainConfig::default(),
+            packaging: PackagingConfig::default(),
+        }
+    }
+}
+
+impl Default for QuicConfig {
+    fn default() -> Self {
+        // the values of MoqWebTransportHelper::configure_quic
+        Self {
+            max_idle_timeout: 30000,
+            initial_max_data: 10_000_000,
+            initial_max_stream_data: 1_000_000,
+            initial_max_streams_bidi: 100,
+            initial_max_streams_uni: 100,
+        }
+    }
+}
+
+impl Default for MoqConfig {
+    fn default() -> Self {
+        let default = moq::Config::default();
+        Self {
+            versions: default.supported_versions.iter()
+                .filter(|&&v| v & !0xff == 0xff000000)
+                .map(|v| v & 0xff)
+                .collect(),
+            max_request_id: default.max_request_id,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_objects: 10000,
+            max_bytes: 64 * 1024 * 1024,
+            max_duration: 10000,
+        }
+    }
+}
+
+impl Default for DvrConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            namespaces: vec![],
+            max_age: 24 * 60 * 60,
+            max_bytes: 10 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            sessions_per_ip: 100,
+            subscriptions_per_session: 1000,
+            namespaces_per_session: 100,
+        }
+    }
+}
+
+impl Default for DrainConfig {
+    fn default() -> Self {
+        Self { grace_period: 30, redirect: None }
+    }
+}
+
+impl Default for SubscriberConfig {
+    fn default() -> Self {
+        Self { max_backlog: 4 * 1024 * 1024 }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let s = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let config: Self = toml::from_str(&s).map_err(|e| format!("{}: {e}", path.display()))?;
+        config.moq.to_moq()?;
+        config.dvr()?;
+        config.packager()?;
+        if let Some(redirect) = &config.drain.redirect {
+            Url::parse(redirect).map_err(|e| format!("drain redirect {redirect}: {e}"))?;
+        }
+        Ok(config)
+    }
+
+    pub(crate) fn cache_limits(&self) -> CacheLimits {
+        CacheLimits {
+            max_objects: self.cache.max_objects,
+            max_bytes: self.cache.max_bytes,
+            max_duration: Duration::from_millis(self.cache.max_duration),
+        }
+    }
+
+    pub(crate) fn acl(&self) -> Acl {
+        Acl(self.acl.clone())
+    }
+
+    pub(crate) fn dvr(&self) -> Result<Option<Dvr>, String> {
+        let Some(dir) = &self.dvr.dir else { return Ok(None) };
+        let namespaces = self.dvr.namespaces.iter()
+            .map(|ns| NamespacePrefix::try_from(ns.clone()).map_err(|e| format!("dvr namespace {ns}: {e}")))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Dvr::new(dir.clone(), namespaces, Duration::from_secs(self.dvr.max_age), self.dvr.max_bytes)))
+    }
+
+    pub(crate) fn packager(&self) -> Result<Option<Packager>, String> {
+        if self.packaging.namespaces.is_empty() {
+            return Ok(None);
+        }
+        let namespaces = self.packaging.namespaces.iter()
+            .map(|ns| NamespacePrefix::try_from(ns.clone()).map_err(|e| format!("packaging namespace {ns}: {e}")))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Packager::new(namespaces)))
+    }
+}
+
+impl QuicConfig {
+    /// Apply on top of `MoqWebTransportHelper::configure_quic`.
+    pub(crate) fn apply(&self, c: &mut quiche::Config) {
+        c.set_max_idle_timeout(self.max_idle_timeout);
+        c.set_initial_max_data(self.initial_max_data);
+        c.set_initial_max_stream_data_bidi_local(self.initial_max_stream_data);
+        c.set_initial_max_stream_data_bidi_remote(self.initial_max_stream_data);
+        c.set_initial_max_stream_data_uni(self.initial_max_stream_data);
+        c.set_initial_max_streams_bidi(self.initial_max_streams_bidi);
+        c.set_initial_max_streams_uni(self.initial_max_streams_uni);
+    }
+}
+
+impl MoqConfig {
+    pub(crate) fn to_moq(&self) -> Result<moq::Config, String> {
+        let mut supported_versions = vec![];
+        for &draft in &self.versions {
+            let version: Version = 0xff000000 | draft;
+            if !SUPPORTED_MOQ_VERSIONS.contains(&version) {
+                return Err(format!("unsupported draft {draft}"));
+            }
+            supported_versions.push(version);
+        }
+        let Some(&setup_version) = supported_versions.iter().max() else {
+            return Err("no MoQ version configured".to_string());
+        };
+        Ok(moq::Config {
+            setup_version,
+            supported_versions,
+            max_request_id: self.max_request_id,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quiche_moq::wire::{MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16};
+
+    #[test]
+    fn parse() {
+        let config: Config = toml::from_str(r#"
+            listen = ["127.0.0.1:4443", "[::1]:4443"]
+            upstream = ["https://origin.example.org:4443"]
+            admin = "127.0.0.1:9090"
+
+            [quic]
+            max_idle_timeout = 5000
+
+            [moq]
+            versions = [14, 16]
+
+            [cache]
+            max_objects = 10
+
+            [subscriber]
+            max_backlog = 1000
+
+            [[acl]]
+            namespace = "live"
+            publish = ["10.0.0.0/8"]
+
+            [auth]
+            hmac_keys = ["new", "old"]
+
+            [dvr]
+            dir = "/tmp/dvr"
+            namespaces = ["live"]
+            max_age = 60
+
+            [limits]
+            sessions_per_ip = 2
+
+            [drain]
+            redirect = "https://relay2.example.org:4443"
+
+            [packaging]
+            namespaces = ["live"]
+        "#).unwrap();
+        assert_eq!(config.listen.len(), 2);
+        assert!(config.listen[1].is_ipv6());
+        assert_eq!(config.admin, Some(SocketAddr::from(([127, 0, 0, 1], 9090))));
+        assert_eq!(config.quic.max_idle_timeout, 5000);
+        assert_eq!(config.quic.initial_max_streams_uni, 100);
+        let moq = config.moq.to_moq().unwrap();
+        assert_eq!(moq.setup_version, MOQ_VERSION_DRAFT_16);
+        assert_eq!(moq.supported_versions, [MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16]);
+        assert_eq!(config.cache_limits().max_objects, 10);
+        assert_eq!(config.cache_limits().max_bytes, 64 * 1024 * 1024);
+        assert_eq!(config.subscriber.max_backlog, 1000);
+        assert_eq!(config.acl.len(), 1);
+        assert_eq!(config.auth.hmac_keys, ["new", "old"]);
+        let dvr = config.dvr().unwrap().unwrap();
+        assert!(dvr.records(&"live-cam".parse().unwrap()));
+        assert_eq!(config.dvr.max_bytes, 10 * 1024 * 1024 * 1024);
+        assert!(Config::default().dvr().unwrap().is_none());
+        assert_eq!(config.limits.sessions_per_ip, 2);
+        assert_eq!(config.limits.subscriptions_per_session, 1000);
+        assert_eq!(config.drain.grace_period, 30);
+        assert_eq!(config.drain.redirect.as_deref(), Some("https://relay2.example.org:4443"));
+        assert!(config.packager().unwrap().unwrap().packages(&"live-cam".parse().unwrap()));
+        assert!(Config::default().packager().unwrap().is_none());
+
+        assert!(toml::from_str::<Config>("port = 1").is_err());
+        let config: Config = toml::from_str("[moq]\nversions = [6]").unwrap();
+        assert!(config.moq.to_moq().is_err());
+        let moq = Config::default().moq.to_moq().unwrap();
+        assert_eq!(moq.supported_versions, (MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_16).collect::<Vec<_>>());
+    }
+}
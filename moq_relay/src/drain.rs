@@ -0,0 +1,50 @@
+//! Graceful shutdown: the relay stops accepting sessions, asks its clients to move with GOAWAY
+//! and closes the sessions after a grace period, see [`Relay::drain`](crate::Relay::drain).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use log::error;
+
+/// Between closing the MoQ sessions and closing the connections, so the peers receive the
+/// last PUBLISH_DONE and the termination code.
+const CLOSE_DELAY: Duration = Duration::from_secs(1);
+
+/// Set by SIGTERM.
+static SIGTERM: LazyLock<Arc<AtomicBool>> = LazyLock::new(Default::default);
+
+/// Set the flag of [`sigterm_received`] on SIGTERM instead of terminating.
+pub(crate) fn handle_sigterm() {
+    if let Err(e) = signal_hook::flag::register(signal_hook::consts::SIGTERM, SIGTERM.clone()) {
+        error!("failed to handle SIGTERM: {e}");
+    }
+}
+
+/// SIGTERM arrived since the last call.
+pub(crate) fn sigterm_received() -> bool {
+    SIGTERM.swap(false, Ordering::Relaxed)
+}
+
+/// Progress of draining the relay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Drain {
+    /// GOAWAY was sent; the clients may move until the deadline.
+    Grace { deadline: Instant },
+    /// The remaining subscribers got PUBLISH_DONE and the sessions were closed at this instant.
+    Closing { since: Instant },
+}
+
+impl Drain {
+    /// Time until the next step.
+    pub(crate) fn timeout(&self, now: Instant) -> Duration {
+        match *self {
+            Drain::Grace { deadline } => deadline.saturating_duration_since(now),
+            Drain::Closing { since } => (since + CLOSE_DELAY).saturating_duration_since(now),
+        }
+    }
+
+    /// The connections can be closed.
+    pub(crate) fn is_done(&self, now: Instant) -> bool {
+        matches!(*self, Drain::Closing { since } if now >= since + CLOSE_DELAY)
+    }
+}
@@ -0,0 +1,380 @@
+//! Recording of tracks to disk for time-shifted playback.
+//!
+//! Each recorded track has its own directory, named after the hex-encoded track name, with
+//! numbered append-only segments. A segment `<n>.seg` holds one record per complete object:
+//!
+//! ```text
+//! group (64) | subgroup ID (64) | object ID (64) | object status + 1, 0 if none (64)
+//! | extension header count (32) | extension headers length (32) | payload length (32)
+//! | extension headers | payload
+//! ```
+//!
+//! Its index `<n>.idx` holds group (64), object ID (64) and offset (64) of every record, appended
+//! after the record. Integers are big-endian; extension headers are encoded as in draft 14.
+//! A new segment is started when the relay restarts and at the first group boundary after the
+//! segment reached [`SEGMENT_BYTES`] or [`SEGMENT_DURATION`]. Retention deletes whole segments.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use log::error;
+use octets::{Octets, OctetsMut};
+use quiche_moq::wire::control_message::FetchRange;
+use quiche_moq::wire::{FromBytes, KeyValuePairs, Location, MOQ_VERSION_DRAFT_14, Namespace, NamespaceTrackname, ToBytes, Version};
+
+use crate::acl::NamespacePrefix;
+use crate::cache::{CacheLimits, CachedObject, TrackCache};
+
+const SEGMENT_BYTES: u64 = 16 * 1024 * 1024;
+const SEGMENT_DURATION: Duration = Duration::from_secs(60);
+/// Version whose encoding of extension headers is stored; unlike later versions it keeps their order.
+const EXT_HDRS_VERSION: Version = MOQ_VERSION_DRAFT_14;
+const RECORD_HEADER_LEN: usize = 4 * 8 + 3 * 4;
+const INDEX_ENTRY_LEN: usize = 3 * 8;
+
+/// Which tracks are recorded where, and for how long.
+pub(crate) struct Dvr {
+    dir: PathBuf,
+    namespaces: Vec<NamespacePrefix>,
+    max_age: Duration,
+    /// Per track.
+    max_bytes: u64,
+}
+
+impl Dvr {
+    pub(crate) fn new(dir: PathBuf, namespaces: Vec<NamespacePrefix>, max_age: Duration, max_bytes: u64) -> Self {
+        Self { dir, namespaces, max_age, max_bytes }
+    }
+
+    pub(crate) fn records(&self, namespace: &Namespace) -> bool {
+        self.namespaces.iter().any(|p| p.matches(namespace))
+    }
+
+    /// Open the recording of a track, with the segments left by earlier runs.
+    pub(crate) fn open(&self, nt: &NamespaceTrackname) -> io::Result<TrackArchive> {
+        let name: String = nt.to_string().bytes().map(|b| format!("{b:02x}")).collect();
+        let dir = self.dir.join(name);
+        fs::create_dir_all(&dir)?;
+        let mut archive = TrackArchive {
+            dir,
+            max_age: self.max_age,
+            max_bytes: self.max_bytes,
+            segments: BTreeMap::new(),
+            index: BTreeMap::new(),
+            writer: None,
+        };
+        for entry in fs::read_dir(&archive.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "seg") { continue; }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) else { continue };
+            let meta = fs::metadata(&path)?;
+            // an index entry is written after its record, so every indexed record is complete
+            let index = fs::read(path.with_extension("idx")).unwrap_or_default();
+            for entry in index.chunks_exact(INDEX_ENTRY_LEN) {
+                let mut b = Octets::with_slice(entry);
+                let location = Location { group: b.get_u64().unwrap(), object: b.get_u64().unwrap() };
+                archive.index.insert(location, (id, b.get_u64().unwrap()));
+            }
+            archive.segments.insert(id, Segment { bytes: meta.len(), modified: meta.modified()? });
+        }
+        archive.enforce_retention(SystemTime::now());
+        Ok(archive)
+    }
+}
+
+struct Segment {
+    bytes: u64,
+    /// When the last record was written.
+    modified: SystemTime,
+}
+
+/// The segment being appended to.
+struct Writer {
+    id: u64,
+    segment: File,
+    index: File,
+    started: SystemTime,
+    /// Newest group recorded in the segment.
+    last_group: u64,
+}
+
+/// Recording of one track.
+pub(crate) struct TrackArchive {
+    dir: PathBuf,
+    max_age: Duration,
+    max_bytes: u64,
+    /// Segments by number.
+    segments: BTreeMap<u64, Segment>,
+    /// Segment and offset of the record of every recorded object.
+    index: BTreeMap<Location, (u64, u64)>,
+    /// Opened with the first object recorded since the relay started.
+    writer: Option<Writer>,
+}
+
+impl TrackArchive {
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append a complete object. A location that is already recorded (e.g. the publisher
+    /// restarted its groups) deletes the recording first, like [`TrackCache::push`].
+    pub(crate) fn record(&mut self, o: &CachedObject, now: SystemTime) -> io::Result<()> {
+        if self.index.contains_key(&o.location) {
+            for id in self.segments.keys().copied().collect::<Vec<_>>() {
+                self.remove_segment(id);
+            }
+            self.writer = None;
+        }
+        let rotate = self.writer.as_ref().is_none_or(|w| {
+            o.location.group > w.last_group
+                && (self.segments[&w.id].bytes >= SEGMENT_BYTES || now.duration_since(w.started).unwrap_or_default() >= SEGMENT_DURATION)
+        });
+        if rotate {
+            self.start_segment(o.location.group, now)?;
+            self.enforce_retention(now);
+        }
+
+        let ext_len = o.ext_hdrs.byte_length(EXT_HDRS_VERSION);
+        let mut record = vec![0; RECORD_HEADER_LEN + ext_len + o.payload.len()];
+        let mut b = OctetsMut::with_slice(&mut record);
+        b.put_u64(o.location.group).unwrap();
+        b.put_u64(o.subgroup_id).unwrap();
+        b.put_u64(o.location.object).unwrap();
+        b.put_u64(o.status.map_or(0, |s| s + 1)).unwrap();
+        b.put_u32(o.ext_hdrs.len() as u32).unwrap();
+        b.put_u32(ext_len as u32).unwrap();
+        b.put_u32(o.payload.len() as u32).unwrap();
+        o.ext_hdrs.to_bytes(&mut b, EXT_HDRS_VERSION).unwrap();
+        b.put_bytes(&o.payload).unwrap();
+
+        let w = self.writer.as_mut().unwrap();
+        let segment = self.segments.get_mut(&w.id).unwrap();
+        let offset = segment.bytes;
+        let mut entry = [0; INDEX_ENTRY_LEN];
+        let mut b = OctetsMut::with_slice(&mut entry);
+        b.put_u64(o.location.group).unwrap();
+        b.put_u64(o.location.object).unwrap();
+        b.put_u64(offset).unwrap();
+        if let Err(e) = w.segment.write_all(&record).and_then(|()| w.index.write_all(&entry)) {
+            // continue in a new segment, the offsets of this one are unknown
+            self.writer = None;
+            return Err(e);
+        }
+        segment.bytes += record.len() as u64;
+        segment.modified = now;
+        w.last_group = w.last_group.max(o.location.group);
+        self.index.insert(o.location, (w.id, offset));
+        Ok(())
+    }
+
+    fn start_segment(&mut self, group: u64, now: SystemTime) -> io::Result<()> {
+        let id = self.segments.keys().next_back().map_or(0, |id| id + 1);
+        let path = self.segment_path(id);
+        let open = |path: &Path| OpenOptions::new().append(true).create_new(true).open(path);
+        let segment = open(&path)?;
+        let index = open(&path.with_extension("idx"))?;
+        self.segments.insert(id, Segment { bytes: 0, modified: now });
+        self.writer = Some(Writer { id, segment, index, started: now, last_group: group });
+        Ok(())
+    }
+
+    fn segment_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id:020}.seg"))
+    }
+
+    /// Delete the segments older than the maximum age, then the oldest ones until the recording
+    /// fits the maximum size. The segment being appended to is kept.
+    fn enforce_retention(&mut self, now: SystemTime) {
+        let current = self.writer.as_ref().map(|w| w.id);
+        let expired: Vec<u64> = self.segments.iter()
+            .filter(|&(&id, s)| Some(id) != current && now.duration_since(s.modified).unwrap_or_default() > self.max_age)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in expired {
+            self.remove_segment(id);
+        }
+        while self.bytes() > self.max_bytes
+            && let Some(&id) = self.segments.keys().find(|&&id| Some(id) != current)
+        {
+            self.remove_segment(id);
+        }
+    }
+
+    fn remove_segment(&mut self, id: u64) {
+        let path = self.segment_path(id);
+        for path in [path.with_extension("idx"), path] {
+            if let Err(e) = fs::remove_file(&path) && e.kind() != io::ErrorKind::NotFound {
+                error!("failed to delete {}: {}", path.display(), e);
+            }
+        }
+        self.segments.remove(&id);
+        self.index.retain(|_, (segment, _)| *segment != id);
+    }
+
+    /// Size of the segments.
+    pub(crate) fn bytes(&self) -> u64 {
+        self.segments.values().map(|s| s.bytes).sum()
+    }
+
+    /// Location of the oldest recorded object.
+    pub(crate) fn first_location(&self) -> Option<Location> {
+        self.index.keys().next().copied()
+    }
+
+    /// The first recorded group from `group` on.
+    pub(crate) fn next_group(&self, group: u64) -> Option<u64> {
+        self.index.range(Location { group, object: 0 }..).next().map(|(l, _)| l.group)
+    }
+
+    /// Location of the last recorded object within `range`.
+    pub(crate) fn last_in(&self, range: &FetchRange) -> Option<Location> {
+        self.index
+            .range(range.start..)
+            .take_while(|(l, _)| range.contains(**l))
+            .last()
+            .map(|(l, _)| *l)
+    }
+
+    /// Read the recorded objects of `group`.
+    pub(crate) fn read_group(&self, group: u64) -> io::Result<ArchivedGroup> {
+        let mut cache = TrackCache::new(CacheLimits { max_objects: usize::MAX, max_bytes: usize::MAX, max_duration: Duration::MAX });
+        let mut files: BTreeMap<u64, File> = BTreeMap::new();
+        let now = Instant::now();
+        let range = Location { group, object: 0 }..=Location { group, object: u64::MAX };
+        for (&location, &(id, offset)) in self.index.range(range) {
+            let file = match files.entry(id) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => e.insert(File::open(self.segment_path(id))?),
+            };
+            file.seek(SeekFrom::Start(offset))?;
+            let mut header = [0; RECORD_HEADER_LEN];
+            file.read_exact(&mut header)?;
+            let mut b = Octets::with_slice(&header);
+            let (_group, subgroup_id, _object) = (b.get_u64().unwrap(), b.get_u64().unwrap(), b.get_u64().unwrap());
+            let status = b.get_u64().unwrap().checked_sub(1);
+            let ext_count = b.get_u32().unwrap() as u64;
+            let ext_len = b.get_u32().unwrap() as usize;
+            let payload_len = b.get_u32().unwrap() as usize;
+            let mut ext = vec![0; ext_len];
+            file.read_exact(&mut ext)?;
+            let ext_hdrs = KeyValuePairs::from_bytes(&mut Octets::with_slice(&ext), (EXT_HDRS_VERSION, ext_count))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{location:?}: {e:?}")))?;
+            let o = cache.push(location, subgroup_id, payload_len, status, ext_hdrs, now);
+            file.read_exact(&mut o.payload)?;
+            o.written = payload_len;
+        }
+        let subgroups: Vec<(u64, u64)> = cache.subgroups_from(group).map(|(k, _)| k).collect();
+        for (group, subgroup_id) in subgroups {
+            cache.finish_subgroup(group, subgroup_id, false);
+        }
+        Ok(ArchivedGroup { group, cache })
+    }
+}
+
+/// One recorded group in a [`TrackCache`] of its own, so it is forwarded like cached objects.
+pub(crate) struct ArchivedGroup {
+    pub(crate) group: u64,
+    pub(crate) cache: TrackCache,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quiche_moq::wire::KeyValuePair;
+
+    fn dvr(name: &str, max_age: Duration, max_bytes: u64) -> Dvr {
+        let dir = std::env::temp_dir().join(format!("moq-relay-dvr-{name}-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        Dvr::new(dir, vec!["live".to_string().try_into().unwrap()], max_age, max_bytes)
+    }
+
+    fn loc(group: u64, object: u64) -> Location {
+        Location { group, object }
+    }
+
+    /// Record objects of one byte per location.
+    fn record(archive: &mut TrackArchive, locations: &[Location], now: SystemTime) {
+        let mut cache = TrackCache::new(CacheLimits { max_objects: 100, max_bytes: 100, max_duration: Duration::MAX });
+        for &location in locations {
+            let o = cache.push(location, 0, 1, None, KeyValuePairs::new(), Instant::now());
+            o.payload[0] = location.object as u8;
+            o.written = 1;
+            archive.record(cache.get(location).unwrap(), now).unwrap();
+        }
+    }
+
+    #[test]
+    fn record_and_reopen() {
+        let dvr = dvr("reopen", Duration::from_secs(3600), u64::MAX);
+        let nt: NamespaceTrackname = "live-cam--video".parse().unwrap();
+        assert!(dvr.records(nt.namespace()));
+        assert!(!dvr.records(&"vod".parse().unwrap()));
+        let now = SystemTime::now();
+        let mut archive = dvr.open(&nt).unwrap();
+        let mut cache = TrackCache::new(CacheLimits { max_objects: 10, max_bytes: 100, max_duration: Duration::MAX });
+        let mut ext_hdrs = KeyValuePairs::new();
+        ext_hdrs.push(KeyValuePair::new_bytes(3, b"ext".to_vec()).unwrap());
+        ext_hdrs.push(KeyValuePair::new_varint(2, 7).unwrap());
+        let o = cache.push(loc(1, 0), 0, 3, None, ext_hdrs.clone(), Instant::now());
+        o.payload.copy_from_slice(b"abc");
+        o.written = 3;
+        cache.push(loc(1, 1), 2, 0, Some(3), KeyValuePairs::new(), Instant::now());
+        archive.record(cache.get(loc(1, 0)).unwrap(), now).unwrap();
+        archive.record(cache.get(loc(1, 1)).unwrap(), now).unwrap();
+        record(&mut archive, &[loc(3, 0)], now);
+        drop(archive);
+
+        let archive = dvr.open(&nt).unwrap();
+        assert_eq!(archive.first_location(), Some(loc(1, 0)));
+        assert_eq!(archive.next_group(2), Some(3));
+        assert_eq!(archive.next_group(4), None);
+        assert_eq!(archive.last_in(&FetchRange { start: loc(0, 0), end: loc(2, 0) }), Some(loc(1, 1)));
+        let g = archive.read_group(1).unwrap();
+        let o = g.cache.get(loc(1, 0)).unwrap();
+        assert_eq!((o.payload.as_slice(), o.subgroup_id, o.status), (b"abc".as_slice(), 0, None));
+        assert_eq!(o.ext_hdrs, ext_hdrs);
+        let o = g.cache.get(loc(1, 1)).unwrap();
+        assert_eq!((o.payload.len(), o.subgroup_id, o.status), (0, 2, Some(3)));
+        assert!(g.cache.subgroup(1, 2).unwrap().fin);
+
+        // the next run appends to a new segment
+        let mut archive = dvr.open(&nt).unwrap();
+        record(&mut archive, &[loc(4, 0)], now);
+        assert_eq!(archive.segments.len(), 2);
+        assert_eq!(archive.read_group(4).unwrap().cache.get(loc(4, 0)).unwrap().payload, [0]);
+
+        // restarted groups replace the recording
+        record(&mut archive, &[loc(1, 0)], now);
+        assert_eq!(archive.first_location(), Some(loc(1, 0)));
+        assert_eq!(archive.next_group(2), None);
+        assert_eq!(archive.segments.len(), 1);
+        fs::remove_dir_all(&dvr.dir).unwrap();
+    }
+
+    #[test]
+    fn retention() {
+        let dvr = dvr("retention", Duration::from_secs(90), u64::MAX);
+        let nt: NamespaceTrackname = "live--audio".parse().unwrap();
+        let t0 = SystemTime::now();
+        let mut archive = dvr.open(&nt).unwrap();
+        record(&mut archive, &[loc(0, 0), loc(0, 1)], t0);
+        // same group, same segment
+        record(&mut archive, &[loc(0, 2)], t0 + SEGMENT_DURATION);
+        assert_eq!(archive.segments.len(), 1);
+        record(&mut archive, &[loc(1, 0)], t0 + 2 * SEGMENT_DURATION);
+        assert_eq!(archive.segments.len(), 2);
+        record(&mut archive, &[loc(2, 0)], t0 + 3 * SEGMENT_DURATION);
+        // the first segment was last written 120 s before
+        assert_eq!(archive.first_location(), Some(loc(1, 0)));
+        assert_eq!(archive.segments.len(), 2);
+
+        archive.max_bytes = archive.bytes() - 1;
+        archive.enforce_retention(t0 + 3 * SEGMENT_DURATION);
+        assert_eq!(archive.first_location(), Some(loc(2, 0)));
+        assert!(!archive.segment_path(1).exists());
+        fs::remove_dir_all(&dvr.dir).unwrap();
+    }
+}
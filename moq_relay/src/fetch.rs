@@ -0,0 +1,131 @@
+use log::error;
+use quiche_moq as moq;
+use quiche_moq::wire::control_message::FetchRange;
+use quiche_moq::wire::{ErrorCode, Location, NamespaceTrackname, OBJECT_STATUS_NORMAL, RequestId};
+use quiche_mio_runner::quiche_endpoint::ClientId;
+use quiche_moq_webtransport_helper::MoqHandle;
+
+use crate::cache::TrackCache;
+use crate::forwarding::drops_extension_headers;
+use crate::dvr::ArchivedGroup;
+use crate::metrics::Metrics;
+
+/// A FETCH received from a downstream client.
+pub(crate) struct RelayFetch {
+    pub(crate) client_id: ClientId,
+    pub(crate) nt: NamespaceTrackname,
+    pub(crate) response: FetchResponse,
+    /// Set when the start of the range is not cached and the range is fetched from the publisher.
+    /// `None` serves the range from the subscription's track cache.
+    pub(crate) upstream: Option<UpstreamFetch>,
+    /// Set when the range is served from the track's recording: the recorded group being forwarded.
+    pub(crate) archived: Option<ArchivedGroup>,
+}
+
+/// The response sent to the downstream client.
+pub(crate) struct FetchResponse {
+    /// Downstream client's request_id.
+    pub(crate) request_id: RequestId,
+    pub(crate) range: FetchRange,
+    /// Largest location announced in FETCH_OK; set once accepted.
+    pub(crate) end_location: Option<Location>,
+    /// Location of the most recent object header forwarded, like `SubscriberInfo::location`.
+    location: Option<Location>,
+    /// Bytes of the current object's payload still to be forwarded.
+    remaining: usize,
+    /// Finished, canceled or failed; the fetch is removed and its upstream fetch canceled.
+    pub(crate) closed: bool,
+}
+
+/// FETCH sent by the relay to the publisher of the track.
+pub(crate) struct UpstreamFetch {
+    pub(crate) client_id: ClientId,
+    /// The range in the publisher's group IDs, see [`PublisherInfo::unmap_range`](crate::subscription::PublisherInfo::unmap_range).
+    pub(crate) range: FetchRange,
+    /// Added to the group IDs of the publisher's objects and FETCH_OK.
+    pub(crate) group_offset: u64,
+    /// Set once the FETCH is sent.
+    pub(crate) request_id: Option<RequestId>,
+    /// End of track and end location from the publisher's FETCH_OK.
+    pub(crate) ok: Option<(bool, Location)>,
+    /// Objects received from the publisher; the last one may still be incomplete.
+    /// The next object is only read once the previous one has been forwarded downstream,
+    /// so the downstream client paces the upstream fetch.
+    pub(crate) cache: TrackCache,
+    /// All objects have been received.
+    pub(crate) fin: bool,
+    /// The publisher rejected the fetch or disconnected before it was complete.
+    pub(crate) error: Option<ErrorCode>,
+}
+
+impl FetchResponse {
+    pub(crate) fn new(request_id: RequestId, range: FetchRange) -> Self {
+        Self { request_id, range, end_location: None, location: None, remaining: 0, closed: false }
+    }
+
+    /// The location after which the next object is read from the publisher.
+    pub(crate) fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// Whether the object at `location` or a later one has been forwarded completely.
+    pub(crate) fn has_forwarded(&self, location: Location) -> bool {
+        self.location.is_some_and(|l| l >= location) && self.remaining == 0
+    }
+
+    /// Forward the objects of the range from `cache` downstream, the same way Phase 5 forwards
+    /// to subscribers. `complete`: no more objects of the range will be added to `cache`.
+    pub(crate) fn forward(&mut self, cache: &TrackCache, complete: bool, moq: &mut MoqHandle<'_>, metrics: &mut Metrics) {
+        let Some(end_location) = self.end_location else { return };
+        loop {
+            if self.remaining > 0 {
+                let loc = self.location.unwrap();
+                let Some(o) = cache.get(loc) else {
+                    error!("reset fetch {}: {:?} is no longer cached", self.request_id, loc);
+                    moq.reset_fetch(self.request_id);
+                    self.closed = true;
+                    return;
+                };
+                let read_pos = o.payload.len() - self.remaining;
+                if read_pos >= o.written { return; }
+                match moq.send_fetch_obj_pld(&o.payload[read_pos..o.written], self.request_id) {
+                    Ok(n) => { self.remaining -= n; }
+                    Err(moq::Error::Done | moq::Error::InsufficientCapacity) => return,
+                    Err(_) => { self.closed = true; return; }
+                }
+                if self.remaining > 0 { return; }
+            }
+            let next = match self.location {
+                Some(l) => cache.next_after(l),
+                None => cache.first_from(self.range.start),
+            }
+            .filter(|o| self.range.contains(o.location) && o.location <= end_location);
+            let Some(o) = next else {
+                if complete || self.location.is_some_and(|l| l >= end_location) {
+                    match moq.fetch_done(self.request_id) {
+                        Err(moq::Error::InsufficientCapacity) => {}
+                        _ => self.closed = true,
+                    }
+                }
+                return;
+            };
+            let loc = o.location;
+            let res = if o.payload.is_empty() {
+                moq.send_fetch_obj_status(self.request_id, loc, o.subgroup_id, o.status.unwrap_or(OBJECT_STATUS_NORMAL), &o.ext_hdrs)
+            } else {
+                moq.send_fetch_obj_hdr(self.request_id, loc, o.subgroup_id, o.payload.len(), &o.ext_hdrs)
+            };
+            match res {
+                Ok(()) => {
+                    self.location = Some(loc);
+                    self.remaining = o.payload.len();
+                    if drops_extension_headers(o, moq) {
+                        metrics.extension_headers_dropped += 1;
+                    }
+                }
+                Err(moq::Error::InsufficientCapacity) => return,
+                Err(_) => { self.closed = true; return; }
+            }
+        }
+    }
+}
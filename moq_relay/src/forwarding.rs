@@ -0,0 +1,199 @@
+//! Forwarding of objects: from the publisher's subgroup streams into the track cache, and from
+//! the cache onto the subscribers' subgroup streams.
+
+use std::time::Instant;
+use log::{error, info};
+use quiche_moq as moq;
+use quiche_moq::wire::{Location, NamespaceTrackname, OBJECT_STATUS_NORMAL, TrackAlias, extension_headers_supported};
+use quiche_moq_webtransport_helper::MoqHandle;
+use quiche_utils::stream_id::StreamID;
+use crate::cache::{CachedObject, TrackCache};
+use crate::metrics::Metrics;
+use crate::subscription::{DownstreamSubgroup, PublisherInfo, UpstreamSubgroup};
+
+/// Forward the cached objects of one subgroup to a subscriber on the subgroup's own stream,
+/// opened with its first object. Returns when the subscriber cannot take more data or the
+/// cache holds nothing more of the subgroup yet.
+pub(crate) fn forward_subgroup(
+    cache: &TrackCache,
+    group: u64,
+    subgroup_id: u64,
+    d: &mut DownstreamSubgroup,
+    sub_ta: TrackAlias,
+    moq: &mut MoqHandle<'_>,
+    metrics: &mut Metrics,
+) {
+    loop {
+        if d.remaining > 0 {
+            let loc = Location { group, object: d.object.unwrap() };
+            let Some(o) = cache.get(loc) else {
+                error!("reset subgroup {}/{}: {:?} is no longer cached", group, subgroup_id, loc);
+                moq.reset_subgroup(d.stream_id.unwrap());
+                metrics.stream_resets += 1;
+                d.done = true;
+                return;
+            };
+            let read_pos = o.payload.len() - d.remaining;
+            if read_pos >= o.written { return; }
+            match moq.send_subgroup_obj_pld(&o.payload[read_pos..o.written], d.stream_id.unwrap()) {
+                Ok(n) => {
+                    d.remaining -= n;
+                    metrics.bytes_forwarded += n as u64;
+                }
+                Err(moq::Error::Done | moq::Error::InsufficientCapacity) => return,
+                Err(e) => { drop_subgroup(d, group, subgroup_id, e, moq, metrics); return; }
+            }
+            if d.remaining > 0 { return; }
+        }
+        let Some(o) = cache.next_in_subgroup(group, subgroup_id, d.object) else {
+            if let Some(state) = cache.subgroup(group, subgroup_id) && state.fin {
+                match d.stream_id {
+                    Some(stream_id) if state.reset => {
+                        moq.reset_subgroup(stream_id);
+                        metrics.stream_resets += 1;
+                    }
+                    Some(stream_id) => moq.fin_subgroup(stream_id),
+                    None => {}
+                }
+                d.done = true;
+            }
+            return;
+        };
+        let stream_id = match d.stream_id {
+            Some(stream_id) => stream_id,
+            None => match moq.open_subgroup(sub_ta, group, subgroup_id) {
+                Ok(stream_id) => *d.stream_id.insert(stream_id),
+                Err(moq::Error::InsufficientCapacity) => return,
+                Err(e) => { drop_subgroup(d, group, subgroup_id, e, moq, metrics); return; }
+            },
+        };
+        let object = o.location.object;
+        let res = if o.payload.is_empty() {
+            moq.send_subgroup_obj_status(stream_id, object, o.status.unwrap_or(OBJECT_STATUS_NORMAL), &o.ext_hdrs)
+        } else {
+            moq.send_subgroup_obj_hdr(stream_id, object, o.payload.len(), &o.ext_hdrs)
+        };
+        match res {
+            Ok(()) => {
+                d.object = Some(object);
+                d.remaining = o.payload.len();
+                metrics.objects_forwarded += 1;
+                if drops_extension_headers(o, moq) {
+                    metrics.extension_headers_dropped += 1;
+                }
+            }
+            Err(moq::Error::InsufficientCapacity) => return,
+            Err(e) => { drop_subgroup(d, group, subgroup_id, e, moq, metrics); return; }
+        }
+    }
+}
+
+/// Give up a subgroup stream to a subscriber that failed, e.g. because the subscriber stopped
+/// it with STOP_SENDING. Only this subscriber misses the rest of the subgroup.
+fn drop_subgroup(d: &mut DownstreamSubgroup, group: u64, subgroup_id: u64, e: moq::Error, moq: &mut MoqHandle<'_>, metrics: &mut Metrics) {
+    info!("drop subgroup {}/{} stream to subscriber: {:?}", group, subgroup_id, e);
+    if let Some(stream_id) = d.stream_id {
+        moq.reset_subgroup(stream_id);
+        metrics.stream_resets += 1;
+    }
+    d.done = true;
+}
+
+/// Whether the extension headers of `o` are lost when it is sent on `moq`. Drafts before 11
+/// cannot carry them; the object is still forwarded, with its payload and status unchanged.
+pub(crate) fn drops_extension_headers(o: &CachedObject, moq: &MoqHandle<'_>) -> bool {
+    !o.ext_hdrs.is_empty() && moq.version().is_some_and(|v| !extension_headers_supported(v))
+}
+
+/// Read one subgroup stream of the publisher into the cache until no more data is available.
+/// Returns true once the stream has ended; its subgroup is then marked finished in the cache.
+/// The locations of the objects completed are added to `completed`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn read_subgroup(
+    cache: &mut TrackCache,
+    publisher: &mut PublisherInfo,
+    up: &mut UpstreamSubgroup,
+    stream_id: StreamID,
+    pub_ta: TrackAlias,
+    nt: &NamespaceTrackname,
+    moq: &mut MoqHandle<'_>,
+    progress: &mut bool,
+    completed: &mut Vec<Location>,
+) -> bool {
+    let mut discard = [0u8; 1024];
+    loop {
+        if let Some((loc, remaining)) = up.live {
+            let buf = match cache.get_mut(loc) {
+                Some(o) if !up.discard => &mut o.payload[o.written..],
+                // dropped, or replaced because the publisher restarted its groups; skip the rest of it
+                _ => &mut discard[..remaining.min(1024)],
+            };
+            match moq.read_stream_obj_pld(buf, pub_ta, stream_id) {
+                Ok(n) => {
+                    if !up.discard && let Some(o) = cache.get_mut(loc) {
+                        o.written += n;
+                        if o.is_complete() { completed.push(loc); }
+                    }
+                    up.live = (remaining > n).then_some((loc, remaining - n));
+                    *progress = true;
+                }
+                Err(moq::Error::Done) => return false,
+                Err(e) => {
+                    // The partial payload is incomplete; subscribers that started
+                    // forwarding it get their subgroup stream reset.
+                    error!("publisher subgroup reset mid-object for {}: {:?}", nt, e);
+                    if !up.discard {
+                        cache.remove(loc);
+                        if let Some((group, subgroup_id)) = up.subgroup {
+                            cache.finish_subgroup(group, subgroup_id, true);
+                        }
+                    }
+                    *progress = true;
+                    return true;
+                }
+            }
+        } else {
+            match moq.read_stream_obj_hdr(pub_ta, stream_id) {
+                Ok(hdr) => {
+                    let (group, subgroup_id) = match up.subgroup {
+                        Some(subgroup) => subgroup,
+                        None => {
+                            let Some(sg) = moq.stream_subgroup_header(stream_id) else {
+                                error!("object without subgroup header from the publisher of {}", nt);
+                                *progress = true;
+                                return true;
+                            };
+                            let group = publisher.map_group(sg.group_id(), cache);
+                            up.discard = group.is_none();
+                            *up.subgroup.insert((group.unwrap_or(sg.group_id()), sg.subgroup_id().unwrap_or(hdr.id())))
+                        }
+                    };
+                    let location = Location { group, object: hdr.id() };
+                    if !up.discard {
+                        cache.push(location, subgroup_id, hdr.payload_len(), hdr.status(), hdr.extension_headers().clone(), Instant::now());
+                    }
+                    if hdr.payload_len() > 0 {
+                        up.live = Some((location, hdr.payload_len()));
+                    } else if !up.discard {
+                        completed.push(location);
+                    }
+                    *progress = true;
+                }
+                Err(moq::Error::Done) => return false,
+                Err(e) => {
+                    // Only a FIN ends the subgroup cleanly; on a reset the subscribers'
+                    // streams are reset as well, so they do not take it as complete.
+                    let reset = !matches!(e, moq::Error::Fin);
+                    if reset {
+                        error!("read obj hdr for {}: {:?}", nt, e);
+                    }
+                    if let Some((group, subgroup_id)) = up.subgroup && !up.discard {
+                        cache.finish_subgroup(group, subgroup_id, reset);
+                    }
+                    *progress = true;
+                    return true;
+                }
+            }
+        }
+    }
+}
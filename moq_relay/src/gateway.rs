@@ -0,0 +1,314 @@
+//! Plain HTTP/3 GET of cached objects for clients that do not speak MoQ:
+//! `/<namespace>/<track>/latest` and `/<namespace>/<track>/<group>/<object>`,
+//! with the namespace and track name in their text form, e.g. `/live-alice/video/latest`.
+//!
+//! The CMAF tracks of the broadcasts of `[packaging] namespaces` are also served as LL-HLS and
+//! DASH, see [`packaging`]:
+//!
+//! ```text
+//! /<namespace>/master.m3u8           multivariant playlist
+//! /<namespace>/manifest.mpd          MPD
+//! /<namespace>/<track>/media.m3u8    media playlist
+//! /<namespace>/<track>/init.mp4      init segment from the catalog
+//! /<namespace>/<track>/<group>.m4s   segment
+//! ```
+//!
+//! A request for something that is about to arrive, e.g. the next part or a blocking playlist
+//! reload, is held until it is there.
+
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+use log::error;
+use quiche_mio_runner::quiche_endpoint::quiche::h3;
+use quiche_moq::wire::{Location, Namespace, NamespaceTrackname, OBJECT_STATUS_NORMAL};
+use quiche_moq_catalog::catalog_track;
+use quiche_moq_webtransport_helper::HttpRequest;
+use url::form_urlencoded;
+
+use crate::Relay;
+use crate::acl::Action;
+use crate::auth::query_token;
+use crate::cache::{CachedObject, TrackCache};
+use crate::packaging::{self, CmafTrack, Segment};
+
+/// Objects never change once published.
+const CACHE_CONTROL_OBJECT: &str = "public, max-age=31536000, immutable";
+/// The latest object, playlists and init segments change.
+const CACHE_CONTROL_MUTABLE: &str = "no-cache";
+/// Longest a request waits for an object, segment or catalog that is about to arrive.
+const MAX_HOLD: Duration = Duration::from_secs(10);
+
+type Response = (Vec<h3::Header>, Vec<u8>);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Latest(NamespaceTrackname),
+    Object(NamespaceTrackname, Location),
+    MultivariantPlaylist(Namespace),
+    Mpd(Namespace),
+    MediaPlaylist(NamespaceTrackname),
+    Init(NamespaceTrackname),
+    Segment(NamespaceTrackname, u64),
+}
+
+impl Target {
+    fn namespace(&self) -> &Namespace {
+        match self {
+            Target::MultivariantPlaylist(namespace) | Target::Mpd(namespace) => namespace,
+            Target::Latest(nt) | Target::Object(nt, _) | Target::MediaPlaylist(nt) | Target::Init(nt) | Target::Segment(nt, _) => nt.namespace(),
+        }
+    }
+}
+
+/// What the path of a request addresses, the query is ignored.
+fn parse_path(path: &str) -> Option<Target> {
+    let path = path.split_once('?').map_or(path, |(p, _)| p);
+    let segments: Vec<&str> = path.strip_prefix('/')?.split('/').collect();
+    let nt = |namespace: &str, track: &str| format!("{namespace}--{track}").parse::<NamespaceTrackname>().ok();
+    Some(match segments.as_slice() {
+        [namespace, "master.m3u8"] => Target::MultivariantPlaylist(namespace.parse().ok()?),
+        [namespace, "manifest.mpd"] => Target::Mpd(namespace.parse().ok()?),
+        [namespace, track, "latest"] => Target::Latest(nt(namespace, track)?),
+        [namespace, track, "media.m3u8"] => Target::MediaPlaylist(nt(namespace, track)?),
+        [namespace, track, "init.mp4"] => Target::Init(nt(namespace, track)?),
+        [namespace, track, segment] => Target::Segment(nt(namespace, track)?, segment.strip_suffix(".m4s")?.parse().ok()?),
+        [namespace, track, group, object] => {
+            let location = Location { group: group.parse().ok()?, object: object.parse().ok()? };
+            Target::Object(nt(namespace, track)?, location)
+        }
+        _ => return None,
+    })
+}
+
+/// Headers and body of the response to `req` from `peer`, or `None` to hold the request and
+/// ask again later; `waited` is how long it has been held. Subscribe rights are checked like
+/// for SUBSCRIBE, with the token from the `token` query parameter.
+/// Objects are served from the track cache, or from the recording for a specific object.
+pub(crate) fn respond(req: &HttpRequest, peer: Option<IpAddr>, waited: Duration, relay: &mut Relay) -> Option<Response> {
+    let (headers, mut body) = match req.method.as_str() {
+        "GET" | "HEAD" => get(req, peer, waited, relay)?,
+        _ => text(405, "method not allowed\n", &[("allow", "GET, HEAD")]),
+    };
+    if req.method == "HEAD" {
+        body.clear();
+    }
+    Some((headers, body))
+}
+
+fn get(req: &HttpRequest, peer: Option<IpAddr>, waited: Duration, relay: &mut Relay) -> Option<Response> {
+    let Some(target) = parse_path(&req.path) else {
+        return Some(not_found());
+    };
+    let token = query_token(&req.path);
+    if !relay.acl.allows(target.namespace(), peer, Action::Subscribe) {
+        return Some(text(403, "forbidden\n", &[]));
+    }
+    if let Some(authorizer) = &relay.authorizer
+        && authorizer.authorize(token.as_deref(), target.namespace(), Action::Subscribe, SystemTime::now()).is_err()
+    {
+        return Some(text(403, "forbidden\n", &[]));
+    }
+    match target {
+        Target::Latest(nt) => {
+            let o = relay.subscriptions.get(&nt).and_then(|s| s.cache.latest_complete());
+            Some(o.map_or_else(not_found, |o| object(o, CACHE_CONTROL_MUTABLE)))
+        }
+        Target::Object(nt, location) => object_at(&nt, location, waited, relay),
+        target => {
+            // the URIs of playlists carry the token of the request on
+            let query = token.map_or(String::new(), |t| format!("?token={}", form_urlencoded::byte_serialize(&t).collect::<String>()));
+            packaged(target, &req.path, &query, waited, relay)
+        }
+    }
+}
+
+/// A specific object from the cache or the recording. The next object of the track is waited
+/// for, e.g. the part of a preload hint, and so is an object still being received.
+fn object_at(nt: &NamespaceTrackname, location: Location, waited: Duration, relay: &Relay) -> Option<Response> {
+    let cache = relay.subscriptions.get(nt).map(|s| &s.cache);
+    if let Some(o) = cache.and_then(|c| c.get(location)) {
+        if !o.is_complete() {
+            return wait(waited, MAX_HOLD);
+        }
+        return Some(object(o, CACHE_CONTROL_OBJECT));
+    }
+    if let Some(c) = cache && is_next(c, location) {
+        return wait(waited, MAX_HOLD);
+    }
+    let archived = relay.archives.get(nt)
+        .filter(|archive| archive.next_group(location.group) == Some(location.group))
+        .and_then(|archive| archive.read_group(location.group)
+            .inspect_err(|e| error!("failed to read group {} from {}: {}", location.group, archive.dir().display(), e))
+            .ok());
+    Some(archived.as_ref().and_then(|g| g.cache.get(location)).map_or_else(not_found, |o| object(o, CACHE_CONTROL_OBJECT)))
+}
+
+/// `location` follows the newest cached object: a later object of its group while the group
+/// is not finished, or the first object of the next group.
+fn is_next(cache: &TrackCache, location: Location) -> bool {
+    cache.largest_location().is_some_and(|l| {
+        (location.group == l.group && location.object > l.object && !cache.is_group_finished(l.group))
+            || location == Location { group: l.group + 1, object: 0 }
+    })
+}
+
+/// Playlists, MPDs, init segments and segments of a broadcast. The relay subscribes to the
+/// catalog track and to the tracks asked for itself, so they are cached without MoQ subscribers.
+fn packaged(target: Target, path: &str, query: &str, waited: Duration, relay: &mut Relay) -> Option<Response> {
+    let namespace = target.namespace().clone();
+    if !relay.packager.as_ref().is_some_and(|p| p.packages(&namespace)) {
+        return Some(not_found());
+    }
+    let catalog_nt = catalog_track(&namespace);
+    if !relay.subscribe_locally(&catalog_nt) {
+        return Some(not_found());
+    }
+    let Some(catalog) = relay.subscriptions.get(&catalog_nt).and_then(|s| packaging::catalog(&s.cache)) else {
+        return wait(waited, MAX_HOLD);
+    };
+    let mut tracks = packaging::cmaf_tracks(&catalog, &namespace);
+    let track_nt = match &target {
+        Target::MultivariantPlaylist(_) | Target::Mpd(_) => None,
+        Target::MediaPlaylist(nt) | Target::Init(nt) | Target::Segment(nt, _) => Some(nt),
+        Target::Latest(_) | Target::Object(..) => unreachable!(),
+    };
+    if let Some(nt) = track_nt {
+        tracks.retain(|t| t.nt == *nt);
+        if tracks.is_empty() {
+            return Some(not_found());
+        }
+    }
+    let mut with_segments: Vec<(CmafTrack, Vec<Segment>)> = vec![];
+    for t in tracks {
+        if !relay.subscribe_locally(&t.nt) { continue; }
+        let segments = relay.subscriptions.get(&t.nt).map(|s| packaging::segments(&s.cache, &t.timing)).unwrap_or_default();
+        with_segments.push((t, segments));
+    }
+    let tracks = with_segments;
+
+    match target {
+        Target::MultivariantPlaylist(_) => match packaging::multivariant_playlist(&tracks, query) {
+            Some(m3u8) => Some(ok("application/vnd.apple.mpegurl", CACHE_CONTROL_MUTABLE, m3u8.into_bytes())),
+            None => wait(waited, MAX_HOLD),
+        },
+        Target::Mpd(_) => {
+            let now = SystemTime::now();
+            let mpd = relay.packager.as_mut().unwrap()
+                .availability_start(&namespace, &tracks, now)
+                .and_then(|availability_start| packaging::mpd(&tracks, availability_start, now, query));
+            match mpd {
+                Some(mpd) => Some(ok("application/dash+xml", CACHE_CONTROL_MUTABLE, mpd.into_bytes())),
+                None => wait(waited, MAX_HOLD),
+            }
+        }
+        Target::MediaPlaylist(_) => {
+            let Some((track, segments)) = tracks.first() else { return Some(not_found()) };
+            let timescale = track.timing.timescale;
+            let param = |name: &str| {
+                let (_, query) = path.split_once('?')?;
+                form_urlencoded::parse(query.as_bytes()).find(|(k, _)| k == name).map(|(_, v)| v.into_owned())
+            };
+            // blocking playlist reload
+            if let Some(msn) = param("_HLS_msn") {
+                let Ok(msn) = msn.parse::<u64>() else { return Some(text(400, "invalid _HLS_msn\n", &[])) };
+                let part = param("_HLS_part").and_then(|p| p.parse::<usize>().ok());
+                if segments.last().is_some_and(|s| msn > s.group + 2) {
+                    return Some(text(400, "_HLS_msn is too far ahead\n", &[]));
+                }
+                if !packaging::has_part(segments, msn, part) {
+                    return wait(waited, 3 * packaging::target_duration(segments, timescale));
+                }
+            }
+            if !segments.iter().any(|s| !s.parts.is_empty()) {
+                return wait(waited, MAX_HOLD);
+            }
+            let m3u8 = packaging::media_playlist(segments, timescale, query);
+            Some(ok("application/vnd.apple.mpegurl", CACHE_CONTROL_MUTABLE, m3u8.into_bytes()))
+        }
+        Target::Init(_) => {
+            let Some((track, _)) = tracks.first() else { return Some(not_found()) };
+            Some(ok(&track.content_type(), CACHE_CONTROL_MUTABLE, track.init.clone()))
+        }
+        Target::Segment(nt, group) => {
+            let (Some((track, segments)), Some(sub)) = (tracks.first(), relay.subscriptions.get(&nt)) else {
+                return Some(not_found());
+            };
+            let cache = &sub.cache;
+            match segments.iter().find(|s| s.group == group) {
+                Some(s) if s.complete => Some(ok(&track.content_type(), CACHE_CONTROL_OBJECT, s.payload(cache))),
+                Some(_) => wait(waited, MAX_HOLD),
+                None if is_next(cache, Location { group, object: 0 }) => wait(waited, MAX_HOLD),
+                None => Some(not_found()),
+            }
+        }
+        Target::Latest(_) | Target::Object(..) => unreachable!(),
+    }
+}
+
+/// Hold the request, or give up with 503 once it waited `limit`.
+fn wait(waited: Duration, limit: Duration) -> Option<Response> {
+    (waited >= limit).then(|| text(503, "not available yet\n", &[("retry-after", "1")]))
+}
+
+fn object(o: &CachedObject, cache_control: &str) -> Response {
+    if o.payload.is_empty() && o.status.is_some_and(|s| s != OBJECT_STATUS_NORMAL) {
+        // END_OF_GROUP and the like mark where objects end, they are no objects of their own
+        return not_found();
+    }
+    let (mut headers, body) = ok("application/octet-stream", cache_control, o.payload.clone());
+    headers.push(h3::Header::new(b"moq-group-id", o.location.group.to_string().as_bytes()));
+    headers.push(h3::Header::new(b"moq-object-id", o.location.object.to_string().as_bytes()));
+    (headers, body)
+}
+
+fn ok(content_type: &str, cache_control: &str, body: Vec<u8>) -> Response {
+    let headers = vec![
+        h3::Header::new(b":status", b"200"),
+        h3::Header::new(b"content-type", content_type.as_bytes()),
+        h3::Header::new(b"content-length", body.len().to_string().as_bytes()),
+        h3::Header::new(b"cache-control", cache_control.as_bytes()),
+        // players on web pages of other origins
+        h3::Header::new(b"access-control-allow-origin", b"*"),
+    ];
+    (headers, body)
+}
+
+fn not_found() -> Response {
+    text(404, "not found\n", &[])
+}
+
+fn text(status: u16, body: &str, extra: &[(&str, &str)]) -> Response {
+    let mut headers = vec![
+        h3::Header::new(b":status", status.to_string().as_bytes()),
+        h3::Header::new(b"content-type", b"text/plain"),
+        h3::Header::new(b"content-length", body.len().to_string().as_bytes()),
+    ];
+    headers.extend(extra.iter().map(|(name, value)| h3::Header::new(name.as_bytes(), value.as_bytes())));
+    (headers, body.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let nt: NamespaceTrackname = "live-alice--video".parse().unwrap();
+        assert_eq!(parse_path("/live-alice/video/latest"), Some(Target::Latest(nt.clone())));
+        assert_eq!(
+            parse_path("/live-alice/video/3/0?token=abc"),
+            Some(Target::Object(nt.clone(), Location { group: 3, object: 0 }))
+        );
+        assert_eq!(parse_path("/live-alice/video"), None);
+        assert_eq!(parse_path("/live-alice/video/x/0"), None);
+        assert_eq!(parse_path("live-alice/video/latest"), None);
+
+        let namespace: Namespace = "live-alice".parse().unwrap();
+        assert_eq!(parse_path("/live-alice/master.m3u8"), Some(Target::MultivariantPlaylist(namespace.clone())));
+        assert_eq!(parse_path("/live-alice/manifest.mpd?token=abc"), Some(Target::Mpd(namespace)));
+        assert_eq!(parse_path("/live-alice/video/media.m3u8?_HLS_msn=3"), Some(Target::MediaPlaylist(nt.clone())));
+        assert_eq!(parse_path("/live-alice/video/init.mp4"), Some(Target::Init(nt.clone())));
+        assert_eq!(parse_path("/live-alice/video/12.m4s"), Some(Target::Segment(nt, 12)));
+        assert_eq!(parse_path("/live-alice/video/12.mp4"), None);
+    }
+}
@@ -0,0 +1,376 @@
+//! MoQ relay: forwards namespaces, subscriptions, objects and fetches between publishers and
+//! subscribers, caching recent objects per track.
+//!
+//! [`Relay`] is the app data of a `quiche_endpoint` endpoint. Call [`Relay::process`] after the
+//! endpoint handled received packets, or use [`Relay::post_handle_recvs`] as the callback of a
+//! `quiche_mio_runner` runner:
+//!
+//! ```no_run
+//! use moq_relay::{Certificate, Config, Relay, Runner};
+//! use quiche_mio_runner::Socket;
+//!
+//! let config = Config::default();
+//! let relay = Relay::new(&config).unwrap();
+//! let certificate = Certificate::load(&config.cert, &config.key);
+//! let mut c = quiche_mio_runner::Config::default();
+//! c.post_handle_recvs = Relay::post_handle_recvs;
+//! let mut r = Runner::new(c, relay.into_endpoint(certificate.ssl_context_builder()), None);
+//! r.register_socket(Socket::bind("0.0.0.0:8080").unwrap());
+//! r.run();
+//! ```
+
+mod acl;
+mod admin;
+mod auth;
+mod cache;
+mod cmaf;
+pub mod config;
+mod drain;
+mod dvr;
+mod fetch;
+mod forwarding;
+mod gateway;
+mod metrics;
+mod namespace_trie;
+mod packaging;
+mod phases;
+mod routing;
+mod subscription;
+mod tls;
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime};
+use boring::ssl::SslContextBuilder;
+use log::{error, info};
+use quiche_mio_runner as runner;
+use quiche_mio_runner::quiche_endpoint::{self, EndpointConfig, ServerConfig, quiche, ClientId};
+use quiche_moq as moq;
+use quiche_moq::wire::{ErrorCode, Namespace, NamespaceTrackname, Parameters, RequestId};
+use quiche_moq_webtransport_helper::{HttpRequest, MoqHandle, MoqWebTransportHelper};
+use url::Url;
+use crate::acl::{Acl, Action};
+use crate::admin::AdminServer;
+use crate::auth::{Authorizer, ConnTokens, HmacAuthorizer};
+use crate::cache::{CacheLimits, TrackCache};
+use crate::config::{DrainConfig, LimitsConfig, QuicConfig};
+use crate::drain::Drain;
+use crate::dvr::{Dvr, TrackArchive};
+use crate::fetch::RelayFetch;
+use crate::metrics::Metrics;
+use crate::namespace_trie::NamespaceTrie;
+use crate::packaging::Packager;
+use crate::phases::post_handle_recvs;
+use crate::routing::route;
+use crate::subscription::{PublisherInfo, Subscription};
+pub use crate::config::Config;
+pub use crate::tls::Certificate;
+
+pub type Endpoint = quiche_endpoint::Endpoint<RelayConn, Relay>;
+pub type Runner = runner::Runner<RelayConn, Relay, ()>;
+
+/// How often the admin listener is polled for requests while no QUIC packets arrive.
+const ADMIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often SIGTERM is checked for while no QUIC packets arrive, see [`Relay::drain_on_sigterm`].
+const SIGTERM_POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// How often held HTTP requests are checked for having waited too long while no QUIC packets arrive.
+const HTTP_HOLD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// State of one connection of the relay: a publisher, a subscriber or another relay.
+pub struct RelayConn {
+    moq_helper: MoqWebTransportHelper,
+    /// Namespaces announced to this connection: namespace → request_id used in publish_namespace.
+    announced_namespaces: HashMap<Namespace, RequestId>,
+    logged_connect: bool,
+    /// Set with `logged_connect` once the MoQ session is established.
+    tokens: Option<ConnTokens>,
+    /// GOAWAY was sent while draining.
+    goaway_sent: bool,
+    /// Counted towards `[limits] sessions_per_ip` of its IP address.
+    admitted: bool,
+    /// Request IDs of the SUBSCRIBEs and PUBLISH_NAMESPACEs held over the session's quota.
+    held_requests: HashSet<RequestId>,
+}
+
+impl RelayConn {
+    fn new(moq_helper: MoqWebTransportHelper) -> Self {
+        Self {
+            moq_helper,
+            announced_namespaces: HashMap::new(),
+            logged_connect: false,
+            tokens: None,
+            goaway_sent: false,
+            admitted: false,
+            held_requests: HashSet::new(),
+        }
+    }
+}
+
+/// A plain HTTP/3 request that was not answered yet, see [`gateway`].
+struct HeldRequest {
+    client_id: ClientId,
+    peer: Option<IpAddr>,
+    req: HttpRequest,
+    since: Instant,
+}
+
+/// Relay state shared by all connections, the app data of the [`Endpoint`].
+pub struct Relay {
+    /// MoQ settings of accepted and upstream connections.
+    moq_config: moq::Config,
+    /// QUIC settings of upstream connections.
+    quic: QuicConfig,
+    /// Publisher of each namespace; the first connection that published it.
+    /// Requests are routed to the publisher of the longest matching prefix, see [`route`].
+    namespaces: NamespaceTrie<ClientId>,
+    /// Further publishers of already published namespaces, in the order they published them.
+    /// The first one takes over when the publisher goes away, see [`Relay::withdraw_namespaces`].
+    standby_publishers: Vec<(Namespace, ClientId)>,
+    /// Connections to the upstream relays, see [`Relay::connect_upstream`].
+    /// Subscriptions to namespaces that no connection published are forwarded to the first of them.
+    upstreams: Vec<ClientId>,
+    subscriptions: HashMap<NamespaceTrackname, Subscription>,
+    fetches: Vec<RelayFetch>,
+    cache_limits: CacheLimits,
+    acl: Acl,
+    /// Payload bytes a subscriber may fall behind before it skips to the newest group.
+    max_subscriber_backlog: usize,
+    /// Quotas of the sessions of clients.
+    limits: LimitsConfig,
+    drain_config: DrainConfig,
+    /// Set by [`Relay::drain`].
+    drain: Option<Drain>,
+    /// Set by [`Relay::drain_on_sigterm`].
+    watch_sigterm: bool,
+    /// Checks the tokens of publishers and subscribers; everyone is authorized if unset.
+    authorizer: Option<Box<dyn Authorizer>>,
+    /// Records the tracks of the configured namespaces; nothing is recorded if unset.
+    dvr: Option<Dvr>,
+    /// Recordings opened by [`Relay::open_archive`].
+    archives: HashMap<NamespaceTrackname, TrackArchive>,
+    /// Serves the CMAF tracks of the configured namespaces as LL-HLS and DASH; nothing is packaged if unset.
+    packager: Option<Packager>,
+    /// Plain HTTP/3 requests, held while what they ask for is about to arrive, see [`gateway`].
+    http_requests: Vec<HeldRequest>,
+    metrics: Metrics,
+    /// Listener given by `--admin`.
+    admin: Option<AdminServer>,
+}
+
+impl Relay {
+    /// Relay state for `config`; binds the admin listener if one is configured.
+    /// Listen addresses, certificate and upstreams of `config` are up to the caller.
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let admin = config.admin
+            .map(|addr| AdminServer::bind(addr).map_err(|e| format!("failed to listen on admin address {addr}: {e}")))
+            .transpose()?;
+        Ok(Self {
+            moq_config: config.moq.to_moq()?,
+            quic: config.quic.clone(),
+            namespaces: Default::default(),
+            standby_publishers: Vec::new(),
+            upstreams: Vec::new(),
+            subscriptions: Default::default(),
+            fetches: Default::default(),
+            cache_limits: config.cache_limits(),
+            acl: config.acl(),
+            max_subscriber_backlog: config.subscriber.max_backlog,
+            limits: config.limits.clone(),
+            drain_config: config.drain.clone(),
+            drain: None,
+            watch_sigterm: false,
+            authorizer: (!config.auth.hmac_keys.is_empty())
+                .then(|| Box::new(HmacAuthorizer::new(&config.auth.hmac_keys)) as Box<dyn Authorizer>),
+            dvr: config.dvr()?,
+            archives: HashMap::new(),
+            packager: config.packager()?,
+            http_requests: Vec::new(),
+            metrics: Metrics::default(),
+            admin,
+        })
+    }
+
+    /// Endpoint that accepts MoQ sessions over WebTransport with the certificate of `ssl_context`.
+    /// Plain HTTP/3 GET requests on the same connections are answered from the track caches.
+    pub fn into_endpoint(self, ssl_context: SslContextBuilder) -> Endpoint {
+        // clients initially get no more request IDs than their quotas allow
+        let mut moq_config = self.moq_config.clone();
+        let quota = self.limits.subscriptions_per_session + self.limits.namespaces_per_session;
+        moq_config.max_request_id = moq_config.max_request_id.min(quota.saturating_sub(1) as u64 * 2);
+        let mut quic_config = quiche::Config::with_boring_ssl_ctx_builder(quiche::PROTOCOL_VERSION, ssl_context).unwrap();
+        MoqWebTransportHelper::configure_quic(&mut quic_config);
+        self.quic.apply(&mut quic_config);
+        let mut c = ServerConfig::new(move |_| RelayConn::new(MoqWebTransportHelper::new_server(moq_config.clone())));
+        c.client_config = quic_config;
+        Endpoint::new(
+            Some(c),
+            {
+                let mut c = EndpointConfig::default();
+                c.setup_qlog = quiche_endpoint_utils::setup_qlog;
+                c
+            },
+            self,
+        )
+    }
+
+    /// Connect to the relay at `url` from the first of `local_addrs` of the same address family;
+    /// these are local addresses the endpoint receives on.
+    /// Subscriptions and fetches for namespaces that no connection published go to the first
+    /// upstream relay, and it learns the namespaces published here.
+    pub fn connect_upstream(endpoint: &mut Endpoint, url: &Url, local_addrs: &[SocketAddr]) -> Result<ClientId, String> {
+        let peer_addr = *url.socket_addrs(|| Some(443))
+            .map_err(|e| format!("{url}: {e}"))?
+            .first()
+            .ok_or_else(|| format!("{url}: no address"))?;
+        let Some(&local_addr) = local_addrs.iter().find(|a| a.is_ipv6() == peer_addr.is_ipv6()) else {
+            return Err(format!("no {} listen address to connect to relay {}", if peer_addr.is_ipv6() { "IPv6" } else { "IPv4" }, url));
+        };
+        let relay = endpoint.app_data_mut();
+        let mut quic_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+        MoqWebTransportHelper::configure_quic(&mut quic_config);
+        relay.quic.apply(&mut quic_config);
+        quic_config.verify_peer(false);
+        let conn = RelayConn::new(MoqWebTransportHelper::new_client(url.clone(), relay.moq_config.clone()));
+        let icid = endpoint.connect(None, local_addr, peer_addr, &mut quic_config, conn, None, None);
+        endpoint.app_data_mut().upstreams.push(icid);
+        info!("connecting to relay {}", url);
+        Ok(icid)
+    }
+
+    /// Handle what the connections of `endpoint` received: publish namespaces, forward
+    /// subscriptions and fetches, and forward objects between publishers and subscribers.
+    /// Call after the endpoint processed received packets, and again after the returned timeout
+    /// even if no packets arrive.
+    pub fn process(endpoint: &mut Endpoint) -> Option<Duration> {
+        post_handle_recvs(endpoint)
+    }
+
+    /// Callback for `quiche_mio_runner::Config::post_handle_recvs`.
+    /// Stops the runner once the relay is drained.
+    pub fn post_handle_recvs(r: &mut Runner) {
+        if let Some(timeout) = Self::process(&mut r.endpoint) {
+            r.set_app_timeout(timeout);
+        }
+        if r.endpoint.app_data_mut().is_drained() {
+            info!("drained, closing connections");
+            r.close();
+        }
+    }
+
+    /// Start draining the relay, e.g. for a rolling deploy: new sessions are refused, the
+    /// namespaces announced to the clients are withdrawn and they get GOAWAY with the redirect
+    /// of `[drain]`. Subscribers still here at the end of the grace period get PUBLISH_DONE and
+    /// all sessions are closed, with GOAWAY_TIMEOUT for clients, see [`Relay::is_drained`].
+    pub fn drain(&mut self) {
+        if self.drain.is_some() { return; }
+        info!("draining, grace period {}s", self.drain_config.grace_period);
+        let deadline = Instant::now() + Duration::from_secs(self.drain_config.grace_period);
+        self.drain = Some(Drain::Grace { deadline });
+    }
+
+    /// The sessions were closed after [`Relay::drain`] and the connections can be closed.
+    pub fn is_drained(&self) -> bool {
+        self.drain.is_some_and(|d| d.is_done(Instant::now()))
+    }
+
+    /// Drain the relay on SIGTERM instead of terminating.
+    pub fn drain_on_sigterm(&mut self) {
+        drain::handle_sigterm();
+        self.watch_sigterm = true;
+    }
+
+    /// When [`Relay::process`] has to run again even if no packets arrive.
+    fn timeout(&self) -> Option<Duration> {
+        [
+            self.admin.is_some().then_some(ADMIN_POLL_INTERVAL),
+            self.watch_sigterm.then_some(SIGTERM_POLL_INTERVAL),
+            (!self.http_requests.is_empty()).then_some(HTTP_HOLD_POLL_INTERVAL),
+            self.drain.map(|d| d.timeout(Instant::now())),
+        ].into_iter().flatten().min()
+    }
+
+    /// Withdraw the namespaces published by `cid` for which `keep` returns false.
+    /// A withdrawn namespace passes to its first standby publisher.
+    fn withdraw_namespaces(&mut self, cid: ClientId, keep: impl Fn(&Namespace) -> bool) {
+        self.standby_publishers.retain(|(ns, id)| *id != cid || keep(ns));
+        let standbys = &mut self.standby_publishers;
+        self.namespaces.retain(|ns, publisher| {
+            if *publisher != cid || keep(ns) { return true; }
+            let Some(i) = standbys.iter().position(|(n, _)| n == ns) else { return false };
+            *publisher = standbys.remove(i).1;
+            info!("namespace {} passed from {} to standby publisher {}", ns, cid, publisher);
+            true
+        });
+    }
+
+    /// Subscriptions of `cid` to any track, accepted or queued.
+    fn subscription_count(&self, cid: ClientId) -> usize {
+        self.subscriptions.values().flat_map(|sub| &sub.subscribers).filter(|s| s.client_id == cid).count()
+    }
+
+    /// Grant `cid` further request IDs as its requests finish. The peer may have as many requests
+    /// outstanding as `[moq] max_request_id` grants initially, and a client no more than its
+    /// subscription and namespace quotas leave, counting the `held` requests. A blocked peer stays
+    /// blocked until one of its subscriptions, fetches or namespaces ends.
+    fn grant_request_ids(&self, cid: ClientId, held: usize, moq: &mut MoqHandle<'_>) {
+        let window = self.moq_config.max_request_id / 2 + 1;
+        let grant = if self.upstreams.contains(&cid) {
+            window
+        } else {
+            let used = self.subscription_count(cid)
+                + self.fetches.iter().filter(|f| f.client_id == cid).count()
+                + moq.received_namespaces().count()
+                + held;
+            let quota = self.limits.subscriptions_per_session + self.limits.namespaces_per_session;
+            window.min(quota.saturating_sub(used) as u64)
+        };
+        if grant > 0 && (moq.peer_requests_blocked() || moq.remaining_peer_requests() * 2 <= grant) {
+            moq.grant_request_ids(grant);
+        }
+    }
+
+    /// Open the recording of `nt` if its namespace is recorded, see [`Dvr`].
+    fn open_archive(&mut self, nt: &NamespaceTrackname) {
+        let Some(dvr) = &self.dvr else { return };
+        if self.archives.contains_key(nt) || !dvr.records(nt.namespace()) { return; }
+        match dvr.open(nt) {
+            Ok(archive) => {
+                info!("recording {} to {}", nt, archive.dir().display());
+                self.archives.insert(nt.clone(), archive);
+            }
+            Err(e) => error!("failed to open the recording of {}: {}", nt, e),
+        }
+    }
+
+    /// Subscribe to `nt` for the relay itself, e.g. to package it for HTTP clients, unless it is
+    /// subscribed already. Like the subscriptions of MoQ subscribers, it stays once made.
+    /// Returns false if no connection publishes the namespace.
+    fn subscribe_locally(&mut self, nt: &NamespaceTrackname) -> bool {
+        if self.subscriptions.contains_key(nt) {
+            return true;
+        }
+        let Some(publisher_id) = route(&self.namespaces, &self.upstreams, nt.namespace(), |_| false) else { return false };
+        info!("new subscription request {} from the relay (publisher: {})", nt, publisher_id);
+        self.open_archive(nt);
+        self.subscriptions.insert(nt.clone(), Subscription {
+            publisher: Some(PublisherInfo::new(publisher_id, false)),
+            subscribers: Vec::new(),
+            cache: TrackCache::new(self.cache_limits),
+        });
+        true
+    }
+
+    /// Check the token of a request, or the connection token if `parameters` is `None`.
+    /// Connections to upstream relays are trusted.
+    fn authorize(&self, cid: ClientId, tokens: &mut ConnTokens, parameters: Option<&Parameters>, namespace: &Namespace, action: Action) -> Result<(), ErrorCode> {
+        let Some(authorizer) = &self.authorizer else { return Ok(()) };
+        if self.upstreams.contains(&cid) {
+            return Ok(());
+        }
+        let token = match parameters {
+            Some(parameters) => tokens.request_token(parameters)?,
+            None => tokens.connection.clone(),
+        };
+        authorizer.authorize(token.as_deref(), namespace, action, SystemTime::now())
+    }
+}
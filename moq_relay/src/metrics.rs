@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use quiche_moq::wire::version_to_name;
+
+use crate::Relay;
+use crate::admin::Session;
+
+/// Counters of the relay since it started; gauges are read from the relay state when rendered.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    /// Objects forwarded to subscribers on subgroup streams.
+    pub(crate) objects_forwarded: u64,
+    /// Payload bytes forwarded to subscribers on subgroup streams.
+    pub(crate) bytes_forwarded: u64,
+    /// Subgroup streams to subscribers that were reset.
+    pub(crate) stream_resets: u64,
+    /// Subscriptions moved to a standby publisher after their publisher went away.
+    pub(crate) failovers: u64,
+    /// Groups not forwarded completely because a subscriber exceeded its backlog limit.
+    pub(crate) groups_skipped: u64,
+    /// FETCH requests served from the track cache.
+    pub(crate) cache_hits: u64,
+    /// FETCH requests forwarded to the publisher.
+    pub(crate) cache_misses: u64,
+    /// FETCH requests served from a track's recording.
+    pub(crate) dvr_hits: u64,
+    /// Connections refused because their IP address exceeded `sessions_per_ip`.
+    pub(crate) quota_rejections: u64,
+    /// Requests held because their session exceeded its subscription or namespace quota.
+    pub(crate) quota_holds: u64,
+    /// Objects forwarded without their extension headers to clients on drafts that cannot carry them.
+    pub(crate) extension_headers_dropped: u64,
+    /// Plain HTTP/3 requests answered by the gateway.
+    pub(crate) http_requests: u64,
+}
+
+/// Render the Prometheus text exposition format.
+pub(crate) fn render(sessions: &[Session], app_data: &Relay) -> String {
+    let mut out = String::new();
+    let m = &app_data.metrics;
+
+    gauge(&mut out, "moq_relay_connections", "Open QUIC connections.");
+    sample(&mut out, "moq_relay_connections", &[], sessions.iter().filter(|s| !s.closed).count());
+
+    gauge(&mut out, "moq_relay_sessions", "Established MoQ sessions by draft version.");
+    let mut versions = BTreeMap::new();
+    for v in sessions.iter().filter(|s| !s.closed).filter_map(|s| s.version) {
+        *versions.entry(version_to_name(v)).or_insert(0) += 1;
+    }
+    for (version, n) in versions {
+        sample(&mut out, "moq_relay_sessions", &[("version", version)], n);
+    }
+
+    gauge(&mut out, "moq_relay_namespaces", "Namespaces published to the relay.");
+    sample(&mut out, "moq_relay_namespaces", &[], app_data.namespaces.len());
+
+    gauge(&mut out, "moq_relay_subscribers", "Subscribers per track.");
+    for (nt, sub) in &app_data.subscriptions {
+        sample(&mut out, "moq_relay_subscribers", &[("track", &nt.to_string())], sub.subscribers.len());
+    }
+
+    gauge(&mut out, "moq_relay_cache_objects", "Objects cached per track.");
+    for (nt, sub) in &app_data.subscriptions {
+        sample(&mut out, "moq_relay_cache_objects", &[("track", &nt.to_string())], sub.cache.len());
+    }
+
+    gauge(&mut out, "moq_relay_cache_bytes", "Payload bytes cached per track.");
+    for (nt, sub) in &app_data.subscriptions {
+        sample(&mut out, "moq_relay_cache_bytes", &[("track", &nt.to_string())], sub.cache.bytes());
+    }
+
+    gauge(&mut out, "moq_relay_dvr_bytes", "Bytes recorded per track.");
+    for (nt, archive) in &app_data.archives {
+        sample(&mut out, "moq_relay_dvr_bytes", &[("track", &nt.to_string())], archive.bytes());
+    }
+
+    gauge(&mut out, "moq_relay_subscriber_lag_groups", "Groups between the oldest group still being forwarded to a subscriber and the newest cached group.");
+    for (nt, sub) in &app_data.subscriptions {
+        for s in sub.subscribers.iter().filter(|s| s.is_accepted()) {
+            let (track, client) = (nt.to_string(), s.client_id.to_string());
+            sample(&mut out, "moq_relay_subscriber_lag_groups", &[("track", &track), ("client", &client)], s.lag_groups(&sub.cache));
+        }
+    }
+
+    counter(&mut out, "moq_relay_objects_forwarded_total", "Objects forwarded to subscribers.", m.objects_forwarded);
+    counter(&mut out, "moq_relay_bytes_forwarded_total", "Payload bytes forwarded to subscribers.", m.bytes_forwarded);
+    counter(&mut out, "moq_relay_stream_resets_total", "Subgroup streams to subscribers reset by the relay.", m.stream_resets);
+    counter(&mut out, "moq_relay_failovers_total", "Subscriptions moved to a standby publisher.", m.failovers);
+    counter(&mut out, "moq_relay_groups_skipped_total", "Groups skipped for subscribers that fell too far behind.", m.groups_skipped);
+    counter(&mut out, "moq_relay_cache_hits_total", "FETCH requests served from the cache.", m.cache_hits);
+    counter(&mut out, "moq_relay_cache_misses_total", "FETCH requests forwarded to the publisher.", m.cache_misses);
+    counter(&mut out, "moq_relay_dvr_hits_total", "FETCH requests served from a recording.", m.dvr_hits);
+    counter(&mut out, "moq_relay_quota_rejections_total", "Connections refused for exceeding the sessions per IP address.", m.quota_rejections);
+    counter(&mut out, "moq_relay_quota_holds_total", "Requests held for exceeding the session quotas.", m.quota_holds);
+    counter(&mut out, "moq_relay_extension_headers_dropped_total", "Objects forwarded without their extension headers to clients on older drafts.", m.extension_headers_dropped);
+    counter(&mut out, "moq_relay_http_requests_total", "Plain HTTP/3 requests answered from the cache.", m.http_requests);
+    out
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge").unwrap();
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter").unwrap();
+    sample(out, name, &[], value);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        out.push('{');
+        for (i, (k, v)) in labels.iter().enumerate() {
+            if i > 0 { out.push(','); }
+            write!(out, "{k}=\"{}\"", escape_label(v)).unwrap();
+        }
+        out.push('}');
+    }
+    writeln!(out, " {value}").unwrap();
+}
+
+fn escape_label(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_labels() {
+        let mut out = String::new();
+        sample(&mut out, "m", &[], 1);
+        sample(&mut out, "m", &[("track", "a\"b"), ("client", "2")], 3);
+        assert_eq!(out, "m 1\nm{track=\"a\\\"b\",client=\"2\"} 3\n");
+    }
+}
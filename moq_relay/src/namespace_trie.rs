@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use quiche_moq::wire::{Namespace, Tuple};
+
+/// Map from namespaces to values that resolves a namespace to the values of all its prefixes.
+///
+/// Each node is one tuple element, so `org-team` is stored below `org` and a lookup of
+/// `org-team-room1` walks `org` → `team` → `room1`. Children are ordered by their element,
+/// so iteration order does not depend on insertion order.
+pub(crate) struct NamespaceTrie<V> {
+    root: Node<V>,
+    len: usize,
+}
+
+struct Node<V> {
+    value: Option<V>,
+    children: BTreeMap<Vec<u8>, Node<V>>,
+}
+
+impl<V> Default for Node<V> {
+    fn default() -> Self {
+        Self { value: None, children: BTreeMap::new() }
+    }
+}
+
+impl<V> Default for NamespaceTrie<V> {
+    fn default() -> Self {
+        Self { root: Node::default(), len: 0 }
+    }
+}
+
+impl<V> NamespaceTrie<V> {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Value of exactly this namespace.
+    pub(crate) fn get(&self, namespace: &Namespace) -> Option<&V> {
+        let mut node = &self.root;
+        for element in namespace.iter() {
+            node = node.children.get(element)?;
+        }
+        node.value.as_ref()
+    }
+
+    pub(crate) fn contains_key(&self, namespace: &Namespace) -> bool {
+        self.get(namespace).is_some()
+    }
+
+    /// Returns the previous value of the namespace.
+    pub(crate) fn insert(&mut self, namespace: Namespace, value: V) -> Option<V> {
+        let mut node = &mut self.root;
+        for element in namespace.0.0 {
+            node = node.children.entry(element).or_default();
+        }
+        let previous = node.value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Values of the namespace and all its prefixes, the shortest prefix first.
+    pub(crate) fn prefixes_of(&self, namespace: &Namespace) -> Vec<&V> {
+        let mut node = &self.root;
+        let mut values: Vec<&V> = node.value.iter().collect();
+        for element in namespace.iter() {
+            let Some(child) = node.children.get(element) else { break };
+            node = child;
+            values.extend(node.value.as_ref());
+        }
+        values
+    }
+
+    /// Value of the longest prefix of the namespace, including the namespace itself.
+    pub(crate) fn longest_prefix(&self, namespace: &Namespace) -> Option<&V> {
+        self.prefixes_of(namespace).pop()
+    }
+
+    /// Keep only the namespaces for which `f` returns true.
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&Namespace, &mut V) -> bool) {
+        let mut path = Namespace(Tuple(vec![]));
+        self.len -= Self::retain_node(&mut self.root, &mut path, &mut f);
+    }
+
+    /// Returns the number of removed values.
+    fn retain_node(node: &mut Node<V>, path: &mut Namespace, f: &mut impl FnMut(&Namespace, &mut V) -> bool) -> usize {
+        let mut removed = 0;
+        if let Some(value) = node.value.as_mut() && !f(path, value) {
+            node.value = None;
+            removed += 1;
+        }
+        node.children.retain(|element, child| {
+            path.0.0.push(element.clone());
+            removed += Self::retain_node(child, path, f);
+            path.0.0.pop();
+            child.value.is_some() || !child.children.is_empty()
+        });
+        removed
+    }
+
+    /// All namespaces with their values, ordered by their elements.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Namespace, &V)> {
+        let mut entries = vec![];
+        let mut stack = vec![(Vec::new(), &self.root)];
+        while let Some((path, node)) = stack.pop() {
+            if let Some(value) = &node.value {
+                entries.push((Namespace(Tuple(path.clone())), value));
+            }
+            // pushed in reverse so the smallest element is visited first
+            for (element, child) in node.children.iter().rev() {
+                let mut path = path.clone();
+                path.push(element.clone());
+                stack.push((path, child));
+            }
+        }
+        entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ns(s: &str) -> Namespace {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn longest_prefix() {
+        let mut trie = NamespaceTrie::default();
+        assert!(trie.insert(ns("org"), 1).is_none());
+        assert!(trie.insert(ns("org-team"), 2).is_none());
+        assert_eq!(trie.insert(ns("org-team"), 3), Some(2));
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.longest_prefix(&ns("org-team-room1")), Some(&3));
+        assert_eq!(trie.longest_prefix(&ns("org-other")), Some(&1));
+        assert_eq!(trie.longest_prefix(&ns("org")), Some(&1));
+        assert_eq!(trie.longest_prefix(&ns("other")), None);
+        assert_eq!(trie.prefixes_of(&ns("org-team-room1")), [&1, &3]);
+        assert_eq!(trie.get(&ns("org-team-room1")), None);
+        assert_eq!(trie.get(&ns("org-team")), Some(&3));
+    }
+
+    #[test]
+    fn retain_prunes() {
+        let mut trie = NamespaceTrie::default();
+        trie.insert(ns("b-x"), 1);
+        trie.insert(ns("a"), 2);
+        trie.insert(ns("a-y-z"), 1);
+        let all: Vec<(String, i32)> = trie.iter().map(|(n, &v)| (n.to_string(), v)).collect();
+        assert_eq!(all, [("a".to_string(), 2), ("a-y-z".to_string(), 1), ("b-x".to_string(), 1)]);
+        trie.retain(|_, v| *v != 1);
+        assert_eq!(trie.len(), 1);
+        assert!(trie.root.children.get(b"b".as_slice()).is_none());
+        assert!(trie.root.children[b"a".as_slice()].children.is_empty());
+        assert_eq!(trie.longest_prefix(&ns("a-y-z")), Some(&2));
+    }
+}
@@ -0,0 +1,587 @@
+//! LL-HLS playlists and DASH MPDs of the CMAF tracks of a broadcast, built from the track caches
+//! for the [`crate::gateway`]. A broadcast is a namespace with a catalog track, see
+//! [`quiche_moq_catalog`]; its video and audio tracks packaged as CMAF with init data are served.
+//!
+//! Every group of a track is a segment, numbered by its group ID, and every object of the group
+//! is a part of it. Objects therefore have to hold whole CMAF chunks, and groups start with an
+//! independent one, as MoQ requires of groups anyway. Parts and segments are timed by the decode
+//! times and sample durations of their fragments.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use log::error;
+use quiche_moq::wire::{Location, Namespace, NamespaceTrackname};
+use quiche_moq_catalog::{Catalog, CatalogReader, PACKAGING_CMAF, ROLE_AUDIO, ROLE_VIDEO, Track};
+
+use crate::acl::NamespacePrefix;
+use crate::cache::TrackCache;
+use crate::cmaf::Timing;
+
+/// Which broadcasts are packaged, see [`crate::config::PackagingConfig`].
+pub(crate) struct Packager {
+    namespaces: Vec<NamespacePrefix>,
+    /// Wall clock time of media time zero of each broadcast, fixed with its first MPD.
+    availability_start: HashMap<Namespace, SystemTime>,
+}
+
+impl Packager {
+    pub(crate) fn new(namespaces: Vec<NamespacePrefix>) -> Self {
+        Self { namespaces, availability_start: HashMap::new() }
+    }
+
+    pub(crate) fn packages(&self, namespace: &Namespace) -> bool {
+        self.namespaces.iter().any(|p| p.matches(namespace))
+    }
+
+    /// Anchor of the DASH timeline of the broadcast in `namespace`: set with its first MPD, so
+    /// that the newest complete segment of `tracks` has just become available.
+    pub(crate) fn availability_start(&mut self, namespace: &Namespace, tracks: &[(CmafTrack, Vec<Segment>)], now: SystemTime) -> Option<SystemTime> {
+        if let Some(&t) = self.availability_start.get(namespace) {
+            return Some(t);
+        }
+        let media_time = tracks.iter()
+            .filter_map(|(t, segments)| {
+                let s = segments.iter().rev().find(|s| s.complete)?;
+                Some(Duration::from_secs_f64((s.start + s.duration()) as f64 / f64::from(t.timing.timescale)))
+            })
+            .max()?;
+        let t = now.checked_sub(media_time)?;
+        self.availability_start.insert(namespace.clone(), t);
+        Some(t)
+    }
+}
+
+/// The catalog of the newest cached group of the catalog track that starts with a full catalog,
+/// with the delta updates received completely after it.
+pub(crate) fn catalog(cache: &TrackCache) -> Option<Catalog> {
+    let group = cache.groups().rev().find(|&group| cache.get(Location { group, object: 0 }).is_some_and(|o| o.is_complete()))?;
+    let mut reader = CatalogReader::new(0);
+    for o in cache.group(group).take_while(|o| o.is_complete()).filter(|o| !o.payload.is_empty()) {
+        if let Err(e) = reader.handle_object(o.location, &o.payload) {
+            error!("failed to apply catalog object {:?}: {:?}", o.location, e);
+            break;
+        }
+    }
+    reader.catalog().cloned()
+}
+
+/// A track of the catalog that can be packaged.
+pub(crate) struct CmafTrack<'a> {
+    pub(crate) track: &'a Track,
+    pub(crate) nt: NamespaceTrackname,
+    pub(crate) init: Vec<u8>,
+    pub(crate) timing: Timing,
+}
+
+impl CmafTrack<'_> {
+    /// Track name as a path segment; its escaped text form only holds `[A-Za-z0-9_.]`.
+    pub(crate) fn path(&self) -> String {
+        let nt = self.nt.to_string();
+        nt.rsplit_once("--").map_or(nt.clone(), |(_, track)| track.to_string())
+    }
+
+    pub(crate) fn content_type(&self) -> String {
+        self.track.mime_type.clone().unwrap_or_else(|| format!("{}/mp4", self.role()))
+    }
+
+    fn role(&self) -> &str {
+        self.track.role.as_deref().unwrap_or(ROLE_VIDEO)
+    }
+
+    /// Bits per second from the catalog, otherwise the peak of the complete segments.
+    fn bandwidth(&self, segments: &[Segment]) -> Option<u64> {
+        self.track.bitrate.or_else(|| {
+            segments.iter()
+                .filter(|s| s.complete && s.duration() > 0)
+                .map(|s| s.bytes as u64 * 8 * u64::from(self.timing.timescale) / s.duration())
+                .max()
+        })
+    }
+}
+
+/// The video and audio tracks of `catalog` in `namespace`, the catalog's namespace, that are
+/// packaged as CMAF with init data. Tracks of other namespaces are left out.
+pub(crate) fn cmaf_tracks<'a>(catalog: &'a Catalog, namespace: &Namespace) -> Vec<CmafTrack<'a>> {
+    catalog.tracks.iter()
+        .filter(|track| track.packaging == PACKAGING_CMAF && matches!(track.role.as_deref(), Some(ROLE_VIDEO | ROLE_AUDIO)))
+        .filter_map(|track| {
+            let nt = track.namespace_trackname(namespace).ok().filter(|nt| nt.namespace() == namespace)?;
+            let init = STANDARD.decode(track.init_data.as_ref()?).ok()?;
+            let timing = Timing::from_init(&init)?;
+            Some(CmafTrack { track, nt, init, timing })
+        })
+        .collect()
+}
+
+/// A group of a track as a segment.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Segment {
+    pub(crate) group: u64,
+    /// Decode time of its first sample, in ticks of the track's timescale.
+    pub(crate) start: u64,
+    /// Object ID and duration in ticks of each part.
+    pub(crate) parts: Vec<(u64, u64)>,
+    /// The publisher finished the group; otherwise more parts may follow.
+    pub(crate) complete: bool,
+    /// Payload bytes of the parts.
+    pub(crate) bytes: usize,
+}
+
+impl Segment {
+    /// In ticks of the track's timescale.
+    pub(crate) fn duration(&self) -> u64 {
+        self.parts.iter().map(|&(_, d)| d).sum()
+    }
+
+    /// The payloads of the parts one after another.
+    pub(crate) fn payload(&self, cache: &TrackCache) -> Vec<u8> {
+        self.parts.iter()
+            .filter_map(|&(object, _)| cache.get(Location { group: self.group, object }))
+            .flat_map(|o| o.payload.iter().copied())
+            .collect()
+    }
+}
+
+/// Segments of the cached groups of a track, oldest first: the newest run of consecutive groups,
+/// up to the first group the publisher has not finished yet. Objects without payload, e.g.
+/// END_OF_GROUP, and objects that are no CMAF fragments are no parts. The oldest group is left
+/// out if it lost its first objects to eviction.
+pub(crate) fn segments(cache: &TrackCache, timing: &Timing) -> Vec<Segment> {
+    let first = cache.first_location();
+    let mut segments: Vec<Segment> = vec![];
+    for group in cache.groups() {
+        if first.is_some_and(|f| f.group == group && f.object > 0) {
+            continue;
+        }
+        let mut segment = Segment { group, start: 0, parts: vec![], complete: cache.is_group_finished(group), bytes: 0 };
+        for o in cache.group(group).filter(|o| !o.payload.is_empty()) {
+            if !o.is_complete() { break; }
+            let Some((start, duration)) = timing.fragments(&o.payload) else { continue };
+            if segment.parts.is_empty() {
+                segment.start = start;
+            }
+            segment.parts.push((o.location.object, duration));
+            segment.bytes += o.payload.len();
+        }
+        // media sequence numbers have to be consecutive
+        if segments.last().is_some_and(|s| s.group + 1 != group) {
+            segments.clear();
+        }
+        if segment.complete && segment.parts.is_empty() {
+            segments.clear();
+            continue;
+        }
+        let complete = segment.complete;
+        segments.push(segment);
+        if !complete { break; }
+    }
+    segments
+}
+
+/// EXT-X-TARGETDURATION of `segments`: their longest duration rounded up, at least a second.
+pub(crate) fn target_duration(segments: &[Segment], timescale: u32) -> Duration {
+    let secs = segments.iter()
+        .filter(|s| s.complete)
+        .map(|s| s.duration().div_ceil(u64::from(timescale)))
+        .max()
+        .unwrap_or(1);
+    Duration::from_secs(secs.max(1))
+}
+
+/// Whether the playlist of `segments` holds part `part` of the segment `msn`, or all of the
+/// segment if `part` is `None`, as asked for by `_HLS_msn` and `_HLS_part`.
+pub(crate) fn has_part(segments: &[Segment], msn: u64, part: Option<usize>) -> bool {
+    segments.iter().any(|s| s.group > msn || (s.group == msn && (s.complete || part.is_some_and(|p| p < s.parts.len()))))
+}
+
+/// LL-HLS media playlist of `segments`, which hold at least one part. Parts are listed for the
+/// segments within three target durations of the end, followed by a preload hint of the next
+/// part. `query` is appended to every URI, e.g. the token of the request.
+pub(crate) fn media_playlist(segments: &[Segment], timescale: u32, query: &str) -> String {
+    let secs = |ticks: u64| ticks as f64 / f64::from(timescale);
+    let target_duration = target_duration(segments, timescale).as_secs();
+    let part_target = segments.iter().flat_map(|s| &s.parts).map(|&(_, d)| secs(d)).fold(0.0, f64::max);
+    let mut with_parts = segments.len();
+    let mut tail = 0.0;
+    for (i, s) in segments.iter().enumerate().rev() {
+        if tail > 3.0 * target_duration as f64 { break; }
+        with_parts = i;
+        tail += secs(s.duration());
+    }
+
+    let mut m = String::new();
+    writeln!(m, "#EXTM3U").unwrap();
+    writeln!(m, "#EXT-X-VERSION:9").unwrap();
+    writeln!(m, "#EXT-X-TARGETDURATION:{target_duration}").unwrap();
+    writeln!(m, "#EXT-X-PART-INF:PART-TARGET={part_target:.5}").unwrap();
+    writeln!(m, "#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK={:.5}", 3.0 * part_target).unwrap();
+    writeln!(m, "#EXT-X-MEDIA-SEQUENCE:{}", segments[0].group).unwrap();
+    writeln!(m, "#EXT-X-MAP:URI=\"init.mp4{query}\"").unwrap();
+    for (i, s) in segments.iter().enumerate() {
+        if i >= with_parts {
+            for (j, &(object, duration)) in s.parts.iter().enumerate() {
+                let independent = if j == 0 { ",INDEPENDENT=YES" } else { "" };
+                writeln!(m, "#EXT-X-PART:DURATION={:.5},URI=\"{}/{}{query}\"{independent}", secs(duration), s.group, object).unwrap();
+            }
+        }
+        if s.complete {
+            writeln!(m, "#EXTINF:{:.5},", secs(s.duration())).unwrap();
+            writeln!(m, "{}.m4s{query}", s.group).unwrap();
+        }
+    }
+    let last = segments.last().unwrap();
+    let next = match last.parts.last() {
+        Some(&(object, _)) if !last.complete => Location { group: last.group, object: object + 1 },
+        _ if !last.complete => Location { group: last.group, object: 0 },
+        _ => Location { group: last.group + 1, object: 0 },
+    };
+    writeln!(m, "#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{}/{}{query}\"", next.group, next.object).unwrap();
+    m
+}
+
+/// LL-HLS multivariant playlist of a broadcast: a variant for each video track with the audio
+/// tracks as renditions, or a variant for each audio track if there is no video. Tracks are left
+/// out until their bandwidth is known. `None` if no track is left.
+pub(crate) fn multivariant_playlist(tracks: &[(CmafTrack, Vec<Segment>)], query: &str) -> Option<String> {
+    let with_bandwidth = |role: &str| {
+        tracks.iter()
+            .filter(|(t, _)| t.role() == role)
+            .filter_map(|(t, segments)| Some((t, t.bandwidth(segments)?)))
+            .collect::<Vec<_>>()
+    };
+    let video = with_bandwidth(ROLE_VIDEO);
+    let audio = with_bandwidth(ROLE_AUDIO);
+    if video.is_empty() && audio.is_empty() {
+        return None;
+    }
+
+    let mut m = String::new();
+    writeln!(m, "#EXTM3U").unwrap();
+    writeln!(m, "#EXT-X-VERSION:9").unwrap();
+    writeln!(m, "#EXT-X-INDEPENDENT-SEGMENTS").unwrap();
+    if video.is_empty() {
+        for (t, bandwidth) in &audio {
+            write!(m, "#EXT-X-STREAM-INF:BANDWIDTH={bandwidth}").unwrap();
+            if let Some(codec) = &t.track.codec {
+                write!(m, ",CODECS=\"{}\"", quoted(codec)).unwrap();
+            }
+            writeln!(m, "\n{}/media.m3u8{query}", t.path()).unwrap();
+        }
+        return Some(m);
+    }
+    for (i, (t, _)) in audio.iter().enumerate() {
+        let name = t.track.label.as_deref().unwrap_or(&t.track.name);
+        write!(m, "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"{}\"", quoted(name)).unwrap();
+        if let Some(lang) = &t.track.lang {
+            write!(m, ",LANGUAGE=\"{}\"", quoted(lang)).unwrap();
+        }
+        let default = if i == 0 { "YES" } else { "NO" };
+        writeln!(m, ",DEFAULT={default},AUTOSELECT=YES,URI=\"{}/media.m3u8{query}\"", t.path()).unwrap();
+    }
+    let audio_bandwidth = audio.iter().map(|&(_, b)| b).max().unwrap_or(0);
+    let audio_codec = audio.first().and_then(|(t, _)| t.track.codec.as_deref());
+    for (t, bandwidth) in &video {
+        write!(m, "#EXT-X-STREAM-INF:BANDWIDTH={}", bandwidth + audio_bandwidth).unwrap();
+        let codecs: Vec<&str> = [t.track.codec.as_deref(), audio_codec].into_iter().flatten().collect();
+        if !codecs.is_empty() {
+            write!(m, ",CODECS=\"{}\"", quoted(&codecs.join(","))).unwrap();
+        }
+        if let (Some(width), Some(height)) = (t.track.width, t.track.height) {
+            write!(m, ",RESOLUTION={width}x{height}").unwrap();
+        }
+        if let Some(framerate) = t.track.framerate {
+            write!(m, ",FRAME-RATE={framerate:.3}").unwrap();
+        }
+        if !audio.is_empty() {
+            write!(m, ",AUDIO=\"audio\"").unwrap();
+        }
+        writeln!(m, "\n{}/media.m3u8{query}", t.path()).unwrap();
+    }
+    Some(m)
+}
+
+/// Dynamic DASH MPD of a broadcast: an adaptation set for each role and language, and a
+/// representation with a segment timeline of the complete segments for each track. The decode
+/// times of the fragments are the segment times, so the tracks have to share a media timeline,
+/// which starts at `availability_start`. Tracks are left out until their bandwidth is known;
+/// `None` if no track is left.
+pub(crate) fn mpd(tracks: &[(CmafTrack, Vec<Segment>)], availability_start: SystemTime, now: SystemTime, query: &str) -> Option<String> {
+    let tracks: Vec<(&CmafTrack, Vec<&Segment>, u64)> = tracks.iter()
+        .filter_map(|(t, segments)| {
+            let complete: Vec<&Segment> = segments.iter().filter(|s| s.complete).collect();
+            let bandwidth = t.bandwidth(segments)?;
+            (!complete.is_empty()).then_some((t, complete, bandwidth))
+        })
+        .collect();
+    let secs = |t: &CmafTrack, ticks: u64| ticks as f64 / f64::from(t.timing.timescale);
+    let max_segment = tracks.iter()
+        .flat_map(|(t, segments, _)| segments.iter().map(move |s| secs(t, s.duration())))
+        .fold(0.0, f64::max);
+    let depth = tracks.iter()
+        .map(|(t, segments, _)| secs(t, segments.iter().map(|s| s.duration()).sum()))
+        .fold(0.0, f64::max);
+    let mut sets: Vec<((&str, Option<&str>), Vec<&(&CmafTrack, Vec<&Segment>, u64)>)> = vec![];
+    for entry in &tracks {
+        let key = (entry.0.role(), entry.0.track.lang.as_deref());
+        match sets.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, entries)) => entries.push(entry),
+            None => sets.push((key, vec![entry])),
+        }
+    }
+    if sets.is_empty() {
+        return None;
+    }
+
+    let mut m = String::new();
+    writeln!(m, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        m,
+        r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-live:2011,urn:mpeg:dash:profile:cmaf:2019" type="dynamic" availabilityStartTime="{}" publishTime="{}" minimumUpdatePeriod="PT{max_segment:.3}S" minBufferTime="PT{max_segment:.3}S" timeShiftBufferDepth="PT{depth:.3}S" suggestedPresentationDelay="PT{:.3}S">"#,
+        date_time(availability_start),
+        date_time(now),
+        3.0 * max_segment,
+    ).unwrap();
+    writeln!(m, r#"  <Period id="0" start="PT0S">"#).unwrap();
+    for (id, ((role, lang), entries)) in sets.iter().enumerate() {
+        write!(m, r#"    <AdaptationSet id="{id}" contentType="{}" segmentAlignment="true" startWithSAP="1""#, xml_escape(role)).unwrap();
+        if let Some(lang) = lang {
+            write!(m, r#" lang="{}""#, xml_escape(lang)).unwrap();
+        }
+        writeln!(m, ">").unwrap();
+        for (t, segments, bandwidth) in entries {
+            let path = t.path();
+            write!(m, r#"      <Representation id="{path}" mimeType="{}" bandwidth="{bandwidth}""#, xml_escape(&t.content_type())).unwrap();
+            if let Some(codec) = &t.track.codec {
+                write!(m, r#" codecs="{}""#, xml_escape(codec)).unwrap();
+            }
+            if let (Some(width), Some(height)) = (t.track.width, t.track.height) {
+                write!(m, r#" width="{width}" height="{height}""#).unwrap();
+            }
+            if let Some(framerate) = t.track.framerate {
+                write!(m, r#" frameRate="{framerate}""#).unwrap();
+            }
+            if let Some(samplerate) = t.track.samplerate {
+                write!(m, r#" audioSamplingRate="{samplerate}""#).unwrap();
+            }
+            writeln!(m, ">").unwrap();
+            writeln!(
+                m,
+                r#"        <SegmentTemplate timescale="{}" initialization="{path}/init.mp4{}" media="{path}/$Number$.m4s{}" startNumber="{}">"#,
+                t.timing.timescale,
+                xml_escape(query),
+                xml_escape(query),
+                segments[0].group,
+            ).unwrap();
+            writeln!(m, "          <SegmentTimeline>").unwrap();
+            let mut next = None;
+            for s in segments {
+                // t is only needed where the timeline does not continue seamlessly
+                if next == Some(s.start) {
+                    writeln!(m, r#"            <S d="{}"/>"#, s.duration()).unwrap();
+                } else {
+                    writeln!(m, r#"            <S t="{}" d="{}"/>"#, s.start, s.duration()).unwrap();
+                }
+                next = Some(s.start + s.duration());
+            }
+            writeln!(m, "          </SegmentTimeline>").unwrap();
+            writeln!(m, "        </SegmentTemplate>").unwrap();
+            writeln!(m, "      </Representation>").unwrap();
+        }
+        writeln!(m, "    </AdaptationSet>").unwrap();
+    }
+    writeln!(m, "  </Period>").unwrap();
+    writeln!(m, "</MPD>").unwrap();
+    Some(m)
+}
+
+/// Value of a quoted-string attribute of a playlist, which cannot hold quotes or line breaks.
+fn quoted(s: &str) -> String {
+    s.chars().filter(|c| !matches!(c, '"' | '\r' | '\n')).collect()
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `t` as xs:dateTime in UTC with milliseconds, e.g. `2026-10-18T12:00:00.000Z`.
+fn date_time(t: SystemTime) -> String {
+    let since_epoch = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    // civil date from days since the epoch, see https://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let time = secs % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use quiche_moq::wire::KeyValuePairs;
+    use quiche_moq_catalog::CatalogPublisher;
+    use super::*;
+    use crate::cache::CacheLimits;
+    use crate::cmaf::tests::{fragment, init};
+
+    const LIMITS: CacheLimits = CacheLimits {
+        max_objects: 100,
+        max_bytes: 1 << 20,
+        max_duration: Duration::from_secs(10),
+    };
+
+    fn push(cache: &mut TrackCache, group: u64, object: u64, payload: &[u8]) {
+        let o = cache.push(Location { group, object }, 0, payload.len(), None, KeyValuePairs::new(), Instant::now());
+        o.payload.copy_from_slice(payload);
+        o.written = payload.len();
+    }
+
+    fn cmaf_track(name: &str, role: &str, bitrate: Option<u64>) -> Track {
+        let mut track = Track::new(name, PACKAGING_CMAF);
+        track.role = Some(role.to_string());
+        track.init_data = Some(STANDARD.encode(init(1000)));
+        track.bitrate = bitrate;
+        track
+    }
+
+    /// Groups 4 and 5 of two parts of 500 ms each, and the first part of group 6.
+    fn video_cache() -> TrackCache {
+        let mut cache = TrackCache::new(LIMITS);
+        for group in 4..7 {
+            for object in 0..2 {
+                if group == 6 && object == 1 { break; }
+                push(&mut cache, group, object, &fragment(group * 1000 + object * 500, 5, 100));
+            }
+            if group < 6 {
+                cache.finish_subgroup(group, 0, false);
+            }
+        }
+        cache
+    }
+
+    #[test]
+    fn catalog_with_delta_updates() {
+        let mut publisher = CatalogPublisher::new(Catalog::new(vec![cmaf_track("video", ROLE_VIDEO, None)])).unwrap();
+        let mut updated = publisher.catalog().clone();
+        updated.tracks.push(cmaf_track("audio", ROLE_AUDIO, None));
+        publisher.update(updated.clone()).unwrap();
+        let mut cache = TrackCache::new(LIMITS);
+        for object in 0..2 {
+            let location = Location { group: 0, object };
+            push(&mut cache, 0, object, publisher.object(location).unwrap());
+        }
+        assert_eq!(catalog(&cache), Some(updated.clone()));
+
+        let tracks = cmaf_tracks(&updated, &"live".parse().unwrap());
+        assert_eq!(tracks.iter().map(|t| t.path()).collect::<Vec<_>>(), ["video", "audio"]);
+        assert_eq!(tracks[1].content_type(), "audio/mp4");
+        assert_eq!(tracks[0].timing.timescale, 1000);
+    }
+
+    #[test]
+    fn segments_and_media_playlist() {
+        let cache = video_cache();
+        let timing = Timing::from_init(&init(1000)).unwrap();
+        let segments = segments(&cache, &timing);
+        assert_eq!(segments.iter().map(|s| (s.group, s.start, s.complete)).collect::<Vec<_>>(), [
+            (4, 4000, true),
+            (5, 5000, true),
+            (6, 6000, false),
+        ]);
+        assert_eq!(segments[0].parts, [(0, 500), (1, 500)]);
+        assert_eq!(segments[0].payload(&cache), [fragment(4000, 5, 100), fragment(4500, 5, 100)].concat());
+        assert_eq!(target_duration(&segments, 1000), Duration::from_secs(1));
+        assert!(has_part(&segments, 6, Some(0)));
+        assert!(!has_part(&segments, 6, Some(1)));
+        assert!(!has_part(&segments, 6, None));
+        assert!(has_part(&segments, 5, None));
+
+        assert_eq!(media_playlist(&segments, 1000, "?token=t"), "\
+#EXTM3U
+#EXT-X-VERSION:9
+#EXT-X-TARGETDURATION:1
+#EXT-X-PART-INF:PART-TARGET=0.50000
+#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK=1.50000
+#EXT-X-MEDIA-SEQUENCE:4
+#EXT-X-MAP:URI=\"init.mp4?token=t\"
+#EXT-X-PART:DURATION=0.50000,URI=\"4/0?token=t\",INDEPENDENT=YES
+#EXT-X-PART:DURATION=0.50000,URI=\"4/1?token=t\"
+#EXTINF:1.00000,
+4.m4s?token=t
+#EXT-X-PART:DURATION=0.50000,URI=\"5/0?token=t\",INDEPENDENT=YES
+#EXT-X-PART:DURATION=0.50000,URI=\"5/1?token=t\"
+#EXTINF:1.00000,
+5.m4s?token=t
+#EXT-X-PART:DURATION=0.50000,URI=\"6/0?token=t\",INDEPENDENT=YES
+#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"6/1?token=t\"
+");
+    }
+
+    #[test]
+    fn segments_restart_after_gap() {
+        let mut cache = video_cache();
+        cache.finish_subgroup(6, 0, false);
+        push(&mut cache, 8, 0, &fragment(8000, 5, 100));
+        let timing = Timing::from_init(&init(1000)).unwrap();
+        let segments = segments(&cache, &timing);
+        assert_eq!(segments.iter().map(|s| s.group).collect::<Vec<_>>(), [8]);
+    }
+
+    #[test]
+    fn multivariant_and_mpd() {
+        let catalog = Catalog::new(vec![
+            Track { width: Some(1280), height: Some(720), codec: Some("avc1.64001f".to_string()), ..cmaf_track("video", ROLE_VIDEO, None) },
+            Track { lang: Some("en".to_string()), codec: Some("mp4a.40.2".to_string()), ..cmaf_track("audio", ROLE_AUDIO, Some(128000)) },
+        ]);
+        let namespace: Namespace = "live".parse().unwrap();
+        let timing = Timing::from_init(&init(1000)).unwrap();
+        let tracks: Vec<_> = cmaf_tracks(&catalog, &namespace).into_iter()
+            .map(|t| {
+                let segments = if t.path() == "video" { segments(&video_cache(), &timing) } else { vec![] };
+                (t, segments)
+            })
+            .collect();
+        let bandwidth = (2 * fragment(0, 5, 100).len() * 8) as u64;
+        assert_eq!(multivariant_playlist(&tracks, "").unwrap(), format!("\
+#EXTM3U
+#EXT-X-VERSION:9
+#EXT-X-INDEPENDENT-SEGMENTS
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"audio\",LANGUAGE=\"en\",DEFAULT=YES,AUTOSELECT=YES,URI=\"audio/media.m3u8\"
+#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"avc1.64001f,mp4a.40.2\",RESOLUTION=1280x720,AUDIO=\"audio\"
+video/media.m3u8
+", bandwidth + 128000));
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut packager = Packager::new(vec![]);
+        let availability_start = packager.availability_start(&namespace, &tracks, now).unwrap();
+        // the end of segment 5 is available now
+        assert_eq!(availability_start, now - Duration::from_secs(6));
+        let mpd = mpd(&tracks, availability_start, now, "").unwrap();
+        assert!(mpd.contains(r#"availabilityStartTime="1970-01-12T13:46:34.000Z""#), "{mpd}");
+        assert!(mpd.contains(r#"<SegmentTemplate timescale="1000" initialization="video/init.mp4" media="video/$Number$.m4s" startNumber="4">"#), "{mpd}");
+        assert!(mpd.contains("<S t=\"4000\" d=\"1000\"/>\n            <S d=\"1000\"/>\n"), "{mpd}");
+        // no segments of the audio track yet
+        assert!(!mpd.contains("audio/init.mp4"), "{mpd}");
+    }
+
+    #[test]
+    fn date_times() {
+        assert_eq!(date_time(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(date_time(UNIX_EPOCH + Duration::from_millis(951_782_400_250)), "2000-02-29T00:00:00.250Z");
+    }
+}
@@ -0,0 +1,702 @@
+//! The work of [`Relay::process`](crate::Relay::process), in phases over all connections.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant, SystemTime};
+use log::{error, info};
+use quiche_moq as moq;
+use quiche_moq::wire::{GOAWAY_TIMEOUT, Location, NO_ERROR, REQUEST_ERROR_DOES_NOT_EXIST, REQUEST_ERROR_INTERNAL_ERROR};
+use crate::acl::Action;
+use crate::admin::Session;
+use crate::drain::Drain;
+use crate::{Endpoint, HeldRequest, admin, drain, gateway, metrics};
+use crate::forwarding::{forward_subgroup, read_subgroup};
+use crate::routing::{peer_ip, post_handle_recvs_conn, route};
+use crate::subscription::{DownstreamSubgroup, PublisherInfo};
+
+/// Relay work after the endpoint handled received packets, see [`Relay::process`](crate::Relay::process).
+pub(crate) fn post_handle_recvs(endpoint: &mut Endpoint) -> Option<Duration> {
+    let relay = endpoint.app_data_mut();
+    if relay.watch_sigterm && drain::sigterm_received() {
+        relay.drain();
+    }
+    // the sessions are closed, wait for the connections to be closed
+    if let Some(Drain::Closing { .. }) = relay.drain {
+        return relay.timeout();
+    }
+
+    remove_closed_connections(endpoint);
+    process_sessions(endpoint);
+    announce_namespaces(endpoint);
+    send_subscriptions(endpoint);
+    poll_subscribe_responses(endpoint);
+    accept_subscribers(endpoint);
+    if end_subscriptions(endpoint) {
+        return endpoint.app_data_mut().timeout();
+    }
+    forward_objects(endpoint);
+    serve_fetches(endpoint);
+    answer_http_requests(endpoint);
+
+    // Phase 8: Answer admin requests.
+    if endpoint.app_data_mut().admin.is_some() {
+        serve_admin(endpoint);
+    }
+    endpoint.app_data_mut().timeout()
+}
+
+/// Phase 0: Detect closed connections and clean up state.
+/// Namespace un-announcement happens in Phase 2; subscriber notifications in Phase 4.6.
+fn remove_closed_connections(endpoint: &mut Endpoint) {
+    let (conns, appdata) = endpoint.mut_conns_and_app_data();
+    for (cid, conn) in conns.iter_mut() {
+        if conn.conn.is_closed() {
+            info!("Client {} disconnected", cid);
+            appdata.withdraw_namespaces(cid, |_| false);
+            for (nt, sub) in appdata.subscriptions.iter_mut() {
+                sub.subscribers.retain(|s| s.client_id != cid);
+                if sub.publisher.as_ref().is_some_and(|p| p.client_id == cid) && sub.publisher_gone(nt, &appdata.namespaces) {
+                    appdata.metrics.failovers += 1;
+                }
+            }
+            for f in &mut appdata.fetches {
+                if f.client_id == cid {
+                    f.response.closed = true;
+                }
+                if let Some(up) = f.upstream.as_mut() && up.client_id == cid && !up.fin {
+                    up.error.get_or_insert(REQUEST_ERROR_INTERNAL_ERROR);
+                }
+            }
+        }
+    }
+}
+
+/// H3_EXCESSIVE_LOAD, for connections refused over `[limits] sessions_per_ip`.
+const H3_EXCESSIVE_LOAD: u64 = 0x107;
+
+/// Phase 1: Per-connection processing (receive plain HTTP/3 requests, subscriptions, namespace publishes)
+fn process_sessions(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    let mut sessions_per_ip: HashMap<IpAddr, usize> = HashMap::new();
+    for (icid, conn) in conns.iter_mut() {
+        if conn.app_data.admitted && !conn.conn.is_closed() && !appdata.upstreams.contains(&icid)
+            && let Some(ip) = peer_ip(&conn.conn)
+        {
+            *sessions_per_ip.entry(ip).or_default() += 1;
+        }
+    }
+    for (icid, conn) in conns.iter_mut() {
+        let peer = peer_ip(&conn.conn);
+        // refuse a new connection over the limit before its session is set up
+        if !conn.app_data.admitted && !conn.conn.is_closed() && !appdata.upstreams.contains(&icid) {
+            if let Some(ip) = peer {
+                let sessions = sessions_per_ip.entry(ip).or_default();
+                if *sessions >= appdata.limits.sessions_per_ip {
+                    info!("refuse connection {} from {} (too many sessions)", icid, ip);
+                    conn.conn.close(true, H3_EXCESSIVE_LOAD, b"too many sessions").ok();
+                    appdata.metrics.quota_rejections += 1;
+                    continue;
+                }
+                *sessions += 1;
+            }
+            conn.app_data.admitted = true;
+        }
+        conn.app_data.moq_helper.on_post_handle_recvs(&mut conn.conn);
+        while let Some(req) = conn.app_data.moq_helper.next_http_request() {
+            appdata.http_requests.push(HeldRequest { client_id: icid, peer, req, since: Instant::now() });
+            appdata.metrics.http_requests += 1;
+        }
+        let new_session = !conn.app_data.logged_connect && !appdata.upstreams.contains(&icid);
+        let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { continue };
+        if new_session && moq.is_closed() {
+            continue;
+        }
+        if new_session && moq.version().is_some() && appdata.drain.is_some() {
+            info!("close session {} (draining)", icid);
+            moq.close(GOAWAY_TIMEOUT, "draining");
+            continue;
+        }
+        post_handle_recvs_conn(
+            icid,
+            moq,
+            appdata,
+            &mut conn.app_data.logged_connect,
+            &mut conn.app_data.tokens,
+            &mut conn.app_data.held_requests,
+        );
+    }
+}
+
+/// Phase 2: Un-announce gone namespaces and announce new ones to all connected MoQ clients.
+/// While draining, all namespaces are un-announced and the clients get GOAWAY.
+fn announce_namespaces(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    let draining = appdata.drain.is_some();
+    for (icid, conn) in conns.iter_mut() {
+        let peer = peer_ip(&conn.conn);
+        let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { continue };
+        conn.app_data.announced_namespaces.retain(|ns, rid| {
+            // a standby publisher that took over the namespace was announced it before
+            if !draining && appdata.namespaces.get(ns).is_some_and(|&p| p != icid) { true } else { moq.publish_namespace_done(*rid); false }
+        });
+        if draining {
+            if !conn.app_data.goaway_sent && moq.version().is_some() && !appdata.upstreams.contains(&icid) {
+                info!("send GOAWAY to {}", icid);
+                moq.goaway(appdata.drain_config.redirect.as_deref());
+                conn.app_data.goaway_sent = true;
+            }
+            continue;
+        }
+        for (ns, &publisher) in appdata.namespaces.iter() {
+            if publisher == icid { continue; }
+            if conn.app_data.announced_namespaces.contains_key(&ns) { continue; }
+            if !appdata.acl.allows(&ns, peer, Action::Subscribe) { continue; }
+            let Some(tokens) = conn.app_data.tokens.as_mut() else { continue };
+            if appdata.authorize(icid, tokens, None, &ns, Action::Subscribe).is_err() { continue; }
+            match moq.publish_namespace(ns.0.0.clone()) {
+                Ok(request_id) => {
+                    info!("announced namespace {} to {}", ns, icid);
+                    conn.app_data.announced_namespaces.insert(ns.clone(), request_id);
+                }
+                // announced once the peer grants more request IDs
+                Err(moq::Error::RequestBlocked) => {}
+                Err(e) => {
+                    error!("failed to announce namespace {} to {}: {:?}", ns, icid, e);
+                }
+            }
+        }
+    }
+}
+
+/// Phase 3: Forward pending subscriptions to publishers
+fn send_subscriptions(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    for (nt, sub) in appdata.subscriptions.iter_mut() {
+        if sub.is_sent() { continue; }
+        // Populate publisher from current namespace map if not set (e.g. after reconnect)
+        if sub.publisher.is_none() {
+            let Some(pub_id) = route(&appdata.namespaces, &appdata.upstreams, nt.namespace(), |id| sub.subscribers.iter().any(|s| s.client_id == id)) else { continue };
+            sub.publisher = Some(PublisherInfo::new(pub_id, false));
+        }
+        let Some(pub_info) = sub.publisher.as_mut() else { continue };
+        let Some(conn) = conns.get_mut(pub_info.client_id) else { continue };
+        let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { continue };
+        match moq.subscribe(nt) {
+            Ok(request_id) => {
+                pub_info.request_id = Some(request_id);
+                info!("sent subscription request {} to {}", nt, pub_info.client_id);
+            }
+            Err(e) => {
+                error!("failed to subscribe {} on publisher {}: {:?}", nt, pub_info.client_id, e);
+            }
+        }
+    }
+}
+
+/// Phase 4: Poll subscribe responses from publishers
+fn poll_subscribe_responses(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    appdata.subscriptions.retain(|nt, sub| {
+        let Some(pub_info) = sub.publisher.as_mut() else { return true };
+        let Some(request_id) = pub_info.request_id else { return true };
+        if pub_info.track_alias.is_some() { return true; }
+        let Some(conn) = conns.get_mut(pub_info.client_id) else { return true };
+        let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { return true };
+        let Some(response) = moq.poll_subscribe_response(request_id) else { return true };
+        match response {
+            Ok((track_alias, ok_msg)) => {
+                pub_info.track_alias = Some(track_alias);
+                pub_info.largest_location = ok_msg.largest_location();
+                if let Some(ms) = ok_msg.max_cache_duration() {
+                    sub.cache.limit_duration(Duration::from_millis(ms));
+                }
+                info!("accepted track {} by {}", nt, pub_info.client_id);
+                true
+            }
+            Err(e) => {
+                error!("publisher rejected {} with {} - {}", nt, e.error_code(), e.error_reason());
+                for s in &sub.subscribers {
+                    if let Some(sub_conn) = conns.get_mut(s.client_id)
+                        && let Some(mut moq) = sub_conn.app_data.moq_helper.moq_handle(&mut sub_conn.conn)
+                    {
+                        // accepted before a failover
+                        if s.is_accepted() {
+                            moq.publish_done(s.request_id);
+                        } else {
+                            moq.reject_subscription(s.request_id, e.error_code());
+                        }
+                    }
+                }
+                false
+            }
+        }
+    });
+}
+
+/// Phase 4.5: Accept pending subscribers now that publisher has accepted
+/// A subscriber whose start location is older than the cache starts with the recorded groups.
+fn accept_subscribers(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    for (nt, sub) in appdata.subscriptions.iter_mut() {
+        if !sub.is_publisher_accepted() { continue; }
+        let largest_location = sub.largest_location();
+        for s in &mut sub.subscribers {
+            if s.is_accepted() { continue; }
+            if let Some(sub_conn) = conns.get_mut(s.client_id)
+                && let Some(mut moq) = sub_conn.app_data.moq_helper.moq_handle(&mut sub_conn.conn) {
+                    s.track_alias = Some(moq.accept_subscription(s.request_id, largest_location));
+                    // a joining fetch may have pinned the start already
+                    let start = s.start.unwrap_or_else(|| s.resolve_start(largest_location));
+                    s.start = Some(start);
+                    info!("accept track for {} from {:?}", s.client_id, start);
+                    if let Some(archive) = appdata.archives.get(nt)
+                        && let Some(group) = s.next_archived_group(archive, &sub.cache, start.group)
+                    {
+                        match archive.read_group(group) {
+                            Ok(g) => {
+                                info!("forward {} to {} from the recording, starting with group {}", nt, s.client_id, group);
+                                s.replay = Some(g);
+                            }
+                            Err(e) => error!("failed to read group {} from {}: {}", group, archive.dir().display(), e),
+                        }
+                    }
+                }
+        }
+    }
+}
+
+/// Phase 4.6: Send PUBLISH_DONE to subscribers whose publisher disconnected, then remove them.
+/// Their open subgroup streams are finished or reset first. At the end of the drain grace
+/// period, all remaining subscribers get PUBLISH_DONE and the sessions are closed.
+/// Returns true once the sessions are closed.
+fn end_subscriptions(endpoint: &mut Endpoint) -> bool {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    let grace_over = matches!(appdata.drain, Some(Drain::Grace { deadline }) if Instant::now() >= deadline);
+    if grace_over {
+        for s in appdata.subscriptions.values_mut().flat_map(|sub| &mut sub.subscribers) {
+            s.publisher_gone = true;
+        }
+    }
+    for sub in appdata.subscriptions.values_mut() {
+        sub.subscribers.retain_mut(|s| {
+            if !s.publisher_gone { return true; }
+            if let Some(sub_conn) = conns.get_mut(s.client_id)
+                && let Some(mut moq) = sub_conn.app_data.moq_helper.moq_handle(&mut sub_conn.conn)
+            {
+                // Finish the subgroups the publisher finished if what is left of them fits, reset the others.
+                for (&(group, subgroup_id), d) in s.subgroups.iter_mut().filter(|(_, d)| !d.done) {
+                    if let Some(sub_ta) = s.track_alias {
+                        forward_subgroup(&sub.cache, group, subgroup_id, d, sub_ta, &mut moq, &mut appdata.metrics);
+                    }
+                    if !d.done && let Some(stream_id) = d.stream_id {
+                        moq.reset_subgroup(stream_id);
+                        appdata.metrics.stream_resets += 1;
+                        d.done = true;
+                    }
+                }
+                moq.publish_done(s.request_id);
+            }
+            false
+        });
+    }
+    if grace_over {
+        for (icid, conn) in conns.iter_mut() {
+            if conn.conn.is_closed() { continue; }
+            let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { continue };
+            if moq.version().is_none() { continue; }
+            let code = if appdata.upstreams.contains(&icid) { NO_ERROR } else { GOAWAY_TIMEOUT };
+            info!("close session {} (drained)", icid);
+            moq.close(code, "draining");
+        }
+        appdata.drain = Some(Drain::Closing { since: Instant::now() });
+        return true;
+    }
+    false
+}
+
+/// Phase 5: Forward object data from publishers to subscribers through the track cache.
+///
+/// Each subgroup stream of the publisher is read independently into sub.cache, so concurrent
+/// subgroups (e.g. layers of a scalable encoding) and overlapping groups are cached in parallel;
+/// several cached objects may still be incomplete. Each subscriber forwards every cached
+/// subgroup on its own downstream stream at its own pace:
+///   object: ID of the most recent object header sent on the subgroup's stream (None = not started).
+///   remaining: bytes of that object's payload still to forward.
+///              read_pos into the cached payload = payload length - remaining.
+///
+/// Loop steps per iteration:
+///   1. Forward: for each subscriber and each cached subgroup from its start location up to its
+///      end group, finish the current object from the cache, then send the header of the next
+///      cached object of the subgroup. The start location comes from the subscriber's filter, so
+///      an absolute start that is still cached is served from the cache. If the current object
+///      left the cache (evicted, or the publisher reset the subgroup mid-object), only that
+///      subgroup's stream is reset. A subgroup finished by the publisher is finished downstream
+///      once all its cached objects are forwarded. Once a group after the end group is cached
+///      and all subgroups up to the end group are done, the subscriber gets PUBLISH_DONE.
+///   2. Read: consume more payload of the live object or the next object header of every
+///      readable publisher stream into the cache.
+///   3. Break when the publisher made no progress.
+///
+/// Subscribers that start before the cache are first served from the track's recording, one
+/// recorded group at a time, until the next group is cached. Every complete object read in
+/// step 2 is also appended to the recording.
+///
+/// The publisher is never blocked by slow subscribers. A subscriber that cannot receive
+/// right now keeps its position and catches up on later post_handle_recvs calls, as long as
+/// the cache still holds the objects it is behind on. Once its backlog of cached but not yet
+/// forwarded bytes exceeds the limit, it skips to the newest group: the streams of older
+/// subgroups are reset with DELIVERY_TIMEOUT and forwarding resumes at the group boundary.
+fn forward_objects(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    for (nt, sub) in appdata.subscriptions.iter_mut() {
+        let Some((pub_id, pub_ta)) = sub.publisher.as_ref().and_then(|p| p.track_alias.map(|ta| (p.client_id, ta))) else { continue };
+
+        loop {
+            // Step 1: Forward cached subgroups to each accepted subscriber.
+            for s in sub.subscribers.iter_mut() {
+                if s.ended { continue; }
+                let Some(sub_ta) = s.track_alias else { continue };
+                let Some(sub_conn) = conns.get_mut(s.client_id) else { continue };
+                let Some(mut moq) = sub_conn.app_data.moq_helper.moq_handle(&mut sub_conn.conn) else { continue };
+                if let Some(archive) = appdata.archives.get(nt)
+                    && s.forward_archive(archive, &sub.cache, sub_ta, &mut moq, &mut appdata.metrics)
+                {
+                    continue;
+                }
+                let Some(start) = s.start else { continue };
+                let mut start_group = start.group;
+                if let Some(newest) = sub.cache.largest_location().map(|l| l.group)
+                    && newest > start_group
+                    && s.end_group.is_none_or(|end_group| newest <= end_group)
+                    && s.backlog(&sub.cache) > appdata.max_subscriber_backlog
+                {
+                    let skipped = s.skip_to_group(newest, &sub.cache, &mut moq, &mut appdata.metrics);
+                    info!("subscriber {} fell behind on {}, skipped {} groups to group {}", s.client_id, nt, skipped, newest);
+                    start_group = newest;
+                }
+                let keys: Vec<(u64, u64)> = sub.cache.subgroups_from(start_group)
+                    .map(|(k, _)| k)
+                    .take_while(|&(group, _)| s.end_group.is_none_or(|end_group| group <= end_group))
+                    .collect();
+                for (group, subgroup_id) in keys {
+                    let skipped_before = s.skipped_before(group);
+                    let d = s.subgroups.entry((group, subgroup_id))
+                        .or_insert_with(|| DownstreamSubgroup { object: skipped_before, ..Default::default() });
+                    if d.done { continue; }
+                    forward_subgroup(&sub.cache, group, subgroup_id, d, sub_ta, &mut moq, &mut appdata.metrics);
+                }
+                // Subgroups that left the cache can no longer be completed.
+                s.subgroups.retain(|&(group, subgroup_id), d| {
+                    if sub.cache.subgroup(group, subgroup_id).is_some() { return true; }
+                    if !d.done && let Some(stream_id) = d.stream_id {
+                        error!("reset stream for subscriber {} on {}: subgroup {}/{} is no longer cached", s.client_id, nt, group, subgroup_id);
+                        moq.reset_subgroup(stream_id);
+                        appdata.metrics.stream_resets += 1;
+                    }
+                    false
+                });
+                if s.is_past_end(&sub.cache) {
+                    info!("subscriber {} on {} reached end group {}", s.client_id, nt, s.end_group.unwrap());
+                    moq.publish_done(s.request_id);
+                    s.ended = true;
+                }
+            }
+
+            // Step 2: Consume data from each publisher stream — more payload of its live object,
+            // or its next object header once the previous object is complete.
+            // Subscribers pick up the new data in step 1 of the next iteration.
+            let mut pub_progress = false;
+            let mut publisher_fin = false;
+            let mut completed = vec![];
+            if let Some(p) = sub.publisher.as_mut()
+                && let Some(pub_conn) = conns.get_mut(pub_id)
+                && let Some(mut moq) = pub_conn.app_data.moq_helper.moq_handle(&mut pub_conn.conn)
+            {
+                for stream_id in moq.readable_streams(pub_ta).to_vec() {
+                    let mut up = p.streams.remove(&stream_id).unwrap_or_default();
+                    let finished = read_subgroup(&mut sub.cache, p, &mut up, stream_id, pub_ta, nt, &mut moq, &mut pub_progress, &mut completed);
+                    if !finished {
+                        p.streams.insert(stream_id, up);
+                    }
+                }
+                publisher_fin = moq.track_done(pub_ta);
+            }
+            if let Some(archive) = appdata.archives.get_mut(nt) {
+                let now = SystemTime::now();
+                for location in completed {
+                    let Some(o) = sub.cache.get(location) else { continue };
+                    if let Err(e) = archive.record(o, now) {
+                        error!("failed to record {:?} of {}: {}", location, nt, e);
+                    }
+                }
+            }
+
+            if publisher_fin {
+                info!("publisher done for {}", nt);
+                sub.publisher = None;
+                for s in &mut sub.subscribers { s.publisher_gone = true; }
+                break;
+            }
+
+            // Step 3: Continue only while the publisher makes progress.
+            if !pub_progress { break; }
+        }
+        sub.subscribers.retain(|s| !s.ended);
+    }
+}
+
+/// Phase 6: Serve FETCH requests.
+///
+/// A fetch whose start is cached (decided in Phase 1) is served from the subscription's track
+/// cache, up to the newest object cached when it is accepted. A fetch whose start is recorded
+/// is served from the recording, one recorded group at a time. Otherwise the relay fetches the
+/// range from the publisher and accepts the downstream fetch with the publisher's FETCH_OK.
+/// Objects received upstream are forwarded one at a time, and also added to the subscription's
+/// cache when the range reaches its oldest object, so later fetches are answered locally.
+fn serve_fetches(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    let subscriptions = &mut appdata.subscriptions;
+    let archives = &appdata.archives;
+    let metrics = &mut appdata.metrics;
+    appdata.fetches.retain_mut(|f| {
+        // Send the upstream FETCH, poll its response or cancel it.
+        if let Some(up) = f.upstream.as_mut()
+            && up.error.is_none()
+            && !up.fin
+            && let Some(pub_conn) = conns.get_mut(up.client_id)
+            && let Some(mut moq) = pub_conn.app_data.moq_helper.moq_handle(&mut pub_conn.conn)
+        {
+            match up.request_id {
+                Some(request_id) if f.response.closed => moq.fetch_cancel(request_id),
+                None if f.response.closed => {}
+                None => match moq.fetch(&f.nt, up.range) {
+                    Ok(request_id) => {
+                        up.request_id = Some(request_id);
+                        info!("sent fetch {} {:?} to {}", f.nt, up.range, up.client_id);
+                    }
+                    Err(moq::Error::RequestBlocked) => {}
+                    Err(e) => {
+                        error!("failed to fetch {} from {}: {:?}", f.nt, up.client_id, e);
+                        up.error = Some(REQUEST_ERROR_INTERNAL_ERROR);
+                    }
+                },
+                Some(request_id) if up.ok.is_none() => match moq.poll_fetch_response(request_id) {
+                    Some(Ok(ok)) => {
+                        let end = ok.end_location();
+                        up.ok = Some((ok.end_of_track(), Location { group: end.group + up.group_offset, ..end }));
+                    }
+                    Some(Err(e)) => {
+                        error!("publisher rejected fetch {} with {} - {}", f.nt, e.error_code(), e.error_reason());
+                        up.error = Some(e.error_code());
+                    }
+                    None => {}
+                },
+                Some(_) => {}
+            }
+        }
+        if f.response.closed { return false; }
+
+        // Accept or reject downstream.
+        {
+            let Some(conn) = conns.get_mut(f.client_id) else { return false };
+            let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { return true };
+            if let Some(error_code) = f.upstream.as_ref().and_then(|up| up.error) {
+                if f.response.end_location.is_some() {
+                    moq.reset_fetch(f.response.request_id);
+                } else if let Err(e) = moq.reject_fetch(f.response.request_id, error_code) {
+                    error!("failed to reject fetch {} {:?} for {}: {:?}", f.nt, f.response.range, f.client_id, e);
+                }
+                return false;
+            }
+            if f.response.end_location.is_none() {
+                let (end_of_track, end_location) = match &f.upstream {
+                    Some(up) => match up.ok {
+                        Some(ok) => ok,
+                        None => return true,
+                    },
+                    None if f.archived.is_some() => (false, archives[&f.nt].last_in(&f.response.range).unwrap_or(f.response.range.start)),
+                    None => {
+                        let Some(sub) = subscriptions.get(&f.nt) else {
+                            if let Err(e) = moq.reject_fetch(f.response.request_id, REQUEST_ERROR_DOES_NOT_EXIST) {
+                                error!("failed to reject fetch {} {:?} for {}: {:?}", f.nt, f.response.range, f.client_id, e);
+                            }
+                            return false;
+                        };
+                        (false, sub.cache.last_in(&f.response.range).unwrap_or(f.response.range.start))
+                    }
+                };
+                match moq.accept_fetch(f.response.request_id, end_of_track, end_location) {
+                    Ok(()) => {
+                        f.response.end_location = Some(end_location);
+                        info!("accepted fetch {} {:?} for {}", f.nt, f.response.range, f.client_id);
+                    }
+                    Err(moq::Error::InsufficientCapacity) => return true,
+                    Err(e) => {
+                        // canceled before it was accepted, or the session is gone
+                        info!("drop fetch {} {:?} for {}: {:?}", f.nt, f.response.range, f.client_id, e);
+                        f.response.closed = true;
+                        return true;
+                    }
+                }
+            }
+        }
+
+        loop {
+            // Step 1: Forward cached objects of the range downstream.
+            {
+                let Some(conn) = conns.get_mut(f.client_id) else { return false };
+                let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { return true };
+                if let Some(g) = f.archived.as_mut() {
+                    // continue with the next recorded group once this one is forwarded
+                    let archive = &archives[&f.nt];
+                    let end_location = f.response.end_location.unwrap();
+                    let next = archive.next_group(g.group + 1).filter(|&group| group <= end_location.group);
+                    f.response.forward(&g.cache, next.is_none(), &mut moq, metrics);
+                    let Some(group) = next else { return true };
+                    if f.response.closed || !g.cache.largest_location().is_none_or(|l| f.response.has_forwarded(l)) {
+                        return true;
+                    }
+                    match archive.read_group(group) {
+                        Ok(next) => *g = next,
+                        Err(e) => {
+                            error!("failed to read group {} from {}: {}", group, archive.dir().display(), e);
+                            moq.reset_fetch(f.response.request_id);
+                            f.response.closed = true;
+                            return true;
+                        }
+                    }
+                    continue;
+                }
+                match &f.upstream {
+                    Some(up) => f.response.forward(&up.cache, up.fin, &mut moq, metrics),
+                    None => match subscriptions.get(&f.nt) {
+                        Some(sub) => f.response.forward(&sub.cache, true, &mut moq, metrics),
+                        None => {
+                            moq.reset_fetch(f.response.request_id);
+                            f.response.closed = true;
+                        }
+                    },
+                }
+            }
+            if f.response.closed { return true; }
+
+            // Step 2: Read more of the current object from the publisher, or the next object
+            // once the current one has been forwarded.
+            let Some(up) = f.upstream.as_mut() else { return true };
+            if up.fin { return true; }
+            let Some(request_id) = up.request_id else { return true };
+            let Some(pub_conn) = conns.get_mut(up.client_id) else { return true };
+            let Some(mut moq) = pub_conn.app_data.moq_helper.moq_handle(&mut pub_conn.conn) else { return true };
+            let mut progress = false;
+            let mut completed = None;
+            if let Some(o) = up.cache.live_mut() {
+                match moq.read_fetch_obj_pld(&mut o.payload[o.written..], request_id) {
+                    Ok(n) => {
+                        o.written += n;
+                        progress = true;
+                        if o.is_complete() { completed = Some(o.location); }
+                    }
+                    Err(moq::Error::Done) => {}
+                    Err(e) => {
+                        // subscribers of the incomplete object get their fetch reset in step 1
+                        error!("fetch {} from {} ended mid-object: {:?}", f.nt, up.client_id, e);
+                        up.cache.remove_live();
+                        up.fin = true;
+                        progress = true;
+                    }
+                }
+            } else if up.cache.largest_location() == f.response.location() {
+                match moq.read_fetch_obj_hdr(request_id) {
+                    Ok(hdr) => {
+                        let location = Location { group: hdr.group_id() + up.group_offset, ..hdr.location() };
+                        up.cache.push(location, hdr.subgroup_id(), hdr.payload_len(), hdr.status(), hdr.extension_headers().clone(), Instant::now());
+                        progress = true;
+                        if hdr.payload_len() == 0 { completed = Some(location); }
+                    }
+                    Err(moq::Error::Done) => {}
+                    Err(moq::Error::Fin) => { up.fin = true; progress = true; }
+                    Err(e) => {
+                        error!("read fetch obj hdr for {}: {:?}", f.nt, e);
+                        up.fin = true;
+                        progress = true;
+                    }
+                }
+            }
+
+            // Keep the object if it extends the subscription's cache backwards without a gap.
+            if let Some(location) = completed
+                && let Some(sub) = subscriptions.get_mut(&f.nt)
+                && sub.cache.first_location().is_some_and(|first| f.response.range.contains(first))
+            {
+                let o = up.cache.get(location).unwrap();
+                sub.cache.insert(location, o.subgroup_id, o.status, o.ext_hdrs.clone(), o.payload.clone(), Instant::now());
+            }
+
+            // Step 3: Continue only while the publisher makes progress.
+            if !progress { return true; }
+        }
+    });
+}
+
+/// Phase 7: Answer plain HTTP/3 requests from the track caches, see gateway.
+/// Requests for objects that are about to arrive, e.g. the next part of an LL-HLS playlist,
+/// are held until they are cached or waited too long. Packaged broadcasts are subscribed to
+/// here, and the subscriptions are sent in Phase 3 of the next call.
+fn answer_http_requests(endpoint: &mut Endpoint) {
+    let (conns, appdata) = &mut endpoint.mut_conns_and_app_data();
+    let now = Instant::now();
+    let mut requests = std::mem::take(&mut appdata.http_requests);
+    requests.retain(|h| {
+        let Some(conn) = conns.get_mut(h.client_id) else { return false };
+        if conn.conn.is_closed() { return false; }
+        let Some((headers, body)) = gateway::respond(&h.req, h.peer, now.duration_since(h.since), appdata) else { return true };
+        conn.app_data.moq_helper.send_http_response(&mut conn.conn, h.req.stream_id, headers, body);
+        false
+    });
+    appdata.http_requests = requests;
+}
+
+
+/// Answer the requests received by the admin listener, see [`AdminServer`](crate::admin::AdminServer).
+fn serve_admin(endpoint: &mut Endpoint) {
+    let (conns, appdata) = endpoint.mut_conns_and_app_data();
+    let Some(mut server) = appdata.admin.take() else { return };
+    let requests = server.poll();
+    appdata.admin = Some(server);
+    if requests.is_empty() { return; }
+    let mut sessions = vec![];
+    for (cid, conn) in conns.iter_mut() {
+        let peer = conn.conn.path_stats().next().map(|s| s.peer_addr);
+        let closed = conn.conn.is_closed();
+        let version = conn.app_data.moq_helper.moq_handle(&mut conn.conn).and_then(|moq| moq.version());
+        sessions.push(Session { client_id: cid, peer, version, closed });
+    }
+    for req in requests {
+        if let Some(cid) = req.close_session() {
+            match conns.get_mut(cid) {
+                Some(conn) if !conn.conn.is_closed() => {
+                    info!("admin closes connection {}", cid);
+                    conn.conn.close(true, 0, b"closed by admin").ok();
+                    req.respond_json(200, &serde_json::json!({ "closed": cid.to_string() }));
+                }
+                _ => req.respond(404, "text/plain", "no such session\n"),
+            }
+            continue;
+        }
+        match (req.method.as_str(), req.path.as_str()) {
+            ("GET", "/metrics") => {
+                let body = metrics::render(&sessions, appdata);
+                req.respond(200, "text/plain; version=0.0.4", &body);
+            }
+            ("GET", "/sessions") => req.respond_json(200, &admin::sessions_json(&sessions)),
+            ("GET", "/namespaces") => req.respond_json(200, &admin::namespaces_json(appdata)),
+            ("GET", "/subscriptions") => req.respond_json(200, &admin::subscriptions_json(appdata)),
+            ("POST", "/drain") => {
+                info!("admin drains the relay");
+                appdata.drain();
+                req.respond_json(200, &serde_json::json!({ "draining": true }));
+            }
+            _ => req.respond(404, "text/plain", "not found\n"),
+        }
+    }
+}
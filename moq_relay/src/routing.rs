@@ -0,0 +1,290 @@
+//! Routing of the requests of a session: SUBSCRIBE, FETCH and PUBLISH_NAMESPACE go to the
+//! publisher of the longest published prefix, the track cache or an upstream relay.
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+use log::{error, info};
+use quiche_mio_runner::quiche_endpoint::{quiche, ClientId};
+use quiche_moq::SubscriptionRequestAction;
+use quiche_moq::wire::control_message::{FetchMessage, FetchRange, FetchType};
+use quiche_moq::wire::{ErrorCode, Location, Namespace, REQUEST_ERROR_DOES_NOT_EXIST, REQUEST_ERROR_INVALID_RANGE, REQUEST_ERROR_JOINING_REQUEST_ID, REQUEST_ERROR_UNAUTHORIZED, RequestId, version_to_name};
+use quiche_moq_webtransport_helper::MoqHandle;
+use crate::acl::Action;
+use crate::auth::ConnTokens;
+use crate::cache::TrackCache;
+use crate::fetch::{FetchResponse, RelayFetch, UpstreamFetch};
+use crate::namespace_trie::NamespaceTrie;
+use crate::Relay;
+use crate::subscription::{PublisherInfo, SubscriberInfo, Subscription};
+
+/// Connection to send a request for `namespace` to: the publisher of the longest published
+/// prefix of it, e.g. the publisher of `org-team` for `org-team-room1`, otherwise the first
+/// upstream relay. Connections for which `requester` returns true are skipped, so a request
+/// is never routed back to where it came from; the next shorter prefix is tried instead.
+pub(crate) fn route(
+    namespaces: &NamespaceTrie<ClientId>,
+    upstreams: &[ClientId],
+    namespace: &Namespace,
+    requester: impl Fn(ClientId) -> bool,
+) -> Option<ClientId> {
+    namespaces.prefixes_of(namespace).into_iter().rev().copied()
+        .chain(upstreams.iter().copied())
+        .find(|&id| !requester(id))
+}
+
+/// Peer address of the connection's active path, for the ACL.
+pub(crate) fn peer_ip(quic: &quiche::Connection) -> Option<IpAddr> {
+    quic.path_stats().next().map(|s| s.peer_addr.ip())
+}
+
+/// Decide how to serve a FETCH: from the track cache if it holds the start of the range,
+/// otherwise from the publisher the namespace is routed to, see [`route`].
+/// Returns `Ok(None)` if a joining fetch has to wait for the publisher to accept the joined subscription.
+fn resolve_fetch(cid: ClientId, cm: &FetchMessage, app_data: &mut Relay) -> Result<Option<RelayFetch>, ErrorCode> {
+    let (nt, range) = match &cm.fetch_type {
+        FetchType::Standalone { namespace_trackname, range } => (namespace_trackname.clone(), *range),
+        FetchType::RelativeJoining { .. } | FetchType::AbsoluteJoining { .. } => {
+            let joining_request_id = cm.fetch_type.joining_request_id().unwrap();
+            let Some((nt, sub)) = app_data.subscriptions.iter_mut().find(|(_, sub)| {
+                sub.subscribers.iter().any(|s| s.client_id == cid && s.request_id == joining_request_id)
+            }) else {
+                return Err(REQUEST_ERROR_JOINING_REQUEST_ID);
+            };
+            let s = sub.subscribers.iter().find(|s| s.client_id == cid && s.request_id == joining_request_id).unwrap();
+            // The group the subscriber starts with, from its filter once the largest location is known.
+            let joined_group = match s.start {
+                Some(start) => start.group,
+                None if sub.is_publisher_accepted() => s.resolve_start(sub.largest_location()).group,
+                None => return Ok(None),
+            };
+            let start_group = match cm.fetch_type {
+                FetchType::RelativeJoining { preceding_groups, .. } => joined_group.saturating_sub(preceding_groups),
+                FetchType::AbsoluteJoining { start_group, .. } => start_group,
+                FetchType::Standalone { .. } => unreachable!(),
+            };
+            if start_group >= joined_group {
+                return Err(REQUEST_ERROR_INVALID_RANGE);
+            }
+            let s = sub.subscribers.iter_mut().find(|s| s.client_id == cid && s.request_id == joining_request_id).unwrap();
+            if s.start.is_none() {
+                s.start = Some(Location { group: joined_group, object: 0 });
+            }
+            let range = FetchRange {
+                start: Location { group: start_group, object: 0 },
+                end: Location { group: joined_group - 1, object: 0 },
+            };
+            (nt.clone(), range)
+        }
+    };
+    let cached = app_data.subscriptions.get(&nt)
+        .and_then(|sub| sub.cache.first_location())
+        .is_some_and(|first| first <= range.start);
+    app_data.open_archive(&nt);
+    let archived = match app_data.archives.get(&nt) {
+        Some(archive) if !cached && archive.first_location().is_some_and(|first| first <= range.start) => {
+            match archive.next_group(range.start.group).map(|group| (group, archive.read_group(group))) {
+                Some((_, Ok(g))) => Some(g),
+                Some((group, Err(e))) => {
+                    error!("failed to read group {} from {}: {}", group, archive.dir().display(), e);
+                    None
+                }
+                None => None,
+            }
+        }
+        _ => None,
+    };
+    let upstream = if cached || archived.is_some() {
+        None
+    } else {
+        let Some(publisher_id) = route(&app_data.namespaces, &app_data.upstreams, nt.namespace(), |id| id == cid) else {
+            return Err(REQUEST_ERROR_DOES_NOT_EXIST);
+        };
+        // after a failover the standby's groups are cached under shifted group IDs
+        let publisher = app_data.subscriptions.get(&nt)
+            .and_then(|sub| sub.publisher.as_ref())
+            .filter(|p| p.client_id == publisher_id);
+        let (upstream_range, group_offset) = match publisher {
+            Some(p) => p.unmap_range(range).ok_or(REQUEST_ERROR_DOES_NOT_EXIST)?,
+            None => (range, 0),
+        };
+        Some(UpstreamFetch {
+            client_id: publisher_id,
+            range: upstream_range,
+            group_offset,
+            request_id: None,
+            ok: None,
+            cache: TrackCache::new(app_data.cache_limits),
+            fin: false,
+            error: None,
+        })
+    };
+    let source = if cached {
+        app_data.metrics.cache_hits += 1;
+        "cached"
+    } else if archived.is_some() {
+        app_data.metrics.dvr_hits += 1;
+        "recorded"
+    } else {
+        app_data.metrics.cache_misses += 1;
+        "upstream"
+    };
+    info!("new fetch {} {:?} from {} ({})", nt, range, cid, source);
+    Ok(Some(RelayFetch {
+        client_id: cid,
+        nt,
+        response: FetchResponse::new(cm.request_id, range),
+        upstream,
+        archived,
+    }))
+}
+
+pub(crate) fn post_handle_recvs_conn(
+    cid: ClientId,
+    mut moq: MoqHandle<'_>,
+    app_data: &mut Relay,
+    logged_connect: &mut bool,
+    tokens: &mut Option<ConnTokens>,
+    held: &mut HashSet<RequestId>,
+) {
+    // log connection
+    if let Some(version) = moq.version() && !*logged_connect {
+        let peer_addr = moq.quic().path_stats().next().map(|s| s.peer_addr).unwrap();
+        info!("Client {cid} connected {peer_addr:?} v{}", version_to_name(version));
+        *logged_connect = true;
+        let setup_path = moq.setup_path().and_then(|p| std::str::from_utf8(p).ok());
+        *tokens = Some(ConnTokens::new(moq.connect_path().or(setup_path), version));
+    }
+    let Some(tokens) = tokens.as_mut() else { return };
+    let peer = peer_ip(moq.quic());
+    // forget held requests the client canceled
+    held.retain(|id| moq.pending_received_subscriptions().contains_key(id) || moq.pending_received_namespace_publishes().contains_key(id));
+
+    moq.process_subscription_requests(|request_id, cm| {
+        let nt = &cm.namespace_trackname;
+        // Skip subscriptions already queued from a previous frame. Request IDs are per session,
+        // other sessions' subscriptions to the track may use the same ones.
+        if app_data.subscriptions.get(nt)
+            .map(|sub| sub.subscribers.iter().any(|s| !s.is_accepted() && s.client_id == cid && s.request_id == *request_id))
+            .unwrap_or(false)
+        {
+            return SubscriptionRequestAction::Keep;
+        }
+        // The drafts define no error code for a quota: hold the request until one of the
+        // client's subscriptions ends.
+        if !app_data.upstreams.contains(&cid) && app_data.subscription_count(cid) >= app_data.limits.subscriptions_per_session {
+            if held.insert(*request_id) {
+                info!("hold subscription {} from {} (too many subscriptions)", nt, cid);
+                app_data.metrics.quota_holds += 1;
+            }
+            return SubscriptionRequestAction::Keep;
+        }
+        held.remove(request_id);
+        if !app_data.acl.allows(nt.namespace(), peer, Action::Subscribe) {
+            info!("reject subscription {} from {} (not allowed)", nt, cid);
+            return SubscriptionRequestAction::Reject(REQUEST_ERROR_UNAUTHORIZED);
+        }
+        if let Err(error_code) = app_data.authorize(cid, tokens, Some(&cm.parameters), nt.namespace(), Action::Subscribe) {
+            info!("reject subscription {} from {} with {}", nt, cid, error_code);
+            return SubscriptionRequestAction::Reject(error_code);
+        }
+        if let (Some(start), Some(end_group)) = (cm.start_location, cm.end_group) && end_group < start.group {
+            info!("reject subscription {} from {} (end group {} before start {:?})", nt, cid, end_group, start);
+            return SubscriptionRequestAction::Reject(REQUEST_ERROR_INVALID_RANGE);
+        }
+        if let Some(publisher_id) = route(&app_data.namespaces, &app_data.upstreams, nt.namespace(), |id| id == cid) {
+            let nt = nt.clone();
+            app_data.open_archive(&nt);
+            let cache_limits = app_data.cache_limits;
+            let sub = app_data.subscriptions.entry(nt.clone()).or_insert_with(|| {
+                info!("new subscription request {} from {} (publisher: {})", nt, cid, publisher_id);
+                Subscription {
+                    publisher: Some(PublisherInfo::new(publisher_id, false)),
+                    subscribers: Vec::new(),
+                    cache: TrackCache::new(cache_limits),
+                }
+            });
+            if !sub.is_publisher_accepted() {
+                info!("queued subscriber {} for {} (awaiting publisher accept)", cid, nt);
+            }
+            sub.subscribers.push(SubscriberInfo::new(cid, cm));
+            SubscriptionRequestAction::Keep
+        } else {
+            info!("reject subscription {} from {} (no publisher)", nt, cid);
+            SubscriptionRequestAction::Reject(REQUEST_ERROR_DOES_NOT_EXIST)
+        }
+    });
+    // Fetches stay pending in the session until Phase 6 accepts them; skip the ones already queued.
+    let new_fetches: Vec<RequestId> = moq.pending_received_fetches().keys()
+        .filter(|&&request_id| !app_data.fetches.iter().any(|f| f.client_id == cid && f.response.request_id == request_id))
+        .copied()
+        .collect();
+    for request_id in new_fetches {
+        let cm = &moq.pending_received_fetches()[&request_id];
+        // joining fetches were checked with their subscription
+        let fetch = match &cm.fetch_type {
+            FetchType::Standalone { namespace_trackname, .. }
+                if !app_data.acl.allows(namespace_trackname.namespace(), peer, Action::Subscribe) => Err(REQUEST_ERROR_UNAUTHORIZED),
+            FetchType::Standalone { namespace_trackname, .. } => app_data
+                .authorize(cid, tokens, Some(&cm.parameters), namespace_trackname.namespace(), Action::Subscribe)
+                .and_then(|()| resolve_fetch(cid, cm, app_data)),
+            _ => resolve_fetch(cid, cm, app_data),
+        };
+        match fetch {
+            Ok(Some(f)) => app_data.fetches.push(f),
+            Ok(None) => {}
+            Err(error_code) => {
+                info!("reject fetch {} from {} with {}", request_id, cid, error_code);
+                if let Err(e) = moq.reject_fetch(request_id, error_code) {
+                    error!("failed to reject fetch {} from {}: {:?}", request_id, cid, e);
+                }
+            }
+        }
+    }
+    // The first connection to publish a namespace keeps it until it is done; later publishers
+    // become standby publishers that take over when it goes away, e.g. a redundant encoder.
+    // Phase 2 announces a namespace to every connection but its publisher, so when relays are
+    // connected in a loop, the announcement that comes back around is ignored and subscriptions
+    // are never routed in a circle. Upstream relays are therefore never standby publishers.
+    let mut namespaces = moq.received_namespaces().count();
+    while let Some((&request_id, cm)) = moq.next_pending_namespace_publish() {
+        let namespace = cm.track_namespace().clone();
+        // held like subscriptions; the ones after it are over the quota as well
+        if !app_data.upstreams.contains(&cid) && namespaces >= app_data.limits.namespaces_per_session {
+            if held.insert(request_id) {
+                info!("hold namespace {} from {} (too many namespaces)", namespace, cid);
+                app_data.metrics.quota_holds += 1;
+            }
+            break;
+        }
+        held.remove(&request_id);
+        if !app_data.acl.allows(&namespace, peer, Action::Publish) {
+            info!("reject namespace {} from {} (not allowed)", namespace, cid);
+            moq.reject_namespace_publish(request_id, REQUEST_ERROR_UNAUTHORIZED);
+            continue;
+        }
+        if let Err(error_code) = app_data.authorize(cid, tokens, Some(cm.parameters()), &namespace, Action::Publish) {
+            info!("reject namespace {} from {} with {}", namespace, cid, error_code);
+            moq.reject_namespace_publish(request_id, error_code);
+            continue;
+        }
+        match app_data.namespaces.get(&namespace) {
+            Some(&publisher)
+                if publisher != cid
+                    && !app_data.upstreams.contains(&cid)
+                    && !app_data.standby_publishers.iter().any(|(ns, id)| *id == cid && *ns == namespace) =>
+            {
+                info!("accept namespace {} from {} as standby (published by {})", namespace, cid, publisher);
+                app_data.standby_publishers.push((namespace, cid));
+            }
+            Some(&publisher) => info!("ignore namespace {} from {} (published by {})", namespace, cid, publisher),
+            None => {
+                info!("accept namespace {} from {}", namespace, cid);
+                app_data.namespaces.insert(namespace, cid);
+            }
+        }
+        moq.accept_namespace_publish(request_id);
+        namespaces += 1;
+    }
+    app_data.withdraw_namespaces(cid, |ns| moq.received_namespaces().any(|n| n == ns));
+    app_data.grant_request_ids(cid, held.len(), &mut moq);
+}
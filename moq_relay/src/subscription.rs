@@ -0,0 +1,335 @@
+//! Per-track state of the relay: the publisher of a track, its subscribers and the streams
+//! between them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use log::{error, info};
+use quiche_mio_runner::quiche_endpoint::ClientId;
+use quiche_moq::wire::control_message::{FetchRange, SubscribeMessage};
+use quiche_moq::wire::control_message::subscribe::FilterType;
+use quiche_moq::wire::{Location, NamespaceTrackname, RequestId, TrackAlias};
+use quiche_moq_webtransport_helper::MoqHandle;
+use quiche_utils::stream_id::StreamID;
+use crate::cache::TrackCache;
+use crate::dvr::{ArchivedGroup, TrackArchive};
+use crate::metrics::Metrics;
+use crate::namespace_trie::NamespaceTrie;
+use crate::forwarding::forward_subgroup;
+
+pub(crate) struct SubscriberInfo {
+    pub(crate) client_id: ClientId,
+    /// Subscriber's request_id; kept for the lifetime of the subscription.
+    pub(crate) request_id: RequestId,
+    /// Set once relay sends SUBSCRIBE_OK to subscriber (Phase 4.5).
+    pub(crate) track_alias: Option<TrackAlias>,
+    /// Set when the publisher disconnects or the drain grace period ends; triggers PUBLISH_DONE in Phase 4.6.
+    pub(crate) publisher_gone: bool,
+    /// Filter of the SUBSCRIBE, with its start location for the absolute filters.
+    pub(crate) filter_type: FilterType,
+    pub(crate) filter_start: Option<Location>,
+    /// Last group of an AbsoluteRange subscription; PUBLISH_DONE follows once it is forwarded.
+    pub(crate) end_group: Option<u64>,
+    /// PUBLISH_DONE was sent after the end group; removed at the end of Phase 5.
+    pub(crate) ended: bool,
+    /// Progress of each cached subgroup forwarded to this subscriber, by group and subgroup ID.
+    pub(crate) subgroups: BTreeMap<(u64, u64), DownstreamSubgroup>,
+    /// Location of the first object forwarded to this subscriber, resolved from its filter when
+    /// it is accepted (Phase 4.5). Pinned to a group start by a joining fetch, which covers the
+    /// groups before it. Moved to the newest group when the subscriber exceeds its backlog limit.
+    pub(crate) start: Option<Location>,
+    /// Groups skipped because the subscriber fell too far behind.
+    pub(crate) groups_skipped: u64,
+    /// Recorded group being forwarded while the start location is older than the cache,
+    /// see [`SubscriberInfo::forward_archive`].
+    pub(crate) replay: Option<ArchivedGroup>,
+}
+
+/// One subgroup forwarded to a subscriber on its own stream.
+#[derive(Default)]
+pub(crate) struct DownstreamSubgroup {
+    /// Opened with the first object forwarded.
+    pub(crate) stream_id: Option<StreamID>,
+    /// Object ID of the most recent object header forwarded on the stream, or of the
+    /// last object before the subscriber's start location.
+    pub(crate) object: Option<u64>,
+    /// Bytes of that object's payload still to be forwarded.
+    /// Offset into the cached payload = payload length - remaining.
+    pub(crate) remaining: usize,
+    /// The stream has been finished or reset.
+    pub(crate) done: bool,
+}
+
+impl SubscriberInfo {
+    pub(crate) fn new(client_id: ClientId, cm: &SubscribeMessage) -> Self {
+        Self {
+            client_id,
+            request_id: cm.request_id,
+            track_alias: None,
+            publisher_gone: false,
+            filter_type: cm.filter_type,
+            filter_start: cm.start_location,
+            end_group: cm.end_group,
+            ended: false,
+            subgroups: BTreeMap::new(),
+            start: None,
+            groups_skipped: 0,
+            replay: None,
+        }
+    }
+
+    pub(crate) fn is_accepted(&self) -> bool { self.track_alias.is_some() }
+
+    /// Start location of the filter, given the largest location of the track when the
+    /// subscription is accepted: the next object for LargestObject, the next group for
+    /// NextGroupStart. Everything is new if nothing was published yet.
+    pub(crate) fn resolve_start(&self, largest: Option<Location>) -> Location {
+        match (self.filter_type, largest) {
+            (FilterType::AbsoluteStart | FilterType::AbsoluteRange, _) => self.filter_start.unwrap(),
+            (FilterType::LargestObject, Some(l)) => Location { group: l.group, object: l.object + 1 },
+            (FilterType::NextGroupStart, Some(l)) => Location { group: l.group + 1, object: 0 },
+            (_, None) => Location { group: 0, object: 0 },
+        }
+    }
+
+    /// The objects of `group` up to this object ID are before the start location.
+    pub(crate) fn skipped_before(&self, group: u64) -> Option<u64> {
+        self.start.filter(|s| s.group == group).and_then(|s| s.object.checked_sub(1))
+    }
+
+    /// Whether everything up to the end group has been forwarded and a later group was cached,
+    /// so the end group is complete.
+    pub(crate) fn is_past_end(&self, cache: &TrackCache) -> bool {
+        let Some(end_group) = self.end_group else { return false };
+        cache.largest_location().is_some_and(|l| l.group > end_group)
+            && self.subgroups.range(..(end_group + 1, 0)).all(|(_, d)| d.done)
+    }
+
+    /// Forward the recorded groups from the start location on, one group at a time, until the
+    /// next one is cached or past the end group. The start location follows the forwarded
+    /// groups, so forwarding from the cache continues after them.
+    /// Returns true while recorded groups are left to forward.
+    pub(crate) fn forward_archive(
+        &mut self,
+        archive: &TrackArchive,
+        cache: &TrackCache,
+        sub_ta: TrackAlias,
+        moq: &mut MoqHandle<'_>,
+        metrics: &mut Metrics,
+    ) -> bool {
+        let Some(mut replay) = self.replay.take() else { return false };
+        loop {
+            let keys: Vec<(u64, u64)> = replay.cache.subgroups_from(replay.group).map(|(k, _)| k).collect();
+            for (group, subgroup_id) in keys {
+                let skipped_before = self.skipped_before(group);
+                let d = self.subgroups.entry((group, subgroup_id))
+                    .or_insert_with(|| DownstreamSubgroup { object: skipped_before, ..Default::default() });
+                if d.done { continue; }
+                forward_subgroup(&replay.cache, group, subgroup_id, d, sub_ta, moq, metrics);
+            }
+            if self.subgroups.range((replay.group, 0)..(replay.group + 1, 0)).any(|(_, d)| !d.done) {
+                self.replay = Some(replay);
+                return true;
+            }
+            self.start = Some(Location { group: replay.group + 1, object: 0 });
+            let Some(group) = self.next_archived_group(archive, cache, replay.group + 1) else { return false };
+            match archive.read_group(group) {
+                Ok(next) => replay = next,
+                Err(e) => {
+                    error!("failed to read group {} from {}: {}", group, archive.dir().display(), e);
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// The first recorded group from `group` on that is older than the cache and within the
+    /// filter. Only subscriptions with an absolute start are served from the recording.
+    pub(crate) fn next_archived_group(&self, archive: &TrackArchive, cache: &TrackCache, group: u64) -> Option<u64> {
+        if !matches!(self.filter_type, FilterType::AbsoluteStart | FilterType::AbsoluteRange) {
+            return None;
+        }
+        archive.next_group(group).filter(|&g| {
+            self.end_group.is_none_or(|end_group| g <= end_group)
+                && !cache.first_location().is_some_and(|first| first.group < g || first == Location { group: g, object: 0 })
+        })
+    }
+
+    /// Groups between the oldest subgroup still being forwarded and the newest cached group.
+    pub(crate) fn lag_groups(&self, cache: &TrackCache) -> u64 {
+        let Some(largest) = cache.largest_location() else { return 0 };
+        let oldest = self.subgroups.iter()
+            .find(|(_, d)| !d.done)
+            .map_or(largest.group, |(&(group, _), _)| group);
+        largest.group.saturating_sub(oldest)
+    }
+
+    /// Payload bytes cached from the start location on that have not been forwarded to this subscriber yet.
+    pub(crate) fn backlog(&self, cache: &TrackCache) -> usize {
+        let Some(start) = self.start else { return 0 };
+        cache.subgroups_from(start.group)
+            .map(|((group, subgroup_id), _)| match self.subgroups.get(&(group, subgroup_id)) {
+                Some(d) if d.done => 0,
+                Some(d) => d.remaining + cache.subgroup_bytes_after(group, subgroup_id, d.object),
+                None => cache.subgroup_bytes_after(group, subgroup_id, self.skipped_before(group)),
+            })
+            .sum()
+    }
+
+    /// Drop everything before `group`: reset the streams of older subgroups still being forwarded
+    /// and continue with the first object of `group` on new streams.
+    /// Returns the number of groups that were not forwarded completely.
+    pub(crate) fn skip_to_group(&mut self, group: u64, cache: &TrackCache, moq: &mut MoqHandle<'_>, metrics: &mut Metrics) -> u64 {
+        let mut skipped = BTreeSet::new();
+        for (&(g, _), d) in self.subgroups.range_mut(..(group, 0)).filter(|(_, d)| !d.done) {
+            if let Some(stream_id) = d.stream_id {
+                moq.reset_subgroup(stream_id);
+                metrics.stream_resets += 1;
+            }
+            d.done = true;
+            skipped.insert(g);
+        }
+        // subgroups that were not started yet
+        if let Some(start) = self.start {
+            for ((g, subgroup_id), _) in cache.subgroups_from(start.group).take_while(|&((g, _), _)| g < group) {
+                if !self.subgroups.contains_key(&(g, subgroup_id)) {
+                    skipped.insert(g);
+                }
+            }
+        }
+        self.start = Some(Location { group, object: 0 });
+        let skipped = skipped.len() as u64;
+        self.groups_skipped += skipped;
+        metrics.groups_skipped += skipped;
+        skipped
+    }
+}
+
+pub(crate) struct PublisherInfo {
+    pub(crate) client_id: ClientId,
+    /// Request ID for the relay's SUBSCRIBE sent to the publisher (set in Phase 3).
+    pub(crate) request_id: Option<RequestId>,
+    /// Track alias from publisher's SUBSCRIBE_OK (set in Phase 4).
+    pub(crate) track_alias: Option<TrackAlias>,
+    /// Largest location from publisher's SUBSCRIBE_OK, forwarded to subscribers.
+    pub(crate) largest_location: Option<Location>,
+    /// Subgroup streams received from the publisher that are still open.
+    pub(crate) streams: HashMap<StreamID, UpstreamSubgroup>,
+    /// Took over the track from a publisher that went away, see [`PublisherInfo::map_group`].
+    pub(crate) failover: bool,
+    /// Added to the publisher's group IDs and the first group forwarded; set with the first group after a failover.
+    pub(crate) group_offset: Option<(u64, u64)>,
+}
+
+impl PublisherInfo {
+    pub(crate) fn new(client_id: ClientId, failover: bool) -> Self {
+        Self {
+            client_id,
+            request_id: None,
+            track_alias: None,
+            largest_location: None,
+            streams: HashMap::new(),
+            failover,
+            group_offset: None,
+        }
+    }
+
+    /// Group ID to cache a group of the publisher under; `None` drops the group.
+    ///
+    /// After a failover, the groups up to the largest location of SUBSCRIBE_OK started before
+    /// the switch and are dropped, so subscribers continue at the next group boundary. If the
+    /// publisher's group IDs are not ahead of the cached ones, its groups are shifted to follow
+    /// the newest cached group, so group IDs stay monotonic for the subscribers.
+    pub(crate) fn map_group(&mut self, group: u64, cache: &TrackCache) -> Option<u64> {
+        if !self.failover {
+            return Some(group);
+        }
+        if self.largest_location.is_some_and(|l| group <= l.group) {
+            return None;
+        }
+        let (offset, first) = *self.group_offset.get_or_insert_with(|| {
+            let offset = cache.largest_location().map_or(0, |l| (l.group + 1).saturating_sub(group));
+            (offset, group)
+        });
+        (group >= first).then_some(group + offset)
+    }
+
+    /// `range` in the publisher's own group IDs, for a FETCH sent to it, and the offset to add to
+    /// the group IDs of the objects it returns. `None` if the whole range precedes the first group
+    /// taken from this publisher, see [`Self::map_group`].
+    pub(crate) fn unmap_range(&self, range: FetchRange) -> Option<(FetchRange, u64)> {
+        let Some((offset, first)) = self.group_offset else { return Some((range, 0)) };
+        if range.end.group < first + offset {
+            return None;
+        }
+        let start = match range.start.group.checked_sub(offset) {
+            Some(group) if group >= first => Location { group, ..range.start },
+            _ => Location { group: first, object: 0 },
+        };
+        Some((FetchRange { start, end: Location { group: range.end.group - offset, ..range.end } }, offset))
+    }
+}
+
+/// A subgroup stream received from the publisher.
+#[derive(Default)]
+pub(crate) struct UpstreamSubgroup {
+    /// Group and subgroup ID as cached; set with the first object header.
+    pub(crate) subgroup: Option<(u64, u64)>,
+    /// The group is dropped, the stream is read but not cached.
+    pub(crate) discard: bool,
+    /// Location of the object whose payload is being received and the bytes still to read.
+    pub(crate) live: Option<(Location, usize)>,
+}
+
+pub(crate) struct Subscription {
+    /// Publisher state; None when publisher has disconnected.
+    pub(crate) publisher: Option<PublisherInfo>,
+    pub(crate) subscribers: Vec<SubscriberInfo>,
+    /// Recent objects received from the publisher; the last one may still be incomplete.
+    pub(crate) cache: TrackCache,
+}
+
+impl Subscription {
+    pub(crate) fn is_sent(&self) -> bool {
+        self.publisher.as_ref().is_some_and(|p| p.request_id.is_some())
+    }
+
+    pub(crate) fn is_publisher_accepted(&self) -> bool {
+        self.publisher.as_ref().is_some_and(|p| p.track_alias.is_some())
+    }
+
+    /// Largest location of the track known to the relay.
+    /// Objects cached since the publisher's SUBSCRIBE_OK are more recent than its largest location.
+    pub(crate) fn largest_location(&self) -> Option<Location> {
+        self.cache.largest_location().max(self.publisher.as_ref().and_then(|p| p.largest_location))
+    }
+
+    /// The publisher went away. Subgroups it left open are finished in the cache. If another
+    /// connection publishes the namespace now, e.g. a standby publisher, the subscription fails
+    /// over to it and the subscribers stay subscribed; otherwise they get PUBLISH_DONE.
+    /// Returns true on failover.
+    pub(crate) fn publisher_gone(&mut self, nt: &NamespaceTrackname, namespaces: &NamespaceTrie<ClientId>) -> bool {
+        let Some(gone) = self.publisher.take() else { return false };
+        for up in gone.streams.values() {
+            if let Some((loc, _)) = up.live && !up.discard {
+                self.cache.remove(loc);
+            }
+            if let Some((group, subgroup_id)) = up.subgroup && !up.discard {
+                self.cache.finish_subgroup(group, subgroup_id, up.live.is_some());
+            }
+        }
+        let next = namespaces.longest_prefix(nt.namespace()).copied()
+            .filter(|&id| id != gone.client_id && !self.subscribers.iter().any(|s| s.client_id == id));
+        match next {
+            Some(id) => {
+                info!("fail over {} from publisher {} to {}", nt, gone.client_id, id);
+                self.publisher = Some(PublisherInfo::new(id, true));
+                true
+            }
+            None => {
+                for s in &mut self.subscribers {
+                    s.publisher_gone = true;
+                }
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use boring::error::ErrorStack;
+use boring::pkey::{PKey, Private};
+use boring::ssl::{SelectCertError, SslContextBuilder, SslMethod, SslRef};
+use boring::x509::X509;
+use log::{error, info};
+use quiche_utils::cert::load_or_generate_keys;
+
+/// Set by SIGHUP; the certificate is reloaded before the next handshake.
+static RELOAD: LazyLock<Arc<AtomicBool>> = LazyLock::new(Default::default);
+
+/// Leaf certificate, the intermediates sent after it, and the private key.
+struct Keys {
+    chain: Vec<X509>,
+    key: PKey<Private>,
+}
+
+impl Keys {
+    fn apply(&self, ssl: &mut SslRef) -> Result<(), ErrorStack> {
+        ssl.set_certificate(&self.chain[0])?;
+        for cert in &self.chain[1..] {
+            ssl.add_chain_cert(cert)?;
+        }
+        ssl.set_private_key(&self.key)
+    }
+}
+
+/// Certificate and key served by the relay.
+/// If they were loaded from files, they are read again after SIGHUP, without dropping connections.
+pub struct Certificate {
+    paths: Option<(PathBuf, PathBuf)>,
+    current: Mutex<Keys>,
+}
+
+impl Certificate {
+    /// Panics if the files cannot be read, or only one of them is given.
+    pub fn load(cert_path: &Option<PathBuf>, key_path: &Option<PathBuf>) -> Arc<Self> {
+        let paths = cert_path.clone().zip(key_path.clone());
+        let keys = match &paths {
+            Some((cert_path, key_path)) => {
+                if let Err(e) = signal_hook::flag::register(signal_hook::consts::SIGHUP, RELOAD.clone()) {
+                    error!("failed to handle SIGHUP, the certificate will not be reloaded: {e}");
+                }
+                read_keys(cert_path, key_path).unwrap_or_else(|e| panic!("{e}"))
+            }
+            None => {
+                let (cert, key) = load_or_generate_keys(cert_path, key_path);
+                Keys { chain: vec![cert], key }
+            }
+        };
+        Arc::new(Self { paths, current: Mutex::new(keys) })
+    }
+
+    /// TLS context for the server side of connections.
+    /// The certificate is chosen on every ClientHello, so a reloaded certificate applies to new
+    /// connections whether or not they send SNI.
+    pub fn ssl_context_builder(self: &Arc<Self>) -> SslContextBuilder {
+        let mut b = SslContextBuilder::new(SslMethod::tls()).unwrap();
+        {
+            let keys = self.current.lock().unwrap();
+            b.set_private_key(&keys.key).unwrap();
+            b.set_certificate(&keys.chain[0]).unwrap();
+            for cert in &keys.chain[1..] {
+                b.add_extra_chain_cert(cert.clone()).unwrap();
+            }
+        }
+        let this = self.clone();
+        b.set_select_certificate_callback(move |mut client_hello| {
+            this.reload_if_requested();
+            let keys = this.current.lock().unwrap();
+            keys.apply(client_hello.ssl_mut()).map_err(|e| {
+                error!("failed to set certificate: {e}");
+                SelectCertError::ERROR
+            })
+        });
+        b
+    }
+
+    fn reload_if_requested(&self) {
+        if !RELOAD.swap(false, Ordering::Relaxed) { return; }
+        let Some((cert_path, key_path)) = &self.paths else { return };
+        match read_keys(cert_path, key_path) {
+            Ok(keys) => {
+                *self.current.lock().unwrap() = keys;
+                info!("reloaded certificate {}", cert_path.display());
+            }
+            Err(e) => error!("failed to reload certificate, keep the current one: {e}"),
+        }
+    }
+}
+
+/// Reads the whole PEM chain, leaf first, so intermediates are sent to the clients.
+fn read_keys(cert_path: &Path, key_path: &Path) -> Result<Keys, String> {
+    let cert = fs::read(cert_path).map_err(|e| format!("{}: {e}", cert_path.display()))?;
+    let key = fs::read(key_path).map_err(|e| format!("{}: {e}", key_path.display()))?;
+    let chain = X509::stack_from_pem(&cert).map_err(|e| format!("{}: {e}", cert_path.display()))?;
+    if chain.is_empty() {
+        return Err(format!("{}: no certificate", cert_path.display()));
+    }
+    let key = PKey::private_key_from_pem(&key).map_err(|e| format!("{}: {e}", key_path.display()))?;
+    Ok(Keys { chain, key })
+}
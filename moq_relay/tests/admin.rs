@@ -0,0 +1,67 @@
+//! The admin listener of a relay in the in-process harness, see `common`: the JSON views of
+//! sessions, namespaces and subscriptions, and closing a session.
+
+mod common;
+
+use std::thread;
+use serde_json::Value;
+use moq_relay::Config;
+use common::{TRACK, admin_addr, admin_json, admin_request, run_subscriber, spawn_publisher, spawn_relay, wait_until};
+
+fn entries(view: &Value) -> &[Value] {
+    view.as_array().unwrap()
+}
+
+#[test]
+fn views_and_close_session() {
+    let admin = admin_addr();
+    let mut config = Config::default();
+    config.admin = Some(admin);
+    let (relay_addr, relay) = spawn_relay(config);
+    let publisher = spawn_publisher(relay_addr, None);
+    publisher.wait_ready();
+
+    let namespaces = admin_json(admin, "/namespaces");
+    let [namespace] = entries(&namespaces) else { panic!("namespaces {namespaces}") };
+    assert_eq!(namespace["namespace"], "inproc");
+    assert_eq!(namespace["standby_publishers"], Value::Array(vec![]));
+    let publisher_id = namespace["publisher"].as_str().unwrap().to_string();
+
+    // the subscriber reads until PUBLISH_DONE
+    let subscriber = thread::spawn(move || run_subscriber(relay_addr, None, usize::MAX));
+    wait_until("objects cached for the subscriber", || {
+        entries(&admin_json(admin, "/subscriptions"))
+            .iter()
+            .any(|t| entries(&t["subscribers"]).len() == 1 && t["cache"]["objects"].as_u64() > Some(0))
+    });
+    let subscriptions = admin_json(admin, "/subscriptions");
+    let [track] = entries(&subscriptions) else { panic!("subscriptions {subscriptions}") };
+    assert_eq!(track["track"], TRACK);
+    assert_eq!(track["publisher"]["client_id"], publisher_id.as_str());
+    let subscriber_id = track["subscribers"][0]["client_id"].as_str().unwrap();
+
+    let sessions = admin_json(admin, "/sessions");
+    let mut ids: Vec<_> = entries(&sessions).iter().map(|s| s["client_id"].as_str().unwrap()).collect();
+    ids.sort();
+    let mut expected = vec![publisher_id.as_str(), subscriber_id];
+    expected.sort();
+    assert_eq!(ids, expected);
+    for s in entries(&sessions) {
+        assert!(s["version"].is_string() && s["peer"].is_string(), "session {s}");
+        assert_eq!(s["closed"], false);
+    }
+
+    // closing the publisher's session withdraws its namespace and ends the subscription
+    let (status, body) = admin_request(admin, "POST", &format!("/sessions/{publisher_id}/close"));
+    assert_eq!(status, 200, "{body}");
+    let closed: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(closed["closed"], publisher_id.as_str());
+    assert!(subscriber.join().unwrap().unwrap().publish_done);
+    wait_until("namespace withdrawn", || entries(&admin_json(admin, "/namespaces")).is_empty());
+
+    assert_eq!(admin_request(admin, "POST", "/sessions/999999/close").0, 404);
+    assert_eq!(admin_request(admin, "GET", "/unknown").0, 404);
+
+    publisher.stop();
+    relay.stop();
+}
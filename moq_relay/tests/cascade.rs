@@ -0,0 +1,67 @@
+//! Three relays on loopback: edge relays B and C both pull from origin relay A.
+//! A publisher on B announces a namespace, which propagates B → A → C.
+//! A subscriber on C then receives the publisher's objects through C → A → B.
+//! Two relays that are each other's upstream must not route in a loop.
+
+mod common;
+
+use std::net::SocketAddr;
+use std::thread;
+use quiche_mio_runner::Socket;
+use moq_relay::Config;
+use common::{groups, run_subscriber, spawn_publisher, spawn_relay, spawn_relay_on};
+
+fn edge_config(origin: SocketAddr) -> Config {
+    let mut config = Config::default();
+    config.upstream = vec![format!("https://{origin}")];
+    config
+}
+
+#[test]
+fn cascade_through_origin() {
+    let (origin_addr, origin) = spawn_relay(Config::default());
+    origin.wait_ready();
+    let (publisher_edge_addr, publisher_edge) = spawn_relay(edge_config(origin_addr));
+    let (subscriber_edge_addr, subscriber_edge) = spawn_relay(edge_config(origin_addr));
+    publisher_edge.wait_ready();
+    subscriber_edge.wait_ready();
+
+    let publisher = spawn_publisher(publisher_edge_addr, None);
+    publisher.wait_ready();
+
+    let s = run_subscriber(subscriber_edge_addr, None, 1).unwrap();
+    let [(payload, group)] = s.received.as_slice() else { panic!("received {:?}", s.received) };
+    assert_eq!(*payload, format!("g{group}").into_bytes());
+
+    publisher.stop();
+    subscriber_edge.stop();
+    publisher_edge.stop();
+    origin.stop();
+}
+
+#[test]
+fn relays_upstream_of_each_other() {
+    let (socket_a, socket_b) = (Socket::bind("127.0.0.1:0").unwrap(), Socket::bind("127.0.0.1:0").unwrap());
+    let (a_addr, b_addr) = (socket_a.local_addr, socket_b.local_addr);
+    let a = spawn_relay_on(socket_a, edge_config(b_addr));
+    let b = spawn_relay_on(socket_b, edge_config(a_addr));
+    a.wait_ready();
+    b.wait_ready();
+
+    // A announces the namespace to B on both connections, and B back to A; both ignore the
+    // announcement that came around, so subscriptions on B go to A and not back to B
+    let publisher = spawn_publisher(a_addr, None);
+    publisher.wait_ready();
+
+    // A's own subscriber and B's subscription for the track may have the same request ID
+    let on_b = thread::spawn(move || run_subscriber(b_addr, None, 2));
+    let on_a = run_subscriber(a_addr, None, 2).unwrap();
+    let on_b = on_b.join().unwrap().unwrap();
+    for received in [groups(&on_a.received), groups(&on_b.received)] {
+        assert!(received.len() == 2 && received[1] == received[0] + 1, "groups {received:?}");
+    }
+
+    publisher.stop();
+    b.stop();
+    a.stop();
+}
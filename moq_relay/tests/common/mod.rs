@@ -0,0 +1,789 @@
+//! Test harness shared by the relay tests: relays, publishers and subscribers in one process,
+//! each on its own thread and loopback socket.
+//! The relay runs as a library with [`Relay::post_handle_recvs`] as the runner callback.
+
+// every test binary uses a different part of the harness
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{LevelFilter, error, info};
+use quiche_mio_runner as runner;
+use quiche_mio_runner::quiche_endpoint::{Endpoint, EndpointConfig, quiche};
+use quiche_mio_runner::quiche_endpoint::quiche::h3::{self, NameValue};
+use quiche_mio_runner::{Socket, mio};
+use quiche_moq as moq;
+use quiche_moq::PublishStatus;
+use quiche_moq::wire::control_message::{FetchRange, FetchType};
+use quiche_moq::wire::{ErrorCode, KeyValuePair, KeyValuePairs, Location, NamespaceTrackname, OBJECT_STATUS_END_OF_GROUP, REQUEST_ERROR_DOES_NOT_EXIST, REQUEST_ERROR_NOT_SUPPORTED, RequestId, TrackAlias, Version};
+use quiche_moq_webtransport_helper::{MoqHandle, MoqWebTransportHelper};
+use quiche_utils::stream_id::StreamID;
+use moq_relay::{Certificate, Config, Relay, RelayConn};
+use url::Url;
+
+pub const TRACK: &str = "inproc--video";
+/// Between the groups sent by the publisher.
+pub const GROUP_INTERVAL: Duration = Duration::from_millis(50);
+/// Extension header sent by the publisher with every object, its value is the group ID.
+pub const GROUP_EXTENSION: u64 = 0x7e;
+/// How long [`wait_until`] waits, e.g. for a publisher's namespace to be accepted.
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+/// A subscriber that is still waiting for objects after this long gives up.
+const SUBSCRIBER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A thread running a runner until its close pipe is written to.
+pub struct Stoppable {
+    close_pipe_tx: mio::unix::pipe::Sender,
+    pub thread: thread::JoinHandle<()>,
+    /// Set by the thread once it is usable, see [`Self::wait_ready`].
+    ready: Arc<AtomicBool>,
+}
+
+impl Stoppable {
+    pub fn stop(mut self) {
+        // fails if the thread already stopped on its own, e.g. after its connection was closed
+        let _ = self.close_pipe_tx.write_all(&[0]);
+        self.thread.join().unwrap();
+    }
+
+    /// A relay is ready once it runs, a publisher once its namespace was accepted.
+    pub fn wait_ready(&self) {
+        wait_until("ready", || self.ready.load(Ordering::Relaxed));
+    }
+}
+
+/// Log to stderr with timestamps, which the test runner shows for failed tests.
+/// Called by [`spawn_relay_on`], as every test runs a relay.
+fn init_logging() {
+    let _ = env_logger::builder().filter(None, LevelFilter::Info).format_timestamp_nanos().try_init();
+}
+
+/// Poll `condition` until it holds; panics after [`READY_TIMEOUT`].
+pub fn wait_until(what: &str, condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + READY_TIMEOUT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {what}");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Run a relay that also connects to the `upstream` relays of `config`.
+pub fn spawn_relay(config: Config) -> (SocketAddr, Stoppable) {
+    let socket = Socket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr;
+    (addr, spawn_relay_on(socket, config))
+}
+
+/// Like [`spawn_relay`] on a socket bound beforehand, e.g. for relays that are each other's upstream.
+pub fn spawn_relay_on(socket: Socket, config: Config) -> Stoppable {
+    init_logging();
+    let addr = socket.local_addr;
+    let (close_pipe_tx, mut close_pipe_rx) = mio::unix::pipe::new().unwrap();
+    let ready = Arc::new(AtomicBool::new(false));
+    let thread = thread::spawn({
+        let ready = ready.clone();
+        move || {
+            let relay = Relay::new(&config).unwrap();
+            let certificate = Certificate::load(&None, &None);
+            let mut r = runner::Runner::new(
+                {
+                    let mut c = runner::Config::<RelayConn, Relay, ()>::default();
+                    c.post_handle_recvs = Relay::post_handle_recvs;
+                    c
+                },
+                relay.into_endpoint(certificate.ssl_context_builder()),
+                Some(&mut close_pipe_rx),
+            );
+            r.register_socket(socket);
+            for upstream in &config.upstream {
+                let url = Url::parse(upstream).unwrap();
+                if let Err(e) = Relay::connect_upstream(&mut r.endpoint, &url, &[addr]) {
+                    error!("{e}");
+                }
+            }
+            ready.store(true, Ordering::Relaxed);
+            r.run();
+        }
+    });
+    Stoppable { close_pipe_tx, thread, ready }
+}
+
+pub fn quic_config() -> quiche::Config {
+    let mut c = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    MoqWebTransportHelper::configure_quic(&mut c);
+    c.verify_peer(false);
+    c.set_max_idle_timeout(5000);
+    c
+}
+
+pub fn url(addr: SocketAddr) -> Url {
+    format!("https://{addr}").parse().unwrap()
+}
+
+/// How a publisher of [`spawn_publisher_with`] behaves.
+pub struct PublisherOptions {
+    /// Disconnect once this many groups are sent.
+    pub max_groups: Option<u64>,
+    pub moq_config: moq::Config,
+    /// Follow the object of each group with an END_OF_GROUP object.
+    pub end_groups: bool,
+    /// ID of the first group sent.
+    pub first_group: u64,
+    /// The payload of each object is this prefix followed by the group ID.
+    pub prefix: &'static str,
+    /// Bytes appended to each payload unless `subgroups` is set, to fill the subscribers' receive windows.
+    pub padding: usize,
+    /// Send each group as this many subgroups of two objects on concurrent streams. The second
+    /// objects follow with the next group, so the subgroups of two groups are open at once.
+    /// The payloads are the prefix followed by `{group}/{subgroup}/{object}`.
+    pub subgroups: Option<u64>,
+    /// Reset this subgroup, given as group and subgroup ID, instead of sending its second object.
+    pub reset: Option<(u64, u64)>,
+}
+
+impl Default for PublisherOptions {
+    fn default() -> Self {
+        Self {
+            max_groups: None,
+            moq_config: moq::Config::default(),
+            end_groups: false,
+            first_group: 0,
+            prefix: "g",
+            padding: 0,
+            subgroups: None,
+            reset: None,
+        }
+    }
+}
+
+struct PublisherData {
+    moq_helper: MoqWebTransportHelper,
+    options: PublisherOptions,
+    /// Request ID of the namespace publication.
+    announced: Option<RequestId>,
+    ready: Arc<AtomicBool>,
+    track_alias: Option<TrackAlias>,
+    next_group: u64,
+    last_sent: Option<Instant>,
+    /// Group, subgroup ID and stream of the subgroups waiting for their second object.
+    open: Vec<(u64, u64, StreamID)>,
+}
+
+/// Publish the track and send one object per group every [`GROUP_INTERVAL`] once subscribed.
+/// Disconnects after `max_groups`, on GOAWAY or when the namespace is rejected.
+pub fn spawn_publisher(relay: SocketAddr, max_groups: Option<u64>) -> Stoppable {
+    spawn_publisher_with(relay, PublisherOptions { max_groups, ..Default::default() })
+}
+
+pub fn spawn_publisher_with(relay: SocketAddr, options: PublisherOptions) -> Stoppable {
+    let (close_pipe_tx, mut close_pipe_rx) = mio::unix::pipe::new().unwrap();
+    let ready = Arc::new(AtomicBool::new(false));
+    let thread = thread::spawn({
+        let ready = ready.clone();
+        move || {
+            let socket = Socket::bind("127.0.0.1:0").unwrap();
+            let mut r = runner::Runner::new(
+                {
+                    let mut c = runner::Config::<PublisherData, (), ()>::default();
+                    c.post_handle_recvs = |r| {
+                        let mut close = false;
+                        for icid in &mut r.endpoint.conn_index_iter() {
+                            let Some(conn) = r.endpoint.conn_mut(icid) else { continue };
+                            conn.app_data.moq_helper.on_post_handle_recvs(&mut conn.conn);
+                            let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { continue };
+                            let data = &mut conn.app_data;
+                            if moq.received_goaway().is_some() {
+                                close = true;
+                                continue;
+                            }
+                            let request_id = match data.announced {
+                                Some(request_id) => request_id,
+                                None => {
+                                    let nt: NamespaceTrackname = TRACK.parse().unwrap();
+                                    *data.announced.insert(moq.publish_namespace(nt.namespace().0.0.clone()).unwrap())
+                                }
+                            };
+                            match moq.publish_namespace_status(request_id) {
+                                PublishStatus::Rejected(error_code) => {
+                                    info!("namespace rejected with {}", error_code);
+                                    close = true;
+                                    continue;
+                                }
+                                PublishStatus::Accepted => data.ready.store(true, Ordering::Relaxed),
+                                _ => {}
+                            }
+                            while let Some((request_id, _subscription)) = moq.subscription_inbox_next() {
+                                data.track_alias = Some(moq.accept_subscription(*request_id, None));
+                            }
+                            serve_fetches(&mut moq, &data.options, data.next_group);
+                            let Some(track_alias) = data.track_alias else { continue };
+                            let due = data.last_sent.is_none_or(|t| t.elapsed() >= GROUP_INTERVAL);
+                            if !due { continue; }
+                            let options = &data.options;
+                            if options.max_groups.is_some_and(|max| data.next_group - options.first_group >= max) {
+                                // one interval after the last group, so it reached the relay
+                                close = true;
+                                continue;
+                            }
+                            let group = data.next_group;
+                            let ext = group_extension(group);
+                            match options.subgroups {
+                                None => {
+                                    let payload = payload(options, group);
+                                    moq.send_obj_with(payload.as_bytes(), Some(group), Some(0), &ext, track_alias).unwrap();
+                                    if options.end_groups {
+                                        moq.send_obj_status(None, None, None, OBJECT_STATUS_END_OF_GROUP, &ext, track_alias).unwrap();
+                                    }
+                                }
+                                Some(subgroups) => {
+                                    for (group, subgroup, stream_id) in data.open.drain(..) {
+                                        if options.reset == Some((group, subgroup)) {
+                                            moq.reset_subgroup(stream_id);
+                                            continue;
+                                        }
+                                        send_subgroup_obj(&mut moq, stream_id, options.prefix, group, subgroup, 1);
+                                        moq.fin_subgroup(stream_id);
+                                    }
+                                    for subgroup in 0..subgroups {
+                                        let stream_id = moq.open_subgroup(track_alias, group, subgroup).unwrap();
+                                        send_subgroup_obj(&mut moq, stream_id, options.prefix, group, subgroup, 0);
+                                        data.open.push((group, subgroup, stream_id));
+                                    }
+                                }
+                            }
+                            data.next_group += 1;
+                            data.last_sent = Some(Instant::now());
+                        }
+                        if close {
+                            info!("publisher disconnects");
+                            r.close();
+                        } else {
+                            r.set_app_timeout(GROUP_INTERVAL);
+                        }
+                    };
+                    c
+                },
+                {
+                    let mut e = Endpoint::new(None, EndpointConfig::default(), ());
+                    let moq_helper = MoqWebTransportHelper::new_client(url(relay), options.moq_config.clone());
+                    e.connect(
+                        None,
+                        socket.local_addr,
+                        relay,
+                        &mut quic_config(),
+                        PublisherData {
+                            moq_helper,
+                            next_group: options.first_group,
+                            options,
+                            announced: None,
+                            ready,
+                            track_alias: None,
+                            last_sent: None,
+                            open: vec![],
+                        },
+                        None,
+                        None,
+                    );
+                    e
+                },
+                Some(&mut close_pipe_rx),
+            );
+            r.register_socket(socket);
+            r.run();
+        }
+    });
+    Stoppable { close_pipe_tx, thread, ready }
+}
+
+fn payload(options: &PublisherOptions, group: u64) -> String {
+    format!("{}{group}{}", options.prefix, ".".repeat(options.padding))
+}
+
+/// Answer standalone fetches with the groups sent before `next_group`, unless `subgroups` is set.
+fn serve_fetches(moq: &mut MoqHandle<'_>, options: &PublisherOptions, next_group: u64) {
+    while let Some((&request_id, cm)) = moq.fetch_inbox_next() {
+        let range = match cm.fetch_type {
+            FetchType::Standalone { range, .. } if options.subgroups.is_none() => range,
+            _ => {
+                moq.reject_fetch(request_id, REQUEST_ERROR_NOT_SUPPORTED).unwrap();
+                continue;
+            }
+        };
+        let groups: Vec<u64> = (options.first_group..next_group).filter(|&g| range.contains(Location { group: g, object: 0 })).collect();
+        let Some(&last) = groups.last() else {
+            moq.reject_fetch(request_id, REQUEST_ERROR_DOES_NOT_EXIST).unwrap();
+            continue;
+        };
+        moq.accept_fetch(request_id, false, Location { group: last, object: 0 }).unwrap();
+        for group in groups {
+            let payload = payload(options, group);
+            moq.send_fetch_obj_hdr(request_id, Location { group, object: 0 }, 0, payload.len(), &group_extension(group)).unwrap();
+            moq.send_fetch_obj_pld(payload.as_bytes(), request_id).unwrap();
+        }
+        moq.fetch_done(request_id).unwrap();
+    }
+}
+
+fn send_subgroup_obj(moq: &mut MoqHandle<'_>, stream_id: StreamID, prefix: &str, group: u64, subgroup: u64, object: u64) {
+    let payload = format!("{prefix}{group}/{subgroup}/{object}");
+    moq.send_subgroup_obj_hdr(stream_id, object, payload.len(), &group_extension(group)).unwrap();
+    moq.send_subgroup_obj_pld(payload.as_bytes(), stream_id).unwrap();
+}
+
+struct SubscriberData {
+    moq_helper: MoqWebTransportHelper,
+    /// Subscribe to this start location and end group instead of the next group.
+    range: Option<(Location, Option<u64>)>,
+    /// Disconnect after this many objects, or subgroups if `reading.per_stream`.
+    max_objects: usize,
+    reading: Reading,
+    subgroups: Vec<ReceivedSubgroup>,
+    started: Instant,
+    request_id: Option<RequestId>,
+    track_alias: Option<TrackAlias>,
+    /// The relay accepted the fetch of `reading.fetch`.
+    fetch_accepted: bool,
+    /// Payload and group ID of each object received.
+    received: Vec<(Vec<u8>, u64)>,
+    /// Status and extension headers of each object received, including objects without payload.
+    headers: Vec<(Option<u64>, KeyValuePairs)>,
+    publish_done: bool,
+    /// The relay rejected the subscription with this error code.
+    error: Option<ErrorCode>,
+    /// New session URI of a GOAWAY from the relay.
+    goaway: Option<Vec<u8>>,
+}
+
+/// How a subscriber requests and reads objects.
+#[derive(Clone, Copy, Default)]
+struct Reading {
+    /// Fetch this range instead of subscribing; `publish_done` is set once all objects were read.
+    fetch: Option<FetchRange>,
+    /// Read each subgroup stream on its own into `subgroups`.
+    per_stream: bool,
+    /// Read no objects for this long after connecting.
+    stall: Duration,
+    /// Receive window of the connection and of each stream, so the relay cannot send far ahead.
+    window: Option<u64>,
+}
+
+/// What a subscriber received until it disconnected.
+#[derive(Debug, PartialEq)]
+pub struct Subscribed {
+    /// Payload and group ID of each object.
+    pub received: Vec<(Vec<u8>, u64)>,
+    /// Status and extension headers of each object, including objects without payload.
+    pub headers: Vec<(Option<u64>, KeyValuePairs)>,
+    pub publish_done: bool,
+    pub goaway: Option<Vec<u8>>,
+    /// The subgroup streams of [`run_subgroup_subscriber`], in the order they were first read.
+    pub subgroups: Vec<ReceivedSubgroup>,
+}
+
+/// One subgroup stream from the relay.
+#[derive(Debug, PartialEq)]
+pub struct ReceivedSubgroup {
+    stream_id: StreamID,
+    pub group: u64,
+    pub subgroup: u64,
+    pub payloads: Vec<Vec<u8>>,
+    /// Payload bytes of the last object still to be read.
+    remaining: usize,
+    pub fin: bool,
+    /// The relay reset the stream.
+    pub reset: bool,
+}
+
+/// Subscribe once the track's namespace is announced and read objects until `max_objects`,
+/// PUBLISH_DONE, GOAWAY or [`SUBSCRIBER_TIMEOUT`]. Returns the error code if the subscription
+/// was rejected.
+pub fn run_subscriber(relay: SocketAddr, range: Option<(Location, Option<u64>)>, max_objects: usize) -> Result<Subscribed, ErrorCode> {
+    run_subscriber_with(relay, range, max_objects, moq::Config::default())
+}
+
+pub fn run_subscriber_with(
+    relay: SocketAddr,
+    range: Option<(Location, Option<u64>)>,
+    max_objects: usize,
+    moq_config: moq::Config,
+) -> Result<Subscribed, ErrorCode> {
+    subscribe(relay, range, max_objects, moq_config, Reading::default())
+}
+
+/// Like [`run_subscriber`], but read the subgroup streams independently, until `max_subgroups`
+/// of them were finished or reset.
+pub fn run_subgroup_subscriber(relay: SocketAddr, max_subgroups: usize) -> Result<Subscribed, ErrorCode> {
+    subscribe(relay, None, max_subgroups, moq::Config::default(), Reading { per_stream: true, ..Default::default() })
+}
+
+/// Like [`run_subgroup_subscriber`], but stop reading for `stall` after connecting, with a receive
+/// window of `window` bytes per stream and for the connection.
+pub fn run_stalled_subscriber(relay: SocketAddr, max_subgroups: usize, stall: Duration, window: u64) -> Result<Subscribed, ErrorCode> {
+    let reading = Reading { per_stream: true, stall, window: Some(window) };
+    subscribe(relay, None, max_subgroups, moq::Config::default(), reading)
+}
+
+/// Fetch `range` of the track once its namespace is announced; returns the objects in
+/// `received`, or the error code if the fetch was rejected.
+pub fn run_fetch(relay: SocketAddr, range: FetchRange) -> Result<Subscribed, ErrorCode> {
+    subscribe(relay, None, usize::MAX, moq::Config::default(), Reading { fetch: Some(range), ..Default::default() })
+}
+
+fn subscribe(
+    relay: SocketAddr,
+    range: Option<(Location, Option<u64>)>,
+    max_objects: usize,
+    moq_config: moq::Config,
+    reading: Reading,
+) -> Result<Subscribed, ErrorCode> {
+    let socket = Socket::bind("127.0.0.1:0").unwrap();
+    let mut r = runner::Runner::new(
+        {
+            let mut c = runner::Config::<SubscriberData, (), ()>::default();
+            c.post_handle_recvs = |r| {
+                r.set_app_timeout(GROUP_INTERVAL);
+                for icid in &mut r.endpoint.conn_index_iter() {
+                    let Some(conn) = r.endpoint.conn_mut(icid) else { continue };
+                    if conn.app_data.started.elapsed() >= SUBSCRIBER_TIMEOUT {
+                        info!("subscriber gives up");
+                        r.close();
+                        return;
+                    }
+                    conn.app_data.moq_helper.on_post_handle_recvs(&mut conn.conn);
+                    let Some(mut moq) = conn.app_data.moq_helper.moq_handle(&mut conn.conn) else { continue };
+                    let data = &mut conn.app_data;
+                    if let Some(uri) = moq.received_goaway() {
+                        data.goaway = Some(uri.to_vec());
+                        r.close();
+                        return;
+                    }
+                    let nt: NamespaceTrackname = TRACK.parse().unwrap();
+                    while let Some((&request_id, cm)) = moq.next_pending_namespace_publish() {
+                        let announced = cm.track_namespace() == nt.namespace();
+                        moq.accept_namespace_publish(request_id);
+                        if announced && data.request_id.is_none() {
+                            let request_id = match (data.reading.fetch, data.range) {
+                                (Some(range), _) => moq.fetch(&nt, range),
+                                (None, Some((start, end_group))) => moq.subscribe_range(&nt, start, end_group),
+                                (None, None) => moq.subscribe(&nt),
+                            };
+                            data.request_id = Some(request_id.unwrap());
+                        }
+                    }
+                    if data.reading.fetch.is_some() {
+                        let Some(request_id) = data.request_id else { continue };
+                        if !data.fetch_accepted {
+                            match moq.poll_fetch_response(request_id) {
+                                Some(Ok(_)) => data.fetch_accepted = true,
+                                Some(Err(e)) => {
+                                    data.error = Some(e.error_code());
+                                    r.close();
+                                    return;
+                                }
+                                None => continue,
+                            }
+                        }
+                        loop {
+                            let hdr = match moq.read_fetch_obj_hdr(request_id) {
+                                Ok(v) => v,
+                                Err(moq::Error::Done) => break,
+                                Err(moq::Error::Fin) => {
+                                    data.publish_done = true;
+                                    r.close();
+                                    return;
+                                }
+                                Err(e) => panic!("{e:?}"),
+                            };
+                            if hdr.payload_len() == 0 { continue; }
+                            let mut buf = vec![0u8; hdr.payload_len()];
+                            let n = moq.read_fetch_obj_pld(&mut buf, request_id).unwrap();
+                            data.received.push((buf[..n].to_vec(), hdr.group_id()));
+                        }
+                        continue;
+                    }
+                    if let Some(request_id) = data.request_id
+                        && data.track_alias.is_none()
+                        && let Some(response) = moq.poll_subscribe_response(request_id)
+                    {
+                        match response {
+                            Ok((track_alias, _)) => data.track_alias = Some(track_alias),
+                            Err(e) => {
+                                data.error = Some(e.error_code());
+                                r.close();
+                                return;
+                            }
+                        }
+                    }
+                    let Some(track_alias) = data.track_alias else { continue };
+                    if data.started.elapsed() < data.reading.stall { continue; }
+                    if data.reading.per_stream {
+                        read_subgroups(&mut moq, track_alias, &mut data.subgroups);
+                        if data.subgroups.iter().filter(|s| s.fin || s.reset).count() >= data.max_objects {
+                            r.close();
+                            return;
+                        }
+                        continue;
+                    }
+                    while data.received.len() < data.max_objects {
+                        let hdr = match moq.read_obj_hdr(track_alias) {
+                            Ok(v) => v,
+                            Err(moq::Error::Done) => break,
+                            Err(moq::Error::Fin) => {
+                                data.publish_done = true;
+                                break;
+                            }
+                            Err(e) => unimplemented!("{:?}", e),
+                        };
+                        let group = moq.subgroup_header(track_alias).unwrap().group_id();
+                        data.headers.push((hdr.status(), hdr.extension_headers().clone()));
+                        if hdr.payload_len() == 0 { continue; }
+                        let mut buf = vec![0u8; hdr.payload_len()];
+                        let n = moq.read_obj_pld(&mut buf, track_alias).unwrap();
+                        data.received.push((buf[..n].to_vec(), group));
+                    }
+                    if data.publish_done || data.received.len() >= data.max_objects {
+                        r.close();
+                        return;
+                    }
+                }
+            };
+            c
+        },
+        {
+            let mut e = Endpoint::new(None, EndpointConfig::default(), ());
+            e.connect(
+                None,
+                socket.local_addr,
+                relay,
+                &mut {
+                    let mut c = quic_config();
+                    if let Some(window) = reading.window {
+                        c.set_initial_max_data(window);
+                        c.set_initial_max_stream_data_uni(window);
+                    }
+                    c
+                },
+                SubscriberData {
+                    moq_helper: MoqWebTransportHelper::new_client(url(relay), moq_config),
+                    range,
+                    max_objects,
+                    reading,
+                    subgroups: vec![],
+                    started: Instant::now(),
+                    request_id: None,
+                    track_alias: None,
+                    fetch_accepted: false,
+                    received: vec![],
+                    headers: vec![],
+                    publish_done: false,
+                    error: None,
+                    goaway: None,
+                },
+                None,
+                None,
+            );
+            e
+        },
+        None,
+    );
+    r.register_socket(socket);
+    r.run();
+    let data = &mut r.endpoint.conn_mut(0).unwrap().app_data;
+    match data.error {
+        Some(error_code) => Err(error_code),
+        None => Ok(Subscribed {
+            received: data.received.clone(),
+            headers: data.headers.clone(),
+            publish_done: data.publish_done,
+            goaway: data.goaway.clone(),
+            subgroups: std::mem::take(&mut data.subgroups),
+        }),
+    }
+}
+
+/// Read the objects of each readable subgroup stream, in as many parts as they arrive;
+/// a stream that ends is marked finished or reset.
+fn read_subgroups(moq: &mut MoqHandle<'_>, track_alias: TrackAlias, subgroups: &mut Vec<ReceivedSubgroup>) {
+    for stream_id in moq.readable_streams(track_alias).to_vec() {
+        let index = match subgroups.iter().position(|s| s.stream_id == stream_id) {
+            Some(index) => index,
+            None => {
+                let Some(sg) = moq.stream_subgroup_header(stream_id) else { continue };
+                subgroups.push(ReceivedSubgroup {
+                    stream_id,
+                    group: sg.group_id(),
+                    // the relay always sends the subgroup ID
+                    subgroup: sg.subgroup_id().unwrap(),
+                    payloads: vec![],
+                    remaining: 0,
+                    fin: false,
+                    reset: false,
+                });
+                subgroups.len() - 1
+            }
+        };
+        let s = &mut subgroups[index];
+        let mut buf = [0u8; 4096];
+        loop {
+            let res = if s.remaining > 0 {
+                moq.read_stream_obj_pld(&mut buf[..s.remaining.min(4096)], track_alias, stream_id).map(|n| {
+                    s.payloads.last_mut().unwrap().extend_from_slice(&buf[..n]);
+                    s.remaining -= n;
+                })
+            } else {
+                moq.read_stream_obj_hdr(track_alias, stream_id).map(|hdr| {
+                    s.payloads.push(vec![]);
+                    s.remaining = hdr.payload_len();
+                })
+            };
+            match res {
+                Ok(()) => {}
+                Err(moq::Error::Done) => break,
+                Err(moq::Error::Fin) => {
+                    s.fin = true;
+                    break;
+                }
+                Err(moq::Error::StreamReset(_)) => {
+                    s.reset = true;
+                    break;
+                }
+                Err(e) => panic!("{e:?}"),
+            }
+        }
+    }
+}
+
+pub fn group_extension(group: u64) -> KeyValuePairs {
+    KeyValuePairs::from(vec![KeyValuePair::new_varint(GROUP_EXTENSION, group).unwrap()])
+}
+
+/// Client config pinned to one draft.
+pub fn pinned(version: Version) -> moq::Config {
+    moq::Config { setup_version: version, supported_versions: vec![version], ..Default::default() }
+}
+
+struct HttpClientData {
+    h3: Option<h3::Connection>,
+    path: String,
+    sent: bool,
+    response: HttpResponse,
+}
+
+#[derive(Debug, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// GET `path` from the relay over plain HTTP/3, without WebTransport.
+pub fn http_get(relay: SocketAddr, path: &str) -> HttpResponse {
+    let socket = Socket::bind("127.0.0.1:0").unwrap();
+    let mut r = runner::Runner::new(
+        {
+            let mut c = runner::Config::<HttpClientData, (), ()>::default();
+            c.post_handle_recvs = |r| {
+                for icid in &mut r.endpoint.conn_index_iter() {
+                    let Some(conn) = r.endpoint.conn_mut(icid) else { continue };
+                    if !conn.conn.is_established() { continue; }
+                    let data = &mut conn.app_data;
+                    let h3_conn = data.h3.get_or_insert_with(|| {
+                        h3::Connection::with_transport(&mut conn.conn, &h3::Config::new().unwrap()).unwrap()
+                    });
+                    if !data.sent {
+                        let headers = [
+                            h3::Header::new(b":method", b"GET"),
+                            h3::Header::new(b":scheme", b"https"),
+                            h3::Header::new(b":authority", b"relay"),
+                            h3::Header::new(b":path", data.path.as_bytes()),
+                        ];
+                        h3_conn.send_request(&mut conn.conn, &headers, true).unwrap();
+                        data.sent = true;
+                    }
+                    loop {
+                        match h3_conn.poll(&mut conn.conn) {
+                            Ok((_, h3::Event::Headers { list, .. })) => {
+                                for h in list {
+                                    let (name, value) = (String::from_utf8_lossy(h.name()), String::from_utf8_lossy(h.value()));
+                                    if name == ":status" {
+                                        data.response.status = value.parse().unwrap();
+                                    } else {
+                                        data.response.headers.push((name.into_owned(), value.into_owned()));
+                                    }
+                                }
+                            }
+                            Ok((stream_id, h3::Event::Data)) => {
+                                let mut buf = [0u8; 4096];
+                                while let Ok(n) = h3_conn.recv_body(&mut conn.conn, stream_id, &mut buf) {
+                                    data.response.body.extend_from_slice(&buf[..n]);
+                                }
+                            }
+                            Ok((_, h3::Event::Finished)) => {
+                                r.close();
+                                return;
+                            }
+                            Ok(_) => {}
+                            Err(h3::Error::Done) => break,
+                            Err(e) => panic!("{e:?}"),
+                        }
+                    }
+                }
+            };
+            c
+        },
+        {
+            let mut e = Endpoint::new(None, EndpointConfig::default(), ());
+            e.connect(
+                None,
+                socket.local_addr,
+                relay,
+                &mut quic_config(),
+                HttpClientData { h3: None, path: path.to_string(), sent: false, response: HttpResponse::default() },
+                None,
+                None,
+            );
+            e
+        },
+        None,
+    );
+    r.register_socket(socket);
+    r.run();
+    std::mem::take(&mut r.endpoint.conn_mut(0).unwrap().app_data.response)
+}
+
+/// A free local address for the relay's admin listener.
+pub fn admin_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+/// Send a request to the relay's admin listener; returns the status and the body.
+pub fn admin_request(admin: SocketAddr, method: &str, path: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(admin).unwrap();
+    write!(stream, "{method} {path} HTTP/1.1\r\nHost: relay\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+/// GET one of the JSON views of the relay's admin listener.
+pub fn admin_json(admin: SocketAddr, path: &str) -> serde_json::Value {
+    let (status, body) = admin_request(admin, "GET", path);
+    assert_eq!(status, 200, "{path}: {body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+pub fn groups(received: &[(Vec<u8>, u64)]) -> Vec<u64> {
+    received.iter().map(|(_, group)| *group).collect()
+}
@@ -0,0 +1,65 @@
+//! Two redundant publishers of the same track on one relay, the second one as standby.
+//! The primary publisher closes its connection after its first group; the subscriber keeps
+//! its subscription and receives the standby's next group with a larger group ID.
+//! Fetches of the standby's groups that are no longer cached use its own group IDs upstream.
+
+mod common;
+
+use quiche_moq::wire::Location;
+use quiche_moq::wire::control_message::FetchRange;
+use moq_relay::Config;
+use common::{PublisherOptions, groups, run_fetch, run_subscriber, spawn_publisher_with, spawn_relay};
+
+#[test]
+fn fail_over_to_standby_publisher() {
+    let (relay_addr, relay) = spawn_relay(Config::default());
+
+    // the standby's group IDs are behind the primary's, the relay shifts them
+    let primary = spawn_publisher_with(relay_addr, PublisherOptions {
+        max_groups: Some(1),
+        first_group: 5,
+        prefix: "primary",
+        ..Default::default()
+    });
+    primary.wait_ready();
+    let standby = spawn_publisher_with(relay_addr, PublisherOptions { prefix: "standby", ..Default::default() });
+    standby.wait_ready();
+
+    let s = run_subscriber(relay_addr, None, 2).unwrap();
+    assert_eq!(s.received, [(b"primary5".to_vec(), 5), (b"standby0".to_vec(), 6)]);
+
+    primary.thread.join().unwrap();
+    standby.stop();
+    relay.stop();
+}
+
+#[test]
+fn fetch_from_standby_after_failover() {
+    let mut config = Config::default();
+    // older groups are fetched from the publisher
+    config.cache.max_objects = 2;
+    let (relay_addr, relay) = spawn_relay(config);
+    let primary = spawn_publisher_with(relay_addr, PublisherOptions {
+        max_groups: Some(1),
+        first_group: 5,
+        prefix: "primary",
+        ..Default::default()
+    });
+    primary.wait_ready();
+    let standby = spawn_publisher_with(relay_addr, PublisherOptions { prefix: "standby", ..Default::default() });
+    standby.wait_ready();
+
+    // the standby's groups 0 to 4 are forwarded as groups 6 to 10
+    let s = run_subscriber(relay_addr, None, 6).unwrap();
+    assert_eq!(groups(&s.received), [5, 6, 7, 8, 9, 10]);
+
+    // groups 6 and 7 are no longer cached, the relay fetches the standby's groups 0 and 1
+    let range = FetchRange { start: Location { group: 6, object: 0 }, end: Location { group: 7, object: 0 } };
+    let f = run_fetch(relay_addr, range).unwrap();
+    assert_eq!(f.received, [(b"standby0".to_vec(), 6), (b"standby1".to_vec(), 7)]);
+    assert!(f.publish_done);
+
+    primary.thread.join().unwrap();
+    standby.stop();
+    relay.stop();
+}
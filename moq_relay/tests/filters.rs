@@ -0,0 +1,26 @@
+//! A publisher sends groups to a live subscriber through the relay. A clip subscriber then
+//! subscribes to the absolute range of groups 1 to 2, which the relay serves from its cache,
+//! and receives PUBLISH_DONE after group 2.
+
+mod common;
+
+use quiche_moq::wire::Location;
+use moq_relay::Config;
+use common::{groups, run_subscriber, spawn_publisher, spawn_relay};
+
+#[test]
+fn absolute_range_from_cache() {
+    let (relay_addr, relay) = spawn_relay(Config::default());
+    let publisher = spawn_publisher(relay_addr, None);
+
+    // the live subscriber makes the relay subscribe and cache the groups
+    let live = run_subscriber(relay_addr, None, 4).unwrap();
+    assert_eq!(groups(&live.received), [0, 1, 2, 3]);
+
+    let clip = run_subscriber(relay_addr, Some((Location { group: 1, object: 0 }, Some(2))), usize::MAX).unwrap();
+    assert_eq!(clip.received, [(b"g1".to_vec(), 1), (b"g2".to_vec(), 2)]);
+    assert!(clip.publish_done);
+
+    publisher.stop();
+    relay.stop();
+}
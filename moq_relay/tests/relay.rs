@@ -0,0 +1,257 @@
+//! Publisher, relay and subscribers in one process, each on its own thread and loopback socket.
+//! The relay runs as a library with [`Relay::post_handle_recvs`](moq_relay::Relay::post_handle_recvs)
+//! as the runner callback, see the harness in `common`.
+
+mod common;
+
+use std::thread;
+use std::time::Instant;
+use quiche_moq::wire::{KeyValuePairs, Location, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_16, OBJECT_STATUS_END_OF_GROUP, extension_headers_supported, version_to_name};
+use moq_relay::Config;
+use common::{GROUP_INTERVAL, PublisherOptions, admin_addr, admin_json, admin_request, group_extension, groups, http_get, pinned, run_stalled_subscriber, run_subgroup_subscriber, run_subscriber, run_subscriber_with, spawn_publisher, spawn_publisher_with, spawn_relay, wait_until};
+
+#[test]
+fn forward_until_publisher_disconnects() {
+    let (relay_addr, relay) = spawn_relay(Config::default());
+    let publisher = spawn_publisher(relay_addr, Some(3));
+
+    let s = run_subscriber(relay_addr, None, usize::MAX).unwrap();
+    assert_eq!(s.received, [(b"g0".to_vec(), 0), (b"g1".to_vec(), 1), (b"g2".to_vec(), 2)]);
+    assert!(s.publish_done, "PUBLISH_DONE after the publisher disconnected");
+
+    publisher.thread.join().unwrap();
+    relay.stop();
+}
+
+#[test]
+fn concurrent_subgroups_with_upstream_reset() {
+    let (relay_addr, relay) = spawn_relay(Config::default());
+    let publisher = spawn_publisher_with(relay_addr, PublisherOptions { subgroups: Some(3), reset: Some((1, 1)), ..Default::default() });
+
+    // the three subgroups of groups 0 to 2; the subgroups of a group are still open when the next group starts
+    let s = run_subgroup_subscriber(relay_addr, 9).unwrap();
+    let mut ended: Vec<_> = s.subgroups.iter().filter(|s| s.fin || s.reset).map(|s| (s.group, s.subgroup)).collect();
+    ended.sort();
+    assert_eq!(ended, (0..3).flat_map(|g| (0..3).map(move |s| (g, s))).collect::<Vec<_>>());
+    for sg in s.subgroups.iter().filter(|s| s.group < 3) {
+        if (sg.group, sg.subgroup) == (1, 1) {
+            // the first object may be discarded with the reset stream
+            assert!(sg.reset && !sg.fin, "upstream reset not forwarded: {sg:?}");
+            assert!(sg.payloads.len() <= 1, "{sg:?}");
+        } else {
+            let expected: Vec<_> = (0..2).map(|o| format!("g{}/{}/{o}", sg.group, sg.subgroup).into_bytes()).collect();
+            assert!(sg.fin && !sg.reset, "{sg:?}");
+            assert_eq!(sg.payloads, expected);
+        }
+    }
+
+    publisher.stop();
+    relay.stop();
+}
+
+#[test]
+fn stalled_subscriber_skips_to_newest_group() {
+    const MAX_BACKLOG: u64 = 8000;
+    const PADDING: u64 = 2000;
+    let admin = admin_addr();
+    let mut config = Config::default();
+    config.admin = Some(admin);
+    config.subscriber.max_backlog = MAX_BACKLOG as usize;
+    let (relay_addr, relay) = spawn_relay(config);
+    let publisher = spawn_publisher_with(relay_addr, PublisherOptions { padding: PADDING as usize, ..Default::default() });
+    publisher.wait_ready();
+
+    // the relay fills the subscriber's receive window with the first groups, then the subscriber
+    // does not read for about 20 groups
+    let stall = 20 * GROUP_INTERVAL;
+    let subscriber = thread::spawn(move || run_stalled_subscriber(relay_addr, 40, stall, 5000));
+    let deadline = Instant::now() + stall;
+    let mut groups_skipped = 0;
+    while Instant::now() < deadline {
+        for track in admin_json(admin, "/subscriptions").as_array().unwrap() {
+            for s in track["subscribers"].as_array().unwrap() {
+                // the backlog is cut back to the newest group once it exceeds the limit
+                let backlog = s["backlog_bytes"].as_u64().unwrap();
+                assert!(backlog <= MAX_BACKLOG + 2 * (PADDING + 10), "backlog {backlog}");
+                groups_skipped = groups_skipped.max(s["groups_skipped"].as_u64().unwrap());
+            }
+        }
+        thread::sleep(GROUP_INTERVAL);
+    }
+    assert!(groups_skipped > 0, "the subscriber never skipped");
+
+    let s = subscriber.join().unwrap().unwrap();
+    assert!(s.subgroups.iter().any(|sg| sg.reset), "no stream of an old group was reset: {:?}", s.subgroups);
+    let mut finished: Vec<_> = s.subgroups.iter().filter(|sg| sg.fin).map(|sg| sg.group).collect();
+    finished.sort();
+    let newest = *finished.last().unwrap();
+    assert!(finished.len() < newest as usize + 1, "no group was skipped: {finished:?}");
+    // after the stall the subscriber keeps up again, without gaps
+    let tail = &finished[finished.len() - 5..];
+    assert!(tail.windows(2).all(|w| w[1] == w[0] + 1), "groups {finished:?}");
+
+    publisher.stop();
+    relay.stop();
+}
+
+#[test]
+fn subscriber_disconnect_keeps_track() {
+    let (relay_addr, relay) = spawn_relay(Config::default());
+    let publisher = spawn_publisher(relay_addr, None);
+
+    let first = run_subscriber(relay_addr, None, 2).unwrap();
+    assert_eq!(groups(&first.received), [0, 1]);
+
+    // the publisher keeps publishing to the relay, the next subscriber joins at a later group
+    let second = run_subscriber(relay_addr, None, 2).unwrap();
+    assert!(!second.publish_done);
+    let second = groups(&second.received);
+    assert!(second[0] > 1 && second[1] == second[0] + 1, "groups {second:?}");
+
+    publisher.stop();
+    relay.stop();
+}
+
+#[test]
+fn absolute_range_from_recording() {
+    let dir = std::env::temp_dir().join(format!("moq-relay-test-dvr-{}", std::process::id()));
+    let mut config = Config::default();
+    config.dvr.dir = Some(dir.clone());
+    config.dvr.namespaces = vec!["inproc".to_string()];
+    // only the two newest groups stay cached
+    config.cache.max_objects = 2;
+    let (relay_addr, relay) = spawn_relay(config);
+    let publisher = spawn_publisher(relay_addr, None);
+
+    let live = run_subscriber(relay_addr, None, 5).unwrap();
+    assert_eq!(groups(&live.received), [0, 1, 2, 3, 4]);
+
+    let clip = run_subscriber(relay_addr, Some((Location { group: 1, object: 0 }, Some(2))), usize::MAX).unwrap();
+    assert_eq!(clip.received, [(b"g1".to_vec(), 1), (b"g2".to_vec(), 2)]);
+    assert!(clip.publish_done);
+
+    publisher.stop();
+    relay.stop();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn subscription_quota() {
+    let admin = admin_addr();
+    let mut config = Config::default();
+    config.admin = Some(admin);
+    config.limits.subscriptions_per_session = 0;
+    let (relay_addr, relay) = spawn_relay(config);
+    let publisher = spawn_publisher(relay_addr, None);
+
+    // the subscription over the quota is held, neither answered nor forwarded
+    let subscriber = thread::spawn(move || run_subscriber(relay_addr, None, 1));
+    wait_until("held subscription", || admin_request(admin, "GET", "/metrics").1.contains("moq_relay_quota_holds_total 1"));
+    let s = subscriber.join().unwrap().unwrap();
+    assert!(s.received.is_empty());
+    assert!(!s.publish_done);
+    assert!(admin_json(admin, "/subscriptions").as_array().unwrap().is_empty());
+
+    publisher.stop();
+    relay.stop();
+}
+
+#[test]
+fn publish_denied_by_acl() {
+    let config: Config = toml::from_str(r#"
+        [[acl]]
+        namespace = "inproc"
+        publish = ["10.0.0.0/8"]
+    "#).unwrap();
+    let (relay_addr, relay) = spawn_relay(config);
+    let publisher = spawn_publisher(relay_addr, None);
+
+    // the publisher disconnects once its namespace is rejected
+    wait_until("namespace rejection", || publisher.thread.is_finished());
+
+    publisher.thread.join().unwrap();
+    relay.stop();
+}
+
+#[test]
+fn drain_sends_goaway_and_stops() {
+    let admin = admin_addr();
+    let mut config = Config::default();
+    config.admin = Some(admin);
+    config.drain.grace_period = 1;
+    config.drain.redirect = Some("https://relay2.example:4443".to_string());
+    let (relay_addr, relay) = spawn_relay(config);
+    let publisher = spawn_publisher(relay_addr, None);
+
+    let drain = thread::spawn(move || {
+        thread::sleep(10 * GROUP_INTERVAL);
+        let (status, body) = admin_request(admin, "POST", "/drain");
+        assert_eq!(status, 200, "{body}");
+    });
+
+    // the subscriber and the publisher move on GOAWAY, the relay stops after the grace period
+    let s = run_subscriber(relay_addr, None, usize::MAX).unwrap();
+    assert_eq!(s.goaway.as_deref(), Some(b"https://relay2.example:4443".as_slice()));
+    assert!(!s.received.is_empty());
+    drain.join().unwrap();
+    publisher.thread.join().unwrap();
+    relay.thread.join().unwrap();
+}
+
+#[test]
+fn bridge_draft_versions() {
+    // unverified: not run against this tree yet, see "Relay version bridging" in the README
+    for pub_version in MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_16 {
+        for sub_version in MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_16 {
+            let (relay_addr, relay) = spawn_relay(Config::default());
+            let publisher = spawn_publisher_with(relay_addr, PublisherOptions {
+                max_groups: Some(2),
+                moq_config: pinned(pub_version),
+                end_groups: true,
+                ..Default::default()
+            });
+
+            let s = run_subscriber_with(relay_addr, None, usize::MAX, pinned(sub_version)).unwrap();
+            let pair = format!("draft {} to {}", version_to_name(pub_version), version_to_name(sub_version));
+            assert_eq!(s.received, [(b"g0".to_vec(), 0), (b"g1".to_vec(), 1)], "{pair}");
+            assert!(s.publish_done, "{pair}");
+            // extension headers are dropped, not translated, if either side's draft cannot carry them
+            let carried = extension_headers_supported(pub_version) && extension_headers_supported(sub_version);
+            let ext = |group| if carried { group_extension(group) } else { KeyValuePairs::new() };
+            let expected: Vec<_> = (0..2)
+                .flat_map(|group| [(None, ext(group)), (Some(OBJECT_STATUS_END_OF_GROUP), ext(group))])
+                .collect();
+            assert_eq!(s.headers, expected, "{pair}");
+
+            publisher.thread.join().unwrap();
+            relay.stop();
+        }
+    }
+}
+
+#[test]
+fn http_gateway_serves_cached_objects() {
+    let (relay_addr, relay) = spawn_relay(Config::default());
+    let publisher = spawn_publisher(relay_addr, None);
+    // the relay caches the track once someone subscribed
+    let s = run_subscriber(relay_addr, None, 2).unwrap();
+    assert_eq!(groups(&s.received), [0, 1]);
+
+    let latest = http_get(relay_addr, "/inproc/video/latest");
+    assert_eq!(latest.status, 200);
+    assert_eq!(latest.header("cache-control"), Some("no-cache"));
+    let group = latest.header("moq-group-id").unwrap();
+    assert_eq!(latest.body, format!("g{group}").into_bytes());
+
+    let first = http_get(relay_addr, "/inproc/video/0/0");
+    assert_eq!(first.status, 200);
+    assert_eq!(first.body, b"g0");
+    assert_eq!(first.header("content-length"), Some("2"));
+    assert_eq!(first.header("cache-control"), Some("public, max-age=31536000, immutable"));
+
+    assert_eq!(http_get(relay_addr, "/inproc/video/0/1").status, 404);
+    assert_eq!(http_get(relay_addr, "/inproc/audio/latest").status, 404);
+
+    publisher.stop();
+    relay.stop();
+}
@@ -1,6 +1,6 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
-use quiche_moq::wire::{Version, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_13};
+use quiche_moq::wire::{Version, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_13, MOQ_VERSION_DRAFT_16};
 
 #[derive(Parser)]
 pub(crate) struct Args {
@@ -14,6 +14,8 @@ pub(crate) enum Command {
     Publish(PublishArgs),
     #[command(name = "sub", about = "Subscribe to a track")]
     Subscribe(SubscribeArgs),
+    #[command(name = "decode", about = "Decode raw MoQ stream bytes")]
+    Decode(DecodeArgs),
 }
 
 #[derive(Parser, Clone)]
@@ -66,6 +68,48 @@ pub(crate) struct SubscribeArgs {
     pub(crate) timeout: u64,
 }
 
+#[derive(Parser, Clone)]
+pub(crate) struct DecodeArgs {
+    /// Hex encoded bytes, whitespace is ignored.
+    /// Read from stdin if neither this, --file nor --qlog is set.
+    #[arg(conflicts_with_all = ["file", "qlog"])]
+    pub(crate) hex: Option<String>,
+    /// Read raw bytes from a binary file
+    #[arg(long, short = 'f', conflicts_with = "qlog")]
+    pub(crate) file: Option<PathBuf>,
+    /// Read bytes from the `raw` fields of a qlog file (.qlog or .sqlog)
+    #[arg(long, requires = "stream_id")]
+    pub(crate) qlog: Option<PathBuf>,
+    /// Stream whose qlog events are decoded
+    #[arg(long)]
+    pub(crate) stream_id: Option<u64>,
+    /// Decode bytes sent or received by the qlog owner
+    #[arg(long, value_enum, default_value_t = Direction::Received)]
+    pub(crate) direction: Direction,
+    /// Draft number, e.g. 14. Detected from the bytes if unset
+    #[arg(long, value_parser = parse_draft_version)]
+    pub(crate) version: Option<Version>,
+    #[arg(long, value_enum, default_value_t = StreamKind::Control)]
+    pub(crate) kind: StreamKind,
+    /// Print one JSON object per line
+    #[arg(long)]
+    pub(crate) json: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, clap::ValueEnum)]
+pub(crate) enum StreamKind {
+    /// Bidirectional control stream
+    Control,
+    /// Unidirectional subgroup data stream
+    Subgroup,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, clap::ValueEnum)]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
 #[derive(Parser, Copy, Clone, clap::ValueEnum)]
 pub(crate) enum SetupVersion {
     Draft07,
@@ -81,6 +125,20 @@ impl From<SetupVersion> for Version {
     }
 }
 
+/// e.g. "14", "draft-14" or "0xff00000e"
+fn parse_draft_version(s: &str) -> Result<Version, String> {
+    let version = if let Some(hex) = s.strip_prefix("0x") {
+        Version::from_str_radix(hex, 16).map_err(|e| e.to_string())?
+    } else {
+        let draft: Version = s.trim_start_matches("draft-").parse().map_err(|e: std::num::ParseIntError| e.to_string())?;
+        0xff000000 | draft
+    };
+    if !(MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_16).contains(&version) {
+        return Err(format!("unsupported version {version:#x}"));
+    }
+    Ok(version)
+}
+
 fn parse_separator(s: &str) -> Result<String, String> {
     match s {
         "\\n" => Ok("\n".to_string()),
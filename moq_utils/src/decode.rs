@@ -0,0 +1,420 @@
+use crate::args::{DecodeArgs, Direction, StreamKind};
+use log::info;
+use octets::Octets;
+use quiche_moq::wire::control_message::ControlMessageEnum;
+use quiche_moq::wire::object::ObjectHeader;
+use quiche_moq::wire::subgroup::SubgroupHeader;
+use quiche_moq::wire::{
+    Error, FromBytes, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_16, SUPPORTED_MOQ_VERSIONS, Version,
+    version_to_name,
+};
+use serde_json::{Value, json};
+use std::io::Read;
+use std::path::Path;
+
+enum Item {
+    ControlMessage(ControlMessageEnum),
+    SubgroupHeader(SubgroupHeader),
+    /// Object header, the payload is skipped
+    Object(ObjectHeader),
+}
+
+/// Item decoded from `offset..offset + len`
+struct Decoded {
+    offset: usize,
+    len: usize,
+    item: Item,
+}
+
+struct Failure {
+    /// Start of the control message, subgroup header or object that failed to decode
+    item_offset: usize,
+    /// Offset after the last field that was decoded successfully
+    offset: usize,
+    /// More bytes are needed, the input ended in the middle of an item
+    truncated: bool,
+    reason: String,
+}
+
+impl Failure {
+    fn new(item_offset: usize, offset: usize, err: Error) -> Self {
+        let (truncated, reason) = match err {
+            Error::Octets(_) => (true, "more bytes needed".to_string()),
+            Error::FromUtf8Error(e) => (false, e.to_string()),
+            Error::ProtocolViolation(reason) => (false, reason),
+        };
+        Self {
+            item_offset,
+            offset,
+            truncated,
+            reason,
+        }
+    }
+}
+
+struct Dissection {
+    items: Vec<Decoded>,
+    failure: Option<Failure>,
+}
+
+impl Dissection {
+    /// Number of bytes decoded without error
+    fn decoded_len(&self) -> usize {
+        self.items.last().map(|d| d.offset + d.len).unwrap_or(0)
+    }
+}
+
+pub(crate) fn run_decode(args: &DecodeArgs) {
+    let bytes = match read_input(args) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    let version = args.version.unwrap_or_else(|| {
+        let version = detect_version(&bytes, args.kind);
+        info!("detected version draft-{}", version_to_name(version));
+        version
+    });
+    let dissection = dissect(&bytes, version, args.kind);
+    for decoded in &dissection.items {
+        if args.json {
+            println!("{}", decoded_to_json(decoded));
+        } else {
+            println!("{}", decoded_to_text(decoded));
+        }
+    }
+    if let Some(failure) = &dissection.failure {
+        if args.json {
+            println!(
+                "{}",
+                json!({
+                    "offset": failure.offset,
+                    "item_offset": failure.item_offset,
+                    "truncated": failure.truncated,
+                    "error": failure.reason,
+                })
+            );
+        } else {
+            println!(
+                "{:>6} error: {} (item at offset {}, {} of {} bytes decoded)",
+                failure.offset,
+                failure.reason,
+                failure.item_offset,
+                dissection.decoded_len(),
+                bytes.len(),
+            );
+        }
+        std::process::exit(1);
+    }
+}
+
+fn dissect(bytes: &[u8], version: Version, kind: StreamKind) -> Dissection {
+    match kind {
+        StreamKind::Control => dissect_control(bytes, version),
+        StreamKind::Subgroup => dissect_subgroup(bytes, version),
+    }
+}
+
+/// The version that decodes the most bytes, the newest one on ties
+fn detect_version(bytes: &[u8], kind: StreamKind) -> Version {
+    SUPPORTED_MOQ_VERSIONS
+        .iter()
+        .copied()
+        .filter(|v| (MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_16).contains(v))
+        .max_by_key(|&v| dissect(bytes, v, kind).decoded_len())
+        .unwrap()
+}
+
+/// Returns the header and body length
+fn control_message_len(b: &[u8], version: Version) -> octets::Result<(usize, usize)> {
+    let mut b = Octets::with_slice(b);
+    b.get_varint()?;
+    let body_len = match version {
+        MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => b.get_varint()? as usize,
+        _ => b.get_u16()? as usize,
+    };
+    Ok((b.off(), body_len))
+}
+
+fn dissect_control(bytes: &[u8], version: Version) -> Dissection {
+    let mut items = vec![];
+    let mut offset = 0;
+    let failure = loop {
+        let rest = &bytes[offset..];
+        if rest.is_empty() {
+            break None;
+        }
+        let Ok((header_len, body_len)) = control_message_len(rest, version) else {
+            break Some(Failure::new(offset, offset, Error::Octets(octets::BufferTooShortError)));
+        };
+        let len = header_len + body_len;
+        if rest.len() < len {
+            break Some(Failure {
+                item_offset: offset,
+                offset: bytes.len(),
+                truncated: true,
+                reason: format!("control message of {len} bytes needs {} more bytes", len - rest.len()),
+            });
+        }
+        let mut b = Octets::with_slice(&rest[..len]);
+        match ControlMessageEnum::from_bytes(&mut b, version) {
+            Ok(cm) if b.off() == len => items.push(Decoded {
+                offset,
+                len,
+                item: Item::ControlMessage(cm),
+            }),
+            Ok(cm) => {
+                break Some(Failure {
+                    item_offset: offset,
+                    offset: offset + b.off(),
+                    truncated: false,
+                    reason: format!("{} trailing bytes in {}", len - b.off(), cm.qlog_type_name()),
+                });
+            }
+            // the length is known, so running out of bytes is a malformed message
+            Err(Error::Octets(_)) => {
+                break Some(Failure {
+                    item_offset: offset,
+                    offset: offset + b.off(),
+                    truncated: false,
+                    reason: format!("fields exceed the control message length of {body_len} bytes"),
+                });
+            }
+            Err(e) => break Some(Failure::new(offset, offset + b.off(), e)),
+        }
+        offset += len;
+    };
+    Dissection { items, failure }
+}
+
+fn dissect_subgroup(bytes: &[u8], version: Version) -> Dissection {
+    let mut b = Octets::with_slice(bytes);
+    let header = match SubgroupHeader::from_bytes(&mut b, version) {
+        Ok(v) => v,
+        Err(e) => {
+            return Dissection {
+                items: vec![],
+                failure: Some(Failure::new(0, b.off(), e)),
+            };
+        }
+    };
+    let header_len = b.off();
+    let mut objects = vec![];
+    let failure = loop {
+        let offset = b.off();
+        if b.cap() == 0 {
+            break None;
+        }
+        let oh = match ObjectHeader::from_bytes(&mut b, version, &header) {
+            Ok(v) => v,
+            Err(e) => break Some(Failure::new(offset, b.off(), e)),
+        };
+        if b.cap() < oh.payload_len() {
+            break Some(Failure {
+                item_offset: offset,
+                offset: bytes.len(),
+                truncated: true,
+                reason: format!(
+                    "object {} payload of {} bytes needs {} more bytes",
+                    oh.id(),
+                    oh.payload_len(),
+                    oh.payload_len() - b.cap()
+                ),
+            });
+        }
+        b.skip(oh.payload_len()).unwrap();
+        objects.push(Decoded {
+            offset,
+            len: b.off() - offset,
+            item: Item::Object(oh),
+        });
+    };
+    let mut items = vec![Decoded {
+        offset: 0,
+        len: header_len,
+        item: Item::SubgroupHeader(header),
+    }];
+    items.extend(objects);
+    Dissection { items, failure }
+}
+
+fn decoded_to_text(d: &Decoded) -> String {
+    let item = match &d.item {
+        Item::ControlMessage(cm) => format!("{}: {cm:?}", cm.qlog_type_name()),
+        Item::SubgroupHeader(h) => format!("subgroup_header: {h:?}"),
+        Item::Object(o) => format!("object: {o:?}"),
+    };
+    format!("{:>6} {:>6}B {item}", d.offset, d.len)
+}
+
+/// Same fields as the qlog `*_parsed` events
+fn decoded_to_json(d: &Decoded) -> Value {
+    let mut v = match &d.item {
+        Item::ControlMessage(cm) => json!({ "control_message": cm.to_qlog() }),
+        Item::SubgroupHeader(h) => json!({
+            "subgroup_header": {
+                "type": h.ty(),
+                "track_alias": h.track_alias(),
+                "group_id": h.group_id(),
+                "subgroup_id": h.subgroup_id(),
+                "publisher_priority": h.publisher_priority(),
+            }
+        }),
+        Item::Object(o) => json!({
+            "object": {
+                "object_id": o.id(),
+                "extension_headers_length": o.extension_headers_len() as u64,
+                "extension_headers": o.extension_headers_to_qlog(),
+                "object_payload_length": o.payload_len() as u64,
+                "object_status": o.status(),
+            }
+        }),
+    };
+    v["offset"] = json!(d.offset);
+    v["length"] = json!(d.len);
+    v
+}
+
+fn read_input(args: &DecodeArgs) -> Result<Vec<u8>, String> {
+    if let Some(path) = &args.file {
+        return std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()));
+    }
+    if let Some(path) = &args.qlog {
+        return read_qlog(path, args.stream_id.unwrap(), args.direction);
+    }
+    let hex = match &args.hex {
+        Some(v) => v.clone(),
+        None => {
+            let mut s = String::new();
+            std::io::stdin().read_to_string(&mut s).map_err(|e| e.to_string())?;
+            s
+        }
+    };
+    parse_hex(&hex)
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    let s: String = s.split_whitespace().collect();
+    let s = s.strip_prefix("0x").unwrap_or(&s);
+    if !s.is_ascii() || !s.len().is_multiple_of(2) {
+        return Err("expected an even number of hex digits".to_string());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| format!("invalid hex at {i}: {e}")))
+        .collect()
+}
+
+/// Concatenate the `raw` bytes of the MoQ events on `stream_id`
+fn read_qlog(path: &Path, stream_id: u64, direction: Direction) -> Result<Vec<u8>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let suffix = match direction {
+        Direction::Sent => "_created",
+        Direction::Received => "_parsed",
+    };
+    let mut bytes = vec![];
+    for event in qlog_events(&text).map_err(|e| format!("{}: {e}", path.display()))? {
+        let name = event["name"].as_str().unwrap_or_default();
+        if !name.starts_with("moqt:") || !name.ends_with(suffix) {
+            continue;
+        }
+        if event["data"]["stream_id"].as_u64() != Some(stream_id) {
+            continue;
+        }
+        if let Some(hex) = event["data"]["raw"]["data"].as_str() {
+            bytes.extend(parse_hex(hex)?);
+        }
+    }
+    if bytes.is_empty() {
+        return Err(format!("{}: no raw data on stream {stream_id}", path.display()));
+    }
+    Ok(bytes)
+}
+
+/// Events of a JSON-SEQ (`.sqlog`) or JSON (`.qlog`) file
+fn qlog_events(text: &str) -> Result<Vec<Value>, String> {
+    if text.trim_start().starts_with('\u{1e}') {
+        // the first record is the file header
+        text.split('\u{1e}')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .skip(1)
+            .map(|r| serde_json::from_str(r).map_err(|e| e.to_string()))
+            .collect()
+    } else {
+        let file: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        Ok(file["traces"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|trace| trace["events"].as_array().cloned().unwrap_or_default())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octets::OctetsMut;
+    use quiche_moq::wire::{KeyValuePairs, MOQ_VERSION_DRAFT_14, ToBytes};
+
+    const SUBSCRIBE_DRAFT_07: &[u8] = &[
+        0x03, 0x1d, 0x1, 0x1, 0x1, 0xf, 0x69, 0x6e, 0x6a, 0x75, 0x72, 0x65, 0x64, 0x2d, 0x77, 0x61, 0x6c, 0x6c, 0x61,
+        0x62, 0x79, 0x5, 0x76, 0x69, 0x64, 0x65, 0x6f, 0x1, 0x2, 0x1, 0x0,
+    ];
+
+    #[test]
+    fn hex_input() {
+        assert_eq!(parse_hex("0x03 1d\n01").unwrap(), vec![0x03, 0x1d, 0x01]);
+        assert!(parse_hex("031").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    #[test]
+    fn control_truncated() {
+        let mut bytes = SUBSCRIBE_DRAFT_07.to_vec();
+        bytes.extend_from_slice(&SUBSCRIBE_DRAFT_07[..10]);
+        let d = dissect(&bytes, MOQ_VERSION_DRAFT_07, StreamKind::Control);
+        assert_eq!(d.items.len(), 1);
+        assert_eq!(d.decoded_len(), SUBSCRIBE_DRAFT_07.len());
+        let failure = d.failure.unwrap();
+        assert!(failure.truncated);
+        assert_eq!(failure.item_offset, SUBSCRIBE_DRAFT_07.len());
+    }
+
+    #[test]
+    fn control_unknown_message() {
+        let mut bytes = SUBSCRIBE_DRAFT_07.to_vec();
+        bytes.extend_from_slice(&[0x3f, 0x0]);
+        let d = dissect(&bytes, MOQ_VERSION_DRAFT_07, StreamKind::Control);
+        let failure = d.failure.unwrap();
+        assert!(!failure.truncated);
+        assert_eq!(failure.offset, SUBSCRIBE_DRAFT_07.len());
+    }
+
+    #[test]
+    fn subgroup_objects() {
+        let version = MOQ_VERSION_DRAFT_14;
+        let header = SubgroupHeader::new(1, 2, 0, version);
+        let mut b = [0u8; 100];
+        let mut o = OctetsMut::with_slice(&mut b);
+        header.to_bytes(&mut o, version).unwrap();
+        for id in 0..2 {
+            ObjectHeader::new(id, 3, header.ty(), KeyValuePairs::new()).to_bytes(&mut o, version).unwrap();
+            o.put_bytes(b"abc").unwrap();
+        }
+        let len = o.off();
+        let d = dissect(&b[..len], version, StreamKind::Subgroup);
+        assert_eq!(d.items.len(), 3);
+        assert!(d.failure.is_none());
+        assert_eq!(detect_version(&b[..len], StreamKind::Subgroup), MOQ_VERSION_DRAFT_16);
+
+        let d = dissect(&b[..len - 1], version, StreamKind::Subgroup);
+        assert_eq!(d.items.len(), 2);
+        let failure = d.failure.unwrap();
+        assert!(failure.truncated);
+        assert_eq!(failure.item_offset, d.decoded_len());
+    }
+}
@@ -1,10 +1,12 @@
 use crate::args::{Args, Command};
+use crate::decode::run_decode;
 use crate::publish::run_publish;
 use crate::subscribe::run_subscribe;
 use clap::Parser;
 use log::LevelFilter;
 
 mod args;
+mod decode;
 mod publish;
 mod subscribe;
 
@@ -15,5 +17,6 @@ fn main() {
     match args.command {
         Command::Publish(args) => run_publish(&args),
         Command::Subscribe(args) => run_subscribe(&args),
+        Command::Decode(args) => run_decode(&args),
     }
 }
@@ -195,6 +195,12 @@ fn post_handle_recvs_conn(
                     *conn_app_data.announced = true;
                 }
             }
+            PublishStatus::Rejected(error_code) => {
+                if !*conn_app_data.announced {
+                    error!("namespace {} rejected with error code {error_code}", conn_app_data.namespace_trackname.namespace());
+                    *conn_app_data.announced = true;
+                }
+            }
             PublishStatus::Unknown => {}
         },
     }
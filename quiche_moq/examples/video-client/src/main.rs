@@ -8,11 +8,18 @@ use quiche_mio_runner::{quiche_endpoint, Socket};
 use std::io;
 use quiche_moq as moq;
 use std::io::{Stdout, Write};
+use quiche_moq::wire::{Namespace, RequestId, TrackAlias};
+use quiche_moq_catalog::{catalog_track, CatalogReader, ROLE_VIDEO};
 use quiche_moq_webtransport_helper::{MoqWebTransportHelper, State};
 
+const NAMESPACE: &str = "testsrc";
+
 struct ConnAppData {
     moq_helper: MoqWebTransportHelper,
-    subscribed: bool,
+    catalog_request_id: Option<RequestId>,
+    catalog: Option<CatalogReader>,
+    video_request_id: Option<RequestId>,
+    video_track_alias: Option<TrackAlias>,
 }
 
 struct AppData {
@@ -51,7 +58,10 @@ fn main() {
         },
         ConnAppData {
             moq_helper: MoqWebTransportHelper::new_client("https://example.org".parse().unwrap(), moq::Config::default()),
-            subscribed: false,
+            catalog_request_id: None,
+            catalog: None,
+            video_request_id: None,
+            video_track_alias: None,
         },
         None,
         None,
@@ -85,39 +95,72 @@ fn post_handle_recvs(runner: &mut Runner) {
         } = &mut conn.app_data.moq_helper.state else {
             continue 'conn;
         };
-        if moq_session.initialized() && !conn.app_data.subscribed {
-            info!("subscribe clock second");
-            moq_session.subscribe(
-                &"testsrc--mp4".parse().unwrap(),
+        let namespace: Namespace = NAMESPACE.parse().unwrap();
+        if moq_session.initialized() && conn.app_data.catalog_request_id.is_none() {
+            info!("subscribe catalog");
+            conn.app_data.catalog_request_id = Some(moq_session.subscribe(
+                &catalog_track(&namespace),
+                wt_conn,
+                quic_conn,
+            ).unwrap());
+        }
+        if let Some(request_id) = conn.app_data.catalog_request_id
+            && conn.app_data.catalog.is_none()
+            && let Some(resp) = moq_session.poll_subscribe_response(request_id)
+        {
+            let (track_alias, _) = resp.expect("catalog subscription rejected");
+            conn.app_data.catalog = Some(CatalogReader::new(track_alias));
+        }
+        if let Some(catalog) = conn.app_data.catalog.as_mut()
+            && catalog.read(moq_session, wt_conn, h3_conn, quic_conn).unwrap()
+            && conn.app_data.video_request_id.is_none()
+        {
+            let track = catalog.catalog().unwrap()
+                .tracks_with_role(ROLE_VIDEO)
+                .next()
+                .expect("no video track in catalog");
+            info!("subscribe video track {} ({})", track.name, track.codec.as_deref().unwrap_or("unknown codec"));
+            conn.app_data.video_request_id = Some(moq_session.subscribe(
+                &track.namespace_trackname(&namespace).unwrap(),
                 wt_conn,
                 quic_conn,
-            ).unwrap();
-            conn.app_data.subscribed = true;
+            ).unwrap());
         }
-        'trackLoop: for track_alias in moq_session.readable() {
-            loop {
-                let rop = moq_session.remaining_object_payload(track_alias).unwrap();
-                if rop == 0 {
-                    match moq_session.read_obj_hdr(track_alias, wt_conn, h3_conn, quic_conn) {
-                        Ok(_) => {}
-                        Err(quiche_moq::Error::Fin) => continue 'trackLoop,
-                        Err(quiche_moq::Error::Done) => continue 'trackLoop,
-                        Err(e) => unimplemented!("{:?}", e),
-                    };
-                }
-                let mut buf = [0u8; 1000];
-                let n = match moq_session.read_obj_pld(
-                    &mut buf,
-                    track_alias,
-                    wt_conn,
-                    h3_conn,
-                    quic_conn,
-                ) {
-                    Ok(n) => n,
+        if let Some(request_id) = conn.app_data.video_request_id
+            && conn.app_data.video_track_alias.is_none()
+            && let Some(resp) = moq_session.poll_subscribe_response(request_id)
+        {
+            let (track_alias, _) = resp.expect("video subscription rejected");
+            conn.app_data.video_track_alias = Some(track_alias);
+        }
+        let Some(track_alias) = conn.app_data.video_track_alias else {
+            continue 'conn;
+        };
+        if !moq_session.readable().contains(&track_alias) {
+            continue 'conn;
+        }
+        loop {
+            let rop = moq_session.remaining_object_payload(track_alias).unwrap();
+            if rop == 0 {
+                match moq_session.read_obj_hdr(track_alias, wt_conn, h3_conn, quic_conn) {
+                    Ok(_) => {}
+                    Err(quiche_moq::Error::Fin) => continue 'conn,
+                    Err(quiche_moq::Error::Done) => continue 'conn,
                     Err(e) => unimplemented!("{:?}", e),
                 };
-                app_data.out.write_all(&buf[..n]).unwrap();
             }
+            let mut buf = [0u8; 1000];
+            let n = match moq_session.read_obj_pld(
+                &mut buf,
+                track_alias,
+                wt_conn,
+                h3_conn,
+                quic_conn,
+            ) {
+                Ok(n) => n,
+                Err(e) => unimplemented!("{:?}", e),
+            };
+            app_data.out.write_all(&buf[..n]).unwrap();
         }
     }
     //todo collect garbage
@@ -20,14 +20,19 @@ use std::collections::HashMap;
 use std::io;
 use std::process::{Command, Stdio};
 use boring::ssl::{SslContextBuilder, SslMethod};
-use quiche_moq::wire::TrackAlias;
+use quiche_moq::wire::{TrackAlias, REQUEST_ERROR_DOES_NOT_EXIST};
+use quiche_moq_catalog::{Catalog, CatalogPublisher, CatalogTrackWriter, Track, CATALOG_TRACK_NAME, PACKAGING_CMAF, ROLE_VIDEO};
 use quiche_utils::cert::load_or_generate_keys;
 
+const NAMESPACE: &[u8] = b"testsrc";
+const VIDEO_TRACK_NAME: &str = "mp4";
+
 struct ConnAppData {
     h3_conn: Option<h3::Connection>,
     moq_session: Option<moq::MoqTransportSession>,
     wt_conn: quiche_webtransport::Connection,
     tracks: HashMap<TrackAlias, Mp4TrackState>,
+    catalog_tracks: HashMap<TrackAlias, CatalogTrackWriter>,
 }
 
 impl Default for ConnAppData {
@@ -37,6 +42,7 @@ impl Default for ConnAppData {
             moq_session: None,
             wt_conn: quiche_webtransport::Connection::new(true),
             tracks: Default::default(),
+            catalog_tracks: Default::default(),
         }
     }
 }
@@ -44,6 +50,20 @@ impl Default for ConnAppData {
 struct AppData {
     video_in: Receiver,
     track: Mp4SharedTrackState,
+    catalog: CatalogPublisher,
+}
+
+/// Describes the video track produced by ffmpeg below.
+fn catalog() -> Catalog {
+    let mut video = Track::new(VIDEO_TRACK_NAME, PACKAGING_CMAF);
+    video.role = Some(ROLE_VIDEO.to_string());
+    video.is_live = Some(true);
+    video.codec = Some("avc1.640028".to_string());
+    video.mime_type = Some("video/mp4".to_string());
+    video.width = Some(1920);
+    video.height = Some(1080);
+    video.framerate = Some(30.0);
+    Catalog::new(vec![video])
 }
 
 type Endpoint = quiche_endpoint::Endpoint<ConnAppData, AppData>;
@@ -119,6 +139,7 @@ fn main() {
         AppData {
             video_in,
             track: Mp4SharedTrackState::new(),
+            catalog: CatalogPublisher::new(catalog()).unwrap(),
         },
     );
 
@@ -149,7 +170,8 @@ fn main() {
 
 fn post_handle_recvs(runner: &mut Runner) {
     for icid in &mut runner.endpoint.conn_index_iter() {
-        let Some(conn) = runner.endpoint.conn_mut(icid) else { continue };
+        let (conn, app_data) = runner.endpoint.conn_with_app_data_mut(icid);
+        let Some(conn) = conn else { continue };
         let quic_conn = &mut conn.conn;
         let h3_conn = match conn.app_data.h3_conn.as_mut() {
             Some(v) => v,
@@ -199,13 +221,23 @@ fn post_handle_recvs(runner: &mut Runner) {
             }
         };
         moq.poll(wt_conn, h3_conn, quic_conn);
-        while let Some((request_id, sub)) = moq.subscription_inbox_next() {
-            assert_eq!(sub.track_namespace().0.0, [b"testsrc"]);
-            assert_eq!(sub.track_name(), b"mp4");
-            let track_alias = moq.accept_subscription(*request_id, None, wt_conn, quic_conn);
-            conn.app_data
-                .tracks
-                .insert(track_alias, Mp4TrackState::new(track_alias));
+        while let Some((&request_id, sub)) = moq.subscription_inbox_next() {
+            assert_eq!(sub.track_namespace().0.0, [NAMESPACE]);
+            let track_name = sub.track_name().to_vec();
+            if track_name == VIDEO_TRACK_NAME.as_bytes() {
+                let track_alias = moq.accept_subscription(request_id, None, wt_conn, quic_conn);
+                conn.app_data
+                    .tracks
+                    .insert(track_alias, Mp4TrackState::new(track_alias));
+            } else if track_name == CATALOG_TRACK_NAME.as_bytes() {
+                let largest_location = Some(app_data.catalog.largest_location());
+                let track_alias = moq.accept_subscription(request_id, largest_location, wt_conn, quic_conn);
+                let mut writer = CatalogTrackWriter::new(track_alias);
+                writer.send(&app_data.catalog, moq, wt_conn, h3_conn, quic_conn).unwrap();
+                conn.app_data.catalog_tracks.insert(track_alias, writer);
+            } else {
+                moq.reject_subscription(request_id, REQUEST_ERROR_DOES_NOT_EXIST, wt_conn, quic_conn);
+            }
         }
     }
     send_video(runner);
@@ -236,8 +268,11 @@ fn send_video(runner: &mut Runner) {
                 continue;
             };
             for ta in moq.writable() {
-                let mp4_track = conn.app_data.tracks.get_mut(&ta).unwrap();
-                mp4_track.send(&app_data.track, moq, wt, h3, quic)
+                if let Some(mp4_track) = conn.app_data.tracks.get_mut(&ta) {
+                    mp4_track.send(&app_data.track, moq, wt, h3, quic)
+                } else if let Some(catalog_track) = conn.app_data.catalog_tracks.get_mut(&ta) {
+                    catalog_track.send(&app_data.catalog, moq, wt, h3, quic).unwrap();
+                }
             }
         }
     }
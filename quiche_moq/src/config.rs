@@ -1,11 +1,13 @@
-use quiche_moq_wire::{MOQ_VERSION_DRAFT_16, SUPPORTED_MOQ_VERSIONS, Version};
+use quiche_moq_wire::{version_to_alpn, MOQ_VERSION_DRAFT_16, RequestId, SUPPORTED_MOQ_VERSIONS, Version};
 
 #[derive(Clone)]
 pub struct Config {
     /// The version to use to send the client setup message
     pub setup_version: Version,
     pub supported_versions: Vec<Version>,
-    pub ignore_max_request_quota: bool
+    pub ignore_max_request_quota: bool,
+    /// MAX_REQUEST_ID granted to the peer in the setup message
+    pub max_request_id: RequestId,
 }
 
 impl Default for Config {
@@ -14,6 +16,18 @@ impl Default for Config {
             setup_version: MOQ_VERSION_DRAFT_16,
             supported_versions: SUPPORTED_MOQ_VERSIONS.to_vec(),
             ignore_max_request_quota: false,
+            max_request_id: 100,
         }
     }
 }
+
+impl Config {
+    /// ALPN / WebTransport protocols to offer for the supported versions that negotiate by ALPN,
+    /// most preferred first: `setup_version`, then newer drafts before older ones.
+    /// Older drafts are negotiated by the setup messages and have no protocol.
+    pub fn protocols(&self) -> Vec<&'static str> {
+        let mut versions = self.supported_versions.clone();
+        versions.sort_by_key(|v| (*v != self.setup_version, std::cmp::Reverse(*v)));
+        versions.into_iter().filter_map(version_to_alpn).collect()
+    }
+}
@@ -13,6 +13,8 @@ pub enum Error {
     WT(quiche_webtransport::Error),
     Done,
     Fin,
+    /// The peer reset the stream with this error code
+    StreamReset(u64),
     InsufficientCapacity,
     ObjectToLong,
     UnfinishedPayload,
@@ -2,9 +2,10 @@ use crate::error::{Error, Result};
 use log::debug;
 use octets::Octets;
 use quiche::h3;
-use quiche_moq_wire::object::ObjectHeader;
+use quiche_moq_wire::fetch::{FetchHeader, FetchObjectHeader};
+use quiche_moq_wire::object::{ObjectHeader, ObjectHeaderRef};
 use quiche_moq_wire::subgroup::SubgroupHeader;
-use quiche_moq_wire::{FromBytes, Version};
+use quiche_moq_wire::{FETCH_UNI_STREAM_TYPE_ID, FromBytes, Version};
 use quiche_utils::stream_id::StreamID;
 use short_buf::ShortBuf;
 use std::cmp::min;
@@ -16,6 +17,8 @@ pub struct InStream {
     session_id: StreamID,
     version: Version,
     subgroup_header: Option<SubgroupHeader>,
+    /// Set instead of `subgroup_header` for streams carrying a fetch response
+    fetch_header: Option<FetchHeader>,
     remaining_object_payload: usize,
     current_object_id: Option<u64>,
     readable: bool,
@@ -24,6 +27,8 @@ pub struct InStream {
     buf: ShortBuf<BUF_LEN>,
     /// WebTransport reported fin
     wt_fin: bool,
+    /// Error code of the peer's RESET_STREAM; the readers report it as fin
+    reset: Option<u64>,
 }
 
 impl InStream {
@@ -33,11 +38,13 @@ impl InStream {
             session_id,
             version,
             subgroup_header: None,
+            fetch_header: None,
             remaining_object_payload: 0,
             current_object_id: None,
             readable: false,
             buf: ShortBuf::new(),
             wt_fin: false,
+            reset: None,
         }
     }
 
@@ -61,8 +68,13 @@ impl InStream {
                     self.readable = false;
                     return Ok(());
                 }
+                Err(quiche_webtransport::Error::StreamReset(code)) => {
+                    self.readable = false;
+                    self.wt_fin = true;
+                    self.reset = Some(code);
+                    return Ok(());
+                }
                 Err(quiche_webtransport::Error::Fin)
-                | Err(quiche_webtransport::Error::StreamReset(_))
                 | Err(quiche_webtransport::Error::InvalidStreamState(_)) => {
                     self.readable = false;
                     self.wt_fin = true;
@@ -72,8 +84,40 @@ impl InStream {
             }
         }
 
-        if self.subgroup_header.is_none() {
-            let _ty = Octets::with_slice(self.buf.buffer()).get_varint()?;
+        if self.subgroup_header.is_none() && self.fetch_header.is_none() {
+            let ty = Octets::with_slice(self.buf.buffer()).get_varint()?;
+            if ty == FETCH_UNI_STREAM_TYPE_ID {
+                let mut b = Octets::with_slice(self.buf.buffer());
+                let fetch_header = FetchHeader::from_bytes(&mut b, self.version)?;
+                debug!("parsed fetch header: {:?}", fetch_header);
+                self.buf.consume(b.off());
+                #[cfg(feature = "qlog")]
+                if let Some(qlog) = quic.qlog_streamer() {
+                    qlog.add_event_now(qlog::events::JsonEvent {
+                        time: 0.0,
+                        importance: qlog::events::EventImportance::Core,
+                        name: "moqt:stream_type_set".into(),
+                        data: serde_json::json!({
+                            "owner": "remote",
+                            "stream_id": self.stream_id.into_u64(),
+                            "stream_type": "fetch_header",
+                        }),
+                    })
+                    .ok();
+                    qlog.add_event_now(qlog::events::JsonEvent {
+                        time: 0.0,
+                        importance: qlog::events::EventImportance::Core,
+                        name: "moqt:fetch_header_parsed".into(),
+                        data: serde_json::json!({
+                            "stream_id": self.stream_id.into_u64(),
+                            "request_id": fetch_header.request_id(),
+                        }),
+                    })
+                    .ok();
+                }
+                self.fetch_header = Some(fetch_header);
+                return Ok(());
+            }
             let mut b = Octets::with_slice(self.buf.buffer());
             self.subgroup_header = Some(SubgroupHeader::from_bytes(&mut b, self.version)?);
             debug!("parsed subgroup header: {:?}", self.subgroup_header);
@@ -81,6 +125,17 @@ impl InStream {
             #[cfg(feature = "qlog")]
             if let Some(qlog) = quic.qlog_streamer() {
                 let h = self.subgroup_header.as_ref().unwrap();
+                qlog.add_event_now(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: "moqt:stream_type_set".into(),
+                    data: serde_json::json!({
+                        "owner": "remote",
+                        "stream_id": self.stream_id.into_u64(),
+                        "stream_type": "subgroup_header",
+                    }),
+                })
+                .ok();
                 qlog.add_event_now(qlog::events::JsonEvent {
                     time: 0.0,
                     importance: qlog::events::EventImportance::Core,
@@ -113,22 +168,26 @@ impl InStream {
 
         let object_header = loop {
             let mut b = Octets::with_slice(self.buf.buffer());
-            let oh = match ObjectHeader::from_bytes(&mut b, self.version, subgroup_header) {
-                Ok(v) => v,
+            // borrowed decoding does not allocate while the header is incomplete
+            let oh = match ObjectHeaderRef::from_bytes(&mut b, self.version, subgroup_header) {
+                Ok(v) => v.into_owned(),
                 Err(quiche_moq_wire::Error::Octets(octets::BufferTooShortError)) => {
                     match self.buf.fill(|b| {
                         wt.recv_stream(self.stream_id.into(), self.session_id.into(), h3, quic, b)
                     }) {
                         Ok(_) => {}
                         Err(quiche_webtransport::Error::Done) => return Err(Error::Done),
+                        Err(quiche_webtransport::Error::StreamReset(code)) => {
+                            self.reset = Some(code);
+                            return Err(Error::Fin);
+                        }
                         Err(quiche_webtransport::Error::Fin)
-                        | Err(quiche_webtransport::Error::StreamReset(_))
                         | Err(quiche_webtransport::Error::InvalidStreamState(_)) => return Err(Error::Fin),
                         Err(e) => unimplemented!("{:?}", e),
                     };
                     continue;
                 }
-                Err(e) => unimplemented!("{:?}", e),
+                Err(e) => return Err(e.into()),
             };
             self.buf.consume(b.off());
             break oh;
@@ -156,6 +215,22 @@ impl InStream {
                 }),
             })
             .ok();
+            if object_header.payload_len() == 0 {
+                // status objects are complete without payload
+                qlog.add_event_now(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: "moqt:subgroup_object_received".into(),
+                    data: serde_json::json!({
+                        "stream_id": self.stream_id.into_u64(),
+                        "group_id": h.group_id(),
+                        "subgroup_id": h.subgroup_id(),
+                        "object_id": object_header.id(),
+                        "object_status": object_header.status(),
+                    }),
+                })
+                .ok();
+            }
         }
         Ok(object_header)
     }
@@ -170,6 +245,68 @@ impl InStream {
         Ok(object_header)
     }
 
+    /// Like `read_obj_hdr` for streams carrying a fetch response.
+    pub fn read_fetch_obj_hdr(
+        &mut self,
+        quic: &mut quiche::Connection,
+        h3: &mut h3::Connection,
+        wt: &mut quiche_webtransport::Connection,
+    ) -> Result<FetchObjectHeader> {
+        assert_eq!(self.remaining_object_payload, 0);
+        assert!(self.fetch_header.is_some());
+
+        let object_header = loop {
+            let mut b = Octets::with_slice(self.buf.buffer());
+            match FetchObjectHeader::from_bytes(&mut b, self.version) {
+                Ok(v) => {
+                    self.buf.consume(b.off());
+                    break v;
+                }
+                Err(quiche_moq_wire::Error::Octets(octets::BufferTooShortError)) => {
+                    match self.buf.fill(|b| {
+                        wt.recv_stream(self.stream_id.into(), self.session_id.into(), h3, quic, b)
+                    }) {
+                        Ok(_) => {}
+                        Err(quiche_webtransport::Error::Done) => return Err(Error::Done),
+                        Err(quiche_webtransport::Error::StreamReset(code)) => {
+                            self.reset = Some(code);
+                            return Err(Error::Fin);
+                        }
+                        Err(quiche_webtransport::Error::Fin)
+                        | Err(quiche_webtransport::Error::InvalidStreamState(_)) => return Err(Error::Fin),
+                        Err(e) => unimplemented!("{:?}", e),
+                    };
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        debug!("parsed fetch object header: {:?}", object_header);
+        self.remaining_object_payload = object_header.payload_len();
+        self.current_object_id = Some(object_header.object_id());
+        #[cfg(feature = "qlog")]
+        if let Some(qlog) = quic.qlog_streamer() {
+            qlog.add_event_now(qlog::events::JsonEvent {
+                time: 0.0,
+                importance: qlog::events::EventImportance::Core,
+                name: "moqt:fetch_object_parsed".into(),
+                data: serde_json::json!({
+                    "stream_id": self.stream_id.into_u64(),
+                    "group_id": object_header.group_id(),
+                    "subgroup_id": object_header.subgroup_id(),
+                    "object_id": object_header.object_id(),
+                    "publisher_priority": object_header.publisher_priority(),
+                    "extension_headers_length": object_header.extension_headers().len() as u64,
+                    "extension_headers": object_header.extension_headers_to_qlog(),
+                    "object_payload_length": object_header.payload_len() as u64,
+                    "object_status": object_header.status(),
+                }),
+            })
+            .ok();
+        }
+        Ok(object_header)
+    }
+
     /// 0 if the next object header can be read.
     pub fn remaining_object_payload(&self) -> usize {
         self.remaining_object_payload
@@ -179,6 +316,15 @@ impl InStream {
         self.subgroup_header.as_ref()
     }
 
+    pub fn fetch_header(&self) -> Option<&FetchHeader> {
+        self.fetch_header.as_ref()
+    }
+
+    /// Error code if the stream ended with a RESET_STREAM rather than a FIN.
+    pub fn reset_code(&self) -> Option<u64> {
+        self.reset
+    }
+
     /// return Error::Done when no data is available at the moment
     pub fn read_obj_pld(
         &mut self,
@@ -196,17 +342,20 @@ impl InStream {
         ) {
             Ok(v) => v,
             Err(quiche_webtransport::Error::Done) => return Err(Error::Done),
+            Err(quiche_webtransport::Error::StreamReset(code)) => {
+                self.reset = Some(code);
+                return Err(Error::Fin);
+            }
             Err(quiche_webtransport::Error::Fin)
-            | Err(quiche_webtransport::Error::StreamReset(_))
             | Err(quiche_webtransport::Error::InvalidStreamState(_)) => return Err(Error::Fin),
             Err(e) => unimplemented!("{:?}", e),
         };
         self.remaining_object_payload -= n;
         #[cfg(feature = "qlog")]
         if self.remaining_object_payload == 0
+            && let Some(h) = self.subgroup_header.as_ref()
             && let Some(qlog) = quic.qlog_streamer()
         {
-            let h = self.subgroup_header.as_ref().unwrap();
             qlog.add_event_now(qlog::events::JsonEvent {
                 time: 0.0,
                 importance: qlog::events::EventImportance::Core,
@@ -3,6 +3,7 @@ extern crate core;
 mod error;
 mod in_stream;
 mod in_track;
+mod out_fetch_stream;
 mod out_stream;
 mod out_track;
 mod pending_subscribe;
@@ -0,0 +1,187 @@
+use crate::error::Result;
+use crate::Error;
+use log::trace;
+use octets::OctetsMut;
+use quiche_moq_wire::fetch::{FetchHeader, FetchObjectHeader};
+use quiche_moq_wire::{RequestId, ToBytes, Version};
+use quiche_utils::stream_id::StreamID;
+use quiche_webtransport as wt;
+
+enum State {
+    FetchHeader,
+    ObjectHeader,
+    ObjectPayload { remaining_bytes: usize },
+}
+
+/// Manages the stream carrying one fetch response
+pub(crate) struct OutFetchStream {
+    stream_id: StreamID,
+    state: State,
+    request_id: RequestId,
+    version: Version,
+}
+
+impl OutFetchStream {
+    pub fn new(stream_id: StreamID, request_id: RequestId, version: Version) -> Self {
+        Self {
+            stream_id,
+            state: State::FetchHeader,
+            request_id,
+            version,
+        }
+    }
+
+    pub fn stream_id(&self) -> StreamID {
+        self.stream_id
+    }
+
+    /// # Errors
+    /// - [`Error::UnfinishedPayload`]: called while the previous object's payload is still in progress.
+    /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    pub fn send_obj_hdr(
+        &mut self,
+        object_header: &FetchObjectHeader,
+        quic: &mut quiche::Connection,
+        wt: &mut wt::Connection,
+    ) -> Result<()> {
+        loop {
+            match self.state {
+                State::FetchHeader => {
+                    let fetch_header = FetchHeader::new(self.request_id);
+                    let mut b = [0u8; 16];
+                    let mut o = OctetsMut::with_slice(&mut b);
+                    fetch_header.to_bytes(&mut o, self.version)?;
+                    let len = o.off();
+                    match wt.stream_send_if_capacity(self.stream_id.into(), quic, &b[..len], false) {
+                        Ok(_) => {}
+                        Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
+                        Err(e) => return Err(e.into()),
+                    }
+                    trace!("sent fetch header on stream {}", self.stream_id);
+                    #[cfg(feature = "qlog")]
+                    if let Some(qlog) = quic.qlog_streamer() {
+                        qlog.add_event_now(qlog::events::JsonEvent {
+                            time: 0.0,
+                            importance: qlog::events::EventImportance::Core,
+                            name: "moqt:stream_type_set".into(),
+                            data: serde_json::json!({
+                                "owner": "local",
+                                "stream_id": self.stream_id.into_u64(),
+                                "stream_type": "fetch_header",
+                            }),
+                        }).ok();
+                        qlog.add_event_now(qlog::events::JsonEvent {
+                            time: 0.0,
+                            importance: qlog::events::EventImportance::Core,
+                            name: "moqt:fetch_header_created".into(),
+                            data: serde_json::json!({
+                                "stream_id": self.stream_id.into_u64(),
+                                "request_id": self.request_id,
+                            }),
+                        }).ok();
+                    }
+                    self.state = State::ObjectHeader;
+                    continue;
+                }
+                State::ObjectHeader => {
+                    let mut b = [0u8; 100];
+                    let mut o = OctetsMut::with_slice(&mut b);
+                    object_header.to_bytes(&mut o, self.version)?;
+                    let len = o.off();
+                    match wt.stream_send_if_capacity(self.stream_id.into(), quic, &b[..len], false) {
+                        Ok(_) => {}
+                        Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
+                        Err(e) => return Err(e.into()),
+                    }
+                    trace!("sent {:?} on stream {}", object_header, self.stream_id);
+                    #[cfg(feature = "qlog")]
+                    if let Some(qlog) = quic.qlog_streamer() {
+                        qlog.add_event_now(qlog::events::JsonEvent {
+                            time: 0.0,
+                            importance: qlog::events::EventImportance::Core,
+                            name: "moqt:fetch_object_created".into(),
+                            data: serde_json::json!({
+                                "stream_id": self.stream_id.into_u64(),
+                                "group_id": object_header.group_id(),
+                                "subgroup_id": object_header.subgroup_id(),
+                                "object_id": object_header.object_id(),
+                                "publisher_priority": object_header.publisher_priority(),
+                                "extension_headers_length": object_header.extension_headers().len() as u64,
+                                "extension_headers": object_header.extension_headers_to_qlog(),
+                                "object_payload_length": object_header.payload_len() as u64,
+                                "object_status": object_header.status(),
+                            }),
+                        }).ok();
+                    }
+                    if object_header.payload_len() > 0 {
+                        self.state = State::ObjectPayload {
+                            remaining_bytes: object_header.payload_len(),
+                        };
+                    }
+                    return Ok(());
+                }
+                State::ObjectPayload { .. } => {
+                    return Err(Error::UnfinishedPayload);
+                }
+            }
+        }
+    }
+
+    /// # Errors
+    /// - [`Error::ExceededPayload`]: `buf` is longer than the remaining object payload.
+    /// - [`Error::Done`]: send buffer full; retry with the same data.
+    /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    pub fn send_obj_pld(
+        &mut self,
+        buf: &[u8],
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<usize> {
+        let State::ObjectPayload { remaining_bytes } = &mut self.state else {
+            panic!("no object header sent")
+        };
+        if *remaining_bytes < buf.len() {
+            return Err(Error::ExceededPayload);
+        }
+        let n = match wt.stream_send(self.stream_id.into(), quic, buf, false) {
+            Ok(v) => v,
+            Err(wt::Error::Done) => return Err(Error::Done),
+            Err(wt::Error::InvalidStreamState(_)) => return Err(Error::Done),
+            Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
+            Err(e) => return Err(e.into()),
+        };
+        *remaining_bytes -= n;
+        trace!(
+            "sent {} byte fetch object payload on stream {}, {} bytes remaining",
+            n, self.stream_id, *remaining_bytes
+        );
+        if *remaining_bytes == 0 {
+            self.state = State::ObjectHeader;
+        }
+        Ok(n)
+    }
+
+    /// Send a QUIC FIN after the last object of the fetch response.
+    /// Must only be called between objects (not while an object payload is in progress).
+    pub fn fin(&mut self, quic: &mut quiche::Connection, wt: &mut wt::Connection) -> Result<()> {
+        assert!(
+            !matches!(self.state, State::ObjectPayload { .. }),
+            "cannot fin stream while object payload is in progress"
+        );
+        if matches!(self.state, State::FetchHeader) {
+            // empty response, the stream still needs its header
+            let mut b = [0u8; 16];
+            let mut o = OctetsMut::with_slice(&mut b);
+            FetchHeader::new(self.request_id).to_bytes(&mut o, self.version)?;
+            let len = o.off();
+            match wt.stream_send_if_capacity(self.stream_id.into(), quic, &b[..len], true) {
+                Ok(_) => {}
+                Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
+                Err(e) => return Err(e.into()),
+            }
+            return Ok(());
+        }
+        wt.stream_send(self.stream_id.into(), quic, &[], true).ok();
+        Ok(())
+    }
+}
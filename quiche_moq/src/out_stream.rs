@@ -49,6 +49,7 @@ impl OutStream {
     /// # Errors
     /// - [`Error::UnfinishedPayload`]: called while the previous object's payload is still in progress.
     /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    /// - [`Error::WT`]: the stream is unusable, e.g. stopped by the peer.
     pub fn send_obj_hdr(
         &mut self,
         object_id: Option<u64>,
@@ -58,6 +59,33 @@ impl OutStream {
         wt: &mut wt::Connection,
     ) -> Result<()> {
         assert!(size > 0);
+        self._send_obj_hdr(object_id, size, None, extension_headers, quic, wt)
+    }
+
+    /// Send an object without payload, e.g. with [`quiche_moq_wire::OBJECT_STATUS_END_OF_GROUP`].
+    /// Errors like `send_obj_hdr`.
+    pub fn send_obj_status(
+        &mut self,
+        object_id: Option<u64>,
+        status: u64,
+        extension_headers: &KeyValuePairs,
+        quic: &mut quiche::Connection,
+        wt: &mut wt::Connection,
+    ) -> Result<()> {
+        self._send_obj_hdr(object_id, 0, Some(status), extension_headers, quic, wt)
+    }
+
+    /// `status` must be `Some` iff `size` is 0
+    fn _send_obj_hdr(
+        &mut self,
+        object_id: Option<u64>,
+        size: usize,
+        status: Option<u64>,
+        extension_headers: &KeyValuePairs,
+        quic: &mut quiche::Connection,
+        wt: &mut wt::Connection,
+    ) -> Result<()> {
+        debug_assert_eq!(size == 0, status.is_some());
         loop {
             match self.state {
                 State::SubgroupHeader => {
@@ -69,11 +97,21 @@ impl OutStream {
                     match wt.stream_send_if_capacity(self.stream_id.into(), quic, &b[..len], false) {
                         Ok(_) => {}
                         Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
-                        Err(e) => unimplemented!("{:?}", e),
+                        Err(e) => return Err(e.into()),
                     }
                     trace!("sent subgroup header on stream {}", self.stream_id);
                     #[cfg(feature = "qlog")]
                     if let Some(qlog) = quic.qlog_streamer() {
+                        qlog.add_event_now(qlog::events::JsonEvent {
+                            time: 0.0,
+                            importance: qlog::events::EventImportance::Core,
+                            name: "moqt:stream_type_set".into(),
+                            data: serde_json::json!({
+                                "owner": "local",
+                                "stream_id": self.stream_id.into_u64(),
+                                "stream_type": "subgroup_header",
+                            }),
+                        }).ok();
                         qlog.add_event_now(qlog::events::JsonEvent {
                             time: 0.0,
                             importance: qlog::events::EventImportance::Core,
@@ -102,7 +140,10 @@ impl OutStream {
                             id
                         }
                     };
-                    let object_header = ObjectHeader::new(object_id, size, subgroup_ty, extension_headers.clone());
+                    let object_header = match status {
+                        None => ObjectHeader::new(object_id, size, subgroup_ty, extension_headers.clone()),
+                        Some(status) => ObjectHeader::new_status(object_id, status, subgroup_ty, extension_headers.clone()),
+                    };
                     let mut b = [0u8; 100];
                     let mut o = OctetsMut::with_slice(&mut b);
                     object_header.to_bytes(&mut o, self.version)?;
@@ -110,7 +151,7 @@ impl OutStream {
                     match wt.stream_send_if_capacity(self.stream_id.into(), quic, &b[..len], false) {
                         Ok(_) => {}
                         Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
-                        Err(e) => unimplemented!("{:?}", e),
+                        Err(e) => return Err(e.into()),
                     }
                     self.next_object_id = object_id + 1; // increment here because object_id was actually used
                     trace!("sent {:?} on stream {}", object_header, self.stream_id);
@@ -122,6 +163,8 @@ impl OutStream {
                             name: "moqt:subgroup_object_created".into(),
                             data: serde_json::json!({
                                 "stream_id": self.stream_id.into_u64(),
+                                "group_id": self.group_id,
+                                "subgroup_id": self.subgroup_id,
                                 "object_id": object_header.id(),
                                 "extension_headers_length": object_header.extension_headers_len() as u64,
                                 "extension_headers": object_header.extension_headers_to_qlog(),
@@ -130,10 +173,12 @@ impl OutStream {
                             }),
                         }).ok();
                     }
-                    self.state = State::ObjectPayload {
-                        subgroup_ty,
-                        remaining_bytes: size,
-                    };
+                    if size > 0 {
+                        self.state = State::ObjectPayload {
+                            subgroup_ty,
+                            remaining_bytes: size,
+                        };
+                    }
                     return Ok(());
                 }
                 State::ObjectPayload { .. } => {
@@ -147,6 +192,7 @@ impl OutStream {
     /// - [`Error::ExceededPayload`]: `buf` is longer than the remaining object payload.
     /// - [`Error::Done`]: send buffer full; retry with the same data.
     /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    /// - [`Error::WT`]: the stream is unusable, e.g. stopped by the peer.
     pub fn send_obj_pld(
         &mut self,
         buf: &[u8],
@@ -166,7 +212,7 @@ impl OutStream {
                     Err(wt::Error::Done) => return Err(Error::Done),
                     Err(wt::Error::InvalidStreamState(_)) => return Err(Error::Done),
                     Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
-                    Err(e) => unimplemented!("{:?}", e),
+                    Err(e) => return Err(e.into()),
                 };
                 *remaining_bytes -= n;
                 trace!(
@@ -3,10 +3,11 @@ use crate::error::Error;
 use crate::error::Result;
 use crate::in_stream::InStream;
 use crate::in_track::InTrack;
+use crate::out_fetch_stream::OutFetchStream;
 use crate::out_stream::OutStream;
 use crate::out_track::OutTrack;
 use crate::pending_subscribe::PendingSubscribe;
-use crate::session::PublishStatus::{Accepted, Pending, Unknown};
+use crate::session::PublishStatus::{Accepted, Pending, Rejected, Unknown};
 use log::{debug, error, trace};
 use octets::{Octets, OctetsMut};
 use partial_borrow::SplitOff;
@@ -15,23 +16,26 @@ use quiche::{Shutdown, h3};
 use quiche_moq_wire::ErrorCode;
 use quiche_moq_wire::control_message::subscribe::{FilterType, SubscribeMessage};
 use quiche_moq_wire::control_message::{
-    ClientSetupMessage, ControlMessageEnum, PublishDoneMessage, PublishNamespaceDoneMessage,
-    PublishNamespaceMessage, PublishOkMessage, RequestErrorMessage, ServerSetupMessage,
-    SubscribeOkMessage,
+    ClientSetupMessage, ControlMessageEnum, FetchCancelMessage, FetchErrorMessage, FetchMessage,
+    FetchOkMessage, FetchRange, FetchType, GoawayMessage, MaxRequestIdMessage, PublishDoneMessage,
+    PublishNamespaceDoneMessage, PublishNamespaceMessage, PublishOkMessage, RequestErrorMessage,
+    RequestsBlockedMessage, ServerSetupMessage, SubscribeOkMessage,
 };
+use quiche_moq_wire::fetch::FetchObjectHeader;
 use quiche_moq_wire::object::ObjectHeader;
 use quiche_moq_wire::subgroup::SubgroupHeader;
 use quiche_moq_wire::{
-    DEFAULT_MAX_REQUEST_ID_SETUP_PARAMETER, FromBytes, KeyValuePairs, Location, MOQ_VERSION_DRAFT_07,
-    MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_16, Namespace, NamespaceTrackname,
-    PROTOCOL_VIOLATION, Parameters, RESET_STREAM_CODE_DELIVERY_TIMEOUT, RequestId, Role,
-    SetupParameters, ToBytes, TrackAlias, Tuple, Version,
+    CLIENT_SETUP_MESSAGE_ID_VERSION_UNTIL_10, DEFAULT_MAX_REQUEST_ID_SETUP_PARAMETER, FromBytes, KeyValuePairs, Location, MOQ_VERSION_DRAFT_07,
+    MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_15,
+    MOQ_VERSION_DRAFT_16, Namespace, NamespaceTrackname, PROTOCOL_VIOLATION, Parameters,
+    RESET_STREAM_CODE_CANCELED, RESET_STREAM_CODE_DELIVERY_TIMEOUT, RESET_STREAM_CODE_INTERNAL_ERROR,
+    RequestId, Role, SetupParameters, TOO_MANY_REQUESTS, ToBytes, TrackAlias, Tuple, VERSION_NEGOTIATION_FAILED, Version, alpn_to_version,
 };
 use quiche_utils::stream_id::StreamID;
 use quiche_webtransport as wt;
 use short_buf::ShortBuf;
 use smallvec::SmallVec;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 const INITIAL_CLIENT_REQUEST_ID: RequestId = 0;
 const INITIAL_SERVER_REQUEST_ID: RequestId = 1;
@@ -46,14 +50,23 @@ pub struct MoqTransportSession {
     ctrl_buf: ShortBuf<1024>,
     /// is none if setup is not complete
     pub(crate) selected_version: Option<Version>,
+    /// Version selected by ALPN or the WebTransport protocol, draft 15+.
+    /// `None` if the version is negotiated by the setup messages.
+    alpn_version: Option<Version>,
+    /// PATH setup parameter sent by the client, only set on the server
+    setup_path: Option<Vec<u8>>,
     // next request_id to send
-    next_request_id: RequestId,
+    pub(crate) next_request_id: RequestId,
     // next expected request_id to receive
     next_expected_request_id: RequestId,
     /// max request_id allowed to send
     max_request_id: RequestId,
     // max request_id allowed to recv
     out_max_request_id: RequestId,
+    /// Limit we reported with REQUESTS_BLOCKED, to send it only once per limit
+    blocked_at: Option<RequestId>,
+    /// Limit the peer reported with REQUESTS_BLOCKED, cleared by the next grant
+    peer_blocked_at: Option<RequestId>,
     pub(crate) in_streams: HashMap<StreamID, InStream>,
     in_tracks: HashMap<TrackAlias, InTrack>,
     /// Egress tracks.
@@ -71,13 +84,15 @@ pub struct MoqTransportSession {
     >,
     /// Streams that cannot be associated with a track yet because the SUBSCRIBE_OK is not received yet.
     /// https://datatracker.ietf.org/doc/html/draft-ietf-moq-transport-13#name-subgroup-header
-    pending_streams: HashMap<TrackAlias, StreamID>,
+    pending_streams: HashMap<TrackAlias, SmallVec<StreamID, 4>>,
     /// Received subscriptions that have not been answered
     pending_received_subscriptions: HashMap<RequestId, SubscribeMessage>,
     pending_received_publish_namespace: HashMap<RequestId, PublishNamespaceMessage>,
     pub(crate) out_streams: HashMap<StreamID, OutStream>,
     config: Config,
     closed: bool,
+    /// New session URI of a GOAWAY received from the server, empty for the same URI.
+    goaway_uri: Option<Vec<u8>>,
     /// Namespaces we announced (outgoing PUBLISH_NAMESPACE), accepted by peer (PUBLISH_OK received).
     /// Maps request_id we used → namespace; needed to send PUBLISH_NAMESPACE_DONE.
     sent_namespaces: HashMap<RequestId, Namespace>,
@@ -85,37 +100,49 @@ pub struct MoqTransportSession {
     /// Maps request_id they used → namespace; needed to process incoming PUBLISH_NAMESPACE_DONE.
     received_namespaces: HashMap<RequestId, Namespace>,
     pending_sent_publish_namespace: HashMap<RequestId, PublishNamespaceMessage>,
+    /// Namespaces we announced that the peer rejected: request_id → error code.
+    rejected_namespaces: HashMap<RequestId, ErrorCode>,
+    /// Fetch requests the peer has not responded to.
+    pending_fetch: HashSet<RequestId>,
+    /// Received fetch responses not yet polled by upper layer
+    pending_fetch_responses: HashMap<RequestId, core::result::Result<FetchOkMessage, RequestErrorMessage>>,
+    /// Incoming streams carrying fetch responses: request_id → stream
+    in_fetches: HashMap<RequestId, StreamID>,
+    /// Received fetches that have not been answered
+    pending_received_fetches: HashMap<RequestId, FetchMessage>,
+    /// Accepted fetches still being served: request_id → outgoing stream
+    out_fetches: HashMap<RequestId, OutFetchStream>,
+}
+
+/// Version selected by the ALPN of the QUIC connection, or by the protocol of the WebTransport session.
+fn negotiated_version(session_id: StreamID, quic: &quiche::Connection, wt: &wt::Connection) -> Option<Version> {
+    alpn_to_version(quic.application_proto())
+        .or_else(|| wt.session_protocol(session_id.into_u64()).and_then(|p| alpn_to_version(p.as_bytes())))
+}
+
+/// Setup message negotiation: `setup_version` if offered by the client, else the first common version.
+fn select_version(config: &Config, offered: &[Version]) -> Option<Version> {
+    if offered.contains(&config.setup_version) {
+        return Some(config.setup_version);
+    }
+    config.supported_versions.iter().copied().find(|v| offered.contains(v))
 }
 
 #[cfg(feature = "qlog")]
-fn cm_qlog_message(cm: &ControlMessageEnum) -> serde_json::Value {
-    use quiche_moq_wire::control_message::GroupOrder;
-    let mut msg = serde_json::json!({ "type": cm.qlog_type_name() });
-    if let ControlMessageEnum::SubscribeOk(som) = cm {
-        msg["request_id"] = som.request_id().into();
-        if let Some(ta) = som.track_alias() {
-            msg["track_alias"] = ta.into();
-        }
-        let mut params: Vec<serde_json::Value> = vec![];
-        if som.expires() != 0 {
-            params.push(serde_json::json!({ "name": "expires", "value": som.expires() }));
-        }
-        params.push(serde_json::json!({
-            "name": "group_order",
-            "value": match som.group_order() { GroupOrder::Ascending => 1u64, GroupOrder::Descending => 2u64 },
-        }));
-        if let Some(loc) = som.largest_location() {
-            params.push(serde_json::json!({
-                "name": "largest_object",
-                "value": { "group": loc.group, "object": loc.object },
-            }));
-        }
-        msg["number_of_parameters"] = params.len().into();
-        if !params.is_empty() {
-            msg["parameters"] = params.into();
-        }
+fn qlog_control_stream_set(quic: &mut quiche::Connection, stream_id: StreamID, owner: &str) {
+    if let Some(qlog) = quic.qlog_streamer() {
+        qlog.add_event_now(qlog::events::JsonEvent {
+            time: 0.0,
+            importance: qlog::events::EventImportance::Core,
+            name: "moqt:stream_type_set".into(),
+            data: serde_json::json!({
+                "owner": owner,
+                "stream_id": stream_id.into_u64(),
+                "stream_type": "control",
+            }),
+        })
+        .ok();
     }
-    msg
 }
 
 #[quiche_moq_macros::generate_moq_handle]
@@ -130,6 +157,65 @@ impl MoqTransportSession {
         self.selected_version
     }
 
+    /// WebTransport session carrying this MoQ session.
+    pub fn session_id(&self) -> StreamID {
+        self.webtransport_session_id
+    }
+
+    /// PATH setup parameter of the client, used by raw QUIC clients to pass what a
+    /// WebTransport client puts into the CONNECT request. Always `None` on the client.
+    pub fn setup_path(&self) -> Option<&[u8]> {
+        self.setup_path.as_deref()
+    }
+
+    /// The session was closed, e.g. because the peer violated the protocol or exceeded MAX_REQUEST_ID.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Ask the client to move to a new session at `new_session_uri`, or to reconnect to the same
+    /// URI if `None`: sends GOAWAY and drains the WebTransport session. Server only.
+    pub fn goaway(
+        &mut self,
+        new_session_uri: Option<&str>,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) {
+        assert!(self.server);
+        self.send_control_message(
+            quic,
+            wt,
+            &ControlMessageEnum::Goaway(GoawayMessage {
+                new_session_uri: new_session_uri.unwrap_or_default().as_bytes().to_vec(),
+            }),
+        );
+        wt.drain_session(self.webtransport_session_id.into_u64(), quic, h3).unwrap();
+    }
+
+    /// Set once the server sent GOAWAY: the URI of the new session, empty to reconnect to the same URI.
+    pub fn received_goaway(&self) -> Option<&[u8]> {
+        self.goaway_uri.as_deref()
+    }
+
+    /// Close the session with a termination code, e.g. [`quiche_moq_wire::GOAWAY_TIMEOUT`].
+    pub fn close(
+        &mut self,
+        error_code: u32,
+        reason: &str,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) {
+        if self.closed {
+            return;
+        }
+        if let Err(e) = wt.close_session(self.webtransport_session_id.into_u64(), error_code, reason, quic, h3) {
+            error!("failed to close session: {:?}", e);
+        }
+        self.closed = true;
+    }
+
     /// connect to server
     pub fn connect(
         session_id: StreamID,
@@ -141,16 +227,23 @@ impl MoqTransportSession {
         let control_stream_id = wt
             .open_stream(session_id.into(), h3_conn, quich_conn, true)
             .unwrap();
+        let alpn_version = negotiated_version(session_id, quich_conn, wt);
+        #[cfg(feature = "qlog")]
+        qlog_control_stream_set(quich_conn, control_stream_id.into(), "local");
         let s = Self {
             server: false,
             control_stream_id: Some(control_stream_id.into()),
             webtransport_session_id: session_id,
             ctrl_buf: ShortBuf::new(),
             selected_version: None,
+            alpn_version,
+            setup_path: None,
             next_request_id: INITIAL_CLIENT_REQUEST_ID,
             next_expected_request_id: INITIAL_SERVER_REQUEST_ID,
             max_request_id: 0,
-            out_max_request_id: 100, //TODO make configurable
+            out_max_request_id: config.max_request_id,
+            blocked_at: None,
+            peer_blocked_at: None,
             in_streams: HashMap::new(),
             in_tracks: HashMap::new(),
             out_tracks: HashMap::new(),
@@ -164,9 +257,16 @@ impl MoqTransportSession {
             out_streams: HashMap::new(),
             config: config.clone(),
             closed: false,
+            goaway_uri: None,
             sent_namespaces: HashMap::new(),
             received_namespaces: HashMap::new(),
             pending_sent_publish_namespace: HashMap::new(),
+            rejected_namespaces: HashMap::new(),
+            pending_fetch: HashSet::new(),
+            pending_fetch_responses: HashMap::new(),
+            in_fetches: HashMap::new(),
+            pending_received_fetches: HashMap::new(),
+            out_fetches: HashMap::new(),
         };
         s.send_control_message(
             quich_conn,
@@ -175,7 +275,7 @@ impl MoqTransportSession {
                 supported_versions: config.supported_versions,
                 setup_parameters: SetupParameters {
                     path: None,
-                    max_request_id: Some(100),
+                    max_request_id: Some(config.max_request_id),
                     role: Some(Role::PubSub),
                     extra_parameters: vec![],
                 },
@@ -192,10 +292,14 @@ impl MoqTransportSession {
             webtransport_session_id: session_id,
             ctrl_buf: ShortBuf::new(),
             selected_version: None,
+            alpn_version: None,
+            setup_path: None,
             next_request_id: INITIAL_SERVER_REQUEST_ID,
             next_expected_request_id: INITIAL_CLIENT_REQUEST_ID,
             max_request_id: 0,
-            out_max_request_id: 0,
+            out_max_request_id: config.max_request_id,
+            blocked_at: None,
+            peer_blocked_at: None,
             in_streams: HashMap::new(),
             in_tracks: HashMap::new(),
             out_tracks: HashMap::new(),
@@ -209,9 +313,16 @@ impl MoqTransportSession {
             out_streams: HashMap::new(),
             config,
             closed: false,
+            goaway_uri: None,
             sent_namespaces: HashMap::new(),
             received_namespaces: HashMap::new(),
             pending_sent_publish_namespace: HashMap::new(),
+            rejected_namespaces: HashMap::new(),
+            pending_fetch: HashSet::new(),
+            pending_fetch_responses: HashMap::new(),
+            in_fetches: HashMap::new(),
+            pending_received_fetches: HashMap::new(),
+            out_fetches: HashMap::new(),
         }
     }
 
@@ -222,11 +333,33 @@ impl MoqTransportSession {
         wt: &mut wt::Connection,
         quic: &mut quiche::Connection,
     ) -> Result<RequestId> {
-        if self.next_request_id > self.max_request_id && !self.config.ignore_max_request_quota {
-            return Err(Error::RequestBlocked);
-            //todo send request blocked control message
-        }
-        let request_id = self.next_request_id;
+        self._subscribe(namespace_trackname, FilterType::NextGroupStart, None, None, wt, quic)
+    }
+
+    /// Subscribe from `start` on, up to and including `end_group` if given.
+    /// Returns the request_id
+    pub fn subscribe_range(
+        &mut self,
+        namespace_trackname: &NamespaceTrackname,
+        start: Location,
+        end_group: Option<u64>,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<RequestId> {
+        let filter_type = if end_group.is_some() { FilterType::AbsoluteRange } else { FilterType::AbsoluteStart };
+        self._subscribe(namespace_trackname, filter_type, Some(start), end_group, wt, quic)
+    }
+
+    fn _subscribe(
+        &mut self,
+        namespace_trackname: &NamespaceTrackname,
+        filter_type: FilterType,
+        start_location: Option<Location>,
+        end_group: Option<u64>,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<RequestId> {
+        let request_id = self.take_request_id(wt, quic)?;
         let track_alias = Some(request_id);
         self.send_control_message(
             quic,
@@ -238,19 +371,96 @@ impl MoqTransportSession {
                 subscriber_priority: 1,
                 group_order: 2,
                 forward: Some(0),
-                filter_type: FilterType::NextGroupStart,
-                start_location: None,
-                end_group: None,
+                filter_type,
+                start_location,
+                end_group,
                 parameters: Parameters(vec![]),
             }),
         );
         self.pending_subscribe
             .insert(request_id, PendingSubscribe::new(track_alias));
-        self.next_request_id += 2;
         debug!("moq subscribe {}", &namespace_trackname);
         Ok(request_id)
     }
 
+    /// Allocate the next request ID within the peer's MAX_REQUEST_ID.
+    /// Sends REQUESTS_BLOCKED once per limit when exhausted.
+    fn take_request_id(&mut self, wt: &mut wt::Connection, quic: &mut quiche::Connection) -> Result<RequestId> {
+        if self.next_request_id > self.max_request_id && !self.config.ignore_max_request_quota {
+            if self.blocked_at != Some(self.max_request_id) {
+                self.blocked_at = Some(self.max_request_id);
+                self.send_control_message(
+                    quic,
+                    wt,
+                    &ControlMessageEnum::RequestsBlocked(RequestsBlockedMessage {
+                        maximum_request_id: self.max_request_id,
+                    }),
+                );
+            }
+            return Err(Error::RequestBlocked);
+        }
+        let request_id = self.next_request_id;
+        self.next_request_id += 2;
+        Ok(request_id)
+    }
+
+    /// Allow the peer `count` more requests beyond the ones it has sent, by sending MAX_REQUEST_ID.
+    /// Never lowers the current limit.
+    pub fn grant_request_ids(&mut self, count: u64, wt: &mut wt::Connection, quic: &mut quiche::Connection) {
+        if count == 0 {
+            return;
+        }
+        let max = self.next_expected_request_id + 2 * (count - 1);
+        if max <= self.out_max_request_id {
+            return;
+        }
+        self.out_max_request_id = max;
+        self.peer_blocked_at = None;
+        self.send_control_message(
+            quic,
+            wt,
+            &ControlMessageEnum::MaxRequestId(MaxRequestIdMessage { request_id: max }),
+        );
+    }
+
+    /// Number of further requests the peer may send under the current MAX_REQUEST_ID.
+    pub fn remaining_peer_requests(&self) -> u64 {
+        if self.next_expected_request_id > self.out_max_request_id {
+            return 0;
+        }
+        (self.out_max_request_id - self.next_expected_request_id) / 2 + 1
+    }
+
+    /// True if the peer sent REQUESTS_BLOCKED and got no grant since.
+    pub fn peer_requests_blocked(&self) -> bool {
+        self.peer_blocked_at.is_some()
+    }
+
+    /// Validate the ID of a request received from the peer.
+    /// Closes the session with PROTOCOL_VIOLATION if the ID was used before,
+    /// and with TOO_MANY_REQUESTS if it exceeds our MAX_REQUEST_ID.
+    fn accept_request_id(
+        &mut self,
+        request_id: RequestId,
+        quic: &mut quiche::Connection,
+        h3: &mut h3::Connection,
+        wt: &mut wt::Connection,
+    ) -> bool {
+        // IDs count up by two, by one before draft-11, so an ID right below the expected one is new.
+        if request_id + 1 < self.next_expected_request_id {
+            error!("request id {} was used before", request_id);
+            self.close(PROTOCOL_VIOLATION, "request id reused", wt, h3, quic);
+            return false;
+        }
+        if request_id > self.out_max_request_id {
+            error!("request id {} exceeds max request id {}", request_id, self.out_max_request_id);
+            self.close(TOO_MANY_REQUESTS, "", wt, h3, quic);
+            return false;
+        }
+        self.next_expected_request_id = self.next_expected_request_id.max(request_id + 2);
+        true
+    }
+
     fn send_control_message(
         &self,
         conn: &mut quiche::Connection,
@@ -261,7 +471,7 @@ impl MoqTransportSession {
     }
 
     fn _send_control_message(
-        s: &partial!(MoqTransportSession const control_stream_id selected_version config, ! *),
+        s: &partial!(MoqTransportSession const control_stream_id selected_version alpn_version config, ! *),
         quic: &mut quiche::Connection,
         wt: &mut wt::Connection,
         cm: &ControlMessageEnum,
@@ -271,12 +481,17 @@ impl MoqTransportSession {
         };
         let mut b = [0u8; 100];
         let mut o = OctetsMut::with_slice(&mut b);
-        cm.to_bytes(&mut o, s.selected_version.unwrap_or(s.config.setup_version))
-            .unwrap();
+        let version = s.selected_version.or(*s.alpn_version).unwrap_or(s.config.setup_version);
+        cm.to_bytes(&mut o, version).unwrap();
         let len = o.off();
-        let n = wt
-            .stream_send(control_stream_id.into(), quic, &b[..len], false)
-            .unwrap();
+        let n = match wt.stream_send(control_stream_id.into(), quic, &b[..len], false) {
+            Ok(n) => n,
+            Err(e) => {
+                // e.g. the connection is closed
+                error!("failed to send control message {:?}: {:?}", cm, e);
+                return;
+            }
+        };
         assert_eq!(n, len);
         debug!(
             "moq send control message on stream {}: {:?}",
@@ -290,7 +505,8 @@ impl MoqTransportSession {
                 name: "moqt:control_message_created".into(),
                 data: serde_json::json!({
                     "stream_id": control_stream_id.into_u64(),
-                    "message": cm_qlog_message(cm),
+                    "message": cm.to_qlog(),
+                    "raw": quiche_moq_wire::raw_info(&b[..len]),
                 }),
             })
             .ok();
@@ -321,6 +537,9 @@ impl MoqTransportSession {
             let Some(id) = id else { return };
             let id = id.into();
             self.control_stream_id = Some(id);
+            self.alpn_version = negotiated_version(self.webtransport_session_id, quic, wt);
+            #[cfg(feature = "qlog")]
+            qlog_control_stream_set(quic, id, "remote");
             id
         };
 
@@ -347,22 +566,22 @@ impl MoqTransportSession {
                     }
                     Err(e) => unimplemented!("{:?}", e),
                 };
-                #[cfg(feature = "qlog")]
-                if let Some(qlog) = quic.qlog_streamer() {
-                    qlog.add_event_now(qlog::events::JsonEvent {
-                        time: 0.0,
-                        importance: qlog::events::EventImportance::Core,
-                        name: "moqt:control_message_parsed".into(),
-                        data: serde_json::json!({
-                            "stream_id": control_stream_id.into_u64(),
-                            "message": cm_qlog_message(&cm),
-                        }),
-                    })
-                    .ok();
-                }
                 match cm {
                     ControlMessageEnum::ServerSetup(cm) => {
                         assert!(!self.server);
+                        if !self.config.supported_versions.contains(&cm.selected_version) {
+                            error!("server selected unsupported version {:#x}", cm.selected_version);
+                            wt.close_session(
+                                self.webtransport_session_id.into_u64(),
+                                VERSION_NEGOTIATION_FAILED,
+                                "",
+                                quic,
+                                h3,
+                            )
+                            .unwrap();
+                            self.closed = true;
+                            return;
+                        }
                         self.selected_version = Some(cm.selected_version);
                         self.max_request_id = cm
                             .setup_parameters
@@ -370,7 +589,15 @@ impl MoqTransportSession {
                             .unwrap_or(DEFAULT_MAX_REQUEST_ID_SETUP_PARAMETER);
                     }
                     ControlMessageEnum::RequestsBlocked(cm) => {
-                        error!("{:?}", cm)
+                        debug!("peer blocked at request id {}", cm.maximum_request_id);
+                        self.peer_blocked_at = Some(cm.maximum_request_id);
+                    }
+                    ControlMessageEnum::MaxRequestId(cm) => {
+                        self.max_request_id = self.max_request_id.max(cm.request_id);
+                    }
+                    ControlMessageEnum::Goaway(cm) => {
+                        debug!("goaway to {:?}", String::from_utf8_lossy(&cm.new_session_uri));
+                        self.goaway_uri = Some(cm.new_session_uri);
                     }
                     ControlMessageEnum::SubscribeOk(cm) => {
                         let req_id = cm.request_id();
@@ -386,7 +613,7 @@ impl MoqTransportSession {
                         };
                         self.in_tracks
                             .insert(track_alias, InTrack::new(track_alias));
-                        if let Some(stream_id) = self.pending_streams.remove(&track_alias) {
+                        for stream_id in self.pending_streams.remove(&track_alias).unwrap_or_default() {
                             self.in_tracks
                                 .get_mut(&track_alias)
                                 .unwrap()
@@ -398,9 +625,41 @@ impl MoqTransportSession {
                     }
                     ControlMessageEnum::RequestError(cm) => {
                         let req_id = cm.request_id();
+                        if self.pending_fetch.remove(&req_id) {
+                            self.pending_fetch_responses.insert(req_id, Err(cm));
+                            continue;
+                        }
+                        if self.pending_sent_publish_namespace.remove(&req_id).is_some() {
+                            self.rejected_namespaces.insert(req_id, cm.error_code());
+                            continue;
+                        }
                         let _req = self.pending_subscribe.remove(&req_id).unwrap();
                         self.pending_subscribe_responses.insert(req_id, Err(cm));
                     }
+                    ControlMessageEnum::Fetch(cm) => {
+                        if !self.accept_request_id(cm.request_id, quic, h3, wt) {
+                            return;
+                        }
+                        self.pending_received_fetches.insert(cm.request_id, cm);
+                    }
+                    ControlMessageEnum::FetchOk(cm) => {
+                        let req_id = cm.request_id();
+                        if self.pending_fetch.remove(&req_id) {
+                            self.pending_fetch_responses.insert(req_id, Ok(cm));
+                        }
+                    }
+                    ControlMessageEnum::FetchError(cm) => {
+                        let req_id = cm.request_id();
+                        if self.pending_fetch.remove(&req_id) {
+                            self.pending_fetch_responses.insert(req_id, Err(cm.into()));
+                        }
+                    }
+                    ControlMessageEnum::FetchCancel(cm) => {
+                        self.pending_received_fetches.remove(&cm.request_id);
+                        if let Some(stream) = self.out_fetches.remove(&cm.request_id) {
+                            quic.stream_shutdown(stream.stream_id().into_u64(), Shutdown::Write, RESET_STREAM_CODE_CANCELED).ok();
+                        }
+                    }
                     ControlMessageEnum::PublishDone(cm) => {
                         if let Some(&track_alias) = self.active_subscriptions.get(&cm.request_id()) && let Some(track) = self.in_tracks.get_mut(&track_alias) {
                             track.mark_done(cm.stream_count());
@@ -408,25 +667,33 @@ impl MoqTransportSession {
                     }
                     ControlMessageEnum::PublishNamespace(cm) => {
                         let request_id = cm.request_id().unwrap(); //todo
-                        assert!(request_id <= self.out_max_request_id, "INVALID_REQUEST_ID");
-                        assert_eq!(
-                            request_id, self.next_expected_request_id,
-                            "INVALID_REQUEST_ID"
-                        );
-                        self.next_expected_request_id += 2;
+                        if !self.accept_request_id(request_id, quic, h3, wt) {
+                            return;
+                        }
                         self.pending_received_publish_namespace
                             .insert(request_id, cm);
                     }
                     ControlMessageEnum::ClientSetup(cm) => {
                         assert!(self.server);
-                        //todo make list of supported version configurable
-                        assert!(cm.supported_versions.contains(&self.config.setup_version));
-                        let version = self.config.setup_version;
+                        let Some(version) = self.alpn_version.or_else(|| select_version(&self.config, &cm.supported_versions)) else {
+                            error!("no common version in {:x?}", cm.supported_versions);
+                            wt.close_session(
+                                self.webtransport_session_id.into_u64(),
+                                VERSION_NEGOTIATION_FAILED,
+                                "",
+                                quic,
+                                h3,
+                            )
+                            .unwrap();
+                            self.closed = true;
+                            return;
+                        };
                         self.selected_version = Some(version);
                         self.max_request_id = cm
                             .setup_parameters
                             .max_request_id
                             .unwrap_or(DEFAULT_MAX_REQUEST_ID_SETUP_PARAMETER);
+                        self.setup_path = cm.setup_parameters.path;
                         self.send_control_message(
                             quic,
                             wt,
@@ -442,6 +709,9 @@ impl MoqTransportSession {
                         );
                     }
                     ControlMessageEnum::Subscribe(cm) => {
+                        if !self.accept_request_id(cm.request_id, quic, h3, wt) {
+                            return;
+                        }
                         self.pending_received_subscriptions
                             .insert(cm.request_id, cm);
                     }
@@ -484,6 +754,10 @@ impl MoqTransportSession {
                 };
                 stream.read(quic, h3, wt).unwrap();
                 stream.mark_readable();
+                if let Some(fetch_header) = stream.fetch_header() {
+                    self.in_fetches.insert(fetch_header.request_id(), stream_id.into());
+                    continue;
+                }
                 let Some(subgroup_header) = stream.subgroup_header() else {
                     continue;
                 };
@@ -493,14 +767,32 @@ impl MoqTransportSession {
                         track.mark_stream_readable(stream_id.into());
                     }
                     None => {
-                        let prev = self.pending_streams.insert(track_alias, stream_id.into());
-                        assert!(prev.is_none()); // todo maybe append list
+                        let pending = self.pending_streams.entry(track_alias).or_default();
+                        if !pending.contains(&stream_id.into()) {
+                            pending.push(stream_id.into());
+                        }
                     }
                 }
             }
         }
     }
 
+    /// Version to decode the next control message with. A server that negotiated no version by
+    /// ALPN or WebTransport protocol gets a CLIENT_SETUP of drafts 07 to 14, which lists the
+    /// client's versions; its message type tells drafts 07 to 10 from 11 to 14.
+    fn receive_version(&self) -> Version {
+        if let Some(version) = self.selected_version.or(self.alpn_version) {
+            return version;
+        }
+        if !self.server {
+            return self.config.setup_version;
+        }
+        match Octets::with_slice(self.ctrl_buf.buffer()).get_varint() {
+            Ok(CLIENT_SETUP_MESSAGE_ID_VERSION_UNTIL_10) => MOQ_VERSION_DRAFT_07,
+            _ => MOQ_VERSION_DRAFT_14,
+        }
+    }
+
     /// Returns `Error::Done` when no control message is available yet
     fn next_control_message(
         &mut self,
@@ -513,11 +805,22 @@ impl MoqTransportSession {
         };
         let cm = loop {
             let mut o = Octets::with_slice(self.ctrl_buf.buffer());
-            match ControlMessageEnum::from_bytes(
-                &mut o,
-                self.selected_version.unwrap_or(self.config.setup_version),
-            ) {
+            match ControlMessageEnum::from_bytes(&mut o, self.receive_version()) {
                 Ok(v) => {
+                    #[cfg(feature = "qlog")]
+                    if let Some(qlog) = quic.qlog_streamer() {
+                        qlog.add_event_now(qlog::events::JsonEvent {
+                            time: 0.0,
+                            importance: qlog::events::EventImportance::Core,
+                            name: "moqt:control_message_parsed".into(),
+                            data: serde_json::json!({
+                                "stream_id": control_stream_id.into_u64(),
+                                "message": v.to_qlog(),
+                                "raw": quiche_moq_wire::raw_info(&self.ctrl_buf.buffer()[..o.off()]),
+                            }),
+                        })
+                        .ok();
+                    }
                     self.ctrl_buf.consume(o.off());
                     trace!("received control message {:?}", v);
                     break v;
@@ -534,7 +837,7 @@ impl MoqTransportSession {
                     })?;
                     trace!("fill ctrl_buf {:?}", self.ctrl_buf.buffer())
                 }
-                Err(e) => unimplemented!("{:?}", e),
+                Err(e) => return Err(e.into()),
             };
         };
         Ok(cm)
@@ -630,6 +933,7 @@ impl MoqTransportSession {
     ///   (a different value within the same group also opens a new stream per the spec).
     /// `object_id`: `None` = auto-increment; `Some(id)` = explicit (must be >= next expected in this subgroup).
     /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    /// - [`Error::WT`]: a new subgroup stream cannot be opened.
     #[allow(clippy::too_many_arguments)]
     pub fn send_obj_hdr_with(
         &mut self,
@@ -643,6 +947,39 @@ impl MoqTransportSession {
         h3: &mut h3::Connection,
         quic: &mut quiche::Connection,
     ) -> Result<()> {
+        let stream_id = self.subgroup_stream(group_id, subgroup_id, track_alias, wt, h3, quic)?;
+        self.out_streams.get_mut(&stream_id).unwrap().send_obj_hdr(object_id, size, extension_headers, quic, wt)
+    }
+
+    /// Send an object without payload, e.g. with [`quiche_moq_wire::OBJECT_STATUS_END_OF_GROUP`].
+    /// IDs and errors like `send_obj_hdr_with`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_obj_status(
+        &mut self,
+        group_id: Option<u64>,
+        subgroup_id: Option<u64>,
+        object_id: Option<u64>,
+        status: u64,
+        extension_headers: &KeyValuePairs,
+        track_alias: TrackAlias,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        let stream_id = self.subgroup_stream(group_id, subgroup_id, track_alias, wt, h3, quic)?;
+        self.out_streams.get_mut(&stream_id).unwrap().send_obj_status(object_id, status, extension_headers, quic, wt)
+    }
+
+    /// Current subgroup stream of the track, opens a new one if the group or subgroup changes.
+    fn subgroup_stream(
+        &mut self,
+        group_id: Option<u64>,
+        subgroup_id: Option<u64>,
+        track_alias: TrackAlias,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<StreamID> {
         let version = self.selected_version.unwrap();
 
         // Determine whether a new subgroup stream is needed.
@@ -668,7 +1005,7 @@ impl MoqTransportSession {
                 .open_stream(self.webtransport_session_id.into(), h3, quic, false) {
                     Ok(s) => s.into(),
                     Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
-                    Err(e) => unimplemented!("{:?}", e),
+                    Err(e) => return Err(e.into()),
                 };
             self.out_tracks.get_mut(&track_alias).unwrap().current_stream_id = Some(stream_id);
             self.out_streams.insert(
@@ -677,8 +1014,7 @@ impl MoqTransportSession {
             );
         }
 
-        let stream_id = self.out_tracks[&track_alias].current_stream_id.unwrap();
-        self.out_streams.get_mut(&stream_id).unwrap().send_obj_hdr(object_id, size, extension_headers, quic, wt)
+        Ok(self.out_tracks[&track_alias].current_stream_id.unwrap())
     }
 
     pub fn send_obj_pld(
@@ -710,6 +1046,96 @@ impl MoqTransportSession {
         self.out_tracks.get_mut(&track_alias).unwrap().current_stream_id = None;
     }
 
+    /// Open a subgroup stream on a track, independent of the current stream used by `send_obj_hdr_with`.
+    /// Several subgroups and groups of a track can be sent concurrently this way,
+    /// each with its own `send_subgroup_obj_hdr` / `send_subgroup_obj_pld` calls on the returned stream.
+    /// - [`Error::InsufficientCapacity`]: no stream can be opened; retry later.
+    /// - [`Error::WT`]: the stream cannot be opened, e.g. because the session is closed.
+    pub fn open_subgroup(
+        &mut self,
+        track_alias: TrackAlias,
+        group_id: u64,
+        subgroup_id: u64,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<StreamID> {
+        assert!(self.out_tracks.contains_key(&track_alias), "unknown track alias {track_alias}");
+        let stream_id: StreamID = match wt.open_stream(self.webtransport_session_id.into(), h3, quic, false) {
+            Ok(s) => s.into(),
+            Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
+            Err(e) => return Err(e.into()),
+        };
+        self.out_streams.insert(
+            stream_id,
+            OutStream::new(stream_id, track_alias, group_id, subgroup_id, self.selected_version.unwrap()),
+        );
+        Ok(stream_id)
+    }
+
+    /// Send an object header on a stream opened by `open_subgroup`.
+    /// `object_id` must be larger than the previous one of the subgroup.
+    /// Before draft 11 the extension headers are not sent, see [`quiche_moq_wire::extension_headers_supported`].
+    /// - [`Error::UnfinishedPayload`]: the previous object's payload is still in progress.
+    /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    pub fn send_subgroup_obj_hdr(
+        &mut self,
+        stream_id: StreamID,
+        object_id: u64,
+        size: usize,
+        extension_headers: &KeyValuePairs,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        self.out_streams.get_mut(&stream_id).unwrap().send_obj_hdr(Some(object_id), size, extension_headers, quic, wt)
+    }
+
+    /// Send an object without payload on a stream opened by `open_subgroup`.
+    pub fn send_subgroup_obj_status(
+        &mut self,
+        stream_id: StreamID,
+        object_id: u64,
+        status: u64,
+        extension_headers: &KeyValuePairs,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        self.out_streams.get_mut(&stream_id).unwrap().send_obj_status(Some(object_id), status, extension_headers, quic, wt)
+    }
+
+    pub fn send_subgroup_obj_pld(
+        &mut self,
+        buf: &[u8],
+        stream_id: StreamID,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<usize> {
+        self.out_streams.get_mut(&stream_id).unwrap().send_obj_pld(buf, wt, quic)
+    }
+
+    /// Finish a stream opened by `open_subgroup` after its last object.
+    pub fn fin_subgroup(
+        &mut self,
+        stream_id: StreamID,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) {
+        if let Some(mut stream) = self.out_streams.remove(&stream_id) {
+            stream.fin(wt, quic);
+        }
+    }
+
+    /// Abort a stream opened by `open_subgroup`, e.g. because its object can no longer be completed.
+    pub fn reset_subgroup(
+        &mut self,
+        stream_id: StreamID,
+        quic: &mut quiche::Connection,
+    ) {
+        if self.out_streams.remove(&stream_id).is_some() {
+            quic.stream_shutdown(stream_id.into_u64(), Shutdown::Write, RESET_STREAM_CODE_DELIVERY_TIMEOUT).ok();
+        }
+    }
+
     /// Get a pending subscription request from the peer if available.
     /// Use `accept_subscription` to accept it.
     /// Or `reject_subscription`.
@@ -725,8 +1151,8 @@ impl MoqTransportSession {
     }
 
     /// Process all pending subscription requests via a closure.
-    /// Return `SubscriptionRequestAction::Accept` to accept, `Reject(error_code)` to reject,
-    /// or `Keep` to defer the decision to a later call.
+    /// Return `SubscriptionRequestAction::Accept` to accept, `Reject(error_code)` or
+    /// `RejectWithReason(error_code, reason)` to reject, or `Keep` to defer the decision to a later call.
     pub fn process_subscription_requests<F>(
         &mut self,
         mut f: F,
@@ -747,7 +1173,11 @@ impl MoqTransportSession {
                     false
                 }
                 SubscriptionRequestAction::Reject(error_code) => {
-                    Self::_reject_subscription(s_msg.as_ref(), sub, error_code, quic, wt);
+                    Self::_reject_subscription(s_msg.as_ref(), sub, error_code, "", quic, wt);
+                    false
+                }
+                SubscriptionRequestAction::RejectWithReason(error_code, reason) => {
+                    Self::_reject_subscription(s_msg.as_ref(), sub, error_code, &reason, quic, wt);
                     false
                 }
             });
@@ -756,7 +1186,7 @@ impl MoqTransportSession {
     /// Must be removed from `Self::pending_received_subscriptions` manually
     #[allow(clippy::type_complexity)]
     pub fn _accept_subscription(
-        s: &mut partial!(MoqTransportSession const control_stream_id config selected_version, mut next_out_track_alias out_tracks, ! *),
+        s: &mut partial!(MoqTransportSession const control_stream_id config selected_version alpn_version, mut next_out_track_alias out_tracks, ! *),
         subscribe_message: &SubscribeMessage,
         largest_location: Option<Location>,
         quic: &mut quiche::Connection,
@@ -804,9 +1234,10 @@ impl MoqTransportSession {
 
     /// Must be removed from `Self::pending_received_subscriptions` manually
     pub fn _reject_subscription(
-        s: &partial!(MoqTransportSession const control_stream_id selected_version config, ! *),
+        s: &partial!(MoqTransportSession const control_stream_id selected_version alpn_version config, ! *),
         subscribe_message: &SubscribeMessage,
         error_code: u64,
+        reason: &str,
         quic: &mut quiche::Connection,
         wt: &mut wt::Connection,
     ) {
@@ -814,10 +1245,9 @@ impl MoqTransportSession {
             s,
             quic,
             wt,
-            &ControlMessageEnum::RequestError(RequestErrorMessage::from(
-                subscribe_message,
-                error_code,
-            )),
+            &ControlMessageEnum::RequestError(
+                RequestErrorMessage::from(subscribe_message, error_code).with_reason(reason),
+            ),
         )
     }
 
@@ -832,7 +1262,7 @@ impl MoqTransportSession {
             .pending_received_subscriptions
             .remove(&request_id)
             .unwrap();
-        Self::_reject_subscription(self.as_mut(), &cm, error_code, quic, wt);
+        Self::_reject_subscription(self.as_mut(), &cm, error_code, "", quic, wt);
     }
 
     /// Get next unanswered namespace publish
@@ -842,6 +1272,11 @@ impl MoqTransportSession {
         self.pending_received_publish_namespace.iter().next()
     }
 
+    /// Namespace publishes that are neither accepted nor rejected yet.
+    pub fn pending_received_namespace_publishes(&self) -> &HashMap<RequestId, PublishNamespaceMessage> {
+        &self.pending_received_publish_namespace
+    }
+
     /// Accept a namespace publish or announce message from the peer
     pub fn accept_namespace_publish(
         &mut self,
@@ -861,6 +1296,43 @@ impl MoqTransportSession {
         self.received_namespaces.insert(request_id, cm.take_track_namespace());
     }
 
+    /// Reject a namespace publish or announce message from the peer
+    pub fn reject_namespace_publish(
+        &mut self,
+        request_id: RequestId,
+        error_code: u64,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) {
+        self.reject_namespace_publish_with_reason(request_id, error_code, "", wt, quic);
+    }
+
+    /// Like [`Self::reject_namespace_publish`], with a reason phrase for the peer
+    pub fn reject_namespace_publish_with_reason(
+        &mut self,
+        request_id: RequestId,
+        error_code: u64,
+        reason: &str,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) {
+        if self.pending_received_publish_namespace.remove(&request_id).is_none() {
+            return;
+        }
+        self.send_control_message(
+            quic,
+            wt,
+            &ControlMessageEnum::RequestError(
+                RequestErrorMessage::new(request_id, error_code).with_reason(reason),
+            ),
+        );
+    }
+
+    /// Namespaces published by the peer that are accepted and not yet done.
+    pub fn received_namespaces(&self) -> impl Iterator<Item = &Namespace> {
+        self.received_namespaces.values()
+    }
+
     pub fn remaining_object_payload(&self, track_alias: TrackAlias) -> Result<usize> {
         let track = self.in_tracks.get(&track_alias).unwrap();
         let stream_id = track.current_stream().unwrap();
@@ -935,6 +1407,65 @@ impl MoqTransportSession {
         }
     }
 
+    /// Subgroup header of one of the `readable_streams` of a track.
+    pub fn stream_subgroup_header(&self, stream_id: StreamID) -> Option<&SubgroupHeader> {
+        self.in_streams.get(&stream_id)?.subgroup_header()
+    }
+
+    /// Like `read_obj_hdr`, but from one of the `readable_streams` of the track,
+    /// to read concurrent subgroups independently.
+    /// - [`Error::Done`]: no data is available on the stream at the moment.
+    /// - [`Error::Fin`]: the stream is finished, it is removed from `readable_streams`.
+    /// - [`Error::StreamReset`]: the peer reset the stream, it is removed as well.
+    pub fn read_stream_obj_hdr(
+        &mut self,
+        track_alias: TrackAlias,
+        stream_id: StreamID,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<ObjectHeader> {
+        let Some(stream) = self.in_streams.get_mut(&stream_id) else {
+            return Err(Error::Fin);
+        };
+        match stream.read_obj_hdr(quic, h3, wt) {
+            Err(Error::Fin) => {
+                let stream = self.in_streams.remove(&stream_id).unwrap();
+                self.in_tracks.get_mut(&track_alias).unwrap().fin_stream(stream_id);
+                Err(stream.reset_code().map_or(Error::Fin, Error::StreamReset))
+            }
+            other => other,
+        }
+    }
+
+    /// Like `read_obj_pld` for a stream of `read_stream_obj_hdr`.
+    pub fn read_stream_obj_pld(
+        &mut self,
+        buf: &mut [u8],
+        track_alias: TrackAlias,
+        stream_id: StreamID,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<usize> {
+        let Some(stream) = self.in_streams.get_mut(&stream_id) else {
+            return Err(Error::Fin);
+        };
+        match stream.read_obj_pld(quic, h3, wt, buf) {
+            Err(Error::Fin) => {
+                let stream = self.in_streams.remove(&stream_id).unwrap();
+                self.in_tracks.get_mut(&track_alias).unwrap().fin_stream(stream_id);
+                Err(stream.reset_code().map_or(Error::Fin, Error::StreamReset))
+            }
+            other => other,
+        }
+    }
+
+    /// All streams of the track announced by PUBLISH_DONE have been read.
+    pub fn track_done(&self, track_alias: TrackAlias) -> bool {
+        self.in_tracks.get(&track_alias).is_some_and(|t| t.is_fully_done())
+    }
+
     /// Cancel sending on stream with Delivery Timeout
     pub fn timeout_stream(
         &mut self,
@@ -961,13 +1492,298 @@ impl MoqTransportSession {
         self.pending_subscribe_responses.remove(&request_id)
     }
 
+    /// Fetch `range` of a track. Returns the request_id.
+    /// The response is available with `poll_fetch_response`, the objects with `read_fetch_obj_hdr`.
+    pub fn fetch(
+        &mut self,
+        namespace_trackname: &NamespaceTrackname,
+        range: FetchRange,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<RequestId> {
+        self.send_fetch(
+            FetchType::Standalone {
+                namespace_trackname: namespace_trackname.clone(),
+                range,
+            },
+            wt,
+            quic,
+        )
+    }
+
+    /// Fetch the `preceding_groups` groups before the largest location of the subscription `joining_request_id`.
+    /// Together with the subscription this delivers the track without a gap, draft 08+.
+    pub fn joining_fetch(
+        &mut self,
+        joining_request_id: RequestId,
+        preceding_groups: u64,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<RequestId> {
+        if self.selected_version == Some(MOQ_VERSION_DRAFT_07) {
+            return Err(Error::Unimplemented);
+        }
+        self.send_fetch(
+            FetchType::RelativeJoining {
+                joining_request_id,
+                preceding_groups,
+            },
+            wt,
+            quic,
+        )
+    }
+
+    fn send_fetch(
+        &mut self,
+        fetch_type: FetchType,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<RequestId> {
+        let request_id = self.take_request_id(wt, quic)?;
+        self.send_control_message(
+            quic,
+            wt,
+            &ControlMessageEnum::Fetch(FetchMessage {
+                request_id,
+                subscriber_priority: 1,
+                group_order: 1,
+                fetch_type,
+                parameters: Parameters(vec![]),
+            }),
+        );
+        self.pending_fetch.insert(request_id);
+        Ok(request_id)
+    }
+
+    pub fn poll_fetch_response(
+        &mut self,
+        request_id: RequestId,
+    ) -> Option<core::result::Result<FetchOkMessage, RequestErrorMessage>> {
+        self.pending_fetch_responses.remove(&request_id)
+    }
+
+    /// Stop a fetch we sent, objects not read yet are dropped.
+    pub fn fetch_cancel(
+        &mut self,
+        request_id: RequestId,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) {
+        self.send_control_message(
+            quic,
+            wt,
+            &ControlMessageEnum::FetchCancel(FetchCancelMessage { request_id }),
+        );
+        self.pending_fetch.remove(&request_id);
+        self.pending_fetch_responses.remove(&request_id);
+        if let Some(stream_id) = self.in_fetches.remove(&request_id) {
+            quic.stream_shutdown(stream_id.into_u64(), Shutdown::Read, RESET_STREAM_CODE_CANCELED).ok();
+            self.in_streams.remove(&stream_id);
+        }
+    }
+
+    /// Next object of a fetch response, in the order the publisher sends them.
+    /// - [`Error::Done`]: the response stream has not arrived yet or no data is available at the moment.
+    /// - [`Error::Fin`]: all objects have been read, returned once.
+    pub fn read_fetch_obj_hdr(
+        &mut self,
+        request_id: RequestId,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<FetchObjectHeader> {
+        let Some(&stream_id) = self.in_fetches.get(&request_id) else {
+            return Err(Error::Done);
+        };
+        let stream = self.in_streams.get_mut(&stream_id).unwrap();
+        match stream.read_fetch_obj_hdr(quic, h3, wt) {
+            Err(Error::Fin) => {
+                self.in_streams.remove(&stream_id);
+                self.in_fetches.remove(&request_id);
+                Err(Error::Fin)
+            }
+            other => other,
+        }
+    }
+
+    pub fn read_fetch_obj_pld(
+        &mut self,
+        buf: &mut [u8],
+        request_id: RequestId,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<usize> {
+        let stream_id = self.in_fetches[&request_id];
+        let stream = self.in_streams.get_mut(&stream_id).unwrap();
+        match stream.read_obj_pld(quic, h3, wt, buf) {
+            Err(Error::Fin) => {
+                self.in_streams.remove(&stream_id);
+                self.in_fetches.remove(&request_id);
+                Err(Error::Fin)
+            }
+            other => other,
+        }
+    }
+
+    /// Get a pending fetch request from the peer if available.
+    /// Use `accept_fetch` to accept it.
+    /// Or `reject_fetch`.
+    pub fn fetch_inbox_next(&self) -> Option<(&RequestId, &FetchMessage)> {
+        self.pending_received_fetches.iter().next()
+    }
+
+    pub fn pending_received_fetches(&self) -> &HashMap<RequestId, FetchMessage> {
+        &self.pending_received_fetches
+    }
+
+    /// Accept a fetch received from the peer and open the stream for its objects.
+    /// `end_location` is the largest object the response will contain.
+    /// - [`Error::InsufficientCapacity`]: no stream can be opened; retry later.
+    /// - [`Error::Fin`]: unknown or canceled request.
+    /// - [`Error::WT`]: the stream cannot be opened, e.g. because the connection is closed.
+    pub fn accept_fetch(
+        &mut self,
+        request_id: RequestId,
+        end_of_track: bool,
+        end_location: Location,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        if !self.pending_received_fetches.contains_key(&request_id) {
+            return Err(Error::Fin);
+        }
+        let stream_id: StreamID = match wt.open_stream(self.webtransport_session_id.into(), h3, quic, false) {
+            Ok(s) => s.into(),
+            Err(wt::Error::InsufficientCapacity) => return Err(Error::InsufficientCapacity),
+            Err(e) => return Err(e.into()),
+        };
+        self.pending_received_fetches.remove(&request_id);
+        self.send_control_message(
+            quic,
+            wt,
+            &ControlMessageEnum::FetchOk(FetchOkMessage::new(request_id, end_of_track, end_location)),
+        );
+        self.out_fetches.insert(
+            request_id,
+            OutFetchStream::new(stream_id, request_id, self.selected_version.unwrap()),
+        );
+        Ok(())
+    }
+
+    /// Reject a fetch received from the peer. Does nothing if the fetch was canceled or answered already.
+    /// - [`Error::Unimplemented`]: the negotiated version has no error message for fetches.
+    pub fn reject_fetch(
+        &mut self,
+        request_id: RequestId,
+        error_code: u64,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        if !self.pending_received_fetches.contains_key(&request_id) {
+            return Ok(());
+        }
+        let cm = match self.selected_version {
+            Some(MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_14) => {
+                ControlMessageEnum::FetchError(FetchErrorMessage::new(request_id, error_code))
+            }
+            Some(MOQ_VERSION_DRAFT_15..=MOQ_VERSION_DRAFT_16) => {
+                ControlMessageEnum::RequestError(RequestErrorMessage::new(request_id, error_code))
+            }
+            _ => return Err(Error::Unimplemented),
+        };
+        self.pending_received_fetches.remove(&request_id);
+        self.send_control_message(quic, wt, &cm);
+        Ok(())
+    }
+
+    /// Send the header of the next object of an accepted fetch.
+    /// Objects must be sent in the requested group order.
+    /// Before draft 11 the extension headers are not sent, see [`quiche_moq_wire::extension_headers_supported`].
+    /// - [`Error::Fin`]: unknown or canceled fetch.
+    /// - [`Error::InsufficientCapacity`]: QUIC stream capacity exhausted; retry later.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_fetch_obj_hdr(
+        &mut self,
+        request_id: RequestId,
+        location: Location,
+        subgroup_id: u64,
+        size: usize,
+        extension_headers: &KeyValuePairs,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        let Some(stream) = self.out_fetches.get_mut(&request_id) else {
+            return Err(Error::Fin);
+        };
+        let object_header = FetchObjectHeader::new(location, subgroup_id, size, extension_headers.clone());
+        stream.send_obj_hdr(&object_header, quic, wt)
+    }
+
+    /// Send an object without payload on an accepted fetch. Errors like `send_fetch_obj_hdr`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_fetch_obj_status(
+        &mut self,
+        request_id: RequestId,
+        location: Location,
+        subgroup_id: u64,
+        status: u64,
+        extension_headers: &KeyValuePairs,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        let Some(stream) = self.out_fetches.get_mut(&request_id) else {
+            return Err(Error::Fin);
+        };
+        stream.send_obj_hdr(&FetchObjectHeader::new_status(location, subgroup_id, status, extension_headers.clone()), quic, wt)
+    }
+
+    pub fn send_fetch_obj_pld(
+        &mut self,
+        buf: &[u8],
+        request_id: RequestId,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<usize> {
+        let Some(stream) = self.out_fetches.get_mut(&request_id) else {
+            return Err(Error::Fin);
+        };
+        stream.send_obj_pld(buf, wt, quic)
+    }
+
+    /// All objects of an accepted fetch have been sent, finishes its stream.
+    pub fn fetch_done(
+        &mut self,
+        request_id: RequestId,
+        wt: &mut wt::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        let Some(stream) = self.out_fetches.get_mut(&request_id) else {
+            return Err(Error::Fin);
+        };
+        stream.fin(quic, wt)?;
+        self.out_fetches.remove(&request_id);
+        Ok(())
+    }
+
+    /// Abort an accepted fetch that cannot be completed.
+    pub fn reset_fetch(
+        &mut self,
+        request_id: RequestId,
+        quic: &mut quiche::Connection,
+    ) {
+        let Some(stream) = self.out_fetches.remove(&request_id) else { return };
+        quic.stream_shutdown(stream.stream_id().into_u64(), Shutdown::Write, RESET_STREAM_CODE_INTERNAL_ERROR).ok();
+    }
+
     pub fn publish_namespace(
         &mut self,
         namespace: Vec<Vec<u8>>,
         wt: &mut wt::Connection,
         quic: &mut quiche::Connection,
     ) -> Result<RequestId> {
-        let request_id = self.next_request_id;
+        let request_id = self.take_request_id(wt, quic)?;
         let cm = ControlMessageEnum::PublishNamespace(PublishNamespaceMessage::new(
             Some(request_id),
             Namespace(Tuple(namespace)),
@@ -978,7 +1794,6 @@ impl MoqTransportSession {
             unreachable!()
         };
         self.pending_sent_publish_namespace.insert(request_id, cm);
-        self.next_request_id += 2;
         Ok(request_id)
     }
 
@@ -1021,6 +1836,8 @@ impl MoqTransportSession {
             Accepted
         } else if self.pending_sent_publish_namespace.contains_key(&request_id) {
             Pending
+        } else if let Some(&error_code) = self.rejected_namespaces.get(&request_id) {
+            Rejected(error_code)
         } else {
             Unknown
         }
@@ -1031,10 +1848,12 @@ pub enum PublishStatus {
     Unknown,
     Pending,
     Accepted,
+    Rejected(ErrorCode),
 }
 
 pub enum SubscriptionRequestAction {
     Keep,
     Accept,
     Reject(ErrorCode),
+    RejectWithReason(ErrorCode, String),
 }
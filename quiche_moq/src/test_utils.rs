@@ -2,7 +2,7 @@ use crate::{Config, MoqTransportSession};
 use quiche::h3;
 use quiche::test_utils::Pipe;
 use quiche_webtransport as wt;
-use quiche_webtransport::test_utils::_init_webtransport_pipe;
+use quiche_webtransport::test_utils::_init_webtransport_pipe_with_protocols;
 
 pub fn _init_moq_pipe(config: Config) -> (
     Pipe,
@@ -13,14 +13,34 @@ pub fn _init_moq_pipe(config: Config) -> (
     wt::Connection,
     MoqTransportSession,
 ) {
-    let (mut pipe, mut c_h3, mut c_wt, mut s_h3, mut s_wt, wt_session_id) = _init_webtransport_pipe();
+    let protocols = config.protocols();
+    _init_moq_pipe_with_protocols(config.clone(), config, &protocols, &protocols)
+}
+
+/// Without protocols the version is negotiated by the setup messages.
+pub fn _init_moq_pipe_with_protocols(
+    client_config: Config,
+    server_config: Config,
+    client_protocols: &[&str],
+    server_protocols: &[&str],
+) -> (
+    Pipe,
+    h3::Connection,
+    wt::Connection,
+    MoqTransportSession,
+    h3::Connection,
+    wt::Connection,
+    MoqTransportSession,
+) {
+    let (mut pipe, mut c_h3, mut c_wt, mut s_h3, mut s_wt, wt_session_id) =
+        _init_webtransport_pipe_with_protocols(client_protocols, server_protocols);
 
     let mut c_moq = MoqTransportSession::connect(
         wt_session_id.into(),
         &mut c_h3,
         &mut pipe.client,
         &mut c_wt,
-        config.clone(),
+        client_config,
     );
 
     pipe.advance().unwrap();
@@ -28,7 +48,7 @@ pub fn _init_moq_pipe(config: Config) -> (
     assert!(matches!(s_h3.poll(&mut pipe.server), Err(h3::Error::Done)));
     s_wt.poll(&mut s_h3, &mut pipe.server);
     let session_id = *s_wt.readable_sessions().first().unwrap();
-    let mut s_moq = MoqTransportSession::accept(session_id.into(), config.clone());
+    let mut s_moq = MoqTransportSession::accept(session_id.into(), server_config);
     s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
     assert!(s_moq.initialized());
 
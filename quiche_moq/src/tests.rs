@@ -1,6 +1,9 @@
-use crate::test_utils::_init_moq_pipe;
+use crate::test_utils::{_init_moq_pipe, _init_moq_pipe_with_protocols};
 use crate::Config;
 use quiche::h3;
+use quiche_moq_wire::control_message::{FetchRange, FetchType};
+use quiche_moq_wire::control_message::subscribe::FilterType;
+use quiche_moq_wire::{KeyValuePairs, Location, OBJECT_STATUS_END_OF_GROUP};
 use quiche_moq_wire::{MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_08, MOQ_VERSION_DRAFT_09, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_13, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_15, MOQ_VERSION_DRAFT_16, Version};
 
 macro_rules! test_webtransport_moq_versions {
@@ -77,3 +80,337 @@ fn test_webtransport_moq(version: Version) {
     let pld = &buf[..n];
     assert_eq!(&pld, b"hello");
 }
+
+#[test]
+fn test_webtransport_protocol_version_negotiation() {
+    let client_config = Config {
+        setup_version: MOQ_VERSION_DRAFT_16,
+        ..Default::default()
+    };
+    let server_config = Config {
+        setup_version: MOQ_VERSION_DRAFT_15,
+        supported_versions: vec![MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_15],
+        ..Default::default()
+    };
+    assert_eq!(client_config.protocols(), ["moqt-16", "moqt-15"]);
+    assert_eq!(server_config.protocols(), ["moqt-15"]);
+
+    let (_pipe, _c_h3, c_wt, c_moq, _s_h3, _s_wt, s_moq) = _init_moq_pipe_with_protocols(
+        client_config.clone(),
+        server_config.clone(),
+        &client_config.protocols(),
+        &server_config.protocols(),
+    );
+    assert_eq!(c_wt.session_protocol(c_moq.webtransport_session_id.into_u64()), Some("moqt-15"));
+    assert_eq!(c_moq.version(), Some(MOQ_VERSION_DRAFT_15));
+    assert_eq!(s_moq.version(), Some(MOQ_VERSION_DRAFT_15));
+}
+
+#[test]
+fn test_setup_version_negotiation() {
+    let client_config = Config {
+        setup_version: MOQ_VERSION_DRAFT_14,
+        supported_versions: vec![MOQ_VERSION_DRAFT_13, MOQ_VERSION_DRAFT_14],
+        ..Default::default()
+    };
+    let server_config = Config {
+        setup_version: MOQ_VERSION_DRAFT_12,
+        supported_versions: vec![MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_13],
+        ..Default::default()
+    };
+    // no protocols offered, fall back to CLIENT_SETUP / SERVER_SETUP
+    let (_pipe, _c_h3, _c_wt, c_moq, _s_h3, _s_wt, s_moq) =
+        _init_moq_pipe_with_protocols(client_config, server_config, &[], &[]);
+    assert_eq!(c_moq.version(), Some(MOQ_VERSION_DRAFT_13));
+    assert_eq!(s_moq.version(), Some(MOQ_VERSION_DRAFT_13));
+}
+
+#[test]
+fn test_setup_version_negotiation_with_alpn_server() {
+    // the server prefers draft 16, negotiated by protocol, but the clients offer none
+    let server_config = Config::default();
+    for version in [MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_14] {
+        let client_config = Config { setup_version: version, supported_versions: vec![version], ..Default::default() };
+        let (_pipe, _c_h3, _c_wt, c_moq, _s_h3, _s_wt, s_moq) =
+            _init_moq_pipe_with_protocols(client_config, server_config.clone(), &[], &server_config.protocols());
+        assert_eq!(c_moq.version(), Some(version));
+        assert_eq!(s_moq.version(), Some(version));
+    }
+}
+
+macro_rules! test_fetch_versions {
+    ($($name:ident: $version:expr,)*) => {
+    $(
+        #[test]
+        fn $name() {
+            test_fetch($version);
+        }
+    )*
+    }
+}
+
+test_fetch_versions! {
+    test_fetch_draft07: MOQ_VERSION_DRAFT_07,
+    test_fetch_draft11: MOQ_VERSION_DRAFT_11,
+    test_fetch_draft14: MOQ_VERSION_DRAFT_14,
+    test_fetch_draft16: MOQ_VERSION_DRAFT_16,
+}
+
+fn test_fetch(version: Version) {
+    let mut config: Config = Default::default();
+    config.setup_version = version;
+
+    let (mut pipe, mut c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    let range = FetchRange {
+        start: Location { group: 3, object: 0 },
+        end: Location { group: 3, object: 0 },
+    };
+    let request_id = c_moq
+        .fetch(&"n1--t1".parse().unwrap(), range, &mut c_wt, &mut pipe.client)
+        .unwrap();
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    let (&fetch_request_id, fetch) = s_moq.fetch_inbox_next().unwrap();
+    assert_eq!(fetch_request_id, request_id);
+    assert!(matches!(&fetch.fetch_type, FetchType::Standalone { range: r, .. } if *r == range));
+    s_moq
+        .accept_fetch(request_id, false, Location { group: 3, object: 1 }, &mut s_wt, &mut s_h3, &mut pipe.server)
+        .unwrap();
+    s_moq
+        .send_fetch_obj_hdr(request_id, Location { group: 3, object: 0 }, 0, 5, &KeyValuePairs::new(), &mut s_wt, &mut pipe.server)
+        .unwrap();
+    assert_eq!(s_moq.send_fetch_obj_pld(b"hello", request_id, &mut s_wt, &mut pipe.server).unwrap(), 5);
+    s_moq
+        .send_fetch_obj_status(request_id, Location { group: 3, object: 1 }, 0, OBJECT_STATUS_END_OF_GROUP, &KeyValuePairs::new(), &mut s_wt, &mut pipe.server)
+        .unwrap();
+    s_moq.fetch_done(request_id, &mut s_wt, &mut pipe.server).unwrap();
+
+    pipe.advance().unwrap();
+
+    c_wt.poll(&mut c_h3, &mut pipe.client);
+    c_moq.poll(&mut c_wt, &mut c_h3, &mut pipe.client);
+    let ok = c_moq.poll_fetch_response(request_id).unwrap().unwrap();
+    assert_eq!(ok.end_location(), Location { group: 3, object: 1 });
+
+    let hdr = c_moq
+        .read_fetch_obj_hdr(request_id, &mut c_wt, &mut c_h3, &mut pipe.client)
+        .unwrap();
+    assert_eq!(hdr.location(), Location { group: 3, object: 0 });
+    let mut buf = [0u8; 10];
+    let n = c_moq
+        .read_fetch_obj_pld(&mut buf, request_id, &mut c_wt, &mut c_h3, &mut pipe.client)
+        .unwrap();
+    assert_eq!(&buf[..n], b"hello");
+    let hdr = c_moq
+        .read_fetch_obj_hdr(request_id, &mut c_wt, &mut c_h3, &mut pipe.client)
+        .unwrap();
+    assert_eq!(hdr.status(), Some(OBJECT_STATUS_END_OF_GROUP));
+    assert!(matches!(
+        c_moq.read_fetch_obj_hdr(request_id, &mut c_wt, &mut c_h3, &mut pipe.client),
+        Err(crate::Error::Fin)
+    ));
+}
+
+#[test]
+fn test_concurrent_subgroups() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+
+    let (mut pipe, mut c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    c_moq.subscribe(&"n1--t1".parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    let (request_id, _subscription) = s_moq.subscription_inbox_next().unwrap();
+    let track_alias = s_moq.accept_subscription(*request_id, None, &mut s_wt, &mut pipe.server);
+    let base = s_moq.open_subgroup(track_alias, 0, 0, &mut s_wt, &mut s_h3, &mut pipe.server).unwrap();
+    let enhancement = s_moq.open_subgroup(track_alias, 0, 1, &mut s_wt, &mut s_h3, &mut pipe.server).unwrap();
+    // interleave the objects of both subgroups
+    s_moq.send_subgroup_obj_hdr(enhancement, 1, 4, &KeyValuePairs::new(), &mut s_wt, &mut pipe.server).unwrap();
+    s_moq.send_subgroup_obj_hdr(base, 0, 4, &KeyValuePairs::new(), &mut s_wt, &mut pipe.server).unwrap();
+    assert_eq!(s_moq.send_subgroup_obj_pld(b"enh1", enhancement, &mut s_wt, &mut pipe.server).unwrap(), 4);
+    assert_eq!(s_moq.send_subgroup_obj_pld(b"base", base, &mut s_wt, &mut pipe.server).unwrap(), 4);
+    s_moq.fin_subgroup(base, &mut s_wt, &mut pipe.server);
+    s_moq.reset_subgroup(enhancement, &mut pipe.server);
+
+    pipe.advance().unwrap();
+
+    c_wt.poll(&mut c_h3, &mut pipe.client);
+    c_moq.poll(&mut c_wt, &mut c_h3, &mut pipe.client);
+    let track_alias = *c_moq.readable().first().unwrap();
+    let streams = c_moq.readable_streams(track_alias).to_vec();
+    assert_eq!(streams.len(), 2);
+
+    let hdr = c_moq
+        .read_stream_obj_hdr(track_alias, streams[0], &mut c_wt, &mut c_h3, &mut pipe.client)
+        .unwrap();
+    assert_eq!(hdr.id(), 0);
+    assert_eq!(c_moq.stream_subgroup_header(streams[0]).unwrap().subgroup_id(), Some(0));
+    let mut buf = [0u8; 10];
+    let n = c_moq
+        .read_stream_obj_pld(&mut buf, track_alias, streams[0], &mut c_wt, &mut c_h3, &mut pipe.client)
+        .unwrap();
+    assert_eq!(&buf[..n], b"base");
+    assert!(matches!(
+        c_moq.read_stream_obj_hdr(track_alias, streams[0], &mut c_wt, &mut c_h3, &mut pipe.client),
+        Err(crate::Error::Fin)
+    ));
+
+    // the reset of the enhancement layer does not affect the base layer
+    assert!(matches!(
+        c_moq.read_stream_obj_hdr(track_alias, streams[1], &mut c_wt, &mut c_h3, &mut pipe.client),
+        Err(crate::Error::StreamReset(_))
+    ));
+    assert!(c_moq.readable_streams(track_alias).is_empty());
+}
+
+#[test]
+fn test_stopped_subgroup() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+
+    let (mut pipe, _c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    c_moq.subscribe(&"n1--t1".parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    let (request_id, _subscription) = s_moq.subscription_inbox_next().unwrap();
+    let track_alias = s_moq.accept_subscription(*request_id, None, &mut s_wt, &mut pipe.server);
+    let stream_id = s_moq.open_subgroup(track_alias, 0, 0, &mut s_wt, &mut s_h3, &mut pipe.server).unwrap();
+    s_moq.send_subgroup_obj_hdr(stream_id, 0, 8, &KeyValuePairs::new(), &mut s_wt, &mut pipe.server).unwrap();
+    assert_eq!(s_moq.send_subgroup_obj_pld(b"hell", stream_id, &mut s_wt, &mut pipe.server).unwrap(), 4);
+
+    pipe.advance().unwrap();
+
+    // the subscriber is not interested anymore
+    pipe.client.stream_shutdown(stream_id.into_u64(), quiche::Shutdown::Read, 0).unwrap();
+
+    pipe.advance().unwrap();
+
+    assert!(matches!(
+        s_moq.send_subgroup_obj_pld(b"o wo", stream_id, &mut s_wt, &mut pipe.server),
+        Err(crate::Error::WT(quiche_webtransport::Error::StreamStopped(0)))
+    ));
+    s_moq.reset_subgroup(stream_id, &mut pipe.server);
+}
+
+#[test]
+fn test_subscribe_range() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+
+    let (mut pipe, _c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    let start = Location { group: 10, object: 2 };
+    c_moq.subscribe_range(&"n1--t1".parse().unwrap(), start, Some(12), &mut c_wt, &mut pipe.client).unwrap();
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    let (_request_id, subscription) = s_moq.subscription_inbox_next().unwrap();
+    assert_eq!(subscription.filter_type, FilterType::AbsoluteRange);
+    assert_eq!(subscription.start_location, Some(start));
+    assert_eq!(subscription.end_group, Some(12));
+}
+
+#[test]
+fn test_request_id_grants() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+    // request IDs 0 and 2 of the client
+    config.max_request_id = 2;
+
+    let (mut pipe, mut c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    for t in ["n1--t1", "n1--t2"] {
+        c_moq.subscribe(&t.parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+    }
+    assert!(matches!(
+        c_moq.subscribe(&"n1--t3".parse().unwrap(), &mut c_wt, &mut pipe.client),
+        Err(crate::Error::RequestBlocked)
+    ));
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    assert!(s_moq.peer_requests_blocked());
+    assert_eq!(s_moq.remaining_peer_requests(), 0);
+    s_moq.grant_request_ids(1, &mut s_wt, &mut pipe.server);
+    assert!(!s_moq.peer_requests_blocked());
+    assert_eq!(s_moq.remaining_peer_requests(), 1);
+
+    pipe.advance().unwrap();
+
+    c_wt.poll(&mut c_h3, &mut pipe.client);
+    c_moq.poll(&mut c_wt, &mut c_h3, &mut pipe.client);
+    assert_eq!(c_moq.subscribe(&"n1--t3".parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap(), 4);
+    assert!(matches!(
+        c_moq.subscribe(&"n1--t4".parse().unwrap(), &mut c_wt, &mut pipe.client),
+        Err(crate::Error::RequestBlocked)
+    ));
+}
+
+#[test]
+fn test_too_many_requests() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+    config.max_request_id = 0;
+    config.ignore_max_request_quota = true;
+
+    let (mut pipe, _c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    for t in ["n1--t1", "n1--t2"] {
+        c_moq.subscribe(&t.parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+    }
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    assert!(s_moq.is_closed());
+    // only the request within MAX_REQUEST_ID was taken
+    assert_eq!(s_moq.pending_received_subscriptions().len(), 1);
+}
+
+#[test]
+fn test_reused_request_id() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+
+    let (mut pipe, _c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    c_moq.subscribe(&"n1--t1".parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+    c_moq.next_request_id = 0;
+    c_moq.subscribe(&"n1--t2".parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+
+    pipe.advance().unwrap();
+
+    s_moq.poll(&mut s_wt, &mut s_h3, &mut pipe.server);
+    assert!(s_moq.is_closed());
+    assert_eq!(s_moq.pending_received_subscriptions().len(), 1);
+}
+
+#[test]
+fn test_goaway() {
+    let mut config: Config = Default::default();
+    config.setup_version = MOQ_VERSION_DRAFT_14;
+
+    let (mut pipe, mut c_h3, mut c_wt, mut c_moq, mut s_h3, mut s_wt, mut s_moq) = _init_moq_pipe(config);
+
+    assert_eq!(c_moq.received_goaway(), None);
+    s_moq.goaway(Some("https://b.example/moq"), &mut s_wt, &mut s_h3, &mut pipe.server);
+
+    pipe.advance().unwrap();
+
+    c_wt.poll(&mut c_h3, &mut pipe.client);
+    c_moq.poll(&mut c_wt, &mut c_h3, &mut pipe.client);
+    assert_eq!(c_moq.received_goaway(), Some(b"https://b.example/moq".as_slice()));
+    // the session stays usable until it is closed
+    c_moq.subscribe(&"n1--t1".parse().unwrap(), &mut c_wt, &mut pipe.client).unwrap();
+}
@@ -0,0 +1,244 @@
+use crate::error::{Error, Result};
+use crate::patch::Patch;
+use crate::CATALOG_VERSION;
+use quiche_moq::wire::{Namespace, NamespaceTrackname};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Root object of a catalog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Catalog {
+    pub version: u64,
+    /// Wall clock time in milliseconds since the unix epoch at which the catalog was generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<u64>,
+    /// `true` if no more tracks will be added to the catalog.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_complete: Option<bool>,
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+    /// Fields unknown to this implementation, kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Description of one track of a broadcast.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Track {
+    /// Text encoded namespace, see `Display` of `Namespace`.
+    /// `None` means the namespace of the catalog track.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    pub name: String,
+    /// e.g. [`crate::PACKAGING_LOC`] or [`crate::PACKAGING_CMAF`]
+    pub packaging: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_live: Option<bool>,
+    /// Target latency in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_latency: Option<u64>,
+    /// e.g. [`crate::ROLE_VIDEO`] or [`crate::ROLE_AUDIO`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Tracks of the same render group are meant to be played together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_group: Option<u64>,
+    /// Tracks of the same alternate group are renditions of the same content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_group: Option<u64>,
+    /// Base64 encoded initialization data, e.g. a CMAF header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init_data: Option<String>,
+    /// Names of tracks this track depends on, e.g. the base layer of an SVC enhancement layer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temporal_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spatial_id: Option<u64>,
+    /// Codec string as defined by RFC 6381, e.g. `avc1.640028`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub framerate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timescale: Option<u64>,
+    /// Bits per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_width: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samplerate: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_config: Option<String>,
+    /// Language tag as defined by BCP 47
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Fields unknown to this implementation, kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Catalog {
+    pub fn new(tracks: Vec<Track>) -> Self {
+        Self {
+            version: CATALOG_VERSION,
+            generated_at: None,
+            is_complete: None,
+            tracks,
+            extra: Map::new(),
+        }
+    }
+
+    pub fn from_json(buf: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(buf)?)
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Returns the first track with the given name.
+    pub fn track(&self, name: &str) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.name == name)
+    }
+
+    /// Returns all tracks with the given role, e.g. [`crate::ROLE_VIDEO`].
+    pub fn tracks_with_role<'a>(&'a self, role: &'a str) -> impl Iterator<Item = &'a Track> {
+        self.tracks.iter().filter(move |t| t.role.as_deref() == Some(role))
+    }
+
+    /// Apply a delta update.
+    /// The catalog is left unchanged if the patch cannot be applied.
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<()> {
+        let mut doc = serde_json::to_value(&*self)?;
+        patch.apply(&mut doc)?;
+        *self = serde_json::from_value(doc)?;
+        Ok(())
+    }
+}
+
+impl Track {
+    pub fn new(name: &str, packaging: &str) -> Self {
+        Self {
+            namespace: None,
+            name: name.to_string(),
+            packaging: packaging.to_string(),
+            is_live: None,
+            target_latency: None,
+            role: None,
+            label: None,
+            render_group: None,
+            alt_group: None,
+            init_data: None,
+            depends: None,
+            temporal_id: None,
+            spatial_id: None,
+            codec: None,
+            mime_type: None,
+            framerate: None,
+            timescale: None,
+            bitrate: None,
+            width: None,
+            height: None,
+            display_width: None,
+            display_height: None,
+            samplerate: None,
+            channel_config: None,
+            lang: None,
+            extra: Map::new(),
+        }
+    }
+
+    /// Full track name to subscribe to.
+    /// `catalog_namespace` is the namespace of the catalog track and is used if the track has no namespace.
+    pub fn namespace_trackname(&self, catalog_namespace: &Namespace) -> Result<NamespaceTrackname> {
+        let namespace = match &self.namespace {
+            Some(ns) => ns.parse::<Namespace>().map_err(Error::InvalidNamespace)?,
+            None => catalog_namespace.clone(),
+        };
+        Ok(NamespaceTrackname::new(namespace.0.0, self.name.as_bytes().to_vec()))
+    }
+
+    /// Tracks are identified by namespace and name.
+    pub(crate) fn same_track(&self, other: &Track) -> bool {
+        self.namespace == other.namespace && self.name == other.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Catalog, Track, PACKAGING_CMAF, ROLE_VIDEO};
+    use quiche_moq::wire::Namespace;
+
+    const EXAMPLE: &str = r#"{
+        "version": 1,
+        "generatedAt": 1746104606044,
+        "tracks": [
+            {
+                "name": "1080p-video",
+                "namespace": "conference.2eexample.2ecom-conference123-alice",
+                "packaging": "cmaf",
+                "isLive": true,
+                "role": "video",
+                "renderGroup": 1,
+                "codec": "avc1.640028",
+                "bitrate": 6000000,
+                "framerate": 30,
+                "width": 1920,
+                "height": 1080,
+                "customField": "kept"
+            },
+            {
+                "name": "audio",
+                "packaging": "loc",
+                "role": "audio",
+                "codec": "opus",
+                "samplerate": 48000,
+                "channelConfig": "2"
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn parse() {
+        let catalog = Catalog::from_json(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(catalog.version, 1);
+        assert_eq!(catalog.tracks.len(), 2);
+        let video = catalog.track("1080p-video").unwrap();
+        assert_eq!(video.width, Some(1920));
+        assert_eq!(video.codec.as_deref(), Some("avc1.640028"));
+        assert_eq!(video.extra["customField"], "kept");
+        assert_eq!(catalog.tracks_with_role("audio").count(), 1);
+        let ntn = video.namespace_trackname(&"ignored".parse().unwrap()).unwrap();
+        assert_eq!(ntn.to_string(), "conference.2eexample.2ecom-conference123-alice--1080p.2dvideo");
+    }
+
+    #[test]
+    fn round_trip() {
+        let catalog = Catalog::from_json(EXAMPLE.as_bytes()).unwrap();
+        let catalog2 = Catalog::from_json(&catalog.to_json().unwrap()).unwrap();
+        assert_eq!(catalog, catalog2);
+    }
+
+    #[test]
+    fn default_namespace() {
+        let mut track = Track::new("mp4", PACKAGING_CMAF);
+        track.role = Some(ROLE_VIDEO.to_string());
+        let ns: Namespace = "testsrc".parse().unwrap();
+        assert_eq!(track.namespace_trackname(&ns).unwrap().to_string(), "testsrc--mp4");
+    }
+}
@@ -0,0 +1,29 @@
+use quiche_moq::wire::Location;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    /// A JSON patch operation could not be applied.
+    Patch(String),
+    /// A delta update was received before the full catalog of its group.
+    MissingBaseCatalog,
+    /// The track ended within the payload of the object at this location.
+    TruncatedObject(Location),
+    /// A namespace in the catalog is not valid text encoding.
+    InvalidNamespace(String),
+    Moq(quiche_moq::Error),
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl From<quiche_moq::Error> for Error {
+    fn from(err: quiche_moq::Error) -> Self {
+        Error::Moq(err)
+    }
+}
@@ -0,0 +1,47 @@
+//! MoQ streaming format (MSF) catalog.
+//!
+//! The catalog is a JSON document published on its own track, by default named [`CATALOG_TRACK_NAME`],
+//! in the namespace of the broadcast it describes.
+//! Object 0 of every catalog group carries the full catalog,
+//! all following objects of that group carry JSON patch (RFC 6902) delta updates.
+//!
+//! https://datatracker.ietf.org/doc/draft-ietf-moq-msf/
+
+mod catalog;
+mod error;
+mod patch;
+mod publisher;
+mod subscriber;
+
+pub use catalog::{Catalog, Track};
+pub use error::{Error, Result};
+pub use patch::{Patch, PatchOperation};
+pub use publisher::{CatalogPublisher, CatalogTrackWriter};
+pub use subscriber::CatalogReader;
+
+use quiche_moq::wire::{Namespace, NamespaceTrackname};
+
+/// Catalog format version written by this implementation.
+pub const CATALOG_VERSION: u64 = 1;
+
+/// Track name of the catalog within the namespace of a broadcast.
+pub const CATALOG_TRACK_NAME: &str = "catalog";
+
+/// Low overhead container, https://datatracker.ietf.org/doc/draft-mzanaty-moq-loc/
+pub const PACKAGING_LOC: &str = "loc";
+/// Common media application format, ISO/IEC 23000-19
+pub const PACKAGING_CMAF: &str = "cmaf";
+
+pub const ROLE_VIDEO: &str = "video";
+pub const ROLE_AUDIO: &str = "audio";
+pub const ROLE_CAPTION: &str = "caption";
+pub const ROLE_SUBTITLE: &str = "subtitle";
+
+/// A catalog group starts over with a full catalog after this many delta updates,
+/// so that subscribers joining late do not have to apply a long patch chain.
+pub const MAX_DELTA_UPDATES_PER_GROUP: usize = 32;
+
+/// Full track name of the catalog track of `namespace`.
+pub fn catalog_track(namespace: &Namespace) -> NamespaceTrackname {
+    NamespaceTrackname::new(namespace.0.0.clone(), CATALOG_TRACK_NAME.as_bytes().to_vec())
+}
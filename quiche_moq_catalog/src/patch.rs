@@ -0,0 +1,248 @@
+use crate::catalog::{Catalog, Track};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One JSON patch operation, https://www.rfc-editor.org/rfc/rfc6902#section-4
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// JSON patch document, https://www.rfc-editor.org/rfc/rfc6902
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Patch(pub Vec<PatchOperation>);
+
+impl Patch {
+    pub fn from_json(buf: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(buf)?)
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Apply all operations in order.
+    /// `doc` is left unchanged if any operation fails.
+    pub fn apply(&self, doc: &mut Value) -> Result<()> {
+        let mut tmp = doc.clone();
+        for op in &self.0 {
+            match op {
+                PatchOperation::Add { path, value } => add(&mut tmp, path, value.clone())?,
+                PatchOperation::Remove { path } => {
+                    remove(&mut tmp, path)?;
+                }
+                PatchOperation::Replace { path, value } => {
+                    let target = tmp
+                        .pointer_mut(path)
+                        .ok_or_else(|| Error::Patch(format!("replace: {path:?} does not exist")))?;
+                    *target = value.clone();
+                }
+                PatchOperation::Move { from, path } => {
+                    if path.starts_with(&format!("{from}/")) {
+                        return Err(Error::Patch(format!("move: {from:?} into its own child {path:?}")));
+                    }
+                    let value = remove(&mut tmp, from)?;
+                    add(&mut tmp, path, value)?;
+                }
+                PatchOperation::Copy { from, path } => {
+                    let value = tmp
+                        .pointer(from)
+                        .cloned()
+                        .ok_or_else(|| Error::Patch(format!("copy: {from:?} does not exist")))?;
+                    add(&mut tmp, path, value)?;
+                }
+                PatchOperation::Test { path, value } => {
+                    if tmp.pointer(path) != Some(value) {
+                        return Err(Error::Patch(format!("test: {path:?} does not match")));
+                    }
+                }
+            }
+        }
+        *doc = tmp;
+        Ok(())
+    }
+
+    /// Delta update that turns `old` into `new`.
+    /// Tracks are matched by namespace and name; changed tracks are replaced as a whole
+    /// and new tracks are appended, so the track order of `new` is not preserved.
+    pub fn diff(old: &Catalog, new: &Catalog) -> Result<Self> {
+        let mut ops = vec![];
+        let (Value::Object(old_root), Value::Object(new_root)) =
+            (serde_json::to_value(old)?, serde_json::to_value(new)?)
+        else {
+            unreachable!("catalog serializes to a JSON object")
+        };
+        for key in old_root.keys() {
+            if key != "tracks" && !new_root.contains_key(key) {
+                ops.push(PatchOperation::Remove { path: format!("/{}", escape_token(key)) });
+            }
+        }
+        for (key, value) in &new_root {
+            if key == "tracks" {
+                continue;
+            }
+            let path = format!("/{}", escape_token(key));
+            match old_root.get(key) {
+                Some(old_value) if old_value == value => {}
+                Some(_) => ops.push(PatchOperation::Replace { path, value: value.clone() }),
+                None => ops.push(PatchOperation::Add { path, value: value.clone() }),
+            }
+        }
+        let mut tracks: Vec<&Track> = old.tracks.iter().collect();
+        // remove from the back so the indices of the remaining tracks stay valid
+        for i in (0..tracks.len()).rev() {
+            if !new.tracks.iter().any(|t| t.same_track(tracks[i])) {
+                ops.push(PatchOperation::Remove { path: format!("/tracks/{i}") });
+                tracks.remove(i);
+            }
+        }
+        for track in &new.tracks {
+            match tracks.iter().position(|t| t.same_track(track)) {
+                Some(i) if tracks[i] == track => {}
+                Some(i) => ops.push(PatchOperation::Replace {
+                    path: format!("/tracks/{i}"),
+                    value: serde_json::to_value(track)?,
+                }),
+                None => {
+                    ops.push(PatchOperation::Add {
+                        path: "/tracks/-".to_string(),
+                        value: serde_json::to_value(track)?,
+                    });
+                    tracks.push(track);
+                }
+            }
+        }
+        Ok(Self(ops))
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6901#section-3
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6901#section-4
+fn unescape_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// Splits a JSON pointer into the pointer of the parent and the unescaped last reference token.
+fn split_pointer(path: &str) -> Result<(&str, String)> {
+    let i = path
+        .rfind('/')
+        .ok_or_else(|| Error::Patch(format!("invalid JSON pointer {path:?}")))?;
+    Ok((&path[..i], unescape_token(&path[i + 1..])))
+}
+
+/// `bound` is exclusive
+fn array_index(token: &str, bound: usize) -> Result<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return Err(Error::Patch(format!("invalid array index {token:?}")));
+    }
+    match token.parse::<usize>() {
+        Ok(i) if i < bound => Ok(i),
+        _ => Err(Error::Patch(format!("invalid array index {token:?}"))),
+    }
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<()> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, value);
+        }
+        Some(Value::Array(array)) => {
+            let i = if token == "-" {
+                array.len()
+            } else {
+                array_index(&token, array.len() + 1)?
+            };
+            array.insert(i, value);
+        }
+        _ => return Err(Error::Patch(format!("add: no object or array at {parent:?}"))),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value> {
+    let (parent, token) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => map
+            .remove(&token)
+            .ok_or_else(|| Error::Patch(format!("remove: {path:?} does not exist"))),
+        Some(Value::Array(array)) => {
+            let i = array_index(&token, array.len())?;
+            Ok(array.remove(i))
+        }
+        _ => Err(Error::Patch(format!("remove: no object or array at {parent:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Catalog, Patch, Track, PACKAGING_LOC};
+    use serde_json::json;
+
+    #[test]
+    fn apply() {
+        let mut doc = json!({ "a": { "b": [1, 2] }, "c/d": 3 });
+        let patch = Patch::from_json(br#"[
+            { "op": "add", "path": "/a/b/1", "value": 5 },
+            { "op": "add", "path": "/a/b/-", "value": 6 },
+            { "op": "remove", "path": "/c~1d" },
+            { "op": "replace", "path": "/a/b/0", "value": 0 },
+            { "op": "copy", "from": "/a/b", "path": "/e" },
+            { "op": "move", "from": "/e", "path": "/f" },
+            { "op": "test", "path": "/f/3", "value": 6 }
+        ]"#).unwrap();
+        patch.apply(&mut doc).unwrap();
+        assert_eq!(doc, json!({ "a": { "b": [0, 5, 2, 6] }, "f": [0, 5, 2, 6] }));
+    }
+
+    #[test]
+    fn apply_is_atomic() {
+        let mut doc = json!({ "a": 1 });
+        let patch = Patch::from_json(br#"[
+            { "op": "replace", "path": "/a", "value": 2 },
+            { "op": "remove", "path": "/missing" }
+        ]"#).unwrap();
+        assert!(patch.apply(&mut doc).is_err());
+        assert_eq!(doc, json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn diff() {
+        let old = Catalog::new(vec![
+            Track::new("a", PACKAGING_LOC),
+            Track::new("b", PACKAGING_LOC),
+            Track::new("c", PACKAGING_LOC),
+        ]);
+        let mut new = Catalog::new(vec![
+            Track::new("c", PACKAGING_LOC),
+            Track::new("d", PACKAGING_LOC),
+        ]);
+        new.tracks[0].bitrate = Some(1000);
+        new.generated_at = Some(1);
+        let patch = Patch::diff(&old, &new).unwrap();
+        let mut patched = old.clone();
+        patched.apply_patch(&patch).unwrap();
+        assert_eq!(patched, new);
+        assert!(Patch::diff(&new, &new).unwrap().is_empty());
+    }
+}
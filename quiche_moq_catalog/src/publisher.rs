@@ -0,0 +1,166 @@
+use crate::catalog::Catalog;
+use crate::error::Result;
+use crate::patch::Patch;
+use crate::MAX_DELTA_UPDATES_PER_GROUP;
+use log::debug;
+use quiche::h3;
+use quiche_moq::wire::{KeyValuePairs, Location, TrackAlias};
+use quiche_moq::MoqTransportSession;
+use quiche_webtransport as wt;
+
+/// Holds the current catalog and the encoded objects of the latest catalog group.
+/// Shared by all subscribers of the catalog track.
+pub struct CatalogPublisher {
+    catalog: Catalog,
+    group_id: u64,
+    /// Object 0 is the full catalog, all following objects are delta updates.
+    objects: Vec<Vec<u8>>,
+}
+
+impl CatalogPublisher {
+    pub fn new(catalog: Catalog) -> Result<Self> {
+        Ok(Self {
+            objects: vec![catalog.to_json()?],
+            catalog,
+            group_id: 0,
+        })
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn group_id(&self) -> u64 {
+        self.group_id
+    }
+
+    /// Location of the most recent catalog object.
+    pub fn largest_location(&self) -> Location {
+        Location {
+            group: self.group_id,
+            object: self.objects.len() as u64 - 1,
+        }
+    }
+
+    /// Encoded catalog object at `location`, if it is part of the latest group.
+    pub fn object(&self, location: Location) -> Option<&[u8]> {
+        if location.group != self.group_id {
+            return None;
+        }
+        self.objects.get(location.object as usize).map(|o| o.as_slice())
+    }
+
+    /// Replace the catalog.
+    /// The change is published as a delta update,
+    /// or as a full catalog in a new group after [`MAX_DELTA_UPDATES_PER_GROUP`] updates.
+    pub fn update(&mut self, catalog: Catalog) -> Result<()> {
+        let patch = Patch::diff(&self.catalog, &catalog)?;
+        if patch.is_empty() {
+            return Ok(());
+        }
+        if self.objects.len() > MAX_DELTA_UPDATES_PER_GROUP {
+            self.group_id += 1;
+            self.objects = vec![catalog.to_json()?];
+            debug!("start catalog group {}", self.group_id);
+        } else {
+            self.objects.push(patch.to_json()?);
+            debug!("catalog delta update {:?}", patch);
+        }
+        self.catalog = catalog;
+        Ok(())
+    }
+}
+
+struct PendingObject {
+    location: Location,
+    buf: Vec<u8>,
+    offset: usize,
+    sent_header: bool,
+}
+
+/// Sends the objects of a [`CatalogPublisher`] to one subscriber of the catalog track.
+pub struct CatalogTrackWriter {
+    track_alias: TrackAlias,
+    /// Next object to send
+    next: Option<Location>,
+    /// Object that is partially sent
+    pending: Option<PendingObject>,
+}
+
+impl CatalogTrackWriter {
+    /// `track_alias` as returned by `MoqTransportSession::accept_subscription`
+    pub fn new(track_alias: TrackAlias) -> Self {
+        Self {
+            track_alias,
+            next: None,
+            pending: None,
+        }
+    }
+
+    pub fn track_alias(&self) -> TrackAlias {
+        self.track_alias
+    }
+
+    /// Send all catalog objects the subscriber has not received yet.
+    /// A subscriber that is behind a newer group skips to the full catalog of that group.
+    /// Returns `Ok` when done or blocked by flow control; call again later to continue.
+    pub fn send(
+        &mut self,
+        publisher: &CatalogPublisher,
+        session: &mut MoqTransportSession,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<()> {
+        loop {
+            if self.pending.is_none() {
+                let location = match self.next {
+                    Some(l) if l.group == publisher.group_id() => l,
+                    _ => Location { group: publisher.group_id(), object: 0 },
+                };
+                let Some(buf) = publisher.object(location) else {
+                    return Ok(()); // up to date
+                };
+                self.pending = Some(PendingObject {
+                    location,
+                    buf: buf.to_vec(),
+                    offset: 0,
+                    sent_header: false,
+                });
+            }
+            let p = self.pending.as_mut().unwrap();
+            if !p.sent_header {
+                match session.send_obj_hdr_with(
+                    Some(p.location.group),
+                    Some(0),
+                    Some(p.location.object),
+                    p.buf.len(),
+                    &KeyValuePairs::new(),
+                    self.track_alias,
+                    wt,
+                    h3,
+                    quic,
+                ) {
+                    Ok(()) => p.sent_header = true,
+                    Err(quiche_moq::Error::InsufficientCapacity) => return Ok(()),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            while p.offset < p.buf.len() {
+                match session.send_obj_pld(&p.buf[p.offset..], self.track_alias, wt, quic) {
+                    Ok(0) => return Ok(()),
+                    Ok(n) => p.offset += n,
+                    Err(quiche_moq::Error::Done) | Err(quiche_moq::Error::InsufficientCapacity) => {
+                        return Ok(());
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            self.next = Some(Location {
+                group: p.location.group,
+                object: p.location.object + 1,
+            });
+            self.pending = None;
+        }
+    }
+}
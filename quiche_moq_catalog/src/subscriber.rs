@@ -0,0 +1,149 @@
+use crate::catalog::Catalog;
+use crate::error::{Error, Result};
+use crate::patch::Patch;
+use log::debug;
+use quiche::h3;
+use quiche_moq::wire::{Location, TrackAlias};
+use quiche_moq::MoqTransportSession;
+use quiche_webtransport as wt;
+
+struct PartialObject {
+    location: Location,
+    buf: Vec<u8>,
+    remaining: usize,
+}
+
+/// Reassembles the objects of a subscribed catalog track and applies delta updates.
+pub struct CatalogReader {
+    track_alias: TrackAlias,
+    catalog: Option<Catalog>,
+    /// Location of the last applied catalog object
+    location: Option<Location>,
+    /// Object whose payload is not completely received yet
+    current: Option<PartialObject>,
+}
+
+impl CatalogReader {
+    /// `track_alias` as returned by `MoqTransportSession::poll_subscribe_response`
+    pub fn new(track_alias: TrackAlias) -> Self {
+        Self {
+            track_alias,
+            catalog: None,
+            location: None,
+            current: None,
+        }
+    }
+
+    pub fn track_alias(&self) -> TrackAlias {
+        self.track_alias
+    }
+
+    /// `None` until the first full catalog is received.
+    pub fn catalog(&self) -> Option<&Catalog> {
+        self.catalog.as_ref()
+    }
+
+    /// Read all available catalog objects from the session.
+    /// Returns `true` if the catalog changed, or [`Error::TruncatedObject`] if the track ends
+    /// within an object.
+    pub fn read(
+        &mut self,
+        session: &mut MoqTransportSession,
+        wt: &mut wt::Connection,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+    ) -> Result<bool> {
+        let mut changed = false;
+        loop {
+            if self.current.is_none() {
+                let hdr = match session.read_obj_hdr(self.track_alias, wt, h3, quic) {
+                    Ok(v) => v,
+                    Err(quiche_moq::Error::Done) | Err(quiche_moq::Error::Fin) => return Ok(changed),
+                    Err(e) => return Err(e.into()),
+                };
+                if hdr.payload_len() == 0 {
+                    continue; // object status, carries no catalog
+                }
+                let group = session
+                    .subgroup_header(self.track_alias)
+                    .map(|h| h.group_id())
+                    .unwrap_or(0);
+                self.current = Some(PartialObject {
+                    location: Location { group, object: hdr.id() },
+                    buf: Vec::with_capacity(hdr.payload_len()),
+                    remaining: hdr.payload_len(),
+                });
+            }
+            let obj = self.current.as_mut().unwrap();
+            while obj.remaining > 0 {
+                let start = obj.buf.len();
+                obj.buf.resize(start + obj.remaining, 0);
+                match session.read_obj_pld(&mut obj.buf[start..], self.track_alias, wt, h3, quic) {
+                    Ok(n) => {
+                        obj.buf.truncate(start + n);
+                        obj.remaining -= n;
+                    }
+                    Err(quiche_moq::Error::Done) => {
+                        obj.buf.truncate(start);
+                        return Ok(changed);
+                    }
+                    Err(quiche_moq::Error::Fin) => {
+                        let location = obj.location;
+                        self.current = None;
+                        return Err(Error::TruncatedObject(location));
+                    }
+                    Err(e) => {
+                        obj.buf.truncate(start);
+                        return Err(e.into());
+                    }
+                }
+            }
+            let obj = self.current.take().unwrap();
+            changed |= self.handle_object(obj.location, &obj.buf)?;
+        }
+    }
+
+    /// Apply a complete catalog object.
+    /// Object 0 of a group is a full catalog, all following objects are delta updates.
+    /// Returns `true` if the catalog changed.
+    pub fn handle_object(&mut self, location: Location, payload: &[u8]) -> Result<bool> {
+        if location.object == 0 {
+            self.catalog = Some(Catalog::from_json(payload)?);
+        } else {
+            if self.location.is_none_or(|l| l.group != location.group) {
+                return Err(Error::MissingBaseCatalog);
+            }
+            let catalog = self.catalog.as_mut().unwrap();
+            catalog.apply_patch(&Patch::from_json(payload)?)?;
+        }
+        debug!("catalog updated at {:?}", location);
+        self.location = Some(location);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CatalogPublisher, CatalogReader, Catalog, Error, Track, PACKAGING_LOC};
+    use quiche_moq::wire::Location;
+
+    #[test]
+    fn delta_updates() {
+        let mut publisher = CatalogPublisher::new(Catalog::new(vec![Track::new("a", PACKAGING_LOC)])).unwrap();
+        let mut catalog = publisher.catalog().clone();
+        catalog.tracks.push(Track::new("b", PACKAGING_LOC));
+        publisher.update(catalog.clone()).unwrap();
+        assert_eq!(publisher.largest_location(), Location { group: 0, object: 1 });
+
+        let mut reader = CatalogReader::new(0);
+        let delta = Location { group: 0, object: 1 };
+        assert!(matches!(
+            reader.handle_object(delta, publisher.object(delta).unwrap()),
+            Err(Error::MissingBaseCatalog)
+        ));
+        let full = Location { group: 0, object: 0 };
+        reader.handle_object(full, publisher.object(full).unwrap()).unwrap();
+        reader.handle_object(delta, publisher.object(delta).unwrap()).unwrap();
+        assert_eq!(reader.catalog(), Some(&catalog));
+    }
+}
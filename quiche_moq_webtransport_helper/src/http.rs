@@ -0,0 +1,66 @@
+use quiche::h3;
+use quiche::h3::NameValue;
+
+/// A plain HTTP/3 request received by a server, i.e. anything but a WebTransport CONNECT.
+/// Request bodies are ignored.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HttpRequest {
+    pub stream_id: u64,
+    pub method: String,
+    /// Path with an optional query, e.g. `/live/video/latest?token=...`
+    pub path: String,
+}
+
+impl HttpRequest {
+    /// `None` for a WebTransport CONNECT, which the WebTransport connection handles.
+    pub(crate) fn from_headers(stream_id: u64, list: &[h3::Header]) -> Option<Self> {
+        let mut method = None;
+        let mut path = String::new();
+        for h in list {
+            match h.name() {
+                b":method" => method = Some(String::from_utf8_lossy(h.value()).into_owned()),
+                b":path" => path = String::from_utf8_lossy(h.value()).into_owned(),
+                _ => {}
+            }
+        }
+        let method = method?;
+        if method == "CONNECT" {
+            return None;
+        }
+        Some(Self { stream_id, method, path })
+    }
+}
+
+/// A response that did not fit into the stream yet.
+pub(crate) struct PendingResponse {
+    pub(crate) stream_id: u64,
+    /// Sent first; `None` once sent.
+    pub(crate) headers: Option<Vec<h3::Header>>,
+    pub(crate) body: Vec<u8>,
+    pub(crate) written: usize,
+}
+
+impl PendingResponse {
+    /// Send as much as the stream takes. Returns true once the response is complete or the
+    /// stream is gone.
+    pub(crate) fn flush(&mut self, h3_conn: &mut h3::Connection, quic_conn: &mut quiche::Connection) -> bool {
+        if let Some(headers) = &self.headers {
+            match h3_conn.send_response(quic_conn, self.stream_id, headers, self.body.is_empty()) {
+                Ok(()) => self.headers = None,
+                Err(h3::Error::StreamBlocked | h3::Error::Done) => return false,
+                Err(_) => return true,
+            }
+            if self.body.is_empty() {
+                return true;
+            }
+        }
+        while self.written < self.body.len() {
+            match h3_conn.send_body(quic_conn, self.stream_id, &self.body[self.written..], true) {
+                Ok(0) | Err(h3::Error::Done) => return false,
+                Ok(n) => self.written += n,
+                Err(_) => return true,
+            }
+        }
+        true
+    }
+}
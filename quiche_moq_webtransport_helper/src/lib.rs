@@ -1,22 +1,32 @@
 #[cfg(test)]
 mod tests;
+mod http;
 mod moq_handle;
 
+pub use http::HttpRequest;
 pub use moq_handle::MoqHandle;
 
+use std::collections::VecDeque;
 use quiche::h3;
 use quiche_moq as moq;
 use quiche_moq::MoqTransportSession;
 use quiche_webtransport as wt;
-use log::debug;
+use log::{debug, error};
 use url::Url;
 use quiche_h3_utils::ALPN_HTTP_3;
+use crate::http::PendingResponse;
+
+/// H3_REQUEST_REJECTED, for request streams the helper does not serve.
+const H3_REQUEST_REJECTED: u64 = 0x10b;
 
 /// Make it easy to write an MoQ WebTransport clients and servers
 pub struct MoqWebTransportHelper {
     pub state: State,
     moq_config: moq::Config,
     perspective: Perspective,
+    /// Plain HTTP/3 requests received by a server, see [`Self::next_http_request`].
+    http_requests: VecDeque<HttpRequest>,
+    http_responses: Vec<PendingResponse>,
 }
 
 impl MoqWebTransportHelper {
@@ -26,7 +36,9 @@ impl MoqWebTransportHelper {
             moq_config,
             perspective: Perspective::Client {
                 url,
-            }
+            },
+            http_requests: VecDeque::new(),
+            http_responses: vec![],
         }
     }
 
@@ -34,7 +46,9 @@ impl MoqWebTransportHelper {
         Self {
             state: State::Quic,
             moq_config,
-            perspective: Perspective::Server {}
+            perspective: Perspective::Server {},
+            http_requests: VecDeque::new(),
+            http_responses: vec![],
         }
     }
 
@@ -75,9 +89,12 @@ impl MoqWebTransportHelper {
                             c
                         },
                     ).expect("Unable to create HTTP/3 connection, check the server's uni stream limit and window size");
+                    let mut wt_conn = wt::Connection::new(matches!(self.perspective, Perspective::Server));
+                    // draft 15+ negotiate the MoQ version by the WebTransport protocol
+                    wt_conn.set_protocols(&self.moq_config.protocols());
                     self.state = State::H3 {
                         h3_conn,
-                        wt_conn: wt::Connection::new(matches!(self.perspective, Perspective::Server))
+                        wt_conn,
                     }
                 }
                 State::H3 { h3_conn, wt_conn } => {
@@ -88,7 +105,7 @@ impl MoqWebTransportHelper {
                                 break 'conn; // not ready for wt
                             }
                             let moq_session_id =
-                                wt_conn.connect_session(h3_conn, quic_conn, url.clone());
+                                wt_conn.connect_session_with_protocols(h3_conn, quic_conn, url.clone(), &self.moq_config.protocols());
                             let State::H3 { h3_conn, wt_conn } = std::mem::take(&mut self.state) else {
                                 unreachable!()
                             };
@@ -99,23 +116,7 @@ impl MoqWebTransportHelper {
                             };
                         }
                         Perspective::Server => {
-                            'h3: loop {
-                                match h3_conn.poll(quic_conn) {
-                                    Ok((stream_id, h3::Event::Headers { list, .. })) => {
-                                        debug!(
-                                            "h3 stream {} received headers",
-                                            stream_id,
-                                        );
-                                        wt_conn.recv_hdrs(stream_id, &list);
-                                    }
-                                    Ok((_stream_id, h3::Event::Data)) => {
-                                        debug!("ignoring h3 data");
-                                    }
-                                    Ok(e) => unimplemented!("{:?}", e),
-                                    Err(h3::Error::Done) => break 'h3,
-                                    Err(e) => unimplemented!("{:?}", e),
-                                }
-                            }
+                            Self::h3_poll_server(h3_conn, wt_conn, quic_conn, &mut self.http_requests);
                             let Some(&moq_session_id) = wt_conn.session_ids().first() else { break 'conn };
                             let moq_session = MoqTransportSession::accept(moq_session_id.into(), self.moq_config.clone());
                             let State::H3 { h3_conn, wt_conn } = std::mem::take(&mut self.state) else {
@@ -140,9 +141,12 @@ impl MoqWebTransportHelper {
                             Ok((stream_id, h3::Event::Headers { list, .. })) => {
                                 wt_conn.recv_hdrs(stream_id, &list);
                             }
-                            Ok(e) => unimplemented!("{:?}", e),
+                            Ok((_, e)) => debug!("ignoring h3 event {:?}", e),
                             Err(h3::Error::Done) => break 'h3,
-                            Err(e) => unimplemented!("{:?}", e),
+                            Err(e) => {
+                                Self::h3_poll_failed(e);
+                                break 'h3;
+                            }
                         }
                     }
                     wt_conn.poll(h3_conn, quic_conn);
@@ -173,7 +177,10 @@ impl MoqWebTransportHelper {
                     wt_conn,
                     moq_session,
                 } => {
-                    Self::h3_poll_expect_nothing(h3_conn, quic_conn);
+                    match self.perspective {
+                        Perspective::Client { .. } => Self::h3_poll_expect_nothing(h3_conn, quic_conn),
+                        Perspective::Server => Self::h3_poll_server(h3_conn, wt_conn, quic_conn, &mut self.http_requests),
+                    }
                     wt_conn.poll(h3_conn, quic_conn);
                     moq_session.poll(wt_conn, h3_conn, quic_conn);
                     if !moq_session.initialized() {
@@ -196,28 +203,100 @@ impl MoqWebTransportHelper {
                     wt_conn,
                     moq_session,
                 } => {
-                    Self::h3_poll_expect_nothing(h3_conn, quic_conn);
+                    match self.perspective {
+                        Perspective::Client { .. } => Self::h3_poll_expect_nothing(h3_conn, quic_conn),
+                        Perspective::Server => Self::h3_poll_server(h3_conn, wt_conn, quic_conn, &mut self.http_requests),
+                    }
                     wt_conn.poll(h3_conn, quic_conn);
                     moq_session.poll(wt_conn, h3_conn, quic_conn);
                     break 'conn;
                 }
             }
         }
+        self.flush_http_responses(quic_conn);
     }
 
-    #[allow(clippy::never_loop)]
+    /// Next plain HTTP/3 request received by a server, e.g. a GET by a client that does not
+    /// speak MoQ. Answer it with [`Self::send_http_response`].
+    pub fn next_http_request(&mut self) -> Option<HttpRequest> {
+        self.http_requests.pop_front()
+    }
+
+    /// Respond to a request of [`Self::next_http_request`]; `headers` must include `:status`.
+    /// What does not fit into the stream is sent by later calls of `on_post_handle_recvs`.
+    pub fn send_http_response(&mut self, quic_conn: &mut quiche::Connection, stream_id: u64, headers: Vec<h3::Header>, body: Vec<u8>) {
+        self.http_responses.push(PendingResponse { stream_id, headers: Some(headers), body, written: 0 });
+        self.flush_http_responses(quic_conn);
+    }
+
+    fn flush_http_responses(&mut self, quic_conn: &mut quiche::Connection) {
+        let h3_conn = match &mut self.state {
+            State::H3 { h3_conn, .. } | State::MoqHandshake { h3_conn, .. } | State::Moq { h3_conn, .. } => h3_conn,
+            State::Quic | State::Wt { .. } => return,
+        };
+        self.http_responses.retain_mut(|r| !r.flush(h3_conn, quic_conn));
+    }
+
+    /// WebTransport CONNECT requests go to `wt_conn`, other requests to `http_requests`.
+    fn h3_poll_server(
+        h3_conn: &mut h3::Connection,
+        wt_conn: &mut wt::Connection,
+        quic_conn: &mut quiche::Connection,
+        http_requests: &mut VecDeque<HttpRequest>,
+    ) {
+        'h3: loop {
+            match h3_conn.poll(quic_conn) {
+                Ok((stream_id, h3::Event::Headers { list, .. })) => {
+                    debug!(
+                        "h3 stream {} received headers",
+                        stream_id,
+                    );
+                    match HttpRequest::from_headers(stream_id, &list) {
+                        Some(request) => http_requests.push_back(request),
+                        None => wt_conn.recv_hdrs(stream_id, &list),
+                    }
+                }
+                Ok((_stream_id, h3::Event::Data)) => {
+                    debug!("ignoring h3 data");
+                }
+                Ok((_, h3::Event::Finished | h3::Event::Reset(_))) => {}
+                // e.g. GOAWAY or PRIORITY_UPDATE
+                Ok((_, e)) => debug!("ignoring h3 event {:?}", e),
+                Err(h3::Error::Done) => break 'h3,
+                Err(e) => {
+                    Self::h3_poll_failed(e);
+                    break 'h3;
+                }
+            }
+        }
+    }
+
+    /// A client sends no requests besides the WebTransport session, so other responses are rejected.
     fn h3_poll_expect_nothing(h3_conn: &mut h3::Connection, quic_conn: &mut quiche::Connection) {
         'h3: loop {
             match h3_conn.poll(quic_conn) {
-                Ok((_, h3::Event::Headers { .. })) => unreachable!("unexpected h3 response"),
+                Ok((stream_id, h3::Event::Headers { .. })) => {
+                    debug!("rejecting unexpected h3 stream {}", stream_id);
+                    quic_conn.stream_shutdown(stream_id, quiche::Shutdown::Read, H3_REQUEST_REJECTED).ok();
+                    quic_conn.stream_shutdown(stream_id, quiche::Shutdown::Write, H3_REQUEST_REJECTED).ok();
+                }
                 Ok((_, h3::Event::Reset(_))) => {},
-                Ok(e) => unimplemented!("{:?}", e),
+                Ok((_, e)) => debug!("ignoring h3 event {:?}", e),
                 Err(h3::Error::Done) => break 'h3,
-                Err(e) => unimplemented!("{:?}", e),
+                Err(e) => {
+                    Self::h3_poll_failed(e);
+                    break 'h3;
+                }
             }
         }
     }
 
+    /// quiche closes the connection itself on HTTP/3 connection errors; errors of a single
+    /// request stream surface as [`h3::Event::Reset`] and only end that stream.
+    fn h3_poll_failed(e: h3::Error) {
+        error!("h3 poll failed: {:?}", e);
+    }
+
     pub fn configure_quic(c: &mut quiche::Config) {
         c.set_application_protos(&[ALPN_HTTP_3]).unwrap();
         c.set_initial_max_streams_bidi(100);
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use quiche::h3;
 use quiche_moq::{MoqTransportSession, PublishStatus, Result, StreamID, SubscriptionRequestAction};
-use quiche_moq::wire::{KeyValuePairs, Location, NamespaceTrackname, RequestId, TrackAlias};
+use quiche_moq::wire::{KeyValuePairs, Location, Namespace, NamespaceTrackname, RequestId, TrackAlias};
 use quiche_moq::wire::control_message::{
-    PublishNamespaceMessage, RequestErrorMessage, SubscribeMessage, SubscribeOkMessage,
+    FetchMessage, FetchOkMessage, FetchRange, PublishNamespaceMessage, RequestErrorMessage,
+    SubscribeMessage, SubscribeOkMessage,
 };
 use quiche_moq::wire::Version;
+use quiche_moq::wire::fetch::FetchObjectHeader;
 use quiche_moq::wire::object::ObjectHeader;
 use quiche_moq::wire::subgroup::SubgroupHeader;
 use quiche_webtransport as wt;
@@ -26,4 +28,9 @@ impl<'a> MoqHandle<'a> {
     pub fn quic(&mut self) -> &mut quiche::Connection{
         self.quic
     }
+
+    /// Path and query of the WebTransport CONNECT request, `None` for raw QUIC sessions.
+    pub fn connect_path(&self) -> Option<&str> {
+        self.wt.session_path(self.session.session_id().into_u64())
+    }
 }
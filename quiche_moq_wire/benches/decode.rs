@@ -0,0 +1,68 @@
+//! Owned vs borrowed decoding, run with `cargo bench -p quiche_moq_wire`
+use criterion::{Criterion, criterion_group, criterion_main};
+use octets::{Octets, OctetsMut};
+use quiche_moq_wire::control_message::subscribe::FilterType;
+use quiche_moq_wire::control_message::{SubscribeMessage, SubscribeMessageRef};
+use quiche_moq_wire::object::{ObjectHeader, ObjectHeaderRef};
+use quiche_moq_wire::subgroup::SubgroupHeader;
+use quiche_moq_wire::{FromBytes, KeyValuePair, KeyValuePairs, MOQ_VERSION_DRAFT_14, Parameter, Parameters, ToBytes};
+use std::hint::black_box;
+
+const VERSION: u64 = MOQ_VERSION_DRAFT_14;
+
+fn object_header(c: &mut Criterion) {
+    let subgroup = SubgroupHeader::new(1, 2, 0, VERSION);
+    let ext = KeyValuePairs::from(vec![
+        KeyValuePair::new_varint(0x2, 1_700_000_000_000_000).unwrap(),
+        KeyValuePair::new_bytes(0xb, b"some extension header".to_vec()).unwrap(),
+    ]);
+    let mut buf = [0u8; 64];
+    let mut o = OctetsMut::with_slice(&mut buf);
+    ObjectHeader::new(3, 1200, subgroup.ty(), ext).to_bytes(&mut o, VERSION).unwrap();
+    let len = o.off();
+    let buf = &buf[..len];
+
+    let mut group = c.benchmark_group("object_header");
+    group.bench_function("owned", |b| {
+        b.iter(|| ObjectHeader::from_bytes(&mut Octets::with_slice(black_box(buf)), VERSION, &subgroup).unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| ObjectHeaderRef::from_bytes(&mut Octets::with_slice(black_box(buf)), VERSION, &subgroup).unwrap().id())
+    });
+    group.finish();
+}
+
+fn subscribe(c: &mut Criterion) {
+    let sm = SubscribeMessage {
+        request_id: 4,
+        track_alias: None,
+        namespace_trackname: "example.2enet-team2-project_x--report".parse().unwrap(),
+        subscriber_priority: 1,
+        group_order: 0,
+        forward: Some(1),
+        filter_type: FilterType::LargestObject,
+        start_location: None,
+        end_group: None,
+        parameters: Parameters(vec![
+            Parameter::from(KeyValuePair::new_varint(0x2, 5000).unwrap()),
+            Parameter::from(KeyValuePair::new_bytes(0x3, b"token".to_vec()).unwrap()),
+        ]),
+    };
+    let mut buf = [0u8; 128];
+    let mut o = OctetsMut::with_slice(&mut buf);
+    sm.to_bytes(&mut o, VERSION).unwrap();
+    let len = o.off();
+    let buf = &buf[..len];
+
+    let mut group = c.benchmark_group("subscribe");
+    group.bench_function("owned", |b| {
+        b.iter(|| SubscribeMessage::from_bytes(&mut Octets::with_slice(black_box(buf)), VERSION).unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| SubscribeMessageRef::from_bytes(&mut Octets::with_slice(black_box(buf)), VERSION).unwrap().request_id)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, object_header, subscribe);
+criterion_main!(benches);
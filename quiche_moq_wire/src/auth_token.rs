@@ -0,0 +1,138 @@
+use crate::bytes::{FromBytes, ToBytes};
+use crate::error::{Error, Result};
+use crate::parameter::ParameterValue;
+use crate::{Parameter, Parameters, Version, AUTHORIZATION_INFO_PARAMETER_ID, AUTHORIZATION_TOKEN_PARAMETER_ID, AUTH_TOKEN_ALIAS_TYPE_DELETE, AUTH_TOKEN_ALIAS_TYPE_REGISTER, AUTH_TOKEN_ALIAS_TYPE_USE_ALIAS, AUTH_TOKEN_ALIAS_TYPE_USE_VALUE, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16};
+use octets::{Octets, OctetsMut};
+
+/// Value of an AUTHORIZATION_TOKEN parameter.
+/// Draft 07 to draft 10 only know the token value, which is decoded as [`AuthToken::UseValue`] with token type 0.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AuthToken {
+    Delete { alias: u64 },
+    Register { alias: u64, token_type: u64, value: Vec<u8> },
+    UseAlias { alias: u64 },
+    UseValue { token_type: u64, value: Vec<u8> },
+}
+
+impl AuthToken {
+    /// The token value carried in the parameter, `None` for a reference to an alias.
+    pub fn value(&self) -> Option<&[u8]> {
+        match self {
+            AuthToken::Register { value, .. } | AuthToken::UseValue { value, .. } => Some(value),
+            AuthToken::Delete { .. } | AuthToken::UseAlias { .. } => None,
+        }
+    }
+
+    /// Encode as a request parameter.
+    pub fn to_parameter(&self, version: Version) -> Result<Parameter> {
+        match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => match self {
+                AuthToken::UseValue { value, .. } => Ok(Parameter::new_bytes(AUTHORIZATION_INFO_PARAMETER_ID, value.clone())),
+                _ => Err(Error::ProtocolViolation("token aliases require draft 11".to_string())),
+            },
+            MOQ_VERSION_DRAFT_11..=MOQ_VERSION_DRAFT_16 => {
+                let mut buf = vec![0u8; 3 * 8 + self.value().map_or(0, |v| v.len())];
+                let mut b = OctetsMut::with_slice(&mut buf);
+                self.to_bytes(&mut b, version)?;
+                let len = b.off();
+                buf.truncate(len);
+                Ok(Parameter::new_bytes(AUTHORIZATION_TOKEN_PARAMETER_ID, buf))
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl FromBytes for AuthToken {
+    /// The whole parameter value, the token value extends to its end.
+    fn from_bytes(b: &mut Octets, _version: Version) -> Result<Self> {
+        let token = match b.get_varint()? {
+            AUTH_TOKEN_ALIAS_TYPE_DELETE => AuthToken::Delete { alias: b.get_varint()? },
+            AUTH_TOKEN_ALIAS_TYPE_REGISTER => AuthToken::Register {
+                alias: b.get_varint()?,
+                token_type: b.get_varint()?,
+                value: b.get_bytes(b.cap())?.to_vec(),
+            },
+            AUTH_TOKEN_ALIAS_TYPE_USE_ALIAS => AuthToken::UseAlias { alias: b.get_varint()? },
+            AUTH_TOKEN_ALIAS_TYPE_USE_VALUE => AuthToken::UseValue {
+                token_type: b.get_varint()?,
+                value: b.get_bytes(b.cap())?.to_vec(),
+            },
+            ty => return Err(Error::ProtocolViolation(format!("unknown token alias type {ty}"))),
+        };
+        if b.cap() > 0 {
+            return Err(Error::ProtocolViolation("trailing bytes after token alias".to_string()));
+        }
+        Ok(token)
+    }
+}
+
+impl ToBytes for AuthToken {
+    fn to_bytes(&self, b: &mut OctetsMut, _version: Version) -> Result<()> {
+        match self {
+            AuthToken::Delete { alias } => {
+                b.put_varint(AUTH_TOKEN_ALIAS_TYPE_DELETE)?;
+                b.put_varint(*alias)?;
+            }
+            AuthToken::Register { alias, token_type, value } => {
+                b.put_varint(AUTH_TOKEN_ALIAS_TYPE_REGISTER)?;
+                b.put_varint(*alias)?;
+                b.put_varint(*token_type)?;
+                b.put_bytes(value)?;
+            }
+            AuthToken::UseAlias { alias } => {
+                b.put_varint(AUTH_TOKEN_ALIAS_TYPE_USE_ALIAS)?;
+                b.put_varint(*alias)?;
+            }
+            AuthToken::UseValue { token_type, value } => {
+                b.put_varint(AUTH_TOKEN_ALIAS_TYPE_USE_VALUE)?;
+                b.put_varint(*token_type)?;
+                b.put_bytes(value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Parameters {
+    /// The first authorization token of a request, `None` if it has none.
+    pub fn authorization_token(&self, version: Version) -> Option<Result<AuthToken>> {
+        let ty = match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => AUTHORIZATION_INFO_PARAMETER_ID,
+            _ => AUTHORIZATION_TOKEN_PARAMETER_ID,
+        };
+        let p = self.0.iter().find(|p| p.ty == ty)?;
+        let ParameterValue::Bytes(v) = &p.value else {
+            return Some(Err(Error::ProtocolViolation("authorization token is not a byte string".to_string())));
+        };
+        Some(match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => Ok(AuthToken::UseValue { token_type: 0, value: v.clone() }),
+            _ => AuthToken::from_bytes(&mut Octets::with_slice(v), version),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOQ_VERSION_DRAFT_14;
+
+    #[test]
+    fn recode_auth_token() {
+        for token in [
+            AuthToken::Delete { alias: 3 },
+            AuthToken::Register { alias: 3, token_type: 1, value: b"secret".to_vec() },
+            AuthToken::UseAlias { alias: 3 },
+            AuthToken::UseValue { token_type: 0, value: b"secret".to_vec() },
+        ] {
+            let params = Parameters(vec![token.to_parameter(MOQ_VERSION_DRAFT_14).unwrap()]);
+            assert_eq!(params.authorization_token(MOQ_VERSION_DRAFT_14).unwrap().unwrap(), token);
+        }
+        let token = AuthToken::UseValue { token_type: 0, value: b"secret".to_vec() };
+        let params = Parameters(vec![token.to_parameter(MOQ_VERSION_DRAFT_07).unwrap()]);
+        assert_eq!(params.authorization_token(MOQ_VERSION_DRAFT_07).unwrap().unwrap().value(), Some(b"secret".as_slice()));
+        assert!(Parameters(vec![]).authorization_token(MOQ_VERSION_DRAFT_14).is_none());
+        let malformed = Parameters(vec![Parameter::new_bytes(AUTHORIZATION_TOKEN_PARAMETER_ID, vec![0x7])]);
+        assert!(malformed.authorization_token(MOQ_VERSION_DRAFT_14).unwrap().is_err());
+    }
+}
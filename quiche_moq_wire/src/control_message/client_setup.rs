@@ -1,5 +1,5 @@
 use crate::bytes::{FromBytes, ToBytes};
-use crate::{CLIENT_SETUP_MESSAGE_ID, CLIENT_SETUP_MESSAGE_ID_VERSION_UNTIL_10, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16, SetupParameters, Version};
+use crate::{CLIENT_SETUP_MESSAGE_ID, CLIENT_SETUP_MESSAGE_ID_VERSION_UNTIL_10, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16, SetupParameters, Version, version_negotiated_by_alpn};
 use octets::{Octets, OctetsMut};
 use crate::control_message::ControlMessage;
 
@@ -25,21 +25,40 @@ impl ControlMessage for ClientSetupMessage {
 
     fn qlog_type_name(&self) -> &'static str { "client_setup" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "number_of_supported_versions": self.supported_versions.len(),
+            "supported_versions": self.supported_versions,
+        });
+        crate::qlog::set_parameters(&mut msg, crate::qlog::setup_parameters(&self.setup_parameters));
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
-        b.put_varint(self.supported_versions.len() as u64)?;
-        for supported_version in &self.supported_versions {
-            b.put_varint(*supported_version)?;
+        // since draft 15 the version is negotiated by ALPN
+        if !version_negotiated_by_alpn(version) {
+            b.put_varint(self.supported_versions.len() as u64)?;
+            for supported_version in &self.supported_versions {
+                b.put_varint(*supported_version)?;
+            }
         }
         self.setup_parameters.to_bytes(b, version)?;
         Ok(())
     }
 
     fn from_body_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
-        let num_supported_versions = b.get_varint()?;
-        let mut supported_versions = Vec::with_capacity(num_supported_versions as usize);
-        for _ in 0..num_supported_versions {
-            supported_versions.push(b.get_varint()?);
-        }
+        let supported_versions = if version_negotiated_by_alpn(version) {
+            vec![version]
+        } else {
+            let num_supported_versions = b.get_varint()?;
+            let mut supported_versions = Vec::with_capacity(num_supported_versions as usize);
+            for _ in 0..num_supported_versions {
+                supported_versions.push(b.get_varint()?);
+            }
+            supported_versions
+        };
         let setup_parameters = SetupParameters::from_bytes(b, version)?;
         Ok(Self{
             supported_versions,
@@ -51,7 +70,7 @@ impl ControlMessage for ClientSetupMessage {
 #[cfg(test)]
 mod tests {
     use crate::bytes::{FromBytes, ToBytes};
-    use crate::{MOQ_VERSION_LITE_01_BY_KIXELATED, MOQ_VERSION_DRAFT_07};
+    use crate::{MOQ_VERSION_LITE_01_BY_KIXELATED, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_16};
     use crate::{Parameter, SetupParameters};
     use octets::{Octets, OctetsMut};
     use crate::control_message::client_setup::ClientSetupMessage;
@@ -92,4 +111,26 @@ mod tests {
         let decoded = ClientSetupMessage::from_bytes(&mut o, MOQ_VERSION_DRAFT_07).unwrap();
         assert_eq!(orig, decoded);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn encode_decode_draft16() {
+        let mut b = [0u8; 100];
+        let mut o = OctetsMut::with_slice(&mut b);
+        let orig = ClientSetupMessage {
+            supported_versions: vec![MOQ_VERSION_DRAFT_16],
+            setup_parameters: SetupParameters {
+                path: None,
+                max_request_id: Some(100),
+                role: None,
+                extra_parameters: vec![],
+            },
+        };
+        orig.to_bytes(&mut o, MOQ_VERSION_DRAFT_16).unwrap();
+        let len = o.off();
+        // type, length, number of parameters, max request id parameter
+        assert_eq!(&b[..len], &[0x20, 0x0, 0x4, 0x1, 0x2, 0x40, 0x64]);
+        let mut o = Octets::with_slice(&b[..len]);
+        let decoded = ClientSetupMessage::from_bytes(&mut o, MOQ_VERSION_DRAFT_16).unwrap();
+        assert_eq!(orig, decoded);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::bytes::{FromBytes, ToBytes};
+use crate::error::Error;
+use crate::{NamespaceTrackname, NamespaceTracknameRef, Parameters, RequestId, Version, ABSOLUTE_JOINING_FETCH_TYPE_ID, FETCH_MESSAGE_ID, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_08, MOQ_VERSION_DRAFT_16, RELATIVE_JOINING_FETCH_TYPE_ID, STANDALONE_FETCH_TYPE_ID};
+use octets::{Octets, OctetsMut};
+use crate::control_message::ControlMessage;
+use crate::location::Location;
+
+#[derive(Debug, Eq, PartialEq)]
+/// Called subscribe ID before draft-11
+pub struct FetchMessage {
+    pub request_id: RequestId,
+    pub subscriber_priority: u8,
+    pub group_order: u8,
+    pub fetch_type: FetchType,
+    pub parameters: Parameters,
+}
+
+/// Objects of a standalone fetch.
+/// `end` is encoded like on the wire: the end object ID plus 1, or 0 for the whole end group.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct FetchRange {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl FetchRange {
+    pub fn contains(&self, location: Location) -> bool {
+        location >= self.start
+            && (location.group < self.end.group
+                || location.group == self.end.group && (self.end.object == 0 || location.object < self.end.object))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum FetchType {
+    Standalone {
+        namespace_trackname: NamespaceTrackname,
+        range: FetchRange,
+    },
+    /// Groups before the largest location of the joined subscription, draft 08+
+    RelativeJoining {
+        joining_request_id: RequestId,
+        preceding_groups: u64,
+    },
+    /// Groups from `start_group` up to the joined subscription, draft 08+
+    AbsoluteJoining {
+        joining_request_id: RequestId,
+        start_group: u64,
+    },
+}
+
+impl FetchType {
+    fn id(&self) -> u64 {
+        match self {
+            FetchType::Standalone { .. } => STANDALONE_FETCH_TYPE_ID,
+            FetchType::RelativeJoining { .. } => RELATIVE_JOINING_FETCH_TYPE_ID,
+            FetchType::AbsoluteJoining { .. } => ABSOLUTE_JOINING_FETCH_TYPE_ID,
+        }
+    }
+
+    #[cfg(feature = "qlog")]
+    fn qlog_name(&self) -> &'static str {
+        match self {
+            FetchType::Standalone { .. } => "standalone",
+            FetchType::RelativeJoining { .. } => "relative_joining",
+            FetchType::AbsoluteJoining { .. } => "absolute_joining",
+        }
+    }
+
+    /// `None` for joining fetches
+    pub fn joining_request_id(&self) -> Option<RequestId> {
+        match self {
+            FetchType::Standalone { .. } => None,
+            FetchType::RelativeJoining { joining_request_id, .. }
+            | FetchType::AbsoluteJoining { joining_request_id, .. } => Some(*joining_request_id),
+        }
+    }
+}
+
+fn put_namespace_trackname(b: &mut OctetsMut, nt: &NamespaceTrackname) -> crate::error::Result<()> {
+    b.put_varint(nt.namespace().len() as u64)?;
+    for namespace in nt.namespace() {
+        b.put_varint(namespace.len() as u64)?;
+        b.put_bytes(namespace)?;
+    }
+    b.put_varint(nt.trackname().len() as u64)?;
+    b.put_bytes(nt.trackname())?;
+    Ok(())
+}
+
+impl ControlMessage for FetchMessage {
+    const MESSAGE_IDS: &'static [u64] = &[FETCH_MESSAGE_ID];
+
+    fn qlog_type_name(&self) -> &'static str { "fetch" }
+
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+            "subscriber_priority": self.subscriber_priority,
+            "group_order": self.group_order,
+            "fetch_type": self.fetch_type.qlog_name(),
+        });
+        match &self.fetch_type {
+            FetchType::Standalone { namespace_trackname, range } => {
+                msg["track_namespace"] = crate::qlog::tuple(&namespace_trackname.namespace().0);
+                msg["track_name"] = crate::qlog::byte_string(namespace_trackname.trackname());
+                msg["start_location"] = crate::qlog::location(&range.start);
+                msg["end_location"] = crate::qlog::location(&range.end);
+            }
+            FetchType::RelativeJoining { joining_request_id, preceding_groups } => {
+                msg["joining_request_id"] = (*joining_request_id).into();
+                msg["joining_start"] = (*preceding_groups).into();
+            }
+            FetchType::AbsoluteJoining { joining_request_id, start_group } => {
+                msg["joining_request_id"] = (*joining_request_id).into();
+                msg["joining_start"] = (*start_group).into();
+            }
+        }
+        crate::qlog::set_parameters(&mut msg, crate::qlog::parameters(&self.parameters));
+        msg
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
+        b.put_varint(self.request_id)?;
+        match version {
+            // draft 07 only knows standalone fetches, with the track before the priority
+            MOQ_VERSION_DRAFT_07 => {
+                let FetchType::Standalone { namespace_trackname, range } = &self.fetch_type else {
+                    return Err(Error::ProtocolViolation("joining fetch requires draft 08".into()));
+                };
+                put_namespace_trackname(b, namespace_trackname)?;
+                b.put_u8(self.subscriber_priority)?;
+                b.put_u8(self.group_order)?;
+                range.start.to_bytes(b, version)?;
+                range.end.to_bytes(b, version)?;
+            }
+            MOQ_VERSION_DRAFT_08..=MOQ_VERSION_DRAFT_16 => {
+                b.put_u8(self.subscriber_priority)?;
+                b.put_u8(self.group_order)?;
+                b.put_varint(self.fetch_type.id())?;
+                match &self.fetch_type {
+                    FetchType::Standalone { namespace_trackname, range } => {
+                        put_namespace_trackname(b, namespace_trackname)?;
+                        range.start.to_bytes(b, version)?;
+                        range.end.to_bytes(b, version)?;
+                    }
+                    FetchType::RelativeJoining { joining_request_id, preceding_groups } => {
+                        b.put_varint(*joining_request_id)?;
+                        b.put_varint(*preceding_groups)?;
+                    }
+                    FetchType::AbsoluteJoining { joining_request_id, start_group } => {
+                        b.put_varint(*joining_request_id)?;
+                        b.put_varint(*start_group)?;
+                    }
+                }
+            }
+            _ => unimplemented!()
+        }
+        self.parameters.to_bytes(b, version)?;
+        Ok(())
+    }
+
+    fn from_body_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
+        let request_id = b.get_varint()?;
+        let (subscriber_priority, group_order, fetch_type) = match version {
+            MOQ_VERSION_DRAFT_07 => {
+                let namespace_trackname = NamespaceTracknameRef::from_bytes(b)?.into_owned();
+                let subscriber_priority = b.get_u8()?;
+                let group_order = b.get_u8()?;
+                let start = Location::from_bytes(b, version)?;
+                let end = Location::from_bytes(b, version)?;
+                (subscriber_priority, group_order, FetchType::Standalone { namespace_trackname, range: FetchRange { start, end } })
+            }
+            MOQ_VERSION_DRAFT_08..=MOQ_VERSION_DRAFT_16 => {
+                let subscriber_priority = b.get_u8()?;
+                let group_order = b.get_u8()?;
+                let ty = b.get_varint()?;
+                let fetch_type = match ty {
+                    STANDALONE_FETCH_TYPE_ID => {
+                        let namespace_trackname = NamespaceTracknameRef::from_bytes(b)?.into_owned();
+                        let start = Location::from_bytes(b, version)?;
+                        let end = Location::from_bytes(b, version)?;
+                        FetchType::Standalone { namespace_trackname, range: FetchRange { start, end } }
+                    }
+                    RELATIVE_JOINING_FETCH_TYPE_ID => FetchType::RelativeJoining {
+                        joining_request_id: b.get_varint()?,
+                        preceding_groups: b.get_varint()?,
+                    },
+                    ABSOLUTE_JOINING_FETCH_TYPE_ID => FetchType::AbsoluteJoining {
+                        joining_request_id: b.get_varint()?,
+                        start_group: b.get_varint()?,
+                    },
+                    _ => return Err(Error::ProtocolViolation(format!("unknown fetch type {ty}"))),
+                };
+                (subscriber_priority, group_order, fetch_type)
+            }
+            _ => unimplemented!()
+        };
+        let parameters = Parameters::from_bytes(b, version)?;
+        Ok(Self {
+            request_id,
+            subscriber_priority,
+            group_order,
+            fetch_type,
+            parameters,
+        })
+    }
+}
@@ -0,0 +1,33 @@
+use octets::{Octets, OctetsMut};
+use crate::{RequestId, Version, FETCH_CANCEL_MESSAGE_ID};
+use crate::control_message::ControlMessage;
+
+#[derive(Debug)]
+pub struct FetchCancelMessage {
+    pub request_id: RequestId,
+}
+
+impl ControlMessage for FetchCancelMessage {
+    const MESSAGE_IDS: &'static [u64] = &[FETCH_CANCEL_MESSAGE_ID];
+
+    fn qlog_type_name(&self) -> &'static str { "fetch_cancel" }
+
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+        })
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
+        b.put_varint(self.request_id)?;
+        Ok(())
+    }
+
+    fn from_body_bytes(b: &mut Octets, _version: Version) -> crate::error::Result<Self> {
+        Ok(Self {
+            request_id: b.get_varint()?,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use octets::{Octets, OctetsMut};
+use crate::{ErrorCode, ReasonPhrase, RequestId, Version, FETCH_ERROR_MESSAGE_ID};
+use crate::control_message::{ControlMessage, RequestErrorMessage};
+
+#[derive(Debug)]
+/// Replaced by REQUEST_ERROR in draft-15
+pub struct FetchErrorMessage {
+    request_id: RequestId,
+    error_code: ErrorCode,
+    error_reason: ReasonPhrase,
+}
+
+impl FetchErrorMessage {
+    pub fn new(request_id: RequestId, error_code: ErrorCode) -> Self {
+        Self {
+            request_id,
+            error_code,
+            error_reason: ReasonPhrase(String::new()),
+        }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    pub fn error_code(&self) -> ErrorCode {
+        self.error_code
+    }
+
+    pub fn error_reason(&self) -> &ReasonPhrase {
+        &self.error_reason
+    }
+}
+
+impl From<FetchErrorMessage> for RequestErrorMessage {
+    fn from(value: FetchErrorMessage) -> Self {
+        let mut cm = RequestErrorMessage::new(value.request_id, value.error_code);
+        cm.error_reason = value.error_reason;
+        cm
+    }
+}
+
+impl ControlMessage for FetchErrorMessage {
+    const MESSAGE_IDS: &'static [u64] = &[FETCH_ERROR_MESSAGE_ID];
+
+    fn qlog_type_name(&self) -> &'static str { "fetch_error" }
+
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+            "error_code": self.error_code,
+            "reason": self.error_reason.0,
+        })
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
+        b.put_varint(self.request_id)?;
+        b.put_varint(self.error_code)?;
+        self.error_reason.to_bytes(b)?;
+        Ok(())
+    }
+
+    fn from_body_bytes(b: &mut Octets, _version: Version) -> crate::error::Result<Self> {
+        Ok(Self {
+            request_id: b.get_varint()?,
+            error_code: b.get_varint()?,
+            error_reason: ReasonPhrase::from_bytes(b)?,
+        })
+    }
+}
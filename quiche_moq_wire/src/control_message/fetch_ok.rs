@@ -0,0 +1,116 @@
+use octets::{Octets, OctetsMut};
+use crate::{FromBytes, Parameters, RequestId, ToBytes, Version, FETCH_OK_MESSAGE_ID, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_15, MOQ_VERSION_DRAFT_16};
+use crate::control_message::{ControlMessage, GroupOrder};
+use crate::error::Error;
+use crate::location::Location;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct FetchOkMessage {
+    request_id: RequestId,
+    /// Not encoded in draft 16, always ascending there
+    group_order: GroupOrder,
+    end_of_track: bool,
+    /// Largest object covered by the fetch response
+    end_location: Location,
+    parameters: Parameters,
+}
+
+impl FetchOkMessage {
+    pub fn new(request_id: RequestId, end_of_track: bool, end_location: Location) -> Self {
+        Self {
+            request_id,
+            group_order: GroupOrder::Ascending,
+            end_of_track,
+            end_location,
+            parameters: Parameters(vec![]),
+        }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    pub fn group_order(&self) -> GroupOrder {
+        self.group_order
+    }
+
+    /// All objects up to the end of the track are covered
+    pub fn end_of_track(&self) -> bool {
+        self.end_of_track
+    }
+
+    pub fn end_location(&self) -> Location {
+        self.end_location
+    }
+
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+}
+
+impl ControlMessage for FetchOkMessage {
+    const MESSAGE_IDS: &'static [u64] = &[FETCH_OK_MESSAGE_ID];
+
+    fn qlog_type_name(&self) -> &'static str { "fetch_ok" }
+
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+            "group_order": match self.group_order {
+                GroupOrder::Ascending => 1,
+                GroupOrder::Descending => 2,
+            },
+            "end_of_track": self.end_of_track as u8,
+            "end_location": crate::qlog::location(&self.end_location),
+        });
+        crate::qlog::set_parameters(&mut msg, crate::qlog::parameters(&self.parameters));
+        msg
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::Result<()> {
+        b.put_varint(self.request_id)?;
+        match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_15 => {
+                b.put_u8(match self.group_order {
+                    GroupOrder::Ascending => 1,
+                    GroupOrder::Descending => 2,
+                })?;
+            }
+            MOQ_VERSION_DRAFT_16 => {}
+            _ => unimplemented!()
+        }
+        b.put_u8(self.end_of_track as u8)?;
+        self.end_location.to_bytes(b, version)?;
+        self.parameters.to_bytes(b, version)?;
+        Ok(())
+    }
+
+    fn from_body_bytes(b: &mut Octets, version: Version) -> crate::Result<Self> {
+        let request_id = b.get_varint()?;
+        let group_order = match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_15 => match b.get_u8()? {
+                1 => GroupOrder::Ascending,
+                2 => GroupOrder::Descending,
+                _ => return Err(Error::ProtocolViolation("invalid group order".into())),
+            },
+            MOQ_VERSION_DRAFT_16 => GroupOrder::Ascending,
+            _ => unimplemented!()
+        };
+        let end_of_track = match b.get_u8()? {
+            0 => false,
+            1 => true,
+            v => return Err(Error::ProtocolViolation(format!("invalid end of track {v}"))),
+        };
+        let end_location = Location::from_bytes(b, version)?;
+        let parameters = Parameters::from_bytes(b, version)?;
+        Ok(Self {
+            request_id,
+            group_order,
+            end_of_track,
+            end_location,
+            parameters,
+        })
+    }
+}
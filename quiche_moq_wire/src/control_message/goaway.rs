@@ -0,0 +1,36 @@
+use octets::{Octets, OctetsMut};
+use crate::{Version, GOAWAY_MESSAGE_ID};
+use crate::control_message::ControlMessage;
+
+#[derive(Debug, PartialEq)]
+/// Sent by the server to ask the client to move to a new session.
+pub struct GoawayMessage {
+    /// URI of the new session; empty to reconnect to the same URI.
+    pub new_session_uri: Vec<u8>,
+}
+
+impl ControlMessage for GoawayMessage {
+    const MESSAGE_IDS: &'static [u64] = &[GOAWAY_MESSAGE_ID];
+
+    fn qlog_type_name(&self) -> &'static str { "goaway" }
+
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "new_session_uri": String::from_utf8_lossy(&self.new_session_uri),
+        })
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
+        b.put_varint(self.new_session_uri.len() as u64)?;
+        b.put_bytes(&self.new_session_uri)?;
+        Ok(())
+    }
+
+    fn from_body_bytes(b: &mut Octets, _version: Version) -> crate::error::Result<Self> {
+        Ok(Self {
+            new_session_uri: b.get_bytes_with_varint_length()?.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,35 @@
+use octets::{Octets, OctetsMut};
+use crate::{RequestId, Version, MAX_REQUEST_ID_MESSAGE_ID};
+use crate::control_message::ControlMessage;
+
+#[derive(Debug, PartialEq)]
+/// Raises the request ID limit granted to the peer.
+/// Called MAX_SUBSCRIBE_ID before draft-11
+pub struct MaxRequestIdMessage {
+    pub request_id: RequestId,
+}
+
+impl ControlMessage for MaxRequestIdMessage {
+    const MESSAGE_IDS: &'static [u64] = &[MAX_REQUEST_ID_MESSAGE_ID];
+
+    fn qlog_type_name(&self) -> &'static str { "max_request_id" }
+
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+        })
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
+        b.put_varint(self.request_id)?;
+        Ok(())
+    }
+
+    fn from_body_bytes(b: &mut Octets, _version: Version) -> crate::error::Result<Self> {
+        Ok(Self {
+            request_id: b.get_varint()?,
+        })
+    }
+}
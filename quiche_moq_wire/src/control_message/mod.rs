@@ -7,9 +7,15 @@ pub use publish_namespace::PublishNamespaceMessage;
 pub use publish_namespace_done::PublishNamespaceDoneMessage;
 pub use request_ok::RequestOkMessage;
 pub use client_setup::ClientSetupMessage;
+pub use fetch::{FetchMessage, FetchRange, FetchType};
+pub use fetch_cancel::FetchCancelMessage;
+pub use fetch_error::FetchErrorMessage;
+pub use fetch_ok::FetchOkMessage;
+pub use goaway::GoawayMessage;
+pub use max_request_id::MaxRequestIdMessage;
 pub use requests_blocked::RequestsBlockedMessage;
 pub use server_setup::ServerSetupMessage;
-pub use subscribe::SubscribeMessage;
+pub use subscribe::{SubscribeMessage, SubscribeMessageRef};
 pub use publish_done::PublishDoneMessage;
 pub use request_error::RequestErrorMessage;
 pub use subscribe_ok::{GroupOrder, SubscribeOkMessage};
@@ -23,6 +29,12 @@ mod publish_namespace;
 mod publish_namespace_done;
 mod request_ok;
 mod client_setup;
+mod fetch;
+mod fetch_cancel;
+mod fetch_error;
+mod fetch_ok;
+mod goaway;
+mod max_request_id;
 pub(crate) mod header;
 mod requests_blocked;
 mod server_setup;
@@ -70,6 +82,14 @@ macro_rules! control_message_enum {
                     $( ControlMessageEnum::$variant(m) => m.qlog_type_name(), )*
                 }
             }
+
+            /// Message with all fields as defined by the qlog MoQ events draft
+            #[cfg(feature = "qlog")]
+            pub fn to_qlog(&self) -> serde_json::Value {
+                match self {
+                    $( ControlMessageEnum::$variant(m) => m.to_qlog(), )*
+                }
+            }
         }
     };
 }
@@ -80,6 +100,8 @@ control_message_enum! {
     ServerSetup(ServerSetupMessage),
     SubscribeOk(SubscribeOkMessage),
     RequestsBlocked(RequestsBlockedMessage),
+    MaxRequestId(MaxRequestIdMessage),
+    Goaway(GoawayMessage),
     PublishDone(PublishDoneMessage),
     RequestError(RequestErrorMessage),
     PublishNamespace(PublishNamespaceMessage),
@@ -88,6 +110,10 @@ control_message_enum! {
     UnsubscribeNamespace(UnsubscribeNamespaceMessage),
     TrackStatus(TrackStatusMessage),
     PublishOk(PublishOkMessage),
+    Fetch(FetchMessage),
+    FetchOk(FetchOkMessage),
+    FetchCancel(FetchCancelMessage),
+    FetchError(FetchErrorMessage),
 }
 
 impl ControlMessageEnum {
@@ -157,6 +183,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decode_subscribe_borrowed() {
+        let msg = [0x03, 0x1d, 0x1, 0x1, 0x1, 0xf, 0x69, 0x6e, 0x6a, 0x75, 0x72, 0x65, 0x64, 0x2d, 0x77, 0x61, 0x6c, 0x6c, 0x61, 0x62, 0x79, 0x5, 0x76, 0x69, 0x64, 0x65, 0x6f, 0x1, 0x2, 0x1, 0x0];
+        let mut o = Octets::with_slice(&msg);
+        let sm_ref = SubscribeMessageRef::from_bytes(&mut o, MOQ_VERSION_DRAFT_07).unwrap();
+        assert_eq!(o.cap(), 0);
+        assert_eq!(sm_ref.namespace_trackname.to_string(), "injured.2dwallaby--video");
+        let sm = SubscribeMessage::from_bytes(&mut Octets::with_slice(&msg), MOQ_VERSION_DRAFT_07).unwrap();
+        assert_eq!(sm_ref.into_owned(), sm);
+    }
+
     #[test]
     fn recode_subscribe_draft7() {
         let cm1 = SubscribeMessage {
@@ -181,6 +218,30 @@ mod tests {
         assert_eq!(cm1, cm2);
     }
 
+    #[test]
+    fn recode_subscribe_absolute_range() {
+        use crate::{MOQ_VERSION_DRAFT_14, location::Location};
+        let cm1 = SubscribeMessage {
+            request_id: 2,
+            track_alias: None,
+            namespace_trackname: "namespace--name".parse().unwrap(),
+            subscriber_priority: 1,
+            group_order: 2,
+            forward: Some(1),
+            filter_type: FilterType::AbsoluteRange,
+            start_location: Some(Location { group: 10, object: 3 }),
+            end_group: Some(12),
+            parameters: Parameters(vec![]),
+        };
+        let mut b = [0u8; 100];
+        let mut o = OctetsMut::with_slice(&mut b);
+        cm1.to_bytes(&mut o, MOQ_VERSION_DRAFT_14).unwrap();
+        let len = o.off();
+        let mut o = Octets::with_slice(&b[..len]);
+        let cm2 = SubscribeMessage::from_bytes(&mut o, MOQ_VERSION_DRAFT_14).unwrap();
+        assert_eq!(cm1, cm2);
+    }
+
     #[test]
     fn decode_subscribe_ok_draft7() {
         let b = [0x4, 0x5, 0x0, 0x0, 0x2, 0x0, 0x0];
@@ -238,6 +299,155 @@ mod tests {
         let som2 = SubscribeOkMessage::from_bytes(&mut o, MOQ_VERSION_DRAFT_16).unwrap();
         assert_eq!(som, som2);
     }
+
+    #[test]
+    fn recode_fetch() {
+        use crate::{location::Location, MOQ_VERSION_DRAFT_14};
+        let standalone = || FetchType::Standalone {
+            namespace_trackname: "namespace--track".parse().unwrap(),
+            range: FetchRange { start: Location { group: 1, object: 0 }, end: Location { group: 3, object: 0 } },
+        };
+        let cases = [
+            (MOQ_VERSION_DRAFT_07, standalone()),
+            (MOQ_VERSION_DRAFT_14, standalone()),
+            (MOQ_VERSION_DRAFT_14, FetchType::RelativeJoining { joining_request_id: 2, preceding_groups: 1 }),
+            (MOQ_VERSION_DRAFT_16, FetchType::AbsoluteJoining { joining_request_id: 2, start_group: 7 }),
+        ];
+        for (version, fetch_type) in cases {
+            let cm1 = FetchMessage {
+                request_id: 4,
+                subscriber_priority: 1,
+                group_order: 1,
+                fetch_type,
+                parameters: Parameters(vec![]),
+            };
+            let mut b = [0u8; 100];
+            let mut o = OctetsMut::with_slice(&mut b);
+            cm1.to_bytes(&mut o, version).unwrap();
+            let len = o.off();
+            let mut o = Octets::with_slice(&b[..len]);
+            let ControlMessageEnum::Fetch(cm2) = ControlMessageEnum::from_bytes(&mut o, version).unwrap() else { panic!() };
+            assert_eq!(cm1, cm2);
+        }
+    }
+
+    #[test]
+    fn recode_fetch_ok() {
+        use crate::{location::Location, MOQ_VERSION_DRAFT_14};
+        for version in [MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16] {
+            let cm1 = FetchOkMessage::new(4, false, Location { group: 3, object: 9 });
+            let mut b = [0u8; 100];
+            let mut o = OctetsMut::with_slice(&mut b);
+            cm1.to_bytes(&mut o, version).unwrap();
+            let len = o.off();
+            let mut o = Octets::with_slice(&b[..len]);
+            let ControlMessageEnum::FetchOk(cm2) = ControlMessageEnum::from_bytes(&mut o, version).unwrap() else { panic!() };
+            assert_eq!(cm1, cm2);
+        }
+    }
+
+    #[test]
+    fn fetch_range_contains() {
+        use crate::location::Location;
+        let loc = |group, object| Location { group, object };
+        let whole_groups = FetchRange { start: loc(1, 2), end: loc(3, 0) };
+        assert!(!whole_groups.contains(loc(1, 1)));
+        assert!(whole_groups.contains(loc(1, 2)));
+        assert!(whole_groups.contains(loc(3, 100)));
+        assert!(!whole_groups.contains(loc(4, 0)));
+        let partial_group = FetchRange { start: loc(1, 0), end: loc(3, 5) };
+        assert!(partial_group.contains(loc(3, 4)));
+        assert!(!partial_group.contains(loc(3, 5)));
+    }
+
+    #[cfg(feature = "qlog")]
+    #[test]
+    fn subscribe_to_qlog() {
+        use crate::location::Location;
+        let cm = ControlMessageEnum::Subscribe(SubscribeMessage {
+            request_id: 2,
+            track_alias: None,
+            namespace_trackname: "n1-n2--t1".parse().unwrap(),
+            subscriber_priority: 1,
+            group_order: 2,
+            forward: Some(1),
+            filter_type: FilterType::AbsoluteStart,
+            start_location: Some(Location { group: 3, object: 4 }),
+            end_group: None,
+            parameters: Parameters(vec![crate::Parameter::new_varint(2, 5000)]),
+        });
+        assert_eq!(cm.to_qlog(), serde_json::json!({
+            "type": "subscribe",
+            "request_id": 2,
+            "track_namespace": [{ "length": 2, "value": "n1" }, { "length": 2, "value": "n2" }],
+            "track_name": { "length": 2, "value": "t1" },
+            "subscriber_priority": 1,
+            "group_order": 2,
+            "forward": 1,
+            "filter_type": "absolute_start",
+            "start_location": { "group": 3, "object": 4 },
+            "number_of_parameters": 1,
+            "parameters": [{ "name": "unknown", "name_bytes": 2, "value": 5000 }],
+        }));
+    }
+
+    #[test]
+    fn recode_unsubscribe_namespace() {
+        use crate::MOQ_VERSION_DRAFT_14;
+        let cases = [
+            (MOQ_VERSION_DRAFT_07, UnsubscribeNamespaceMessage::new(None, Some("n1-n2".parse().unwrap()))),
+            (MOQ_VERSION_DRAFT_14, UnsubscribeNamespaceMessage::new(None, Some("n1".parse().unwrap()))),
+            (MOQ_VERSION_DRAFT_16, UnsubscribeNamespaceMessage::new(Some(6), None)),
+        ];
+        for (version, cm1) in cases {
+            let mut b = [0u8; 32];
+            let mut o = OctetsMut::with_slice(&mut b);
+            cm1.to_bytes(&mut o, version).unwrap();
+            let len = o.off();
+            let mut o = Octets::with_slice(&b[..len]);
+            let ControlMessageEnum::UnsubscribeNamespace(cm2) = ControlMessageEnum::from_bytes(&mut o, version).unwrap() else { panic!() };
+            assert_eq!(cm1, cm2);
+        }
+    }
+
+    #[cfg(feature = "qlog")]
+    #[test]
+    fn unsubscribe_namespace_to_qlog() {
+        let cm = ControlMessageEnum::UnsubscribeNamespace(UnsubscribeNamespaceMessage::new(None, Some("n1-n2".parse().unwrap())));
+        assert_eq!(cm.to_qlog(), serde_json::json!({
+            "type": "unsubscribe_namespace",
+            "track_namespace_prefix": [{ "length": 2, "value": "n1" }, { "length": 2, "value": "n2" }],
+        }));
+    }
+
+    #[test]
+    fn recode_max_request_id() {
+        use crate::MOQ_VERSION_DRAFT_14;
+        let mut b = [0u8; 16];
+        let mut o = OctetsMut::with_slice(&mut b);
+        ControlMessageEnum::MaxRequestId(MaxRequestIdMessage { request_id: 200 })
+            .to_bytes(&mut o, MOQ_VERSION_DRAFT_14)
+            .unwrap();
+        let len = o.off();
+        assert_eq!(&b[..len], &[0x15, 0x00, 0x02, 0x40, 0xc8]);
+        let mut o = Octets::with_slice(&b[..len]);
+        let ControlMessageEnum::MaxRequestId(cm) = ControlMessageEnum::from_bytes(&mut o, MOQ_VERSION_DRAFT_14).unwrap() else { panic!() };
+        assert_eq!(cm, MaxRequestIdMessage { request_id: 200 });
+    }
+
+    #[test]
+    fn recode_goaway() {
+        use crate::MOQ_VERSION_DRAFT_14;
+        let cm1 = GoawayMessage { new_session_uri: b"https://b.example".to_vec() };
+        let mut b = [0u8; 32];
+        let mut o = OctetsMut::with_slice(&mut b);
+        cm1.to_bytes(&mut o, MOQ_VERSION_DRAFT_14).unwrap();
+        let len = o.off();
+        assert_eq!(&b[..4], &[0x10, 0x00, 0x12, 0x11]);
+        let mut o = Octets::with_slice(&b[..len]);
+        let ControlMessageEnum::Goaway(cm2) = ControlMessageEnum::from_bytes(&mut o, MOQ_VERSION_DRAFT_14).unwrap() else { panic!() };
+        assert_eq!(cm1, cm2);
+    }
 }
 
 pub(crate) trait ControlMessage: Debug + Sized {
@@ -247,6 +457,10 @@ pub(crate) trait ControlMessage: Debug + Sized {
 
     fn qlog_type_name(&self) -> &'static str;
 
+    /// Message with all fields as defined by the qlog MoQ events draft
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value;
+
     /// Returns the message ID to use for encoding based on the version.
     /// Default implementation returns the first ID.
     fn message_id_for_version(_version: Version) -> u64 {
@@ -32,6 +32,17 @@ impl ControlMessage for PublishDoneMessage {
 
     fn qlog_type_name(&self) -> &'static str { "publish_done" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+            "status_code": self.status_code,
+            "stream_count": self.stream_count,
+            "reason": self.error_reason.0,
+        })
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
         b.put_varint(self.request_id)?;
         b.put_varint(self.status_code)?;
@@ -27,6 +27,10 @@ impl PublishNamespaceMessage {
     /// Some for DRAFT 11 to 13
     pub fn request_id(&self) -> Option<RequestId> { self.request_id }
 
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
     pub fn track_namespace(&self) -> &Namespace {
         &self.track_namespace
     }
@@ -41,6 +45,19 @@ impl ControlMessage for PublishNamespaceMessage {
 
     fn qlog_type_name(&self) -> &'static str { "publish_namespace" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "track_namespace": crate::qlog::tuple(&self.track_namespace.0),
+        });
+        if let Some(request_id) = self.request_id {
+            msg["request_id"] = request_id.into();
+        }
+        crate::qlog::set_parameters(&mut msg, crate::qlog::parameters(&self.parameters));
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> Result<()> {
         match version {
             MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => {},
@@ -28,6 +28,18 @@ impl ControlMessage for PublishNamespaceDoneMessage {
 
     fn qlog_type_name(&self) -> &'static str { "publish_namespace_done" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({ "type": self.qlog_type_name() });
+        if let Some(request_id) = self.request_id {
+            msg["request_id"] = request_id.into();
+        }
+        if let Some(namespace) = &self.namespace {
+            msg["track_namespace"] = crate::qlog::tuple(&namespace.0);
+        }
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
         match version {
             MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_15 => {
@@ -31,6 +31,16 @@ impl ControlMessage for PublishOkMessage {
 
     fn qlog_type_name(&self) -> &'static str { "publish_ok" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+        });
+        crate::qlog::set_parameters(&mut msg, crate::qlog::parameters(&self.parameters));
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::Result<()> {
         b.put_u16(self.request_id as u16)?;
         self.parameters.to_bytes(b, version)?;
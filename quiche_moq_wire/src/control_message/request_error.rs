@@ -1,5 +1,5 @@
 use octets::{Octets, OctetsMut};
-use crate::{ReasonPhrase, RequestId, TrackAlias, Version, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_13, REQUEST_ERROR_MESSAGE_ID};
+use crate::{ReasonPhrase, RequestId, TrackAlias, Version, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_16, REQUEST_ERROR_MESSAGE_ID};
 use crate::control_message::{ControlMessage, SubscribeMessage};
 
 #[allow(unused)]
@@ -10,7 +10,7 @@ pub struct RequestErrorMessage {
     /// formerly known as subscribe ID
     request_id: RequestId,
     error_code: u64,
-    error_reason: ReasonPhrase,
+    pub(crate) error_reason: ReasonPhrase,
     /// only present from draft 07 to draft 11
     track_alias: Option<TrackAlias>
 }
@@ -23,6 +23,15 @@ impl RequestErrorMessage {
     pub fn error_reason(&self) -> &ReasonPhrase {
         &self.error_reason
     }
+    /// For requests other than SUBSCRIBE, which carry no track alias
+    pub fn new(request_id: RequestId, error_code: u64) -> Self {
+        Self {
+            request_id,
+            error_code,
+            error_reason: ReasonPhrase("".to_string()),
+            track_alias: None,
+        }
+    }
     pub fn from(sm: &SubscribeMessage, error_code: u64) -> Self {
         Self {
             request_id: sm.request_id,
@@ -31,6 +40,10 @@ impl RequestErrorMessage {
             track_alias: sm.track_alias,
         }
     }
+    pub fn with_reason(mut self, reason: &str) -> Self {
+        self.error_reason = ReasonPhrase(reason.to_string());
+        self
+    }
 }
 
 impl ControlMessage for RequestErrorMessage {
@@ -38,15 +51,30 @@ impl ControlMessage for RequestErrorMessage {
 
     fn qlog_type_name(&self) -> &'static str { "request_error" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+            "error_code": self.error_code,
+            "reason": self.error_reason.0,
+        });
+        if let Some(track_alias) = self.track_alias {
+            msg["track_alias"] = track_alias.into();
+        }
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
         b.put_varint(self.request_id)?;
         b.put_varint(self.error_code)?;
         self.error_reason.to_bytes(b)?;
         match version {
             MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_11 => {
-                b.put_varint(self.track_alias.unwrap())?;
+                // requests other than SUBSCRIBE have no track alias
+                b.put_varint(self.track_alias.unwrap_or(0))?;
             },
-            MOQ_VERSION_DRAFT_12..=MOQ_VERSION_DRAFT_13 => {},
+            MOQ_VERSION_DRAFT_12..=MOQ_VERSION_DRAFT_16 => {},
             _ => unimplemented!()
         };
         Ok(())
@@ -58,7 +86,7 @@ impl ControlMessage for RequestErrorMessage {
         let error_reason = ReasonPhrase::from_bytes(b)?;
         let track_alias = match version {
             MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_11 => Some(b.get_varint()?),
-            MOQ_VERSION_DRAFT_12..=MOQ_VERSION_DRAFT_13 => None,
+            MOQ_VERSION_DRAFT_12..=MOQ_VERSION_DRAFT_16 => None,
             _ => unimplemented!()
         };
         Ok(Self {
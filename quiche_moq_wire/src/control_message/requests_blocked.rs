@@ -14,6 +14,14 @@ impl ControlMessage for RequestsBlockedMessage {
 
     fn qlog_type_name(&self) -> &'static str { "requests_blocked" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "maximum_request_id": self.maximum_request_id,
+        })
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
         b.put_varint(self.maximum_request_id)?;
         Ok(())
@@ -1,5 +1,5 @@
 use crate::bytes::{FromBytes, ToBytes};
-use crate::{MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16, SERVER_SETUP_MESSAGE_ID, SERVER_SETUP_MESSAGE_ID_VERSION_UNTIL_10, SetupParameters, Version};
+use crate::{MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16, SERVER_SETUP_MESSAGE_ID, SERVER_SETUP_MESSAGE_ID_VERSION_UNTIL_10, SetupParameters, Version, version_negotiated_by_alpn};
 use octets::{Octets, OctetsMut};
 use crate::control_message::ControlMessage;
 
@@ -34,14 +34,31 @@ impl ControlMessage for ServerSetupMessage {
 
     fn qlog_type_name(&self) -> &'static str { "server_setup" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "selected_version": self.selected_version,
+        });
+        crate::qlog::set_parameters(&mut msg, crate::qlog::setup_parameters(&self.setup_parameters));
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
-        b.put_varint(self.selected_version)?;
+        // since draft 15 the version is negotiated by ALPN
+        if !version_negotiated_by_alpn(version) {
+            b.put_varint(self.selected_version)?;
+        }
         SetupParameters::to_bytes(&self.setup_parameters, b, version)?;
         Ok(())
     }
 
     fn from_body_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
-        let selected_version = b.get_varint().unwrap();
+        let selected_version = if version_negotiated_by_alpn(version) {
+            version
+        } else {
+            b.get_varint()?
+        };
         let setup_parameters = SetupParameters::from_bytes(b, version)?;
         Ok(Self {
             selected_version,
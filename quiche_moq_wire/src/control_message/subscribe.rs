@@ -1,10 +1,10 @@
 use crate::bytes::{FromBytes, ToBytes};
 use crate::error::Error;
-use crate::{Namespace, NamespaceTrackname, Parameters, RequestId, TrackAlias, Version, ABSOLUTE_RANGE_FILTER_ID, ABSOLUTE_START_FILTER_ID, LARGEST_OBJECT_FILTER_ID, MAX_FULL_TRACK_NAME_LEN, MAX_TRACK_NAMESPACE_TUPLE_LENGTH, MIN_TRACK_NAMESPACE_TUPLE_LENGTH, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_16, NEXT_GROUP_START_FILTER_ID, SUBSCRIBE_MESSAGE_ID};
+use crate::{Namespace, NamespaceTrackname, NamespaceTracknameRef, Parameters, ParametersRef, RequestId, TrackAlias, Version, ABSOLUTE_RANGE_FILTER_ID, ABSOLUTE_START_FILTER_ID, LARGEST_OBJECT_FILTER_ID, MAX_FULL_TRACK_NAME_LEN, MAX_TRACK_NAMESPACE_TUPLE_LENGTH, MIN_TRACK_NAMESPACE_TUPLE_LENGTH, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_16, NEXT_GROUP_START_FILTER_ID, SUBSCRIBE_MESSAGE_ID};
 use octets::{Octets, OctetsMut};
 use crate::control_message::ControlMessage;
+use crate::control_message::header::ControlMessageHeader;
 use crate::location::Location;
-use crate::tuple::Tuple;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct SubscribeMessage {
@@ -54,6 +54,33 @@ impl ControlMessage for SubscribeMessage {
 
     fn qlog_type_name(&self) -> &'static str { "subscribe" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+            "track_namespace": crate::qlog::tuple(&self.track_namespace().0),
+            "track_name": crate::qlog::byte_string(self.track_name()),
+            "subscriber_priority": self.subscriber_priority,
+            "group_order": self.group_order,
+            "filter_type": self.filter_type.qlog_name(),
+        });
+        if let Some(track_alias) = self.track_alias {
+            msg["track_alias"] = track_alias.into();
+        }
+        if let Some(forward) = self.forward {
+            msg["forward"] = forward.into();
+        }
+        if let Some(start_location) = &self.start_location {
+            msg["start_location"] = crate::qlog::location(start_location);
+        }
+        if let Some(end_group) = self.end_group {
+            msg["end_group"] = end_group.into();
+        }
+        crate::qlog::set_parameters(&mut msg, crate::qlog::parameters(&self.parameters));
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
         self.validate()?;
         b.put_varint(self.request_id)?;
@@ -88,15 +115,45 @@ impl ControlMessage for SubscribeMessage {
     }
 
     fn from_body_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
+        Ok(SubscribeMessageRef::from_body_bytes(b, version)?.into_owned())
+    }
+}
+
+/// Borrowed [`SubscribeMessage`], the full track name and parameters point into the decoded buffer
+#[derive(Debug, Clone, Copy)]
+pub struct SubscribeMessageRef<'a> {
+    pub request_id: RequestId,
+    /// `Some` from draft 07 to draft 11
+    pub track_alias: Option<TrackAlias>,
+    pub namespace_trackname: NamespaceTracknameRef<'a>,
+    pub subscriber_priority: u8,
+    pub group_order: u8,
+    /// `Some` from draft 11 to draft 13
+    pub forward: Option<u8>,
+    pub filter_type: FilterType,
+    pub start_location: Option<Location>,
+    pub end_group: Option<u64>,
+    pub parameters: ParametersRef<'a>,
+}
+
+impl<'a> SubscribeMessageRef<'a> {
+    /// Including the control message header
+    pub fn from_bytes(b: &mut Octets<'a>, version: Version) -> crate::error::Result<Self> {
+        let header = ControlMessageHeader::from_bytes(b, version)?;
+        if header.ty() != SUBSCRIBE_MESSAGE_ID {
+            return Err(Error::ProtocolViolation(format!("expected subscribe message, got id {}", header.ty())));
+        }
+        Self::from_body_bytes(b, version)
+    }
+
+    fn from_body_bytes(b: &mut Octets<'a>, version: Version) -> crate::error::Result<Self> {
         let request_id = b.get_varint()?;
         let track_alias = match version {
             MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_11 => Some(b.get_varint()?),
             MOQ_VERSION_DRAFT_12..=MOQ_VERSION_DRAFT_16 => None,
             _ => unimplemented!()
         };
-        let track_namespace = Tuple::from_bytes(b, version)?.0;
-        let track_name_len = b.get_varint()?;
-        let track_name = b.get_bytes(track_name_len as usize)?.to_vec();
+        let namespace_trackname = NamespaceTracknameRef::from_bytes(b)?;
         let subscriber_priority = b.get_u8()?;
         let group_order = b.get_u8()?;
         let forward = match version {
@@ -115,11 +172,11 @@ impl ControlMessage for SubscribeMessage {
         } else {
             None
         };
-        let parameters = Parameters::from_bytes(b, version)?;
+        let parameters = ParametersRef::from_bytes(b, version)?;
         Ok(Self {
             request_id,
             track_alias,
-            namespace_trackname: NamespaceTrackname::new(track_namespace, track_name),
+            namespace_trackname,
             subscriber_priority,
             group_order,
             forward,
@@ -129,9 +186,30 @@ impl ControlMessage for SubscribeMessage {
             parameters,
         })
     }
+
+    pub fn into_owned(self) -> SubscribeMessage {
+        SubscribeMessage {
+            request_id: self.request_id,
+            track_alias: self.track_alias,
+            namespace_trackname: self.namespace_trackname.into_owned(),
+            subscriber_priority: self.subscriber_priority,
+            group_order: self.group_order,
+            forward: self.forward,
+            filter_type: self.filter_type,
+            start_location: self.start_location,
+            end_group: self.end_group,
+            parameters: self.parameters.into_owned(),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+impl From<SubscribeMessageRef<'_>> for SubscribeMessage {
+    fn from(value: SubscribeMessageRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum FilterType {
     LargestObject,
     NextGroupStart,
@@ -149,6 +227,16 @@ impl FilterType {
         }
     }
 
+    #[cfg(feature = "qlog")]
+    pub(crate) fn qlog_name(&self) -> &'static str {
+        match self {
+            FilterType::LargestObject => "largest_object",
+            FilterType::NextGroupStart => "next_group_start",
+            FilterType::AbsoluteStart => "absolute_start",
+            FilterType::AbsoluteRange => "absolute_range",
+        }
+    }
+
     pub fn has_end_group(&self) -> bool {
         match self {
             FilterType::LargestObject => false,
@@ -164,7 +252,8 @@ impl ToBytes for FilterType {
         match self {
             FilterType::LargestObject => b.put_varint(LARGEST_OBJECT_FILTER_ID)?,
             FilterType::NextGroupStart => b.put_varint(NEXT_GROUP_START_FILTER_ID)?,
-            _ => unimplemented!()
+            FilterType::AbsoluteStart => b.put_varint(ABSOLUTE_START_FILTER_ID)?,
+            FilterType::AbsoluteRange => b.put_varint(ABSOLUTE_RANGE_FILTER_ID)?,
         };
         Ok(())
     }
@@ -178,7 +267,7 @@ impl FromBytes for FilterType {
             LARGEST_OBJECT_FILTER_ID => Self::LargestObject,
             ABSOLUTE_START_FILTER_ID => Self::AbsoluteStart,
             ABSOLUTE_RANGE_FILTER_ID => Self::AbsoluteRange,
-            _ => unimplemented!()
+            _ => return Err(Error::ProtocolViolation(format!("unknown filter type {ty}"))),
         })
     }
 }
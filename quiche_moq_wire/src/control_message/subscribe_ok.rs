@@ -1,7 +1,7 @@
 use crate::bytes::{FromBytes, ToBytes};
 use crate::{
     DEFAULT_PUBLISHER_GROUP_ORDER_EXTENSION_ID, EXPIRES_PARAMETER_ID, LARGEST_OBJECT_PARAMETER_ID,
-    Parameter, Parameters, RequestId, TrackAlias, Version,
+    MAX_CACHE_DURATION_PARAMETER_ID, Parameter, Parameters, RequestId, TrackAlias, Version,
     MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_12, MOQ_VERSION_DRAFT_15,
     MOQ_VERSION_DRAFT_16, SUBSCRIBE_OK_MESSAGE_ID,
 };
@@ -68,6 +68,12 @@ impl SubscribeOkMessage {
     pub fn largest_location(&self) -> Option<Location> {
         self.parameters.largest_location
     }
+
+    /// Maximum time in milliseconds the publisher wants objects of this track cached.
+    /// `None` if absent or for draft 7 to draft 10
+    pub fn max_cache_duration(&self) -> Option<u64> {
+        self.parameters.extra_parameters.get_varint(MAX_CACHE_DURATION_PARAMETER_ID)
+    }
 }
 
 impl ControlMessage for SubscribeOkMessage {
@@ -75,6 +81,34 @@ impl ControlMessage for SubscribeOkMessage {
 
     fn qlog_type_name(&self) -> &'static str { "subscribe_ok" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": self.qlog_type_name(),
+            "request_id": self.request_id,
+        });
+        if let Some(track_alias) = self.track_alias {
+            msg["track_alias"] = track_alias.into();
+        }
+        let mut params = vec![];
+        if self.expires() != 0 {
+            params.push(serde_json::json!({ "name": "expires", "value": self.expires() }));
+        }
+        params.push(serde_json::json!({
+            "name": "group_order",
+            "value": match self.group_order() { GroupOrder::Ascending => 1u64, GroupOrder::Descending => 2u64 },
+        }));
+        if let Some(loc) = self.largest_location() {
+            params.push(serde_json::json!({
+                "name": "largest_object",
+                "value": crate::qlog::location(&loc),
+            }));
+        }
+        params.extend(crate::qlog::parameters(&self.parameters.extra_parameters));
+        crate::qlog::set_parameters(&mut msg, params);
+        msg
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
         b.put_varint(self.request_id)?;
         match version {
@@ -18,6 +18,17 @@ impl ControlMessage for TrackStatusMessage {
 
     fn qlog_type_name(&self) -> &'static str { "track_status" }
 
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.qlog_type_name(),
+            "track_namespace": crate::qlog::tuple(&self.track_namespace.0),
+            "track_name": crate::qlog::byte_string(&self.track_name),
+            "status_code": self.status_code,
+            "largest_location": { "group": self.last_group_id, "object": self.last_object_id },
+        })
+    }
+
     fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
         self.track_namespace.to_bytes(b, version)?;
         b.put_varint(self.track_name.len() as u64)?;
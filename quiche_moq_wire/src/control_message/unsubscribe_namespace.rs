@@ -1,10 +1,25 @@
 use octets::{Octets, OctetsMut};
-use crate::{Version, UNSUBSCRIBE_NAMESPACE_MESSAGE_ID};
+use crate::bytes::{FromBytes, ToBytes};
+use crate::{Namespace, RequestId, Version, UNSUBSCRIBE_NAMESPACE_MESSAGE_ID};
+use crate::{MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_15, MOQ_VERSION_DRAFT_16};
 use crate::control_message::ControlMessage;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+/// Called UNSUBSCRIBE_ANNOUNCES before draft-14
 pub struct UnsubscribeNamespaceMessage {
+    /// Present in draft 16+
+    request_id: Option<RequestId>,
+    /// Present in drafts 07–15
+    track_namespace_prefix: Option<Namespace>,
+}
+
+impl UnsubscribeNamespaceMessage {
+    pub fn new(request_id: Option<RequestId>, track_namespace_prefix: Option<Namespace>) -> Self {
+        Self { request_id, track_namespace_prefix }
+    }
 
+    pub fn request_id(&self) -> Option<RequestId> { self.request_id }
+    pub fn track_namespace_prefix(&self) -> Option<&Namespace> { self.track_namespace_prefix.as_ref() }
 }
 
 impl ControlMessage for UnsubscribeNamespaceMessage {
@@ -12,11 +27,42 @@ impl ControlMessage for UnsubscribeNamespaceMessage {
 
     fn qlog_type_name(&self) -> &'static str { "unsubscribe_namespace" }
 
-    fn to_body_bytes(&self, _b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
-        todo!()
+    #[cfg(feature = "qlog")]
+    fn to_qlog(&self) -> serde_json::Value {
+        let mut msg = serde_json::json!({ "type": self.qlog_type_name() });
+        if let Some(request_id) = self.request_id {
+            msg["request_id"] = request_id.into();
+        }
+        if let Some(prefix) = &self.track_namespace_prefix {
+            msg["track_namespace_prefix"] = crate::qlog::tuple(&prefix.0);
+        }
+        msg
+    }
+
+    fn to_body_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
+        match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_15 => {
+                self.track_namespace_prefix.as_ref().unwrap().to_bytes(b, version)?;
+            }
+            MOQ_VERSION_DRAFT_16.. => {
+                b.put_varint(self.request_id.unwrap())?;
+            }
+            _ => unimplemented!()
+        }
+        Ok(())
     }
 
-    fn from_body_bytes(_b: &mut Octets, _version: Version) -> crate::error::Result<Self> {
-        todo!()
+    fn from_body_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
+        match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_15 => {
+                let prefix = Namespace::from_bytes(b, version)?;
+                Ok(Self { request_id: None, track_namespace_prefix: Some(prefix) })
+            }
+            MOQ_VERSION_DRAFT_16.. => {
+                let request_id = b.get_varint()?;
+                Ok(Self { request_id: Some(request_id), track_namespace_prefix: None })
+            }
+            _ => unimplemented!()
+        }
     }
 }
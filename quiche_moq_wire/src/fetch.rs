@@ -0,0 +1,212 @@
+use crate::bytes::{FromBytes, ToBytes};
+use crate::error::Error::ProtocolViolation;
+use crate::key_value_pairs::{KeyValuePairs, KeyValuePairsRef};
+use crate::location::Location;
+use crate::{RequestId, Version, FETCH_UNI_STREAM_TYPE_ID, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16};
+use octets::{Octets, OctetsMut};
+
+/// Header of the stream carrying the objects of one fetch response
+#[derive(Debug, Eq, PartialEq)]
+pub struct FetchHeader {
+    request_id: RequestId,
+}
+
+impl FetchHeader {
+    pub fn new(request_id: RequestId) -> Self {
+        Self { request_id }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+}
+
+impl FromBytes for FetchHeader {
+    fn from_bytes(b: &mut Octets, _version: Version) -> crate::error::Result<Self> {
+        let ty = b.get_varint()?;
+        if ty != FETCH_UNI_STREAM_TYPE_ID {
+            return Err(ProtocolViolation(format!("unexpected fetch stream type {ty:#x}")));
+        }
+        Ok(Self {
+            request_id: b.get_varint()?,
+        })
+    }
+}
+
+impl ToBytes for FetchHeader {
+    fn to_bytes(&self, b: &mut OctetsMut, _version: Version) -> crate::error::Result<()> {
+        b.put_varint(FETCH_UNI_STREAM_TYPE_ID)?;
+        b.put_varint(self.request_id)?;
+        Ok(())
+    }
+}
+
+/// Object on a fetch stream, unlike on subgroup streams each object carries its full location
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FetchObjectHeader {
+    group_id: u64,
+    subgroup_id: u64,
+    object_id: u64,
+    publisher_priority: u8,
+    /// Always empty for draft 7 to draft 10
+    extension_headers: KeyValuePairs,
+    payload_len: usize,
+    status: Option<u64>,
+}
+
+impl FetchObjectHeader {
+    pub fn new(location: Location, subgroup_id: u64, payload_len: usize, extension_headers: KeyValuePairs) -> Self {
+        Self {
+            group_id: location.group,
+            subgroup_id,
+            object_id: location.object,
+            publisher_priority: 0,
+            extension_headers,
+            payload_len,
+            status: None,
+        }
+    }
+
+    /// Object without payload signaling `status`, e.g. [`crate::OBJECT_STATUS_END_OF_GROUP`]
+    pub fn new_status(location: Location, subgroup_id: u64, status: u64, extension_headers: KeyValuePairs) -> Self {
+        Self {
+            group_id: location.group,
+            subgroup_id,
+            object_id: location.object,
+            publisher_priority: 0,
+            extension_headers,
+            payload_len: 0,
+            status: Some(status),
+        }
+    }
+
+    fn extensions_present(version: Version) -> bool {
+        match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => false,
+            MOQ_VERSION_DRAFT_11..=MOQ_VERSION_DRAFT_16 => true,
+            _ => unimplemented!()
+        }
+    }
+
+    pub fn location(&self) -> Location {
+        Location { group: self.group_id, object: self.object_id }
+    }
+
+    pub fn group_id(&self) -> u64 {
+        self.group_id
+    }
+
+    pub fn subgroup_id(&self) -> u64 {
+        self.subgroup_id
+    }
+
+    pub fn object_id(&self) -> u64 {
+        self.object_id
+    }
+
+    pub fn publisher_priority(&self) -> u8 {
+        self.publisher_priority
+    }
+
+    pub fn extension_headers(&self) -> &KeyValuePairs {
+        &self.extension_headers
+    }
+
+    pub fn payload_len(&self) -> usize {
+        self.payload_len
+    }
+
+    pub fn status(&self) -> Option<u64> {
+        self.status
+    }
+
+    /// Returns extension headers formatted as `[* MOQTExtensionHeader]` per the qlog draft.
+    #[cfg(feature = "qlog")]
+    pub fn extension_headers_to_qlog(&self) -> Vec<serde_json::Value> {
+        crate::qlog::extension_headers(&self.extension_headers)
+    }
+}
+
+impl FromBytes for FetchObjectHeader {
+    fn from_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
+        let group_id = b.get_varint()?;
+        let subgroup_id = b.get_varint()?;
+        let object_id = b.get_varint()?;
+        let publisher_priority = b.get_u8()?;
+        let extension_headers = if Self::extensions_present(version) {
+            let ext_hdr_len = b.get_varint()? as usize;
+            KeyValuePairsRef::from_bytes_with_byte_len(b, version, ext_hdr_len)?.into_owned()
+        } else {
+            KeyValuePairs::new()
+        };
+        let payload_len = b.get_varint()? as usize;
+        let status = if payload_len == 0 {
+            Some(b.get_varint()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            group_id,
+            subgroup_id,
+            object_id,
+            publisher_priority,
+            extension_headers,
+            payload_len,
+            status,
+        })
+    }
+}
+
+impl ToBytes for FetchObjectHeader {
+    fn to_bytes(&self, b: &mut OctetsMut, version: Version) -> crate::error::Result<()> {
+        b.put_varint(self.group_id)?;
+        b.put_varint(self.subgroup_id)?;
+        b.put_varint(self.object_id)?;
+        b.put_u8(self.publisher_priority)?;
+        if Self::extensions_present(version) {
+            b.put_varint(self.extension_headers.byte_length(version) as u64)?;
+            self.extension_headers.to_bytes(b, version)?;
+        }
+        b.put_varint(self.payload_len as u64)?;
+        if self.payload_len == 0 {
+            b.put_varint(self.status.unwrap())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyValuePair, MOQ_VERSION_DRAFT_14};
+
+    #[test]
+    fn recode() {
+        for version in [MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16] {
+            let ext = if version == MOQ_VERSION_DRAFT_07 {
+                KeyValuePairs::new()
+            } else {
+                KeyValuePairs::from(vec![KeyValuePair::new_varint(2, 7).unwrap()])
+            };
+            let header = FetchHeader::new(6);
+            let objects = [
+                FetchObjectHeader::new(Location { group: 1, object: 2 }, 0, 5, ext.clone()),
+                FetchObjectHeader::new_status(Location { group: 1, object: 3 }, 0, crate::OBJECT_STATUS_END_OF_GROUP, ext),
+            ];
+            let mut b = [0u8; 100];
+            let mut o = OctetsMut::with_slice(&mut b);
+            header.to_bytes(&mut o, version).unwrap();
+            for oh in &objects {
+                oh.to_bytes(&mut o, version).unwrap();
+            }
+            let len = o.off();
+
+            let mut o = Octets::with_slice(&b[..len]);
+            assert_eq!(FetchHeader::from_bytes(&mut o, version).unwrap(), header);
+            for oh in &objects {
+                assert_eq!(&FetchObjectHeader::from_bytes(&mut o, version).unwrap(), oh);
+            }
+            assert_eq!(o.cap(), 0);
+        }
+    }
+}
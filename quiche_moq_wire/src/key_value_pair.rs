@@ -93,18 +93,7 @@ impl TryInto<Vec<u8>> for KeyValuePairValue {
 
 impl FromBytes<KvpCtx> for KeyValuePair {
     fn from_bytes(b: &mut Octets, ctx: KvpCtx) -> crate::error::Result<Self> {
-        let ty = if ctx.version >= MOQ_VERSION_DRAFT_15 {
-            ctx.previous_key + b.get_varint()?
-        } else {
-            b.get_varint()?
-        };
-        let value = if ty % 2 == 0 {
-            KeyValuePairValue::Varint(b.get_varint()?)
-        } else {
-            let len = b.get_varint()? as usize;
-            KeyValuePairValue::Bytes(b.get_bytes(len)?.to_vec())
-        };
-        Ok(Self { ty, value })
+        Ok(KeyValuePairRef::from_bytes(b, ctx)?.into_owned())
     }
 }
 
@@ -125,3 +114,57 @@ impl ToBytes<KvpCtx> for KeyValuePair {
         Ok(())
     }
 }
+
+/// Borrowed [`KeyValuePair`], byte values point into the decoded buffer
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct KeyValuePairRef<'a> {
+    pub(crate) ty: u64,
+    pub(crate) value: KeyValuePairValueRef<'a>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum KeyValuePairValueRef<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> KeyValuePairRef<'a> {
+    pub(crate) fn from_bytes(b: &mut Octets<'a>, ctx: KvpCtx) -> crate::error::Result<Self> {
+        let ty = if ctx.version >= MOQ_VERSION_DRAFT_15 {
+            ctx.previous_key + b.get_varint()?
+        } else {
+            b.get_varint()?
+        };
+        let value = if ty % 2 == 0 {
+            KeyValuePairValueRef::Varint(b.get_varint()?)
+        } else {
+            let len = b.get_varint()? as usize;
+            KeyValuePairValueRef::Bytes(b.get_bytes(len)?.buf())
+        };
+        Ok(Self { ty, value })
+    }
+
+    pub fn ty(&self) -> u64 {
+        self.ty
+    }
+
+    pub fn value(&self) -> KeyValuePairValueRef<'a> {
+        self.value
+    }
+
+    pub fn into_owned(self) -> KeyValuePair {
+        KeyValuePair {
+            ty: self.ty,
+            value: match self.value {
+                KeyValuePairValueRef::Varint(v) => KeyValuePairValue::Varint(v),
+                KeyValuePairValueRef::Bytes(v) => KeyValuePairValue::Bytes(v.to_vec()),
+            },
+        }
+    }
+}
+
+impl From<KeyValuePairRef<'_>> for KeyValuePair {
+    fn from(value: KeyValuePairRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
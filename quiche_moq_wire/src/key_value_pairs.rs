@@ -1,12 +1,13 @@
 use octets::{Octets, OctetsMut};
 
 use crate::bytes::{FromBytes, ToBytes};
-use crate::key_value_pair::{KeyValuePair, KvpCtx};
+use crate::error::Error;
+use crate::key_value_pair::{KeyValuePair, KeyValuePairRef, KvpCtx};
 use crate::{MOQ_VERSION_DRAFT_15, Version};
 
 /// A sequence of `KeyValuePair`s without a count prefix.
 /// For draft-15+, pairs are sorted by type ID before delta-encoding so deltas never underflow.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct KeyValuePairs(pub(crate) Vec<KeyValuePair>);
 
 impl KeyValuePairs {
@@ -22,12 +23,12 @@ impl KeyValuePairs {
         self.0.is_empty()
     }
 
-    pub(crate) fn push(&mut self, value: KeyValuePair) {
+    pub fn push(&mut self, value: KeyValuePair) {
         self.0.push(value)
     }
 
     /// Returns the encoded byte length of all KVPs for the given version.
-    pub(crate) fn byte_length(&self, version: Version) -> usize {
+    pub fn byte_length(&self, version: Version) -> usize {
         let sorted;
         let pairs: &[KeyValuePair] = if version >= MOQ_VERSION_DRAFT_15 {
             sorted = { let mut v = self.0.clone(); v.sort_by_key(|p| p.ty); v };
@@ -47,14 +48,7 @@ impl KeyValuePairs {
 
 impl FromBytes<(Version, u64)> for KeyValuePairs {
     fn from_bytes(b: &mut Octets, (version, count): (Version, u64)) -> crate::error::Result<Self> {
-        let mut pairs = vec![];
-        let mut prev_key = 0u64;
-        for _ in 0..count {
-            let p = KeyValuePair::from_bytes(b, KvpCtx { version, previous_key: prev_key })?;
-            prev_key = p.ty;
-            pairs.push(p);
-        }
-        Ok(Self(pairs))
+        Ok(KeyValuePairsRef::from_bytes_with_count(b, version, count)?.into_owned())
     }
 }
 
@@ -81,3 +75,120 @@ impl From<Vec<KeyValuePair>> for KeyValuePairs {
         Self(value)
     }
 }
+
+/// Borrowed [`KeyValuePairs`].
+/// The pairs are validated when decoding and parsed again from the input buffer when iterating.
+#[derive(Clone, Copy)]
+pub struct KeyValuePairsRef<'a> {
+    version: Version,
+    len: usize,
+    encoded: &'a [u8],
+}
+
+impl<'a> KeyValuePairsRef<'a> {
+    pub(crate) fn empty(version: Version) -> Self {
+        Self { version, len: 0, encoded: &[] }
+    }
+
+    /// Decodes `count` pairs
+    pub(crate) fn from_bytes_with_count(b: &mut Octets<'a>, version: Version, count: u64) -> crate::error::Result<Self> {
+        let start = b.off();
+        let mut prev_key = 0u64;
+        for _ in 0..count {
+            let p = KeyValuePairRef::from_bytes(b, KvpCtx { version, previous_key: prev_key })?;
+            prev_key = p.ty;
+        }
+        Ok(Self {
+            version,
+            len: count as usize,
+            encoded: &b.buf()[start..b.off()],
+        })
+    }
+
+    /// Decodes pairs filling exactly `byte_len` bytes
+    pub(crate) fn from_bytes_with_byte_len(b: &mut Octets<'a>, version: Version, byte_len: usize) -> crate::error::Result<Self> {
+        let encoded = b.get_bytes(byte_len)?.buf();
+        let mut pairs = Octets::with_slice(encoded);
+        let mut len = 0;
+        let mut prev_key = 0u64;
+        while pairs.cap() > 0 {
+            let p = KeyValuePairRef::from_bytes(&mut pairs, KvpCtx { version, previous_key: prev_key })
+                .map_err(|_| Error::ProtocolViolation("key value pairs exceed their length".to_string()))?;
+            prev_key = p.ty;
+            len += 1;
+        }
+        Ok(Self { version, len, encoded })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> KeyValuePairsIter<'a> {
+        KeyValuePairsIter {
+            b: Octets::with_slice(self.encoded),
+            version: self.version,
+            previous_key: 0,
+            remaining: self.len,
+        }
+    }
+
+    pub fn into_owned(self) -> KeyValuePairs {
+        let mut pairs = Vec::with_capacity(self.len);
+        pairs.extend(self.iter().map(KeyValuePairRef::into_owned));
+        KeyValuePairs(pairs)
+    }
+}
+
+impl std::fmt::Debug for KeyValuePairsRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for KeyValuePairsRef<'a> {
+    type Item = KeyValuePairRef<'a>;
+    type IntoIter = KeyValuePairsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<KeyValuePairsRef<'_>> for KeyValuePairs {
+    fn from(value: KeyValuePairsRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+pub struct KeyValuePairsIter<'a> {
+    b: Octets<'a>,
+    version: Version,
+    previous_key: u64,
+    remaining: usize,
+}
+
+impl<'a> Iterator for KeyValuePairsIter<'a> {
+    type Item = KeyValuePairRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // already validated by `KeyValuePairsRef::from_bytes_*`
+        let p = KeyValuePairRef::from_bytes(&mut self.b, KvpCtx { version: self.version, previous_key: self.previous_key }).ok()?;
+        self.previous_key = p.ty;
+        Some(p)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for KeyValuePairsIter<'_> {}
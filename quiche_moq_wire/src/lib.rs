@@ -5,6 +5,7 @@ mod reason_phrase;
 mod setup_parameters;
 pub mod object;
 pub mod subgroup;
+pub mod fetch;
 mod parameters;
 mod tuple;
 mod namespace;
@@ -16,24 +17,34 @@ mod octets;
 pub mod control_message;
 mod namespace_trackname;
 mod version;
+mod auth_token;
+#[cfg(feature = "qlog")]
+mod qlog;
 
 pub use bytes::FromBytes;
 pub use bytes::ToBytes;
-pub use key_value_pair::{KeyValuePair, KeyValuePairValue};
-pub use key_value_pairs::KeyValuePairs;
+pub use key_value_pair::{KeyValuePair, KeyValuePairRef, KeyValuePairValue, KeyValuePairValueRef};
+pub use key_value_pairs::{KeyValuePairs, KeyValuePairsIter, KeyValuePairsRef};
 pub use error::Error;
 pub use error::Result;
 pub use parameter::Parameter;
-pub use parameters::Parameters;
+pub use parameters::{Parameters, ParametersIter, ParametersRef};
 pub use reason_phrase::ReasonPhrase;
 pub use setup_parameters::SetupParameters;
+pub use auth_token::AuthToken;
 pub use role::Role;
 pub use location::Location;
 pub use namespace::Namespace;
-pub use tuple::Tuple;
-pub use namespace_trackname::NamespaceTrackname;
+pub use tuple::{Tuple, TupleIter, TupleRef};
+pub use namespace_trackname::{NamespaceTrackname, NamespaceTracknameRef};
 pub use version::Version;
 pub use version::version_to_name;
+pub use version::version_to_alpn;
+pub use version::alpn_to_version;
+pub use version::version_negotiated_by_alpn;
+pub use version::extension_headers_supported;
+#[cfg(feature = "qlog")]
+pub use qlog::raw_info;
 
 pub type RequestId = u64;
 pub type TrackAlias = u64;
@@ -153,10 +164,25 @@ pub const PUB_SUB_ROLE_ID: u64 = 0x03;
 /// https://www.ietf.org/archive/id/draft-ietf-moq-transport-11.html#name-max_request_id
 pub const DEFAULT_MAX_REQUEST_ID_SETUP_PARAMETER: u64 = 0;
 
+/// MAX_CACHE_DURATION parameter type ID (draft-11+). Even type → varint value in milliseconds.
+pub const MAX_CACHE_DURATION_PARAMETER_ID: u64 = 0x4;
 /// EXPIRES parameter type ID (draft-16 section 9.2.2.6). Even type → varint value.
 pub const EXPIRES_PARAMETER_ID: u64 = 0x8;
 /// LARGEST_OBJECT parameter type ID (draft-16 section 9.2.2.7). Odd type → length-prefixed Location.
 pub const LARGEST_OBJECT_PARAMETER_ID: u64 = 0x9;
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-07.html#name-authorization-info
+/// AUTHORIZATION_INFO parameter type ID, only valid from draft 07 to draft 10. The value is the token.
+pub const AUTHORIZATION_INFO_PARAMETER_ID: u64 = 0x2;
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-11.html#name-authorization-token
+/// AUTHORIZATION_TOKEN parameter type ID (draft-11+). Odd type → length-prefixed [`AuthToken`].
+pub const AUTHORIZATION_TOKEN_PARAMETER_ID: u64 = 0x3;
+
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-11.html#name-authorization-token
+pub const AUTH_TOKEN_ALIAS_TYPE_DELETE: u64 = 0x0;
+pub const AUTH_TOKEN_ALIAS_TYPE_REGISTER: u64 = 0x1;
+pub const AUTH_TOKEN_ALIAS_TYPE_USE_ALIAS: u64 = 0x2;
+pub const AUTH_TOKEN_ALIAS_TYPE_USE_VALUE: u64 = 0x3;
+
 /// DEFAULT_PUBLISHER_GROUP_ORDER Track Extension type ID (draft-16 section 11.1). Even type → varint value.
 pub const DEFAULT_PUBLISHER_GROUP_ORDER_EXTENSION_ID: u64 = 0x22;
 
@@ -169,6 +195,13 @@ pub const ABSOLUTE_START_FILTER_ID: u64 = 0x3;
 /// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-subscribe
 pub const ABSOLUTE_RANGE_FILTER_ID: u64 = 0x4;
 
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-fetch
+pub const STANDALONE_FETCH_TYPE_ID: u64 = 0x1;
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-fetch
+pub const RELATIVE_JOINING_FETCH_TYPE_ID: u64 = 0x2;
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-fetch
+pub const ABSOLUTE_JOINING_FETCH_TYPE_ID: u64 = 0x3;
+
 /// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-track-naming
 const MIN_TRACK_NAMESPACE_TUPLE_LENGTH: usize = 1;
 /// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-track-naming
@@ -179,15 +212,18 @@ const MAX_FULL_TRACK_NAME_LEN: usize = 4096;
 /// used from draft 7 to draft 10
 const STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID: u64 = 0x4;
 
-#[allow(unused)]
-const FETCH_HEADER_SUBGROUP_STREAM_TYPE_ID: u64 = 0x5;
-
 /// used from draft 10 to draft 13
 const SUBGROUP_UNI_STREAM_TYPE_IDS: [u64; 6] = [0x8, 0x9, 0xA, 0xB, 0xC, 0xD];
 
-#[allow(unused)]
 /// https://www.ietf.org/archive/id/draft-ietf-moq-transport-13.html#name-data-streams-and-datagrams
-const FETCH_UNI_STREAM_TYPE_ID: u64 = 0x05;
+/// FETCH_HEADER in draft 7 to draft 10
+pub const FETCH_UNI_STREAM_TYPE_ID: u64 = 0x05;
+
+/// https://www.ietf.org/archive/id/draft-ietf-moq-transport-16.html#name-object-status
+pub const OBJECT_STATUS_NORMAL: u64 = 0x0;
+pub const OBJECT_STATUS_DOES_NOT_EXIST: u64 = 0x1;
+pub const OBJECT_STATUS_END_OF_GROUP: u64 = 0x3;
+pub const OBJECT_STATUS_END_OF_TRACK: u64 = 0x4;
 
 pub const RESET_STREAM_CODE_INTERNAL_ERROR: u64 = 0x0;
 pub const RESET_STREAM_CODE_CANCELED: u64 = 0x1;
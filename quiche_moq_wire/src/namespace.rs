@@ -2,6 +2,7 @@ use crate::bytes::{FromBytes, ToBytes};
 use crate::Version;
 use octets::{Octets, OctetsMut};
 use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
 use crate::tuple::Tuple;
 
 #[derive(Eq, PartialEq, Clone, Hash)]
@@ -42,6 +43,23 @@ impl ToBytes for Namespace {
     }
 }
 
+/// Parses the text form produced by `Display`, e.g. `example.2enet-team2`.
+/// The empty string is the empty namespace.
+impl FromStr for Namespace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self(Tuple(vec![])));
+        }
+        let fields = s
+            .split('-')
+            .map(crate::namespace_trackname::unescape)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(Tuple(fields)))
+    }
+}
+
 impl Debug for Namespace {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_list()
@@ -1,7 +1,9 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use octets::Octets;
 use crate::{Namespace, Tuple};
+use crate::tuple::TupleRef;
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct NamespaceTrackname {
@@ -51,15 +53,12 @@ impl FromStr for NamespaceTrackname {
         let trackname_raw = parts.next().ok_or("Missing track name")?;
         let namespace_raw = parts.next().ok_or("Missing namespace delimiter '--'")?;
 
-        let namespace: Vec<Vec<u8>> = namespace_raw
-            .split('-')
-            .map(unescape)
-            .collect::<Result<Vec<_>, _>>()?;
+        let namespace: Namespace = namespace_raw.parse()?;
 
         // 3. Unescape the track name
         let trackname = unescape(trackname_raw)?;
 
-        Ok(NamespaceTrackname { namespace: Namespace(Tuple(namespace)), trackname })
+        Ok(NamespaceTrackname { namespace, trackname })
     }
 }
 
@@ -71,7 +70,59 @@ impl Display for NamespaceTrackname {
     }
 }
 
-fn unescape(s: &str) -> Result<Vec<u8>, String> {
+/// Borrowed [`NamespaceTrackname`] as encoded in control messages, pointing into the decoded buffer
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NamespaceTracknameRef<'a> {
+    namespace: TupleRef<'a>,
+    trackname: &'a [u8],
+}
+
+impl<'a> NamespaceTracknameRef<'a> {
+    /// Reads the namespace tuple followed by the length-prefixed track name
+    pub fn from_bytes(b: &mut Octets<'a>) -> crate::error::Result<Self> {
+        let namespace = TupleRef::from_bytes(b)?;
+        let trackname_len = b.get_varint()? as usize;
+        let trackname = b.get_bytes(trackname_len)?.buf();
+        Ok(Self { namespace, trackname })
+    }
+
+    pub fn namespace(&self) -> TupleRef<'a> {
+        self.namespace
+    }
+
+    pub fn trackname(&self) -> &'a [u8] {
+        self.trackname
+    }
+
+    pub fn into_owned(self) -> NamespaceTrackname {
+        NamespaceTrackname {
+            namespace: Namespace(self.namespace.into_owned()),
+            trackname: self.trackname.to_vec(),
+        }
+    }
+}
+
+impl From<NamespaceTracknameRef<'_>> for NamespaceTrackname {
+    fn from(value: NamespaceTracknameRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+/// Same text form as [`NamespaceTrackname`]
+impl Display for NamespaceTracknameRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, part) in self.namespace.iter().enumerate() {
+            if i > 0 {
+                f.write_str("-")?;
+            }
+            write_escape(f, part)?;
+        }
+        f.write_str("--")?;
+        write_escape(f, self.trackname)
+    }
+}
+
+pub(crate) fn unescape(s: &str) -> Result<Vec<u8>, String> {
     let mut bytes = Vec::new();
     let chars: Vec<char> = s.chars().collect();
     let mut i = 0;
@@ -124,7 +175,7 @@ pub(crate) fn write_escape(f: &mut fmt::Formatter<'_>, data: &[u8]) -> fmt::Resu
 
 #[cfg(test)]
 mod tests {
-    use crate::NamespaceTrackname;
+    use crate::{Namespace, NamespaceTrackname};
 
     #[test]
     fn to_string() {
@@ -132,10 +183,36 @@ mod tests {
         assert_eq!(ntn.to_string(), "example.2enet-team2-project_x--report")
     }
 
+    #[test]
+    fn decode_borrowed() {
+        use crate::ToBytes;
+        use crate::namespace_trackname::NamespaceTracknameRef;
+        let ntn: NamespaceTrackname = "example.2enet-team2--report".parse().unwrap();
+        let mut buf = [0u8; 32];
+        let mut o = octets::OctetsMut::with_slice(&mut buf);
+        ntn.namespace().to_bytes(&mut o, crate::MOQ_VERSION_DRAFT_14).unwrap();
+        o.put_varint(ntn.trackname().len() as u64).unwrap();
+        o.put_bytes(ntn.trackname()).unwrap();
+        let len = o.off();
+        let ntn_ref = NamespaceTracknameRef::from_bytes(&mut octets::Octets::with_slice(&buf[..len])).unwrap();
+        assert_eq!(ntn_ref.to_string(), ntn.to_string());
+        assert_eq!(ntn_ref.into_owned(), ntn);
+    }
+
     #[test]
     fn from_string() {
         let txt = "example.2enet-team2-project_x--report";
         let ntn = NamespaceTrackname::new(vec![b"example.net".to_vec(), b"team2".to_vec(), b"project_x".to_vec()], b"report".to_vec());
         assert_eq!(ntn, txt.parse().unwrap());
     }
+
+    #[test]
+    fn empty_namespace() {
+        let ns: Namespace = "".parse().unwrap();
+        assert!(ns.is_empty());
+        assert_eq!(ns.to_string(), "");
+        let ntn: NamespaceTrackname = "--report".parse().unwrap();
+        assert!(ntn.namespace().is_empty());
+        assert_eq!(ntn.to_string(), "--report");
+    }
 }
\ No newline at end of file
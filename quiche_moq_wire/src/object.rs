@@ -1,9 +1,8 @@
-use crate::bytes::{FromBytes, ToBytes};
+use crate::bytes::ToBytes;
 use crate::error::Result;
-use crate::{SubgroupType, Version, STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID};
+use crate::{SubgroupType, Version};
 use octets::{Octets, OctetsMut};
-use crate::key_value_pair::{KeyValuePair, KvpCtx};
-use crate::key_value_pairs::KeyValuePairs;
+use crate::key_value_pairs::{KeyValuePairs, KeyValuePairsRef};
 use crate::subgroup::SubgroupHeader;
 
 #[derive(Debug, Clone)]
@@ -27,43 +26,23 @@ impl ObjectHeader {
         }
     }
 
+    /// Object without payload signaling `status`, e.g. [`crate::OBJECT_STATUS_END_OF_GROUP`]
+    pub fn new_status(id: u64, status: u64, subgroup_ty: SubgroupType, extension_headers: KeyValuePairs) -> Self {
+        Self {
+            id,
+            subgroup_ty,
+            extension_headers,
+            payload_len: 0,
+            status: Some(status),
+        }
+    }
+
     pub fn from_bytes(
         b: &mut Octets,
         version: Version,
         subgroup: &SubgroupHeader,
     ) -> Result<Self> {
-        let subgroup_ty = subgroup.ty();
-        let id = b.get_varint()?;
-        let mut extension_headers = KeyValuePairs::new();
-        match subgroup_ty {
-            STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID => {},
-            0xD => {
-                let ext_hdr_len = b.get_varint()? as usize;
-                let ext_hdr_end = b.off() + ext_hdr_len;
-                let mut prev_key = 0u64;
-                while b.off() < ext_hdr_end {
-                    let kvp = KeyValuePair::from_bytes(b, KvpCtx::new(version).with_previous_key(prev_key))?;
-                    prev_key = kvp.ty;
-                    extension_headers.push(kvp);
-                }
-                assert_eq!(b.off(), ext_hdr_end);
-            }
-            _ => unimplemented!()
-        }
-        let payload_len = b.get_varint()? as usize;
-        let status = if payload_len == 0 {
-            Some(b.get_varint()?)
-        } else {
-            None
-        };
-
-        Ok(Self {
-            id,
-            subgroup_ty,
-            extension_headers,
-            payload_len,
-            status,
-        })
+        Ok(ObjectHeaderRef::from_bytes(b, version, subgroup)?.into_owned())
     }
 
     pub fn id(&self) -> u64 {
@@ -89,37 +68,17 @@ impl ObjectHeader {
     /// Returns extension headers formatted as `[* MOQTExtensionHeader]` per the qlog draft.
     #[cfg(feature = "qlog")]
     pub fn extension_headers_to_qlog(&self) -> Vec<serde_json::Value> {
-        use crate::KeyValuePairValue;
-        self.extension_headers.0.iter().map(|kvp| {
-            match kvp.value() {
-                KeyValuePairValue::Varint(v) => serde_json::json!({
-                    "header_type": kvp.ty(),
-                    "header_value": v,
-                }),
-                KeyValuePairValue::Bytes(b) => {
-                    let hex: String = b.iter().map(|byte| format!("{byte:02x}")).collect();
-                    serde_json::json!({
-                        "header_type": kvp.ty(),
-                        "header_length": b.len() as u64,
-                        "payload": { "data": hex },
-                    })
-                }
-            }
-        }).collect()
+        crate::qlog::extension_headers(&self.extension_headers)
     }
 }
 
 impl ToBytes for ObjectHeader {
     fn to_bytes(&self, b: &mut OctetsMut, version: Version) -> Result<()> {
         b.put_varint(self.id)?;
-        match self.subgroup_ty {
-            STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID => {},
-            0xD => {
-                let kvps = self.extension_headers.clone();
-                b.put_varint(kvps.byte_length(version) as u64)?;
-                kvps.to_bytes(b, version)?;
-            }
-            _ => unimplemented!(),
+        if SubgroupHeader::extensions_present(self.subgroup_ty) {
+            let kvps = &self.extension_headers;
+            b.put_varint(kvps.byte_length(version) as u64)?;
+            kvps.to_bytes(b, version)?;
         }
         b.put_varint(self.payload_len as u64)?;
         if self.payload_len == 0 {
@@ -128,3 +87,146 @@ impl ToBytes for ObjectHeader {
         Ok(())
     }
 }
+
+/// Borrowed [`ObjectHeader`], the extension headers point into the decoded buffer
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectHeaderRef<'a> {
+    id: u64,
+    subgroup_ty: SubgroupType,
+    extension_headers: KeyValuePairsRef<'a>,
+    payload_len: usize,
+    status: Option<u64>,
+}
+
+impl<'a> ObjectHeaderRef<'a> {
+    pub fn from_bytes(
+        b: &mut Octets<'a>,
+        version: Version,
+        subgroup: &SubgroupHeader,
+    ) -> Result<Self> {
+        let subgroup_ty = subgroup.ty();
+        let id = b.get_varint()?;
+        let extension_headers = if SubgroupHeader::extensions_present(subgroup_ty) {
+            let ext_hdr_len = b.get_varint()? as usize;
+            KeyValuePairsRef::from_bytes_with_byte_len(b, version, ext_hdr_len)?
+        } else {
+            KeyValuePairsRef::empty(version)
+        };
+        let payload_len = b.get_varint()? as usize;
+        let status = if payload_len == 0 {
+            Some(b.get_varint()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            id,
+            subgroup_ty,
+            extension_headers,
+            payload_len,
+            status,
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn payload_len(&self) -> usize {
+        self.payload_len
+    }
+
+    pub fn extension_headers(&self) -> KeyValuePairsRef<'a> {
+        self.extension_headers
+    }
+
+    pub fn status(&self) -> Option<u64> {
+        self.status
+    }
+
+    pub fn into_owned(self) -> ObjectHeader {
+        ObjectHeader {
+            id: self.id,
+            subgroup_ty: self.subgroup_ty,
+            extension_headers: self.extension_headers.into_owned(),
+            payload_len: self.payload_len,
+            status: self.status,
+        }
+    }
+}
+
+impl From<ObjectHeaderRef<'_>> for ObjectHeader {
+    fn from(value: ObjectHeaderRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromBytes, KeyValuePair, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16, OBJECT_STATUS_END_OF_GROUP, extension_headers_supported};
+
+    fn encode(version: Version, subgroup: &SubgroupHeader, oh: &ObjectHeader, buf: &mut [u8]) -> usize {
+        let mut o = OctetsMut::with_slice(buf);
+        subgroup.to_bytes(&mut o, version).unwrap();
+        oh.to_bytes(&mut o, version).unwrap();
+        o.off()
+    }
+
+    #[test]
+    fn decode_borrowed() {
+        for version in [MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16] {
+            let subgroup = SubgroupHeader::new(1, 2, 3, version);
+            let ext = KeyValuePairs::from(vec![
+                KeyValuePair::new_varint(2, 7).unwrap(),
+                KeyValuePair::new_bytes(5, b"abc".to_vec()).unwrap(),
+            ]);
+            let oh = ObjectHeader::new(4, 10, subgroup.ty(), ext);
+            let mut buf = [0u8; 64];
+            let len = encode(version, &subgroup, &oh, &mut buf);
+            let mut b = Octets::with_slice(&buf[..len]);
+            let subgroup = SubgroupHeader::from_bytes(&mut b, version).unwrap();
+            let oh_ref = ObjectHeaderRef::from_bytes(&mut b, version, &subgroup).unwrap();
+            assert_eq!(b.cap(), 0);
+            assert_eq!(oh_ref.id(), 4);
+            assert_eq!(oh_ref.payload_len(), 10);
+            let ext: Vec<_> = oh_ref.extension_headers().iter().map(KeyValuePair::from).collect();
+            assert_eq!(ext, oh.extension_headers().0);
+            assert_eq!(oh_ref.into_owned().extension_headers().0, oh.extension_headers().0);
+        }
+    }
+
+    #[test]
+    fn status_and_extension_headers_per_version() {
+        for version in [MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_14, MOQ_VERSION_DRAFT_16] {
+            let subgroup = SubgroupHeader::new(1, 2, 3, version);
+            let ext = KeyValuePairs::from(vec![KeyValuePair::new_varint(2, 7).unwrap()]);
+            let oh = ObjectHeader::new_status(5, OBJECT_STATUS_END_OF_GROUP, subgroup.ty(), ext);
+            let mut buf = [0u8; 64];
+            let len = encode(version, &subgroup, &oh, &mut buf);
+            let mut b = Octets::with_slice(&buf[..len]);
+            let subgroup = SubgroupHeader::from_bytes(&mut b, version).unwrap();
+            let decoded = ObjectHeader::from_bytes(&mut b, version, &subgroup).unwrap();
+            assert_eq!(b.cap(), 0);
+            assert_eq!(decoded.status(), Some(OBJECT_STATUS_END_OF_GROUP));
+            // not representable before draft 11, the object is sent without them
+            assert_eq!(!decoded.extension_headers().is_empty(), extension_headers_supported(version));
+        }
+    }
+
+    #[test]
+    fn decode_borrowed_truncated() {
+        let version = MOQ_VERSION_DRAFT_14;
+        let subgroup = SubgroupHeader::new(1, 2, 3, version);
+        let ext = KeyValuePairs::from(vec![KeyValuePair::new_bytes(5, b"abc".to_vec()).unwrap()]);
+        let oh = ObjectHeader::new(4, 10, subgroup.ty(), ext);
+        let mut buf = [0u8; 64];
+        let len = encode(version, &subgroup, &oh, &mut buf);
+        let mut b = Octets::with_slice(&buf[..len - 2]);
+        let subgroup = SubgroupHeader::from_bytes(&mut b, version).unwrap();
+        assert!(matches!(
+            ObjectHeaderRef::from_bytes(&mut b, version, &subgroup),
+            Err(crate::Error::Octets(_))
+        ));
+    }
+}
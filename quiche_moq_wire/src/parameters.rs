@@ -1,5 +1,5 @@
 use crate::bytes::{FromBytes, ToBytes};
-use crate::key_value_pair::{KeyValuePair, KvpCtx};
+use crate::key_value_pair::{KeyValuePair, KeyValuePairRef, KeyValuePairValueRef, KvpCtx};
 use crate::key_value_pairs::KeyValuePairs;
 use crate::parameter::ParameterValue;
 use crate::{Parameter, Version, MOQ_VERSION_DRAFT_10};
@@ -48,23 +48,10 @@ impl Parameters {
 
 impl FromBytes for Parameters {
     /// Reads a count-prefixed sequence of parameters.
-    /// For draft-07–10 uses the old (ty + len + bytes) encoding via `Parameter::from_bytes`.
-    /// For draft-11+ delegates to `KeyValuePairs` which handles delta-decoding for draft-15+.
+    /// For draft-07–10 uses the old (ty + len + bytes) encoding.
+    /// For draft-11+ uses Key-Value-Pairs which are delta-decoded for draft-15+.
     fn from_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
-        let count = b.get_varint()?;
-        if version <= MOQ_VERSION_DRAFT_10 {
-            let mut params = vec![];
-            let mut prev_key = 0u64;
-            for _ in 0..count {
-                let p = Parameter::from_bytes(b, KvpCtx::new(version).with_previous_key(prev_key))?;
-                prev_key = p.ty;
-                params.push(p);
-            }
-            Ok(Self(params))
-        } else {
-            let kvps = KeyValuePairs::from_bytes(b, (version, count))?;
-            Ok(Self(kvps.0.into_iter().map(Parameter::from).collect()))
-        }
+        Ok(ParametersRef::from_bytes(b, version)?.into_owned())
     }
 }
 
@@ -85,3 +72,120 @@ impl ToBytes for Parameters {
         Ok(())
     }
 }
+
+/// Borrowed [`Parameters`].
+/// The parameters are validated when decoding and parsed again from the input buffer when iterating.
+#[derive(Clone, Copy)]
+pub struct ParametersRef<'a> {
+    version: Version,
+    len: usize,
+    encoded: &'a [u8],
+}
+
+impl<'a> ParametersRef<'a> {
+    /// Reads a count-prefixed sequence of parameters, see [`Parameters::from_bytes`]
+    pub fn from_bytes(b: &mut Octets<'a>, version: Version) -> crate::error::Result<Self> {
+        let count = b.get_varint()?;
+        let start = b.off();
+        let mut prev_key = 0u64;
+        for _ in 0..count {
+            prev_key = parameter_from_bytes(b, version, prev_key)?.ty;
+        }
+        Ok(Self {
+            version,
+            len: count as usize,
+            encoded: &b.buf()[start..b.off()],
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Parameters of draft-07–10 are always byte strings
+    pub fn iter(&self) -> ParametersIter<'a> {
+        ParametersIter {
+            b: Octets::with_slice(self.encoded),
+            version: self.version,
+            previous_key: 0,
+            remaining: self.len,
+        }
+    }
+
+    /// Get the varint value for the given even type ID.
+    pub fn get_varint(&self, ty: u64) -> Option<u64> {
+        self.iter().find(|p| p.ty == ty).and_then(|p| match p.value {
+            KeyValuePairValueRef::Varint(v) => Some(v),
+            KeyValuePairValueRef::Bytes(_) => None,
+        })
+    }
+
+    /// Get the byte-string value for the given odd type ID.
+    pub fn get_bytes(&self, ty: u64) -> Option<&'a [u8]> {
+        self.iter().find(|p| p.ty == ty).and_then(|p| match p.value {
+            KeyValuePairValueRef::Bytes(v) => Some(v),
+            KeyValuePairValueRef::Varint(_) => None,
+        })
+    }
+
+    pub fn into_owned(self) -> Parameters {
+        let mut params = Vec::with_capacity(self.len);
+        params.extend(self.iter().map(|p| Parameter::from(p.into_owned())));
+        Parameters(params)
+    }
+}
+
+impl std::fmt::Debug for ParametersRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl From<ParametersRef<'_>> for Parameters {
+    fn from(value: ParametersRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+fn parameter_from_bytes<'a>(b: &mut Octets<'a>, version: Version, previous_key: u64) -> crate::error::Result<KeyValuePairRef<'a>> {
+    if version <= MOQ_VERSION_DRAFT_10 {
+        let ty = b.get_varint()?;
+        let len = b.get_varint()? as usize;
+        let value = b.get_bytes(len)?.buf();
+        Ok(KeyValuePairRef { ty, value: KeyValuePairValueRef::Bytes(value) })
+    } else {
+        KeyValuePairRef::from_bytes(b, KvpCtx::new(version).with_previous_key(previous_key))
+    }
+}
+
+pub struct ParametersIter<'a> {
+    b: Octets<'a>,
+    version: Version,
+    previous_key: u64,
+    remaining: usize,
+}
+
+impl<'a> Iterator for ParametersIter<'a> {
+    type Item = KeyValuePairRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // already validated by `ParametersRef::from_bytes`
+        let p = parameter_from_bytes(&mut self.b, self.version, self.previous_key).ok()?;
+        self.previous_key = p.ty;
+        Some(p)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for ParametersIter<'_> {}
@@ -0,0 +1,87 @@
+//! Helpers to serialize wire types as defined by
+//! https://datatracker.ietf.org/doc/draft-pardue-moq-qlog-moq-events/
+use crate::parameter::ParameterValue;
+use crate::{KeyValuePairValue, KeyValuePairs, Location, Parameter, Parameters, SetupParameters, Tuple};
+use serde_json::{json, Value};
+
+pub(crate) fn hex(b: &[u8]) -> String {
+    b.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// `RawInfo` as defined by the qlog main schema
+pub fn raw_info(b: &[u8]) -> Value {
+    json!({ "length": b.len() as u64, "data": hex(b) })
+}
+
+/// `MOQTByteString`, `value` if the bytes are valid UTF-8, else `value_bytes`
+pub(crate) fn byte_string(b: &[u8]) -> Value {
+    match std::str::from_utf8(b) {
+        Ok(s) => json!({ "length": b.len() as u64, "value": s }),
+        Err(_) => json!({ "length": b.len() as u64, "value_bytes": hex(b) }),
+    }
+}
+
+pub(crate) fn tuple(t: &Tuple) -> Value {
+    t.0.iter().map(|field| byte_string(field)).collect()
+}
+
+pub(crate) fn location(l: &Location) -> Value {
+    json!({ "group": l.group, "object": l.object })
+}
+
+/// `MOQTUnknownParameter`, the meaning of parameter types depends on the message
+pub(crate) fn parameter(p: &Parameter) -> Value {
+    match &p.value {
+        ParameterValue::Varint(v) => json!({ "name": "unknown", "name_bytes": p.ty, "value": v }),
+        ParameterValue::Bytes(b) => json!({
+            "name": "unknown",
+            "name_bytes": p.ty,
+            "length": b.len() as u64,
+            "value_bytes": hex(b),
+        }),
+    }
+}
+
+/// `[* MOQTExtensionHeader]`
+pub(crate) fn extension_headers(kvps: &KeyValuePairs) -> Vec<Value> {
+    kvps.0.iter().map(|kvp| {
+        match kvp.value() {
+            KeyValuePairValue::Varint(v) => json!({
+                "header_type": kvp.ty(),
+                "header_value": v,
+            }),
+            KeyValuePairValue::Bytes(b) => json!({
+                "header_type": kvp.ty(),
+                "header_length": b.len() as u64,
+                "payload": { "data": hex(b) },
+            }),
+        }
+    }).collect()
+}
+
+/// Sets `number_of_parameters` and `parameters` of `msg`.
+pub(crate) fn set_parameters(msg: &mut Value, params: Vec<Value>) {
+    msg["number_of_parameters"] = params.len().into();
+    if !params.is_empty() {
+        msg["parameters"] = params.into();
+    }
+}
+
+pub(crate) fn parameters(p: &Parameters) -> Vec<Value> {
+    p.0.iter().map(parameter).collect()
+}
+
+pub(crate) fn setup_parameters(p: &SetupParameters) -> Vec<Value> {
+    let mut params = vec![];
+    if let Some(path) = &p.path {
+        params.push(json!({ "name": "path", "value": String::from_utf8_lossy(path) }));
+    }
+    if let Some(max_request_id) = p.max_request_id {
+        params.push(json!({ "name": "max_request_id", "value": max_request_id }));
+    }
+    if let Some(role) = &p.role {
+        params.push(json!({ "name": "role", "value": role.to_id() }));
+    }
+    params.extend(p.extra_parameters.iter().map(parameter));
+    params
+}
@@ -4,6 +4,7 @@ use octets::{Octets, OctetsMut};
 use crate::key_value_pair::KvpCtx;
 use crate::parameter::ParameterValue;
 use crate::role::Role;
+use crate::error::Error::ProtocolViolation;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SetupParameters {
@@ -29,8 +30,10 @@ impl FromBytes for SetupParameters {
             prev_key = p.ty;
             match (p.ty, &p.value, version) {
                 (MAX_REQUEST_ID_SETUP_PARAMETER_ID, ParameterValue::Bytes(v), MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10) => {
-                    assert_eq!(v.len(), 1);
-                    s.max_request_id = Some(v[0] as u64);
+                    let [v] = v.as_slice() else {
+                        return Err(ProtocolViolation(format!("invalid max request id length {}", v.len())));
+                    };
+                    s.max_request_id = Some(*v as u64);
                 }
                 (MAX_REQUEST_ID_SETUP_PARAMETER_ID, ParameterValue::Varint(v), MOQ_VERSION_DRAFT_11..) => {
                     s.max_request_id = Some(*v)
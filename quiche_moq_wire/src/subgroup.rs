@@ -1,5 +1,6 @@
 use crate::bytes::{FromBytes, ToBytes};
 use crate::{TrackAlias, Version, MOQ_VERSION_DRAFT_07, MOQ_VERSION_DRAFT_08, MOQ_VERSION_DRAFT_10, MOQ_VERSION_DRAFT_11, MOQ_VERSION_DRAFT_16, STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID, SUBGROUP_UNI_STREAM_TYPE_IDS};
+use crate::error::Error::ProtocolViolation;
 use octets::{Octets, OctetsMut};
 
 #[derive(Debug, Eq, PartialEq)]
@@ -63,14 +64,13 @@ impl SubgroupHeader {
 impl FromBytes for SubgroupHeader {
     fn from_bytes(b: &mut Octets, version: Version) -> crate::error::Result<Self> {
         let ty = b.get_varint()?;
-        match version {
-            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => {
-                assert_eq!(ty, STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID)
-            }
-            MOQ_VERSION_DRAFT_11..=MOQ_VERSION_DRAFT_16 => {
-                assert!(SUBGROUP_UNI_STREAM_TYPE_IDS.contains(&ty));
-            }
+        let valid = match version {
+            MOQ_VERSION_DRAFT_07..=MOQ_VERSION_DRAFT_10 => ty == STREAM_HEADER_SUBGROUP_STREAM_TYPE_ID,
+            MOQ_VERSION_DRAFT_11..=MOQ_VERSION_DRAFT_16 => SUBGROUP_UNI_STREAM_TYPE_IDS.contains(&ty),
             _ => unimplemented!()
+        };
+        if !valid {
+            return Err(ProtocolViolation(format!("unexpected subgroup stream type {ty:#x}")));
         }
         let _subscribe_id = match version {
             MOQ_VERSION_DRAFT_07 => Some(b.get_varint()?), // todo not sure, this is not in the spec, but cloudflare uses it, https://github.com/englishm/moq-rs/blob/ebc843de8504e37d36c3134a1181513ebdf7a34a/moq-transport/src/data/subgroup.rs
@@ -8,14 +8,7 @@ pub struct Tuple(pub Vec<Vec<u8>>);
 
 impl FromBytes for Tuple {
     fn from_bytes(b: &mut Octets, _version: Version) -> Result<Self> {
-        let num_fields = b.get_varint()?;
-        let mut fields = Vec::with_capacity(num_fields as usize);
-        for _ in 0..num_fields {
-            let len = b.get_varint()? as usize;
-            let data = b.get_bytes(len)?.to_vec();
-            fields.push(data);
-        }
-        Ok(Tuple(fields))
+        Ok(TupleRef::from_bytes(b)?.into_owned())
     }
 }
 
@@ -29,3 +22,113 @@ impl ToBytes for Tuple {
         Ok(())
     }
 }
+
+/// Borrowed [`Tuple`], the fields point into the decoded buffer
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct TupleRef<'a> {
+    len: usize,
+    /// fields without the count prefix
+    encoded: &'a [u8],
+}
+
+impl<'a> TupleRef<'a> {
+    pub fn from_bytes(b: &mut Octets<'a>) -> Result<Self> {
+        let len = b.get_varint()? as usize;
+        let start = b.off();
+        for _ in 0..len {
+            let field_len = b.get_varint()? as usize;
+            b.skip(field_len)?;
+        }
+        Ok(Self {
+            len,
+            encoded: &b.buf()[start..b.off()],
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> TupleIter<'a> {
+        TupleIter {
+            b: Octets::with_slice(self.encoded),
+            remaining: self.len,
+        }
+    }
+
+    pub fn into_owned(self) -> Tuple {
+        let mut fields = Vec::with_capacity(self.len);
+        fields.extend(self.iter().map(<[u8]>::to_vec));
+        Tuple(fields)
+    }
+}
+
+impl std::fmt::Debug for TupleRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for TupleRef<'a> {
+    type Item = &'a [u8];
+    type IntoIter = TupleIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<TupleRef<'_>> for Tuple {
+    fn from(value: TupleRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+pub struct TupleIter<'a> {
+    b: Octets<'a>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for TupleIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // already validated by `TupleRef::from_bytes`
+        let len = self.b.get_varint().ok()? as usize;
+        Some(self.b.get_bytes(len).ok()?.buf())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for TupleIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_borrowed() {
+        let tuple = Tuple(vec![b"a".to_vec(), vec![], b"bc".to_vec()]);
+        let mut buf = [0u8; 16];
+        let mut o = OctetsMut::with_slice(&mut buf);
+        tuple.to_bytes(&mut o, crate::MOQ_VERSION_DRAFT_14).unwrap();
+        let len = o.off();
+        let mut b = Octets::with_slice(&buf[..len]);
+        let tuple_ref = TupleRef::from_bytes(&mut b).unwrap();
+        assert_eq!(b.off(), len);
+        assert_eq!(tuple_ref.iter().collect::<Vec<_>>(), vec![b"a".as_slice(), b"", b"bc"]);
+        assert_eq!(tuple_ref.into_owned(), tuple);
+        assert!(TupleRef::from_bytes(&mut Octets::with_slice(&buf[..len - 1])).is_err());
+    }
+}
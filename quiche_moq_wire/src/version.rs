@@ -17,3 +17,32 @@ pub fn version_to_name(v: Version) -> &'static str {
         _ => unimplemented!()
     }
 }
+
+/// ALPN of the native QUIC mapping, also used as WebTransport protocol.
+/// Only defined for drafts that negotiate the version by ALPN, see [`version_negotiated_by_alpn`].
+pub fn version_to_alpn(v: Version) -> Option<&'static str> {
+    match v {
+        MOQ_VERSION_DRAFT_15 => Some("moqt-15"),
+        MOQ_VERSION_DRAFT_16 => Some("moqt-16"),
+        _ => None,
+    }
+}
+
+pub fn alpn_to_version(alpn: &[u8]) -> Option<Version> {
+    match alpn {
+        b"moqt-15" => Some(MOQ_VERSION_DRAFT_15),
+        b"moqt-16" => Some(MOQ_VERSION_DRAFT_16),
+        _ => None,
+    }
+}
+
+/// Since draft 15 CLIENT_SETUP and SERVER_SETUP carry no versions,
+/// the version is negotiated by ALPN or the WebTransport protocol instead.
+pub fn version_negotiated_by_alpn(v: Version) -> bool {
+    (MOQ_VERSION_DRAFT_15..=MOQ_VERSION_DRAFT_16).contains(&v)
+}
+
+/// Since draft 11 objects carry extension headers, on older drafts they cannot be sent.
+pub fn extension_headers_supported(v: Version) -> bool {
+    (MOQ_VERSION_DRAFT_11..=MOQ_VERSION_DRAFT_16).contains(&v)
+}
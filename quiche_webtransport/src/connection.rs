@@ -3,7 +3,8 @@ use crate::pending_request::PendingRequest;
 use crate::pending_response::PendingResponse;
 use crate::session::Session;
 use crate::stream::Stream;
-use crate::{Error, SessionId, PROTOCOL_HEADER_WEBTRANSPORT, WT_CLOSE_SESSION};
+use crate::protocol::{decode_protocol, decode_protocol_list, encode_protocol, encode_protocol_list};
+use crate::{Error, SessionId, PROTOCOL_HEADER_WEBTRANSPORT, WT_AVAILABLE_PROTOCOLS_HEADER, WT_CLOSE_SESSION, WT_DRAIN_SESSION, WT_PROTOCOL_HEADER};
 use log::{debug, trace};
 use quiche::h3;
 use quiche::h3::NameValue;
@@ -22,6 +23,8 @@ pub struct Connection {
     pub(crate) streams: HashMap<u64, Stream>,
     closed: bool,
     perspective: Perspective,
+    /// Application protocols supported by the server, most preferred first.
+    protocols: Vec<String>,
 }
 
 enum Perspective {
@@ -49,9 +52,28 @@ impl Connection {
                 false => Perspective::Client { pending_requests: HashMap::new() },
             },
             closed: false,
+            protocols: vec![],
         }
     }
 
+    /// Set the application protocols a server selects from, most preferred first.
+    /// A session without a common protocol is established without protocol.
+    pub fn set_protocols(&mut self, protocols: &[&str]) {
+        self.protocols = protocols.iter().map(|p| p.to_string()).collect();
+    }
+
+    /// Application protocol selected by the server for the session,
+    /// `None` if no protocol was negotiated or the session is not established.
+    pub fn session_protocol(&self, session_id: SessionId) -> Option<&str> {
+        self.sessions.get(&session_id)?.protocol()
+    }
+
+    /// Path and query of the CONNECT request of a session accepted by a server,
+    /// `None` for a client or an unknown session.
+    pub fn session_path(&self, session_id: SessionId) -> Option<&str> {
+        self.sessions.get(&session_id)?.path()
+    }
+
     /// Sends a CONNECT request for a new WebTransport session.
     /// Returns session id.
     pub fn connect_session(
@@ -59,16 +81,35 @@ impl Connection {
         h3: &mut h3::Connection,
         quic: &mut quiche::Connection,
         url: Url,
+    ) -> SessionId {
+        self.connect_session_with_protocols(h3, quic, url, &[])
+    }
+
+    /// Like `connect_session`, offering application `protocols`, most preferred first.
+    /// The selected protocol is available with `session_protocol` once established.
+    pub fn connect_session_with_protocols(
+        &mut self,
+        h3: &mut h3::Connection,
+        quic: &mut quiche::Connection,
+        url: Url,
+        protocols: &[&str],
     ) -> SessionId {
         let Perspective::Client { pending_requests } = &mut self.perspective else { panic!("Perspective is not client") };
-        let hdrs = vec![
+        let protocols: Vec<String> = protocols.iter().map(|p| p.to_string()).collect();
+        let available_protocols = encode_protocol_list(&protocols);
+        // the query is part of the path, e.g. to carry an access token
+        let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+        let mut hdrs = vec![
                 h3::Header::new(b":method", METHOD_CONNECT),
                 h3::Header::new(b":protocol", PROTOCOL_HEADER_WEBTRANSPORT),
                 h3::Header::new(b":scheme", url.scheme().as_bytes()),
                 h3::Header::new(b":authority", url.authority().as_bytes()),
-                h3::Header::new(b":path", url.path().as_bytes()),
+                h3::Header::new(b":path", path.as_bytes()),
                 h3::Header::new(b"sec-webtransport-http3-draft02", b"1"), // this is outdated
         ];
+        if !protocols.is_empty() {
+            hdrs.push(h3::Header::new(WT_AVAILABLE_PROTOCOLS_HEADER, &available_protocols));
+        }
         trace!("send {:?}", hdrs_to_strings(&hdrs));
         let session_id = h3.send_request(quic, &hdrs, false).unwrap();
         pending_requests
@@ -108,13 +149,17 @@ impl Connection {
         }
         if let Perspective::Server { pending_responds } = &mut self.perspective {
             pending_responds.retain(|_, resp| {
+                let mut hdrs = vec![
+                    h3::Header::new(b":status", b"200"),
+                    h3::Header::new(b"sec-webtransport-http3-draft", b"draft02"),
+                ];
+                if let Some(protocol) = resp.protocol() {
+                    hdrs.push(h3::Header::new(WT_PROTOCOL_HEADER, &encode_protocol(protocol)));
+                }
                 h3.send_response(
                     quic,
                     resp.session_id(),
-                    &[
-                        h3::Header::new(b":status", b"200"),
-                        h3::Header::new(b"sec-webtransport-http3-draft", b"draft02"),
-                    ],
+                    &hdrs,
                     false,
                 )
                     .unwrap();
@@ -202,6 +247,9 @@ impl Connection {
         let mut wt_draft_supported = false;
         let mut wt_draft_selected = false;
         let mut protocol_webtransport = false;
+        let mut available_protocols = vec![];
+        let mut selected_protocol = None;
+        let mut path = None;
         for header in headers {
             match (header.name(), header.value()) {
                 (b":status", s) => status = Some(<[u8; 3]>::try_from(s).unwrap()),
@@ -209,6 +257,9 @@ impl Connection {
                 (b":protocol", b"webtransport") => protocol_webtransport = true,
                 (b"sec-webtransport-http3-draft02", b"1") => wt_draft_supported = true,
                 (b"sec-webtransport-http3-draft", b"draft02") => wt_draft_selected = true,
+                (WT_AVAILABLE_PROTOCOLS_HEADER, v) => available_protocols = decode_protocol_list(v),
+                (WT_PROTOCOL_HEADER, v) => selected_protocol = decode_protocol(v),
+                (b":path", v) => path = Some(String::from_utf8_lossy(v).into_owned()),
                 _ => debug!("ignore header {:?}", header),
             }
         }
@@ -218,9 +269,15 @@ impl Connection {
         match &mut self.perspective {
             Perspective::Server { pending_responds } => {
                 if method_connect && protocol_webtransport && wt_draft_supported {
-                    self.sessions.insert(stream_id, Session::accept(stream_id));
+                    let protocol = self
+                        .protocols
+                        .iter()
+                        .find(|p| available_protocols.contains(p))
+                        .cloned();
+                    debug!("webtransport session {} selected protocol {:?}", stream_id, protocol);
+                    self.sessions.insert(stream_id, Session::accept(stream_id, protocol.clone()).with_path(path));
                     pending_responds
-                        .insert(stream_id, PendingResponse::new(stream_id));
+                        .insert(stream_id, PendingResponse::new(stream_id, protocol));
                 }
             }
             Perspective::Client { pending_requests } => {
@@ -229,8 +286,8 @@ impl Connection {
                 assert_eq!(status, Some(*b"200"));
                 assert!(wt_draft_selected);
                 let session_id = stream_id;
-                debug!("webtransport session {} established", session_id);
-                self.sessions.insert(session_id, Session::accept(session_id));
+                debug!("webtransport session {} established with protocol {:?}", session_id, selected_protocol);
+                self.sessions.insert(session_id, Session::accept(session_id, selected_protocol));
             }
         }
     }
@@ -288,4 +345,15 @@ impl Connection {
         assert_eq!(sent, len);
         Ok(())
     }
+
+    /// Ask the peer to wind down the session, e.g. before the server shuts down.
+    /// The session stays usable until one side closes it.
+    pub fn drain_session(&mut self, session_id: SessionId, quic: &mut quiche::Connection, h3: &mut h3::Connection) -> Result<()> {
+        let mut buf = [0u8; 16];
+        let capsule = Capsule::new(WT_DRAIN_SESSION, vec![]);
+        let len = capsule.encode(&mut buf)?;
+        let sent = h3.send_body(quic, session_id, &buf[..len], false).unwrap();
+        assert_eq!(sent, len);
+        Ok(())
+    }
 }
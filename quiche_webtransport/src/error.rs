@@ -13,6 +13,11 @@ pub enum Error {
     /// The error code sent as part of the `RESET_STREAM` frame is provided as
     /// associated data.
     StreamReset(u64),
+    /// The specified stream was stopped by the peer.
+    ///
+    /// The error code sent as part of the `STOP_SENDING` frame is provided as
+    /// associated data.
+    StreamStopped(u64),
     /// The operation cannot be completed because the stream is in an
     /// invalid state.
     ///
@@ -2,6 +2,7 @@ mod connection;
 mod error;
 mod pending_request;
 mod pending_response;
+mod protocol;
 mod session;
 mod stream;
 pub mod test_utils;
@@ -40,8 +41,13 @@ pub const WEBTRANSPORT_MAX_SESSIONS_SETTINGS_PARAMETER_ID_DRAFT_7_12: u64 = 0xc6
 pub const WT_MAX_SESSIONS_H3_SETTINGS_PARAMETER_ID: u64 = 0x14e9cd29;
 
 
+/// https://www.ietf.org/archive/id/draft-ietf-webtrans-http3-14.html#name-application-protocol-negoti
+const WT_AVAILABLE_PROTOCOLS_HEADER: &[u8] = b"wt-available-protocols";
+const WT_PROTOCOL_HEADER: &[u8] = b"wt-protocol";
+
 /// https://www.ietf.org/archive/id/draft-ietf-webtrans-http3-14.html#name-capsule-types
 pub const WT_CLOSE_SESSION: u64 = 0x2843;
+pub const WT_DRAIN_SESSION: u64 = 0x78ae;
 
 /// Configure H3 for WebTransport.
 pub fn configure_h3(c: &mut quiche::h3::Config) -> Result<(), quiche::h3::Error> {
@@ -81,7 +87,7 @@ pub fn webtransport_enabled_by_server(c: &quiche::h3::Connection) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utils::_init_webtransport_pipe;
+    use crate::test_utils::{_init_webtransport_pipe, _init_webtransport_pipe_with_protocols};
     use quiche::h3;
 
     #[test]
@@ -105,4 +111,35 @@ mod tests {
         let len = s_wt.recv_stream(wt_stream_id, wt_session_id, &mut s_h3, &mut pipe.server, &mut b).unwrap();
         assert_eq!(&b[..len], MSG);
     }
+
+    #[test]
+    fn protocol_negotiation() {
+        let (_pipe, _c_h3, c_wt, _s_h3, s_wt, wt_session_id) =
+            _init_webtransport_pipe_with_protocols(&["moqt-16", "moqt-15"], &["moqt-15", "moqt-14"]);
+        assert_eq!(s_wt.session_protocol(wt_session_id), Some("moqt-15"));
+        assert_eq!(c_wt.session_protocol(wt_session_id), Some("moqt-15"));
+
+        let (_pipe, _c_h3, c_wt, _s_h3, s_wt, wt_session_id) =
+            _init_webtransport_pipe_with_protocols(&["moqt-16"], &["moqt-15"]);
+        assert_eq!(s_wt.session_protocol(wt_session_id), None);
+        assert_eq!(c_wt.session_protocol(wt_session_id), None);
+    }
+
+    #[test]
+    fn connect_path() {
+        let (_pipe, _c_h3, c_wt, _s_h3, s_wt, wt_session_id) = _init_webtransport_pipe();
+        assert_eq!(s_wt.session_path(wt_session_id), Some("/moq?token=secret"));
+        assert_eq!(c_wt.session_path(wt_session_id), None);
+    }
+
+    #[test]
+    fn protocol_header_encoding() {
+        use crate::protocol::{decode_protocol, decode_protocol_list, encode_protocol_list};
+        let protocols = vec!["moqt-16".to_string(), "a\"b".to_string()];
+        let encoded = encode_protocol_list(&protocols);
+        assert_eq!(encoded, br#""moqt-16", "a\"b""#);
+        assert_eq!(decode_protocol_list(&encoded), protocols);
+        assert_eq!(decode_protocol_list(b"\"moqt-16\";q=1,token, \"moqt-15\""), ["moqt-16", "moqt-15"]);
+        assert_eq!(decode_protocol(b" \"moqt-15\" "), Some("moqt-15".to_string()));
+    }
 }
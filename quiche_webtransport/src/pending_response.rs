@@ -1,15 +1,22 @@
 pub(crate) struct PendingResponse {
     session_id: u64,
+    protocol: Option<String>,
 }
 
 impl PendingResponse {
-    pub(crate) fn new(session_id: u64) -> Self {
+    pub(crate) fn new(session_id: u64, protocol: Option<String>) -> Self {
         Self {
             session_id,
+            protocol,
         }
     }
 
     pub fn session_id(&self) -> u64 {
         self.session_id
     }
+
+    /// Selected application protocol
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+//! Application protocol negotiation of WebTransport sessions.
+//! https://www.ietf.org/archive/id/draft-ietf-webtrans-http3-14.html#name-application-protocol-negoti
+//! Both headers are structured fields (RFC 9651), a list of strings and a string.
+
+/// Encode `protocols` as `wt-available-protocols` value.
+pub(crate) fn encode_protocol_list(protocols: &[String]) -> Vec<u8> {
+    protocols
+        .iter()
+        .map(|p| encode_protocol(p))
+        .collect::<Vec<_>>()
+        .join(&b", "[..])
+}
+
+/// Encode `protocol` as `wt-protocol` value.
+pub(crate) fn encode_protocol(protocol: &str) -> Vec<u8> {
+    format!("\"{}\"", protocol.replace('\\', "\\\\").replace('"', "\\\"")).into_bytes()
+}
+
+/// Decode a `wt-available-protocols` value.
+/// Members that are not strings are ignored, parameters are dropped.
+pub(crate) fn decode_protocol_list(value: &[u8]) -> Vec<String> {
+    let Ok(value) = std::str::from_utf8(value) else {
+        return vec![];
+    };
+    let mut protocols = vec![];
+    let mut rest = value.trim_start();
+    while !rest.is_empty() {
+        let (protocol, tail) = decode_string(rest);
+        protocols.extend(protocol);
+        // skip parameters and whitespace up to the next member
+        rest = match tail.find(',') {
+            Some(i) => tail[i + 1..].trim_start(),
+            None => "",
+        };
+    }
+    protocols
+}
+
+/// Decode a `wt-protocol` value.
+pub(crate) fn decode_protocol(value: &[u8]) -> Option<String> {
+    let value = std::str::from_utf8(value).ok()?;
+    decode_string(value.trim()).0
+}
+
+/// Returns the decoded string if `s` starts with a structured field string, and the remainder.
+fn decode_string(s: &str) -> (Option<String>, &str) {
+    let Some(s) = s.strip_prefix('"') else {
+        return (None, s);
+    };
+    let mut out = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (Some(out), &s[i + 1..]),
+            '\\' => match chars.next() {
+                Some((_, c @ ('"' | '\\'))) => out.push(c),
+                _ => return (None, ""),
+            },
+            c => out.push(c),
+        }
+    }
+    (None, "")
+}
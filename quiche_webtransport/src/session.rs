@@ -1,6 +1,10 @@
 #[allow(unused)]
 pub struct Session {
-    state: State
+    state: State,
+    /// Negotiated application protocol
+    protocol: Option<String>,
+    /// Path of the CONNECT request, only known by the server
+    path: Option<String>,
 }
 
 #[allow(unused)]
@@ -15,12 +19,29 @@ impl Session {
     pub fn connect(_session_id: u64) -> Self {
         Self {
             state: State::Pending,
+            protocol: None,
+            path: None,
         }
     }
 
-    pub fn accept(_session_id: u64) -> Self {
+    pub fn accept(_session_id: u64, protocol: Option<String>) -> Self {
         Self {
             state: State::Established,
+            protocol,
+            path: None,
         }
     }
+
+    pub fn with_path(mut self, path: Option<String>) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
 }
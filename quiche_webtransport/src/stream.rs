@@ -139,16 +139,22 @@ impl Stream {
             let mut b = [0u8; MAX_VARINT_LEN];
             let mut b = OctetsMut::with_slice(&mut b);
             let b = b.put_varint(self.session_id.unwrap()).unwrap();
-            if quic.stream_capacity(self.stream_id).unwrap() < b.len() {
+            if self.quic_capacity(quic)? < b.len() {
                 return Err(Error::Done);
             }
-            let sent = quic.stream_send(self.stream_id, b, false).unwrap();
+            let sent = match quic.stream_send(self.stream_id, b, false) {
+                Ok(v) => v,
+                Err(quiche::Error::StreamStopped(c)) => return Err(Error::StreamStopped(c)),
+                Err(e) => unimplemented!("{:?}", e),
+            };
             assert_eq!(b.len(), sent);
             self.sent_session_id = true;
         };
         let sent = match quic.stream_send(self.stream_id, buf, fin) {
             Ok(v) => v,
             Err(quiche::Error::Done) => return Err(Error::Done),
+            Err(quiche::Error::StreamStopped(c)) => return Err(Error::StreamStopped(c)),
+            Err(quiche::Error::InvalidStreamState(i)) => return Err(Error::InvalidStreamState(i)),
             Err(e) => unimplemented!("{:?}", e),
         };
         Ok(sent)
@@ -174,17 +180,24 @@ impl Stream {
     }
 
     pub fn capacity(&self, quic: &mut quiche::Connection) -> Result<usize> {
-        let mut capacity = match quic.stream_capacity(self.stream_id) {
-            Ok(v) => v,
-            Err(e) => unimplemented!("{:?}", e),
-        };
+        let mut capacity = self.quic_capacity(quic)?;
         if !self.sent_session_id {
             let session_id_len = varint_len(self.session_id.unwrap());
-            capacity -= session_id_len
+            capacity = capacity.saturating_sub(session_id_len);
         }
         Ok(capacity)
     }
 
+    /// Returns `Error::StreamStopped` once the peer asked to stop sending.
+    fn quic_capacity(&self, quic: &mut quiche::Connection) -> Result<usize> {
+        match quic.stream_capacity(self.stream_id) {
+            Ok(v) => Ok(v),
+            Err(quiche::Error::StreamStopped(c)) => Err(Error::StreamStopped(c)),
+            Err(quiche::Error::InvalidStreamState(i)) => Err(Error::InvalidStreamState(i)),
+            Err(e) => unimplemented!("{:?}", e),
+        }
+    }
+
     /// true if stream is closed and no more data is buffered.
     /// stream state can be removed.
     pub fn finished(&self) -> bool {
@@ -54,6 +54,18 @@ pub fn _init_webtransport_pipe() -> (
     h3::Connection,
     crate::Connection,
     crate::SessionId,
+) {
+    _init_webtransport_pipe_with_protocols(&[], &[])
+}
+
+/// `client_protocols` are offered by the client, the server selects from `server_protocols`.
+pub fn _init_webtransport_pipe_with_protocols(client_protocols: &[&str], server_protocols: &[&str]) -> (
+    Pipe,
+    h3::Connection,
+    crate::Connection,
+    h3::Connection,
+    crate::Connection,
+    crate::SessionId,
 ) {
     let mut pipe = Pipe::with_config(&mut {
         let (key, cert) = key_pair();
@@ -105,13 +117,15 @@ pub fn _init_webtransport_pipe() -> (
     assert!(crate::webtransport_enabled_by_server(&c_h3));
 
     let mut c_wt = crate::Connection::new(false);
-    let wt_session_id = c_wt.connect_session(
+    let wt_session_id = c_wt.connect_session_with_protocols(
         &mut c_h3,
         &mut pipe.client,
-        "https://example.org/".parse().unwrap(),
+        "https://example.org/moq?token=secret".parse().unwrap(),
+        client_protocols,
     );
 
     let mut s_wt = crate::Connection::new(true);
+    s_wt.set_protocols(server_protocols);
 
     pipe.advance().unwrap();
 